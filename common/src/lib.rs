@@ -1,18 +1,4 @@
-#[macro_export]
-macro_rules! debug {
-    ($str: expr) => {
-        #[cfg(debug_assertions)]
-        {
-            println!($str)
-        }
-    };
-    ($str: expr, $($rest: expr),+) => {
-        #[cfg(debug_assertions)]
-        {
-            println!($str, $($rest),+)
-        }
-    };
-}
+pub mod logging;
 
 #[macro_export]
 macro_rules! release {
@@ -0,0 +1,44 @@
+//! Diagnostic output of the binaries, written to stderr
+
+use clap::ValueEnum;
+use tracing::level_filters::LevelFilter;
+
+/// Max level of log messages that are printed
+///
+/// Release builds never contain messages above [LogLevel::Info].
+#[derive(ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Off,
+    Error,
+    #[default]
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Prints all log messages up to `level` to stderr, as JSON lines if `json` is set
+pub fn init_logging(level: LogLevel, json: bool) {
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(LevelFilter::from(level))
+        .with_writer(std::io::stderr);
+
+    if json {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
+}
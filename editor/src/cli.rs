@@ -0,0 +1,14 @@
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub(crate) struct Args {
+    #[arg(help = "Task (or cli file) to edit")]
+    pub task: String,
+
+    #[arg(
+        long,
+        help = "File the result is saved to by `save` without a path (default: the edited file)"
+    )]
+    pub out: Option<String>,
+}
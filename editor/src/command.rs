@@ -0,0 +1,148 @@
+//! Commands of the editor
+
+use model::{
+    object::{Coord, Object},
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
+};
+
+pub(crate) const HELP: &str = "\
+Commands:
+  place obstacle <x> <y> <width> <height>
+  place deposit <x> <y> <width> <height> <subtype>
+  place mine|factory|conveyor|combiner <x> <y> <subtype>
+  remove <x> <y>     remove the object occupying the cell
+  show               print the map
+  simulate           print the score of the current map
+  save [path]        save the map as cli file
+  help               print this help
+  quit               exit without saving";
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Command {
+    Place(Object),
+    Remove(Coord, Coord),
+    Show,
+    Simulate,
+    Save(Option<String>),
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// Parses a single line of input
+    ///
+    /// Returns Ok(None) for empty lines.
+    pub(crate) fn parse(line: &str) -> Result<Option<Command>, String> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+        let args: Vec<&str> = words.collect();
+
+        let command = match (command, args.as_slice()) {
+            ("place", [kind, args @ ..]) => Command::Place(parse_object(kind, args)?),
+            ("remove", [x, y]) => Command::Remove(parse(x)?, parse(y)?),
+            ("show", []) => Command::Show,
+            ("simulate", []) => Command::Simulate,
+            ("save", []) => Command::Save(None),
+            ("save", [path]) => Command::Save(Some(path.to_string())),
+            ("help", []) => Command::Help,
+            ("quit" | "exit", []) => Command::Quit,
+            _ => return Err(format!("Invalid command '{}' (see `help`)", line.trim())),
+        };
+
+        Ok(Some(command))
+    }
+}
+
+fn parse_object(kind: &str, args: &[&str]) -> Result<Object, String> {
+    let object = match (kind, args) {
+        ("obstacle", [x, y, width, height]) => Object::Obstacle {
+            x: parse(x)?,
+            y: parse(y)?,
+            width: parse(width)?,
+            height: parse(height)?,
+        },
+        ("deposit", [x, y, width, height, subtype]) => Object::Deposit {
+            x: parse(x)?,
+            y: parse(y)?,
+            width: parse(width)?,
+            height: parse(height)?,
+            subtype: parse(subtype)?,
+        },
+        ("mine", [x, y, subtype]) => Object::Mine {
+            x: parse(x)?,
+            y: parse(y)?,
+            subtype: MineSubtype::try_from(parse::<u8>(subtype)?)?,
+        },
+        ("factory", [x, y, subtype]) => Object::Factory {
+            x: parse(x)?,
+            y: parse(y)?,
+            subtype: parse(subtype)?,
+        },
+        ("conveyor", [x, y, subtype]) => Object::Conveyor {
+            x: parse(x)?,
+            y: parse(y)?,
+            subtype: ConveyorSubtype::try_from(parse::<u8>(subtype)?)?,
+        },
+        ("combiner", [x, y, subtype]) => Object::Combiner {
+            x: parse(x)?,
+            y: parse(y)?,
+            subtype: CombinerSubtype::try_from(parse::<u8>(subtype)?)?,
+        },
+        _ => return Err(format!("Invalid arguments for {}: {:?}", kind, args)),
+    };
+
+    Ok(object)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_place_commands() {
+        assert_eq!(
+            Command::parse("place conveyor 3 4 5"),
+            Ok(Some(Command::Place(Object::Conveyor {
+                x: 3,
+                y: 4,
+                subtype: ConveyorSubtype::LongSouth,
+            })))
+        );
+        assert_eq!(
+            Command::parse("place deposit 0 0 2 3 1"),
+            Ok(Some(Command::Place(Object::Deposit {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 3,
+                subtype: 1,
+            })))
+        );
+        assert!(Command::parse("place mine 1 1 4").is_err());
+        assert!(Command::parse("place conveyor 1 1").is_err());
+    }
+
+    #[test]
+    fn parses_other_commands() {
+        assert_eq!(Command::parse("  "), Ok(None));
+        assert_eq!(
+            Command::parse("remove 2 -1"),
+            Ok(Some(Command::Remove(2, -1)))
+        );
+        assert_eq!(
+            Command::parse("save out.json"),
+            Ok(Some(Command::Save(Some(String::from("out.json")))))
+        );
+        assert!(Command::parse("remove 2").is_err());
+        assert!(Command::parse("draw").is_err());
+    }
+}
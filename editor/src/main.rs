@@ -0,0 +1,84 @@
+mod cli;
+mod command;
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use clap::Parser;
+
+use cli::Args;
+use command::{Command, HELP};
+use model::{map::Map, task::Task};
+use simulator::simulate;
+
+fn main() {
+    let args = Args::parse();
+    let task = Task::from_json_file(&args.task)
+        .unwrap_or_else(|e| panic!("Cannot read task from {}: {}", args.task, e));
+    let mut map = Map::new(task.width, task.height, task.objects.clone());
+
+    let interactive = io::stdin().is_terminal();
+    println!("{}", map);
+
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush().unwrap();
+        }
+
+        let line = match lines.next() {
+            Some(line) => line.expect("Cannot read from stdin"),
+            None => break,
+        };
+
+        let command = match Command::parse(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+
+        match command {
+            Command::Place(object) => match map.can_insert_object(&object) {
+                Ok(()) => {
+                    map.insert_object(object).unwrap();
+                    println!("{}", map);
+                }
+                Err(e) => eprintln!("Cannot place {:?}: {}", object, e),
+            },
+            Command::Remove(x, y) => match map.object_at(x, y).cloned() {
+                Some(object) => {
+                    map.remove_object(&object).unwrap();
+                    println!("{}", map);
+                }
+                None => eprintln!("No object at ({}, {})", x, y),
+            },
+            Command::Show => println!("{}", map),
+            Command::Simulate => {
+                let result = simulate(&task, &map, true);
+                println!("Score {} at turn {}", result.score, result.turn);
+            }
+            Command::Save(path) => {
+                let path = path
+                    .or_else(|| args.out.clone())
+                    .unwrap_or_else(|| args.task.clone());
+                match save(&task, &map, &path) {
+                    Ok(()) => println!("Saved to {}", path),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            Command::Help => println!("{}", HELP),
+            Command::Quit => break,
+        }
+    }
+}
+
+/// Saves `map` as cli file, i.e. as `task` with all objects of `map`
+fn save(task: &Task, map: &Map, path: &str) -> Result<(), String> {
+    let mut task = task.clone();
+    task.objects = map.get_objects().cloned().collect();
+    std::fs::write(path, task.to_json_string().unwrap())
+        .map_err(|e| format!("Cannot save to {}: {}", path, e))
+}
@@ -0,0 +1,14 @@
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub(crate) struct Args {
+    #[arg(
+        long,
+        help = "Read the solution from the given file instead of the objects of the input task"
+    )]
+    pub solution: Option<String>,
+
+    #[arg(short, long, help = "Print every removed object to stderr")]
+    pub verbose: bool,
+}
@@ -0,0 +1,63 @@
+mod cli;
+
+use clap::Parser;
+
+use cli::Args;
+use model::{input::read_input_from_stdin, object::Object, solution::Solution, task::Task};
+use simulator::{generate_map, simulate};
+
+fn main() {
+    let args = Args::parse();
+    let (task, solution) = read_input_from_stdin().unwrap();
+    let solution = match args.solution {
+        Some(path) => Solution::from_json_file(&path)
+            .unwrap_or_else(|e| panic!("Cannot read solution from {}: {}", path, e)),
+        None => solution.unwrap_or_default(),
+    };
+
+    if let Err(e) = generate_map(&task, &solution) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    let minimized = minimize(&task, solution, args.verbose);
+    println!("{}", minimized.to_json_string().unwrap());
+}
+
+/// Greedily removes objects from `solution` as long as its simulated score stays the same
+///
+/// Objects are tried last to first, and passes are repeated until no more object can be removed.
+/// `solution` must be valid, so all solutions with fewer objects are valid as well.
+fn minimize(task: &Task, solution: Solution, verbose: bool) -> Solution {
+    let map = |solution: &Solution| generate_map(task, solution).unwrap();
+    let score = simulate(task, &map(&solution), true).score;
+    let mut objects = solution.0;
+
+    loop {
+        let num_objects = objects.len();
+
+        for index in (0..objects.len()).rev() {
+            let mut candidate = objects.clone();
+            let removed = candidate.remove(index);
+
+            let candidate = Solution(candidate);
+            if simulate(task, &map(&candidate), true).score == score {
+                if verbose {
+                    eprintln!("Removed {}", describe(&removed));
+                }
+                objects = candidate.0;
+            }
+        }
+
+        if objects.len() == num_objects {
+            break;
+        }
+    }
+
+    Solution(objects)
+}
+
+fn describe(object: &Object) -> String {
+    let (x, y) = object.coords();
+    format!("{:?} at ({}, {})", object.kind(), x, y)
+}
@@ -1,24 +1,28 @@
 use criterion::{criterion_group, criterion_main, Criterion};
-use model::{map::Map, object::Object};
+use model::{
+    map::Map,
+    object::Object,
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
+};
 
 fn map_can_insert_object(c: &mut Criterion) {
     let map = Map::new(100, 100, vec![]);
 
-    let objects = vec![
+    let objects = [
         Object::Mine {
             x: 10,
             y: 10,
-            subtype: 0,
+            subtype: MineSubtype::East,
         },
         Object::Conveyor {
             x: 50,
             y: 50,
-            subtype: 0,
+            subtype: ConveyorSubtype::East,
         },
         Object::Combiner {
             x: 75,
             y: 75,
-            subtype: 0,
+            subtype: CombinerSubtype::East,
         },
         Object::Factory {
             x: 60,
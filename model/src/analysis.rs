@@ -0,0 +1,293 @@
+//! Static analysis of tasks, independent of any solution
+
+use std::collections::BTreeMap;
+
+use crate::{
+    object::{Object, Subtype},
+    task::{Product, Task},
+};
+
+/// Number of different resource types
+pub const NUM_RESOURCE_TYPES: usize = 8;
+
+/// Max amount of resources a deposit hands out to a single adjacent mine per turn
+pub const MAX_EXTRACTION_PER_TURN: u32 = 3;
+
+/// Min number of turns between extracting a resource and using it in a factory
+///
+/// A resource extracted at the end of a turn is taken by a mine in the next turn and by a factory
+/// (directly attached to the mine) in the turn after that.
+const MIN_TRANSPORT_TURNS: u32 = 2;
+
+/// Total amount of each resource type (indexed by subtype) in all deposits of `objects`
+pub fn resource_capacities<'a>(
+    objects: impl IntoIterator<Item = &'a Object>,
+) -> [u32; NUM_RESOURCE_TYPES] {
+    let mut capacities = [0; NUM_RESOURCE_TYPES];
+    for object in objects {
+        if let Object::Deposit { subtype, .. } = object {
+            capacities[*subtype as usize] += object.initial_resources();
+        }
+    }
+    capacities
+}
+
+/// Max number of units of `product`, that can be produced from `capacities`
+///
+/// This assumes that no other product uses any of the resources.
+pub fn max_producible(product: &Product, capacities: &[u32; NUM_RESOURCE_TYPES]) -> u32 {
+    product
+        .resources
+        .iter()
+        .zip(capacities.iter())
+        .filter(|(&amount, _)| amount > 0)
+        .map(|(&amount, &capacity)| capacity / amount)
+        .min()
+        .unwrap_or(0)
+}
+
+/// Theoretical max number of units of each product (by subtype) of `task`
+///
+/// Each product is looked at on its own, so the returned amounts can usually not be produced at
+/// the same time, if products share resource types.
+pub fn max_producible_by_product(task: &Task) -> BTreeMap<Subtype, u32> {
+    let capacities = resource_capacities(task.objects.iter());
+    task.products
+        .iter()
+        .map(|product| (product.subtype, max_producible(product, &capacities)))
+        .collect()
+}
+
+/// Amount of each resource type (indexed by subtype) that can be extracted from all deposits of
+/// `objects` and reach a factory within `turns` turns
+///
+/// The extraction rate of a deposit is bounded by the number of cells around it, where mines can
+/// be attached.
+pub fn extractable_resources<'a>(
+    objects: impl IntoIterator<Item = &'a Object>,
+    turns: u32,
+) -> [u32; NUM_RESOURCE_TYPES] {
+    let mut extractable = [0; NUM_RESOURCE_TYPES];
+    for object in objects {
+        if let Object::Deposit {
+            width,
+            height,
+            subtype,
+            ..
+        } = object
+        {
+            let num_max_mines = 2 * (*width as u32 + *height as u32);
+            extractable[*subtype as usize] += extractable_by_mines(object, num_max_mines, turns);
+        }
+    }
+    extractable
+}
+
+/// Amount of resources that `num_mines` mines attached to `deposit` can extract, such that they
+/// reach a factory within `turns` turns
+pub fn extractable_by_mines(deposit: &Object, num_mines: u32, turns: u32) -> u32 {
+    let turns = turns.saturating_sub(MIN_TRANSPORT_TURNS);
+    let max_extraction = num_mines
+        .saturating_mul(MAX_EXTRACTION_PER_TURN)
+        .saturating_mul(turns);
+    deposit.initial_resources().min(max_extraction)
+}
+
+/// Amount of each resource type (indexed by subtype) that can actually be turned into products
+///
+/// Resources are only useful as long as the other resources of a product's recipe are available
+/// as well. Anything beyond that amount cannot contribute any points, no matter how many mines
+/// extract it.
+pub fn consumable_resources(task: &Task) -> [u32; NUM_RESOURCE_TYPES] {
+    let capacities = extractable_resources(task.objects.iter(), task.turns);
+    let mut consumable = [0u32; NUM_RESOURCE_TYPES];
+    for product in task.products.iter() {
+        let num_producible = max_producible(product, &capacities);
+        for (resource, &amount) in product.resources.iter().enumerate() {
+            consumable[resource] = consumable[resource].saturating_add(num_producible * amount);
+        }
+    }
+    for (consumable, capacity) in consumable.iter_mut().zip(capacities) {
+        *consumable = (*consumable).min(capacity);
+    }
+    consumable
+}
+
+/// Upper bound of the score that can be achieved for `task`
+///
+/// The bound takes into account that products may share resource types (and therefore compete
+/// for them) and how many resources can be extracted in the available number of turns. It ignores
+/// the map's layout, so the actual optimum is usually (much) lower.
+pub fn score_upper_bound(task: &Task) -> u32 {
+    let capacities = extractable_resources(task.objects.iter(), task.turns);
+
+    let products: Vec<&Product> = task
+        .products
+        .iter()
+        .filter(|product| max_producible(product, &capacities) > 0)
+        .collect();
+
+    // Producing every product on its own, as often as possible, is always a valid bound
+    let independent_bound: f64 = products
+        .iter()
+        .map(|product| product.points as f64 * max_producible(product, &capacities) as f64)
+        .sum();
+
+    /* A tighter bound is found by assigning each product to one of its resources. Each unit of a
+     * resource can at most yield the best 'points per unit of resource' of all products assigned
+     * to it. Every assignment is a valid bound, so the minimum of all is used.
+     */
+    let mut best = independent_bound;
+    let mut points_per_resource = [0f64; NUM_RESOURCE_TYPES];
+    assign_products(&products, &capacities, &mut points_per_resource, &mut best);
+
+    // compensate floating point errors, so the bound is never too low
+    (best + 1e-6).floor() as u32
+}
+
+/// Assigns products to resources recursively and stores the lowest bound in `best`
+fn assign_products(
+    products: &[&Product],
+    capacities: &[u32; NUM_RESOURCE_TYPES],
+    points_per_resource: &mut [f64; NUM_RESOURCE_TYPES],
+    best: &mut f64,
+) {
+    let bound: f64 = capacities
+        .iter()
+        .zip(points_per_resource.iter())
+        .map(|(&capacity, &points)| capacity as f64 * points)
+        .sum();
+
+    // assigning further products can only increase the bound
+    if bound >= *best {
+        return;
+    }
+
+    let (product, products) = match products.split_first() {
+        Some(split) => split,
+        None => {
+            *best = bound;
+            return;
+        }
+    };
+
+    for (resource, &amount) in product.resources.iter().enumerate() {
+        if amount == 0 {
+            continue;
+        }
+
+        let old = points_per_resource[resource];
+        points_per_resource[resource] = old.max(product.points as f64 / amount as f64);
+        assign_products(products, capacities, points_per_resource, best);
+        points_per_resource[resource] = old;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_producible_is_limited_by_scarcest_resource() {
+        let objects = [
+            Object::Deposit {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 2,
+                subtype: 0,
+            },
+            Object::Deposit {
+                x: 5,
+                y: 5,
+                width: 3,
+                height: 3,
+                subtype: 1,
+            },
+        ];
+        let capacities = resource_capacities(objects.iter());
+        assert_eq!(capacities[..3], [20, 45, 0]);
+
+        let product = Product {
+            kind: String::from("product"),
+            subtype: 0,
+            resources: vec![3, 5, 0, 0, 0, 0, 0, 0],
+            points: 10,
+        };
+        assert_eq!(max_producible(&product, &capacities), 6);
+
+        let product = Product {
+            resources: vec![1, 0, 1, 0, 0, 0, 0, 0],
+            ..product
+        };
+        assert_eq!(max_producible(&product, &capacities), 0);
+    }
+
+    #[test]
+    fn score_upper_bound_respects_shared_resources() {
+        let deposit = |x, subtype| Object::Deposit {
+            x,
+            y: 0,
+            width: 2,
+            height: 2,
+            subtype,
+        };
+        let product = |subtype, resources, points| Product {
+            kind: String::from("product"),
+            subtype,
+            resources,
+            points,
+        };
+        let task = Task {
+            width: 20,
+            height: 20,
+            objects: vec![deposit(0, 0), deposit(10, 1)],
+            products: vec![
+                product(0, vec![1, 0, 0, 0, 0, 0, 0, 0], 10),
+                product(1, vec![1, 1, 0, 0, 0, 0, 0, 0], 15),
+            ],
+            turns: 100,
+            time: None,
+        };
+
+        // product 1 uses all of resource 0
+        assert_eq!(score_upper_bound(&task), 300);
+
+        // only a single turn left for extraction
+        let task = Task { turns: 3, ..task };
+        assert_eq!(
+            extractable_resources(task.objects.iter(), task.turns)[0],
+            20
+        );
+        let task = Task { turns: 2, ..task };
+        assert_eq!(score_upper_bound(&task), 0);
+    }
+
+    #[test]
+    fn consumable_resources_are_limited_by_recipes() {
+        let deposit = |x, width, subtype| Object::Deposit {
+            x,
+            y: 0,
+            width,
+            height: 2,
+            subtype,
+        };
+        let task = Task {
+            width: 30,
+            height: 20,
+            objects: vec![deposit(0, 2, 0), deposit(10, 8, 1), deposit(20, 2, 2)],
+            products: vec![Product {
+                kind: String::from("product"),
+                subtype: 0,
+                resources: vec![2, 1, 0, 0, 0, 0, 0, 0],
+                points: 10,
+            }],
+            turns: 100,
+            time: None,
+        };
+
+        // 10 units are producible from 20 units of resource 0, so only 10 units of resource 1 are
+        // useful. Resource 2 is not used by any product.
+        assert_eq!(consumable_resources(&task)[..3], [20, 10, 0]);
+    }
+}
@@ -0,0 +1,14 @@
+//! Prints the JSON schemas of the task and solution formats
+//!
+//! Run with `cargo run -p model --features schema --bin schema`.
+
+use model::{solution::Solution, task::Task};
+use schemars::schema_for;
+
+fn main() {
+    let schemas = serde_json::json!({
+        "task": schema_for!(Task),
+        "solution": schema_for!(Solution),
+    });
+    println!("{}", serde_json::to_string_pretty(&schemas).unwrap());
+}
@@ -0,0 +1,25 @@
+//! Errors of building a [Map](crate::map::Map) from input
+
+use std::fmt::Display;
+
+use crate::map::Violation;
+
+/// Why a task and its solution cannot be turned into a map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelError {
+    /// The task itself is malformed (e.g. the map is too large or objects of the task overlap)
+    InvalidTask(String),
+    /// An object of the solution breaks a placement rule (e.g. it overlaps an object of the task)
+    InvalidSolution(Violation),
+}
+
+impl Display for ModelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ModelError::InvalidTask(reason) => write!(f, "Invalid task: {}", reason),
+            ModelError::InvalidSolution(violation) => write!(f, "Invalid solution: {}", violation),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
@@ -1,50 +1,132 @@
 use std::io::Read;
 
+use flate2::read::GzDecoder;
+
 use crate::{object::Object, solution::Solution, task::Task};
 
+/// First bytes of gzip compressed data
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+
 // TODO: proper error types
 pub fn read_input_from_stdin() -> Result<(Task, Option<Solution>), String> {
-    let mut input_string = String::new();
-    std::io::stdin()
-        .lock()
-        .read_to_string(&mut input_string)
-        .map_err(|_| String::from("Could not read from stdin"))?;
+    let input_string = read_stdin()?;
 
     read_input(input_string.as_str())
 }
 
+/// Reads all tasks from stdin
+///
+/// The input may contain multiple tasks, separated by whitespace (e.g. newline-delimited JSON).
+pub fn read_inputs_from_stdin() -> Result<Vec<(Task, Option<Solution>)>, String> {
+    let input_string = read_stdin()?;
+
+    read_inputs(input_string.as_str())
+}
+
 pub fn read_input_from_file(file_path: &str) -> Result<(Task, Option<Solution>), String> {
-    let input_string = std::fs::read_to_string(file_path)
+    let input_bytes = std::fs::read(file_path)
         .map_err(|_| format!("Cannot read input from file {}", file_path))?;
+    let input_string = decode(input_bytes)?;
 
     read_input(input_string.as_str())
 }
 
+/// Reads stdin completely and decompresses it, if it is gzipped
+fn read_stdin() -> Result<String, String> {
+    let mut input_bytes = vec![];
+    std::io::stdin()
+        .lock()
+        .read_to_end(&mut input_bytes)
+        .map_err(|_| String::from("Could not read from stdin"))?;
+
+    decode(input_bytes)
+}
+
+/// Turns `bytes` into a string, decompressing them first, if they are gzipped
+fn decode(bytes: Vec<u8>) -> Result<String, String> {
+    if bytes.starts_with(&GZIP_MAGIC_BYTES) {
+        let mut input_string = String::new();
+        GzDecoder::new(bytes.as_slice())
+            .read_to_string(&mut input_string)
+            .map_err(|e| format!("Cannot decompress input: {}", e))?;
+        Ok(input_string)
+    } else {
+        String::from_utf8(bytes).map_err(|_| String::from("Input is not valid UTF-8"))
+    }
+}
+
+fn read_inputs(input: &str) -> Result<Vec<(Task, Option<Solution>)>, String> {
+    let inputs = serde_json::Deserializer::from_str(input)
+        .into_iter::<Task>()
+        .map(|task| task.map(split_task).map_err(|e| format!("{:?}", e)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    if inputs.is_empty() {
+        return Err(String::from("Input does not contain any task"));
+    }
+
+    Ok(inputs)
+}
+
 fn read_input(input: &str) -> Result<(Task, Option<Solution>), String> {
-    match serde_json::de::from_str::<Task>(input) {
-        Ok(mut task) => {
-            let construction_objects: Vec<Object> = task
-                .objects
-                .iter()
-                .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
-                .cloned()
-                .collect();
-
-            if !construction_objects.is_empty() {
-                let landscape_objects: Vec<Object> = task
-                    .objects
-                    .iter()
-                    .filter(|obj| matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
-                    .cloned()
-                    .collect();
-
-                task.objects = landscape_objects;
-                let solution = Solution::from(construction_objects);
-                Ok((task, Some(solution)))
-            } else {
-                Ok((task, None))
-            }
-        }
-        Err(e) => Err(format!("{:?}", e)),
+    serde_json::de::from_str::<Task>(input)
+        .map(split_task)
+        .map_err(|e| format!("{:?}", e))
+}
+
+/// Moves all non-landscape objects of `task` into a solution (if there are any)
+fn split_task(mut task: Task) -> (Task, Option<Solution>) {
+    let construction_objects: Vec<Object> = task
+        .objects
+        .iter()
+        .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
+        .cloned()
+        .collect();
+
+    if !construction_objects.is_empty() {
+        let landscape_objects: Vec<Object> = task
+            .objects
+            .iter()
+            .filter(|obj| matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
+            .cloned()
+            .collect();
+
+        task.objects = landscape_objects;
+        let solution = Solution::from(construction_objects);
+        (task, Some(solution))
+    } else {
+        (task, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    #[test]
+    fn reads_gzipped_newline_delimited_tasks() {
+        let input = concat!(
+            r#"{"width":10,"height":10,"objects":[],"products":[],"turns":10,"time":1}"#,
+            "\n",
+            r#"{"width":20,"height":5,"objects":[{"type":"mine","x":1,"y":1,"subtype":0}],"#,
+            r#""products":[],"turns":5}"#,
+            "\n",
+        );
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(input.as_bytes()).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let inputs = read_inputs(&decode(bytes).unwrap()).unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!((inputs[0].0.width, inputs[1].0.width), (10, 20));
+        assert!(inputs[0].1.is_none());
+        assert_eq!(inputs[1].1.as_ref().unwrap().0.len(), 1);
+
+        assert!(read_inputs("").is_err());
+        assert!(read_inputs(&input[..30]).is_err());
     }
 }
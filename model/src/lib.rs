@@ -1,6 +1,10 @@
+pub mod analysis;
 pub mod coord;
+pub mod error;
 pub mod input;
 pub mod map;
 pub mod object;
 pub mod solution;
+pub mod strict;
+pub mod subtype;
 pub mod task;
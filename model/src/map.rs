@@ -3,11 +3,20 @@ use std::{fmt::Display, sync::Arc};
 use fxhash::FxHashMap as HashMap;
 
 use crate::{
+    analysis::NUM_RESOURCE_TYPES,
     coord::{neighbours, Point},
-    object::{Coord, Object, ObjectCell, ObjectID, ObjectType},
+    object::{Coord, Object, ObjectCell, ObjectID, ObjectIndex, ObjectType},
     task::Task,
 };
 
+/// Max number of layers below a map, before [Map::from_map] compacts them
+///
+/// Cell lookups hook into lower layers, so they take time linear in the number of layers.
+pub const MAX_LAYER_DEPTH: u32 = 16;
+
+/// Max width and height of a map (in cells)
+pub const MAX_SIZE: u8 = 100;
+
 /// A container that holds objects and information about which cells being occupied
 ///
 /// Note that maps can be _layered_, meaning on map can have a reference to another map in the
@@ -20,30 +29,258 @@ pub struct Map {
     width: u8,
     height: u8,
     map: HashMap<Point, ObjectCell>,
-    objects: HashMap<ObjectID, Object>, //TODO: try (and measure) turning this into hashset
+    /// Index of the first arena slot of this layer (lower slots belong to lower layers)
+    index_offset: u32,
+    /// Arena of the objects of this layer
+    objects: Vec<ArenaSlot>,
+    /// Slots of removed objects, that can be reused
+    free_slots: Vec<u32>,
+    /// Indices of this layer's objects by their ids
+    indices: HashMap<ObjectID, ObjectIndex>,
+    /// Number of additional objects occupying a cell of this layer (i.e. crossing conveyors)
+    crossings: HashMap<Point, u8>,
+    /// Ingress and egress cells of this layer's objects, by the cells next to them
+    adjacency: HashMap<Point, AdjacentCells>,
+    /// Zobrist hash of all objects of this map (including lower layers)
+    zobrist: u64,
+    /// Number of layers below this layer
+    depth: u32,
+}
+
+#[derive(Debug, Clone)]
+struct ArenaSlot {
+    generation: u32,
+    object: Option<Object>,
+}
+
+/// Ingress and egress cells next to a cell, as returned by [Map::adjacent_cells]
+///
+/// Cells are ordered like the points returned by [neighbours].
+#[derive(Debug, Clone, Default)]
+pub struct AdjacentCells([Option<(Point, ObjectCell)>; 4]);
+
+impl AdjacentCells {
+    /// All neighbouring ingress and egress cells
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &ObjectCell)> {
+        self.0
+            .iter()
+            .filter_map(|entry| entry.as_ref().map(|(point, cell)| (*point, cell)))
+    }
+
+    /// All neighbouring ingress cells
+    pub fn ingresses(&self) -> impl Iterator<Item = (Point, &ObjectCell)> {
+        self.iter()
+            .filter(|(_, cell)| matches!(cell, ObjectCell::Ingress { .. }))
+    }
+
+    /// All neighbouring egress cells
+    pub fn egresses(&self) -> impl Iterator<Item = (Point, &ObjectCell)> {
+        self.iter()
+            .filter(|(_, cell)| matches!(cell, ObjectCell::Egress { .. }))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(Option::is_none)
+    }
+
+    /// Adds the cells of `other`, which take precedence over the cells of `self`
+    fn overlay(&mut self, other: &AdjacentCells) {
+        for (entry, other_entry) in self.0.iter_mut().zip(other.0.iter()) {
+            if other_entry.is_some() {
+                *entry = other_entry.clone();
+            }
+        }
+    }
+}
+
+/// An object that breaks a placement rule, as returned by [Map::audit]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub object: Object,
+    /// The broken rule, as reported by [Map::can_insert_object]
+    pub reason: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.object, self.reason)
+    }
+}
+
+/// Difference between two maps, as returned by [Map::diff]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiffEntry {
+    /// The object only exists in the first map
+    Removed(Object),
+    /// The object only exists in the second map
+    Added(Object),
 }
 
 impl Map {
     /// Creates a new Map and inserts the given objects
+    ///
+    /// Panics if the objects cannot be inserted (see [Map::try_new]).
     pub fn new(width: u8, height: u8, objects: Vec<Object>) -> Self {
-        debug_assert!(width <= 100);
-        debug_assert!(height <= 100);
+        Map::try_new(width, height, objects)
+            .unwrap_or_else(|e| panic!("Cannot create map from objects: '{}'", e))
+    }
+
+    /// Creates a new Map and inserts the given objects
+    ///
+    /// Fails if the map is larger than 100x100 cells or an object cannot be inserted.
+    pub fn try_new(width: u8, height: u8, objects: Vec<Object>) -> Result<Self, String> {
+        if width > MAX_SIZE || height > MAX_SIZE {
+            return Err(format!(
+                "Map of {}x{} cells exceeds {}x{} cells",
+                width, height, MAX_SIZE, MAX_SIZE
+            ));
+        }
 
         let mut map = Map {
             inner: None,
             width,
             height,
-            objects: HashMap::default(),
             map: HashMap::default(),
+            index_offset: 0,
+            objects: Vec::new(),
+            free_slots: Vec::new(),
+            indices: HashMap::default(),
+            crossings: HashMap::default(),
+            adjacency: HashMap::default(),
+            zobrist: 0,
+            depth: 0,
         };
 
         for object in objects {
-            if let Err(e) = map.insert_object(object) {
-                panic!("Cannot create map from objects: '{}'", e);
+            map.insert_object(object)?;
+        }
+
+        Ok(map)
+    }
+
+    /// Creates a map from an ASCII drawing, as printed by [Map]'s [Display] implementation
+    ///
+    /// The column and row numbers may be left out. Only objects that can be told apart by their
+    /// characters are recognized: obstacles (blocks of `X`, split into rectangles), deposits
+    /// (digits surrounded by `-`) and factories (3x3 digits surrounded by `+`). Fails on cells of
+    /// any other object (e.g. conveyors) and on rows of different lengths.
+    pub fn from_ascii(ascii: &str) -> Result<Map, String> {
+        let mut lines: Vec<&str> = ascii
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect();
+        // the column numbers are indented by the width of the row numbers
+        if lines.first().is_some_and(|line| line.starts_with("   ")) {
+            lines = lines
+                .iter()
+                .skip(2)
+                .map(|line| line.get(3..).unwrap_or_default())
+                .collect();
+        }
+        let rows: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect()).collect();
+
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        if let Some(y) = rows.iter().position(|row| row.len() != width) {
+            return Err(format!(
+                "Row {} has {} cells, but the first row has {}",
+                y,
+                rows[y].len(),
+                width
+            ));
+        }
+        if width > MAX_SIZE as usize || height > MAX_SIZE as usize {
+            return Err(format!(
+                "Map of {}x{} cells exceeds {}x{} cells",
+                width, height, MAX_SIZE, MAX_SIZE
+            ));
+        }
+
+        let at = |x: usize, y: usize| rows.get(y).and_then(|row| row.get(x)).copied();
+        let mut parsed = vec![vec![false; width]; height];
+        let mut objects = vec![];
+
+        // the block of unparsed cells of `c` whose top left cell is (x, y), as (width, height)
+        let block = |parsed: &[Vec<bool>], x: usize, y: usize, c: char| {
+            let is_free = |x: usize, y: usize| at(x, y) == Some(c) && !parsed[y][x];
+            let w = (x..width).take_while(|&x| is_free(x, y)).count();
+            let h = (y..height)
+                .take_while(|&y| (x..x + w).all(|x| is_free(x, y)))
+                .count();
+            (w, h)
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                let c = rows[y][x];
+                if parsed[y][x] || !(c == 'X' || c.is_ascii_digit()) {
+                    continue;
+                }
+                let (w, h) = block(&parsed, x, y, c);
+
+                let object = if c == 'X' {
+                    Object::Obstacle {
+                        x: x as Coord,
+                        y: y as Coord,
+                        width: w as u8,
+                        height: h as u8,
+                    }
+                } else {
+                    // the digits are the inner cells of a deposit or factory, framed by its border
+                    let border: Vec<(usize, usize)> = (x.wrapping_sub(1)..=x + w)
+                        .flat_map(|bx| [(bx, y.wrapping_sub(1)), (bx, y + h)])
+                        .chain((y..y + h).flat_map(|by| [(x.wrapping_sub(1), by), (x + w, by)]))
+                        .collect();
+                    let frame = at(x.wrapping_sub(1), y.wrapping_sub(1));
+                    let is_framed = border.iter().all(|&(bx, by)| at(bx, by) == frame);
+                    let subtype = c.to_digit(10).unwrap() as u8;
+                    let (x, y) = (x as Coord - 1, y as Coord - 1);
+                    let object = match frame {
+                        Some('-') if is_framed => Object::Deposit {
+                            x,
+                            y,
+                            width: w as u8 + 2,
+                            height: h as u8 + 2,
+                            subtype,
+                        },
+                        Some('+') if is_framed && (w, h) == (3, 3) => {
+                            Object::Factory { x, y, subtype }
+                        }
+                        _ => {
+                            return Err(format!(
+                                "Cannot tell which object the cell '{}' at {:?} belongs to",
+                                c,
+                                (x + 1, y + 1)
+                            ))
+                        }
+                    };
+                    for (bx, by) in border {
+                        parsed[by][bx] = true;
+                    }
+                    object
+                };
+
+                for row in parsed.iter_mut().skip(y).take(h) {
+                    row[x..x + w].fill(true);
+                }
+                objects.push(object);
             }
         }
 
-        map
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                if c != '.' && !parsed[y][x] {
+                    return Err(format!(
+                        "Cannot tell which object the cell '{}' at {:?} belongs to",
+                        c,
+                        (x, y)
+                    ));
+                }
+            }
+        }
+
+        Map::try_new(width as u8, height as u8, objects)
     }
 
     /// Creates a 'layered map' above `map`
@@ -51,26 +288,173 @@ impl Map {
     /// A layered map can be used to add objects to a layer without effecting the lower layers.
     /// Calculations about if an object can be placed at a given location will lower layers into
     /// account.
+    ///
+    /// If `map` already has [MAX_LAYER_DEPTH] layers below it, its layers are compacted first (see
+    /// [Map::compact]).
     pub fn from_map(map: &Arc<Map>) -> Self {
+        let map = if map.depth >= MAX_LAYER_DEPTH {
+            Arc::new(map.compact())
+        } else {
+            Arc::clone(map)
+        };
+
         Self {
-            inner: Some(Arc::clone(map)),
             width: map.width,
             height: map.height,
             map: Default::default(),
+            index_offset: map.index_offset + map.objects.len() as u32,
             objects: Default::default(),
+            free_slots: Default::default(),
+            indices: Default::default(),
+            crossings: Default::default(),
+            adjacency: Default::default(),
+            zobrist: map.zobrist,
+            depth: map.depth + 1,
+            inner: Some(map),
+        }
+    }
+
+    /// Creates a map without layers, that contains the objects of this map and all lower layers
+    ///
+    /// Objects keep their indices.
+    pub fn flatten(&self) -> Map {
+        Map::merge_layers(&self.layers(), None)
+    }
+
+    /// Merges all layers above the lowest one into a single layer
+    ///
+    /// Other than [Map::flatten], the (usually large) lowest layer is shared instead of copied.
+    /// Objects keep their indices.
+    pub fn compact(&self) -> Map {
+        let layers = self.layers();
+        match layers.split_first() {
+            Some((_, upper_layers)) if upper_layers.len() > 1 => {
+                let lowest = upper_layers[0].inner.as_ref().map(Arc::clone);
+                Map::merge_layers(upper_layers, lowest)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Number of layers below this map
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// All layers of this map, from the lowest to this one
+    fn layers(&self) -> Vec<&Map> {
+        let mut layers = vec![self];
+        while let Some(ref inner) = layers.last().unwrap().inner {
+            layers.push(inner);
+        }
+        layers.reverse();
+        layers
+    }
+
+    /// Merges consecutive `layers` (ordered from lowest to highest) into a single layer above
+    /// `inner`
+    fn merge_layers(layers: &[&Map], inner: Option<Arc<Map>>) -> Map {
+        let lowest = layers[0];
+        let highest = layers[layers.len() - 1];
+
+        let mut merged = Map {
+            width: highest.width,
+            height: highest.height,
+            map: HashMap::default(),
+            index_offset: lowest.index_offset,
+            objects: Vec::new(),
+            free_slots: Vec::new(),
+            indices: HashMap::default(),
+            crossings: HashMap::default(),
+            adjacency: HashMap::default(),
+            zobrist: highest.zobrist,
+            depth: inner.as_ref().map(|inner| inner.depth + 1).unwrap_or(0),
+            inner,
+        };
+
+        for layer in layers {
+            for (point, cell) in layer.map.iter() {
+                // a cell of a higher layer above a cell of a lower layer is a crossing
+                if merged.map.insert(*point, cell.clone()).is_some() {
+                    *merged.crossings.entry(*point).or_default() += 1;
+                }
+            }
+            for (point, count) in layer.crossings.iter() {
+                *merged.crossings.entry(*point).or_default() += count;
+            }
+            for (point, cells) in layer.adjacency.iter() {
+                merged.adjacency.entry(*point).or_default().overlay(cells);
+            }
+
+            let slot_offset = layer.index_offset - merged.index_offset;
+            merged.objects.extend(layer.objects.iter().cloned());
+            merged
+                .free_slots
+                .extend(layer.free_slots.iter().map(|slot| slot + slot_offset));
+            merged.indices.extend(layer.indices.iter());
         }
+
+        merged
     }
 
     /// Returns an objects of this map
     ///
     /// Panics if the object identified by `id` cannot be found in this map's layer
     pub fn get_object(&self, id: ObjectID) -> &Object {
-        &self.objects[&id]
+        let index = self.indices[&id];
+        self.objects[index.index() - self.index_offset as usize]
+            .object
+            .as_ref()
+            .unwrap()
+    }
+
+    /// Returns the object stored at `index`
+    ///
+    /// This method will hook into lower layers, if `index` belongs to a lower layer.
+    pub fn get_object_by_index(&self, index: ObjectIndex) -> Option<&Object> {
+        match index.index().checked_sub(self.index_offset as usize) {
+            Some(slot) => self
+                .objects
+                .get(slot)
+                .filter(|slot| slot.generation == index.generation())
+                .and_then(|slot| slot.object.as_ref()),
+            None => self.inner.as_ref()?.get_object_by_index(index),
+        }
+    }
+
+    /// Returns the index of the object identified by `id`
+    ///
+    /// This method will hook into lower layers, if no object identified by `id` can be found at
+    /// the current layer.
+    pub fn get_object_index(&self, id: &ObjectID) -> Option<ObjectIndex> {
+        match self.indices.get(id) {
+            Some(index) => Some(*index),
+            None => self.inner.as_ref()?.get_object_index(id),
+        }
+    }
+
+    /// Upper bound of the (raw) indices of all objects of this map (including lower layers)
+    ///
+    /// Can be used to size dense per-object arrays.
+    pub fn num_object_indices(&self) -> usize {
+        self.index_offset as usize + self.objects.len()
     }
 
     /// Returns all objects stored in this map's layer
     pub fn get_objects(&self) -> impl Iterator<Item = &Object> {
-        self.objects.values()
+        self.objects.iter().filter_map(|slot| slot.object.as_ref())
+    }
+
+    /// Returns all objects stored in this map's layer together with their indices
+    pub fn get_indexed_objects(&self) -> impl Iterator<Item = (ObjectIndex, &Object)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(slot_index, slot)| {
+                let index =
+                    ObjectIndex::new(self.index_offset + slot_index as u32, slot.generation);
+                slot.object.as_ref().map(|object| (index, object))
+            })
     }
 
     /// Returns the cell at `(x,y)`
@@ -83,12 +467,58 @@ impl Map {
         })
     }
 
+    /// Returns the object occupying the cell at `(x,y)`
+    ///
+    /// If multiple conveyors cross at `(x,y)`, one of them is returned. This method will hook into
+    /// lower layers, if no cell can be found at the current layer.
+    pub fn object_at(&self, x: Coord, y: Coord) -> Option<&Object> {
+        self.get_cell(x, y)
+            .and_then(|cell| self.get_object_by_index(cell.index()))
+    }
+
+    /// Returns the ingress and egress cells next to `(x,y)`
+    ///
+    /// This is a lookup into an index that is maintained while objects are inserted and removed,
+    /// so it is cheaper than checking all [neighbours] with [Map::get_cell]. This method will hook
+    /// into lower layers.
+    pub fn adjacent_cells(&self, x: Coord, y: Coord) -> AdjacentCells {
+        let mut cells = match self.inner {
+            Some(ref inner) => inner.adjacent_cells(x, y),
+            None => AdjacentCells::default(),
+        };
+        if let Some(layer_cells) = self.adjacency.get(&(x, y)) {
+            cells.overlay(layer_cells);
+        }
+        cells
+    }
+
+    /// Returns all occupied cells of this map (including lower layers)
+    ///
+    /// Each point is returned once. Cells of higher layers hide the cells of lower layers.
+    pub fn iter_cells(&self) -> impl Iterator<Item = (Point, &ObjectCell)> + '_ {
+        self.cells_of_all_layers()
+    }
+
+    fn cells_of_all_layers(&self) -> Box<dyn Iterator<Item = (Point, &ObjectCell)> + '_> {
+        let cells = self.map.iter().map(|(point, cell)| (*point, cell));
+        match self.inner {
+            Some(ref inner) => Box::new(
+                cells.chain(
+                    inner
+                        .cells_of_all_layers()
+                        .filter(|(point, _)| !self.map.contains_key(point)),
+                ),
+            ),
+            None => Box::new(cells),
+        }
+    }
+
     /// Checks if this map already contains the object identified by `id`
     ///
     /// This method will hook into lower layers, if no object identified by `id` can be found at
     /// the current layer.
     pub fn contains_object(&self, id: &ObjectID) -> bool {
-        self.objects.contains_key(id)
+        self.indices.contains_key(id)
             || match self.inner {
                 Some(ref inner) => inner.contains_object(id),
                 None => false,
@@ -106,6 +536,29 @@ impl Map {
             && self.get_cell(x, y).is_none()
     }
 
+    /// Checks if the cell at `(x,y)` is the inner cell of a conveyor, which can be crossed by
+    /// another conveyor
+    ///
+    /// This method will hook into lower layers to check the cell.
+    pub fn is_crossable_at(&self, x: Coord, y: Coord) -> bool {
+        matches!(
+            self.get_cell(x, y),
+            Some(ObjectCell::Inner {
+                kind: ObjectType::Conveyor,
+                ..
+            })
+        )
+    }
+
+    /// Hash of all objects on this map (including lower layers)
+    ///
+    /// The hash is updated incrementally whenever an object is inserted or removed, so this is
+    /// cheap to call. Maps containing the same objects have the same hash, regardless of the
+    /// order (or layer) the objects were inserted in.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.zobrist
+    }
+
     /// The map's width
     pub fn width(&self) -> u8 {
         self.width
@@ -126,12 +579,7 @@ impl Map {
 
         self.can_insert_object(&object)?;
 
-        let cells = object.get_cells();
-        for ((x, y), cell) in cells {
-            self.map.insert((x, y), cell);
-        }
-
-        self.objects.insert(object.id(), object);
+        self.insert_into_arena(object);
 
         Ok(())
     }
@@ -140,16 +588,11 @@ impl Map {
     ///
     /// Returns `true` if this map did not contain `object` already
     pub fn insert_object_unchecked(&mut self, object: Object) -> bool {
-        if self.objects.contains_key(&object.id()) {
+        if self.indices.contains_key(&object.id()) {
             return false;
         }
 
-        let cells = object.get_cells();
-        for ((x, y), cell) in cells {
-            self.map.insert((x, y), cell);
-        }
-
-        self.objects.insert(object.id(), object);
+        self.insert_into_arena(object);
 
         true
     }
@@ -175,23 +618,166 @@ impl Map {
     }
 
     /// Remove an object from this map lyer
-    fn remove_object(&mut self, object: &Object) -> Result<(), String> {
-        if self.objects.remove(&object.id()).is_none() {
-            return Err(String::from(
-                "Cannot remove object. Map does not contain such object.",
-            ));
-        }
+    pub fn remove_object(&mut self, object: &Object) -> Result<(), String> {
+        let index = match self.indices.remove(&object.id()) {
+            Some(index) => index,
+            None => {
+                return Err(String::from(
+                    "Cannot remove object. Map does not contain such object.",
+                ))
+            }
+        };
 
-        for (point, _) in object.get_cells() {
-            self.map.remove(&point);
+        let slot_index = index.index() - self.index_offset as usize;
+        let slot = &mut self.objects[slot_index];
+        slot.object = None;
+        slot.generation += 1;
+        self.free_slots.push(slot_index as u32);
+
+        self.remove_adjacency(object, index);
+        for (point, _) in object.cells(index) {
+            match self.crossings.get_mut(&point) {
+                Some(count) => {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.crossings.remove(&point);
+                    }
+                    // the cell must belong to one of the objects that still cross it
+                    if self.map.get(&point).map(ObjectCell::index) == Some(index) {
+                        self.restore_cell(point);
+                    }
+                }
+                None => {
+                    self.map.remove(&point);
+                }
+            }
         }
+        self.zobrist ^= zobrist_key(object.id());
 
         Ok(())
     }
 
+    /// Index the next inserted object will be stored at
+    fn next_index(&self) -> ObjectIndex {
+        match self.free_slots.last() {
+            Some(&slot_index) => ObjectIndex::new(
+                self.index_offset + slot_index,
+                self.objects[slot_index as usize].generation,
+            ),
+            None => ObjectIndex::new(self.index_offset + self.objects.len() as u32, 0),
+        }
+    }
+
+    /// Stores `object` in this layer's arena and marks its cells as occupied
+    fn insert_into_arena(&mut self, object: Object) {
+        let index = self.next_index();
+        self.insert_cells(&object, index);
+        self.zobrist ^= zobrist_key(object.id());
+        self.indices.insert(object.id(), index);
+
+        match self.free_slots.pop() {
+            Some(slot_index) => self.objects[slot_index as usize].object = Some(object),
+            None => self.objects.push(ArenaSlot {
+                generation: 0,
+                object: Some(object),
+            }),
+        }
+    }
+
+    /// Marks the cells of `object` as occupied in this map layer
+    ///
+    /// Cells that are already occupied (by a crossing conveyor) are counted, so they stay occupied
+    /// until all of their objects got removed.
+    fn insert_cells(&mut self, object: &Object, index: ObjectIndex) {
+        for (point, cell) in object.cells(index) {
+            if !matches!(cell, ObjectCell::Inner { .. }) {
+                // `point` is the neighbour at the opposite position of each of its neighbours
+                for (i, neighbour) in neighbours(point.0, point.1).into_iter().enumerate() {
+                    self.adjacency.entry(neighbour).or_default().0[i ^ 1] =
+                        Some((point, cell.clone()));
+                }
+            }
+            if self.map.insert(point, cell).is_some() {
+                *self.crossings.entry(point).or_default() += 1;
+            }
+        }
+    }
+
+    /// Removes the ingress and egress cells of `object` from this layer's adjacency index
+    fn remove_adjacency(&mut self, object: &Object, index: ObjectIndex) {
+        for (point, cell) in object.cells(index) {
+            if matches!(cell, ObjectCell::Inner { .. }) {
+                continue;
+            }
+            for (i, neighbour) in neighbours(point.0, point.1).into_iter().enumerate() {
+                if let Some(cells) = self.adjacency.get_mut(&neighbour) {
+                    if matches!(&cells.0[i ^ 1], Some((_, cell)) if cell.index() == index) {
+                        cells.0[i ^ 1] = None;
+                    }
+                    if cells.is_empty() {
+                        self.adjacency.remove(&neighbour);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets the cell at `point` to the cell of any (other) conveyor of this layer crossing it
+    fn restore_cell(&mut self, point: Point) {
+        let cell = self
+            .get_indexed_objects()
+            .filter(|(_, object)| {
+                let (x, y) = object.coords();
+                // conveyors span at most 2 cells from their anchor
+                object.kind() == ObjectType::Conveyor
+                    && (x - point.0).abs() <= 2
+                    && (y - point.1).abs() <= 2
+            })
+            .find_map(|(index, object)| {
+                object
+                    .cells(index)
+                    .find(|(cell_point, _)| *cell_point == point)
+            });
+        if let Some((_, cell)) = cell {
+            self.map.insert(point, cell);
+        }
+    }
+
+    /// Returns the objects that must be removed from and added to this map to get `other`
+    ///
+    /// Objects of all layers are compared. Removed objects are listed before added objects.
+    pub fn diff(&self, other: &Map) -> Vec<DiffEntry> {
+        let objects = self.get_objects_of_all_layers();
+        let other_objects = other.get_objects_of_all_layers();
+
+        let removed = objects
+            .iter()
+            .filter(|(id, _)| !other_objects.contains_key(id))
+            .map(|(_, object)| DiffEntry::Removed((*object).clone()));
+        let added = other_objects
+            .iter()
+            .filter(|(id, _)| !objects.contains_key(id))
+            .map(|(_, object)| DiffEntry::Added((*object).clone()));
+
+        let mut diff: Vec<DiffEntry> = removed.chain(added).collect();
+        diff.sort();
+        diff
+    }
+
+    fn get_objects_of_all_layers(&self) -> HashMap<ObjectID, &Object> {
+        let mut objects = match self.inner {
+            Some(ref inner) => inner.get_objects_of_all_layers(),
+            None => HashMap::default(),
+        };
+        objects.extend(self.get_objects().map(|object| (object.id(), object)));
+        objects
+    }
+
     /// Checks if an object can be inserted onto this map
     ///
     /// This method will hook into lower layers to check if the object can be inserted.
+    /// Conveyors may cross each other, i.e. share their inner cells. Cells only touch if they
+    /// share an edge, so e.g. a factory may be placed diagonally next to a deposit's corner.
     pub fn can_insert_object(&self, object: &Object) -> Result<(), String> {
         if self.contains_object(&object.id()) {
             return Ok(());
@@ -200,12 +786,22 @@ impl Map {
         let width = self.width();
         let height = self.height();
 
+        // check that the object's bounding box is inside the map first, so all of its cells are
+        let ((left, top), (right, bottom)) = object.bounding_box();
+        if left < 0 || top < 0 || right >= width as Coord || bottom >= height as Coord {
+            return Err(format!("Cannot insert {:?} outside of the map", object));
+        }
+
+        if let Object::Deposit { subtype, .. } = object {
+            if *subtype as usize >= NUM_RESOURCE_TYPES {
+                return Err(format!("Invalid deposit subtype {}", subtype));
+            }
+        }
+
         // check that no part of object is outside map or placed over another building
-        let cells = object.get_cells();
-        for ((x, y), cell) in cells.iter() {
-            if *x < 0 || *y < 0 || *x >= width as Coord || *y >= height as Coord {
-                return Err(format!("Cannot insert cell at {:?}", (x, y)));
-            } else if let Some(old_cell) = self.get_cell(*x, *y) {
+        let index = self.next_index();
+        for ((x, y), cell) in object.cells(index) {
+            if let Some(old_cell) = self.get_cell(x, y) {
                 if !(matches!(
                     old_cell,
                     ObjectCell::Inner {
@@ -220,9 +816,9 @@ impl Map {
                     }
                 )) {
                     return Err(format!(
-                        "Cannot place {:?} above {:?} at {:?}",
-                        cell,
-                        old_cell,
+                        "Cannot place {:?} cell above {:?} cell at {:?}",
+                        cell.kind(),
+                        old_cell.kind(),
                         (x, y)
                     ));
                 }
@@ -231,16 +827,14 @@ impl Map {
 
         // check that the new part's ingress does not touch a deposits egress, unless it is a mine
         if object.kind() != ObjectType::Mine {
-            for (x, y) in object.ingresses() {
-                let neighbour_to_deposit = neighbours(x, y).iter().any(|coord| {
-                    matches!(
-                        self.get_cell(coord.0, coord.1),
-                        Some(ObjectCell::Egress {
-                            kind: ObjectType::Deposit,
-                            ..
-                        })
-                    )
-                });
+            for ((x, y), _) in object
+                .cells(index)
+                .filter(|(_, cell)| matches!(cell, ObjectCell::Ingress { .. }))
+            {
+                let neighbour_to_deposit = self
+                    .adjacent_cells(x, y)
+                    .egresses()
+                    .any(|(_, cell)| cell.kind() == ObjectType::Deposit);
                 if neighbour_to_deposit {
                     return Err(format!(
                         "Cannot place {:?} because its ingress touches a deposit's egress",
@@ -250,21 +844,32 @@ impl Map {
             }
         }
 
+        // check that the new deposit's egresses do not touch an ingress, unless it is a mine's
+        if object.kind() == ObjectType::Deposit {
+            for ((x, y), _) in object
+                .cells(index)
+                .filter(|(_, cell)| matches!(cell, ObjectCell::Egress { .. }))
+            {
+                let neighbour_to_ingress = self
+                    .adjacent_cells(x, y)
+                    .ingresses()
+                    .any(|(_, cell)| cell.kind() != ObjectType::Mine);
+                if neighbour_to_ingress {
+                    return Err(format!(
+                        "Cannot place {:?} because its egress touches an ingress other than a mine's",
+                        object,
+                    ));
+                }
+            }
+        }
+
         // check that the new part's egress does not touch multiple ingresses
         if object.kind() == ObjectType::Conveyor
             || object.kind() == ObjectType::Combiner
             || object.kind() == ObjectType::Mine
         {
             if let Some((x, y)) = object.egress() {
-                let num_neighbouring_ingresses = neighbours(x, y)
-                    .iter()
-                    .filter(|coord| {
-                        matches!(
-                            self.get_cell(coord.0, coord.1),
-                            Some(ObjectCell::Ingress { .. })
-                        )
-                    })
-                    .count();
+                let num_neighbouring_ingresses = self.adjacent_cells(x, y).ingresses().count();
 
                 if num_neighbouring_ingresses >= 2 {
                     return Err(format!(
@@ -278,25 +883,14 @@ impl Map {
         // check that the new part does not touch an egress (w/ its ingress), that is already
         // connected to another ingress
         for (x, y) in object.ingresses() {
-            let neighbouring_egresses = neighbours(x, y).into_iter().filter(|coord| {
-                matches!(
-                    self.get_cell(coord.0, coord.1),
-                    Some(ObjectCell::Egress { .. })
-                )
-            });
-
-            for egress in neighbouring_egresses {
-                let num_neighbouring_ingresses = neighbours(egress.0, egress.1)
-                    .iter()
-                    .filter(|coord| {
-                        matches!(
-                            self.get_cell(coord.0, coord.1),
-                            Some(ObjectCell::Ingress { .. })
-                        )
-                    })
-                    .count();
-
-                if num_neighbouring_ingresses >= 1 {
+            for (egress, _) in self.adjacent_cells(x, y).egresses() {
+                let is_connected = self
+                    .adjacent_cells(egress.0, egress.1)
+                    .ingresses()
+                    .next()
+                    .is_some();
+
+                if is_connected {
                     return Err(format!(
                         "Cannot place {:?} because its ingress touches an egress that is already connected to another ingress",
                         object,
@@ -307,15 +901,48 @@ impl Map {
 
         Ok(())
     }
+
+    /// Checks the placement rules of [Map::can_insert_object] for all objects of this map
+    ///
+    /// Objects inserted by [Map::insert_object_unchecked] may break these rules. The objects of
+    /// all layers are placed onto an empty map again, deposits and obstacles first, and every
+    /// object that cannot be placed is reported (but placed nonetheless, so later objects are
+    /// checked against it).
+    pub fn audit(&self) -> Vec<Violation> {
+        let layers = self.layers();
+        let objects = || layers.iter().flat_map(|layer| layer.get_objects());
+        let (landscape, buildings): (Vec<&Object>, Vec<&Object>) = objects()
+            .partition(|object| matches!(object, Object::Deposit { .. } | Object::Obstacle { .. }));
+
+        let mut map = Map::new(self.width, self.height, vec![]);
+        let mut violations = vec![];
+        for object in landscape.into_iter().chain(buildings) {
+            if let Err(reason) = map.can_insert_object(object) {
+                violations.push(Violation {
+                    object: object.clone(),
+                    reason,
+                });
+            }
+            map.insert_object_unchecked(object.clone());
+        }
+        violations
+    }
+}
+
+/// Pseudo random key of an object, used for zobrist hashing (see [Map::zobrist_hash])
+///
+/// Object ids are unique, so instead of a table of random values, the id is scrambled by a
+/// splitmix64 finalizer.
+fn zobrist_key(id: ObjectID) -> u64 {
+    let mut z = id.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
 }
 
 impl std::hash::Hash for Map {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        for x in 0..=self.width {
-            for y in 0..=self.height {
-                self.get_cell(x as i8, y as i8).hash(state)
-            }
-        }
+        self.zobrist.hash(state)
     }
 }
 
@@ -354,7 +981,7 @@ impl Display for Map {
 
 impl From<&Task> for Map {
     fn from(task: &Task) -> Self {
-        let objects = task.objects.iter().cloned().map(Object::from).collect();
+        let objects = task.objects.to_vec();
         Map::new(task.width, task.height, objects)
     }
 }
@@ -362,6 +989,7 @@ impl From<&Task> for Map {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype};
 
     #[test]
     fn all_pieces_can_be_placed_on_empty_map() {
@@ -400,7 +1028,7 @@ mod tests {
             assert!(result.is_ok());
         }
 
-        for subtype in 0..=3 {
+        for subtype in MineSubtype::ALL {
             let mut map = map.clone();
             let result = map.insert_object(Object::Mine {
                 x: 3,
@@ -410,7 +1038,7 @@ mod tests {
             assert!(result.is_ok());
         }
 
-        for subtype in 0..=7 {
+        for subtype in ConveyorSubtype::ALL {
             let mut map = map.clone();
             let result = map.insert_object(Object::Conveyor {
                 x: 3,
@@ -420,7 +1048,7 @@ mod tests {
             assert!(result.is_ok());
         }
 
-        for subtype in 0..=3 {
+        for subtype in CombinerSubtype::ALL {
             let mut map = map.clone();
             let result = map.insert_object(Object::Combiner {
                 x: 3,
@@ -477,7 +1105,7 @@ mod tests {
             assert!(result.is_err());
         }
 
-        for subtype in 0..=3 {
+        for subtype in MineSubtype::ALL {
             let mut map = map.clone();
             let result = map.insert_object(Object::Mine {
                 x: 3,
@@ -487,7 +1115,7 @@ mod tests {
             assert!(result.is_err());
         }
 
-        for subtype in 0..=7 {
+        for subtype in ConveyorSubtype::ALL {
             let mut map = map.clone();
             let result = map.insert_object(Object::Conveyor {
                 x: 3,
@@ -497,7 +1125,7 @@ mod tests {
             assert!(result.is_err());
         }
 
-        for subtype in 0..=3 {
+        for subtype in CombinerSubtype::ALL {
             let mut map = map.clone();
             let result = map.insert_object(Object::Combiner {
                 x: 3,
@@ -508,6 +1136,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn malformed_objects_are_rejected() {
+        let map = Map::new(100, 100, vec![]);
+        let obstacle = Object::Obstacle {
+            x: 90,
+            y: 0,
+            width: 100,
+            height: 1,
+        };
+        let factory = Object::Factory {
+            x: 127,
+            y: 0,
+            subtype: 0,
+        };
+        let deposit = Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 3,
+            height: 3,
+            subtype: 8,
+        };
+        for object in [obstacle, factory, deposit] {
+            assert!(map.can_insert_object(&object).is_err(), "{:?}", object);
+        }
+
+        assert!(Map::try_new(101, 10, vec![]).is_err());
+    }
+
     #[test]
     fn no_piece_can_be_placed_outside_map() {
         let map = Map::new(10, 10, vec![]);
@@ -542,19 +1198,19 @@ mod tests {
                 assert!(result.is_err());
             }
 
-            for subtype in 0..=3 {
+            for subtype in MineSubtype::ALL {
                 let mut map = map.clone();
                 let result = map.insert_object(Object::Mine { x, y, subtype });
                 assert!(result.is_err());
             }
 
-            for subtype in 0..=7 {
+            for subtype in ConveyorSubtype::ALL {
                 let mut map = map.clone();
                 let result = map.insert_object(Object::Conveyor { x, y, subtype });
                 assert!(result.is_err());
             }
 
-            for subtype in 0..=3 {
+            for subtype in CombinerSubtype::ALL {
                 let mut map = map.clone();
                 let result = map.insert_object(Object::Combiner { x, y, subtype });
                 assert!(result.is_err());
@@ -587,17 +1243,17 @@ mod tests {
             Object::Mine {
                 x: 3,
                 y: 3,
-                subtype: 0,
+                subtype: MineSubtype::East,
             },
             Object::Conveyor {
                 x: 3,
                 y: 3,
-                subtype: 0,
+                subtype: ConveyorSubtype::East,
             },
             Object::Combiner {
                 x: 3,
                 y: 3,
-                subtype: 0,
+                subtype: CombinerSubtype::East,
             },
         ];
 
@@ -628,7 +1284,7 @@ mod tests {
             let result = map.can_insert_object(&Object::Mine {
                 x: 1,
                 y: 0,
-                subtype: 0,
+                subtype: MineSubtype::East,
             });
             assert!(result.is_ok());
         }
@@ -642,12 +1298,12 @@ mod tests {
             Object::Conveyor {
                 x: 2,
                 y: 0,
-                subtype: 0,
+                subtype: ConveyorSubtype::East,
             },
             Object::Combiner {
                 x: 2,
                 y: 1,
-                subtype: 0,
+                subtype: CombinerSubtype::East,
             },
         ];
 
@@ -658,6 +1314,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deposit_cannot_touch_ingress_but_mine_with_egress() {
+        let deposit = Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+            subtype: 0,
+        };
+
+        let map = Map::new(
+            10,
+            10,
+            vec![Object::Factory {
+                x: 2,
+                y: 1,
+                subtype: 0,
+            }],
+        );
+        assert!(map.can_insert_object(&deposit).is_err());
+
+        // touches the factory only diagonally
+        let map = Map::new(
+            10,
+            10,
+            vec![Object::Factory {
+                x: 2,
+                y: 2,
+                subtype: 0,
+            }],
+        );
+        assert!(map.can_insert_object(&deposit).is_ok());
+
+        let map = Map::new(
+            10,
+            10,
+            vec![Object::Mine {
+                x: 3,
+                y: 0,
+                subtype: MineSubtype::East,
+            }],
+        );
+        assert!(map.can_insert_object(&deposit).is_ok());
+    }
+
     #[test]
     fn no_pieces_egress_can_touch_multiple_ingresses() {
         let map = Map::new(
@@ -667,12 +1368,12 @@ mod tests {
                 Object::Conveyor {
                     x: 6,
                     y: 3,
-                    subtype: 0,
+                    subtype: ConveyorSubtype::East,
                 },
                 Object::Conveyor {
                     x: 6,
                     y: 5,
-                    subtype: 0,
+                    subtype: ConveyorSubtype::East,
                 },
             ],
         );
@@ -681,17 +1382,17 @@ mod tests {
             Object::Mine {
                 x: 3,
                 y: 3,
-                subtype: 0,
+                subtype: MineSubtype::East,
             },
             Object::Conveyor {
                 x: 4,
                 y: 4,
-                subtype: 0,
+                subtype: ConveyorSubtype::East,
             },
             Object::Combiner {
                 x: 4,
                 y: 4,
-                subtype: 0,
+                subtype: CombinerSubtype::East,
             },
         ];
 
@@ -711,12 +1412,12 @@ mod tests {
                 Object::Conveyor {
                     x: 4,
                     y: 4,
-                    subtype: 0,
+                    subtype: ConveyorSubtype::East,
                 },
                 Object::Conveyor {
                     x: 6,
                     y: 5,
-                    subtype: 0,
+                    subtype: ConveyorSubtype::East,
                 },
             ],
         );
@@ -725,17 +1426,17 @@ mod tests {
             Object::Mine {
                 x: 6,
                 y: 2,
-                subtype: 0,
+                subtype: MineSubtype::East,
             },
             Object::Conveyor {
                 x: 6,
                 y: 3,
-                subtype: 0,
+                subtype: ConveyorSubtype::East,
             },
             Object::Combiner {
                 x: 6,
                 y: 2,
-                subtype: 0,
+                subtype: CombinerSubtype::East,
             },
         ];
 
@@ -745,4 +1446,373 @@ mod tests {
             assert!(result.is_err());
         }
     }
+
+    #[test]
+    fn diff_contains_added_and_removed_objects() {
+        let obstacle = Object::Obstacle {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let conveyor = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::East,
+        };
+        let factory = Object::Factory {
+            x: 5,
+            y: 0,
+            subtype: 0,
+        };
+        let map = Map::new(10, 10, vec![obstacle.clone(), conveyor.clone()]);
+
+        let mut other = Map::from_map(&Arc::new(Map::new(10, 10, vec![obstacle])));
+        other.insert_object(factory.clone()).unwrap();
+
+        assert_eq!(
+            map.diff(&other),
+            vec![DiffEntry::Removed(conveyor), DiffEntry::Added(factory)]
+        );
+        assert!(map.diff(&map).is_empty());
+    }
+
+    #[test]
+    fn zobrist_hash_only_depends_on_objects() {
+        let obstacle = Object::Obstacle {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let conveyor = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::East,
+        };
+
+        let map = Map::new(10, 10, vec![obstacle.clone(), conveyor.clone()]);
+        let other = Map::new(10, 10, vec![conveyor.clone(), obstacle.clone()]);
+        assert_eq!(map.zobrist_hash(), other.zobrist_hash());
+
+        let mut layered = Map::from_map(&Arc::new(Map::new(10, 10, vec![obstacle.clone()])));
+        layered.insert_object(conveyor.clone()).unwrap();
+        assert_eq!(map.zobrist_hash(), layered.zobrist_hash());
+
+        layered.remove_object(&conveyor).unwrap();
+        assert_eq!(
+            layered.zobrist_hash(),
+            Map::new(10, 10, vec![obstacle]).zobrist_hash()
+        );
+        assert_ne!(map.zobrist_hash(), layered.zobrist_hash());
+    }
+
+    #[test]
+    fn conveyors_can_cross() {
+        let horizontal = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::East,
+        };
+        let vertical = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::South,
+        };
+        let reversed = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::West,
+        };
+
+        let mut map = Map::new(10, 10, vec![horizontal.clone()]);
+        assert!(map.can_insert_object(&reversed).is_err());
+        map.insert_object(vertical.clone()).unwrap();
+
+        map.remove_object(&horizontal).unwrap();
+        assert!(!map.is_empty_at(4, 4));
+        map.remove_object(&vertical).unwrap();
+        assert!(map.is_empty_at(4, 4));
+
+        let mut layered = Map::from_map(&Arc::new(Map::new(10, 10, vec![horizontal])));
+        assert!(layered.insert_object(vertical).is_ok());
+    }
+
+    #[test]
+    fn object_at_returns_owner_of_cell() {
+        let obstacle = Object::Obstacle {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let horizontal = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::East,
+        };
+        let vertical = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::South,
+        };
+
+        let mut map = Map::from_map(&Arc::new(Map::new(10, 10, vec![obstacle.clone()])));
+        map.insert_object(horizontal.clone()).unwrap();
+        map.insert_object(vertical.clone()).unwrap();
+        assert_eq!(map.object_at(1, 1), Some(&obstacle));
+        assert_eq!(map.object_at(5, 5), None);
+
+        map.remove_object(&vertical).unwrap();
+        assert_eq!(map.object_at(4, 4), Some(&horizontal));
+
+        let mut points = map.iter_cells().map(|(point, _)| point).collect::<Vec<_>>();
+        points.sort_unstable();
+        let mut expected = obstacle.get_points();
+        expected.extend(horizontal.get_points());
+        expected.sort_unstable();
+        assert_eq!(points, expected);
+        for (point, cell) in map.iter_cells() {
+            assert_eq!(
+                map.object_at(point.0, point.1),
+                map.get_object_by_index(cell.index())
+            );
+        }
+    }
+
+    #[test]
+    fn deep_layers_are_compacted() {
+        let obstacle = Object::Obstacle {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        };
+        let horizontal = Object::Conveyor {
+            x: 2,
+            y: 4,
+            subtype: ConveyorSubtype::East,
+        };
+        let vertical = Object::Conveyor {
+            x: 2,
+            y: 4,
+            subtype: ConveyorSubtype::South,
+        };
+
+        let mut map = Arc::new(Map::new(100, 10, vec![obstacle, horizontal.clone()]));
+        let mut conveyors = vec![];
+        for i in 0..2 * MAX_LAYER_DEPTH as Coord {
+            let conveyor = Object::Conveyor {
+                x: 2 + 3 * i,
+                y: 8,
+                subtype: ConveyorSubtype::East,
+            };
+            let mut layer = Map::from_map(&map);
+            layer.insert_object(conveyor.clone()).unwrap();
+            if i == 0 {
+                layer.insert_object(vertical.clone()).unwrap();
+            }
+            assert!(layer.depth() <= MAX_LAYER_DEPTH);
+            conveyors.push((layer.get_object_index(&conveyor.id()).unwrap(), conveyor));
+            map = Arc::new(layer);
+        }
+
+        let mut flat = map.flatten();
+        assert_eq!(flat.depth(), 0);
+        assert_eq!(flat.zobrist_hash(), map.zobrist_hash());
+        for (index, conveyor) in conveyors.iter() {
+            assert_eq!(map.get_object_by_index(*index), Some(conveyor));
+            assert_eq!(flat.get_object_by_index(*index), Some(conveyor));
+        }
+        let mut cells = flat
+            .iter_cells()
+            .map(|(point, _)| point)
+            .collect::<Vec<_>>();
+        let mut layered_cells = map.iter_cells().map(|(point, _)| point).collect::<Vec<_>>();
+        cells.sort_unstable();
+        layered_cells.sort_unstable();
+        assert_eq!(cells, layered_cells);
+
+        // the crossing of conveyors of different layers is kept
+        flat.remove_object(&vertical).unwrap();
+        assert_eq!(flat.object_at(2, 4), Some(&horizontal));
+    }
+
+    #[test]
+    fn object_indices_are_reused_with_new_generation() {
+        let conveyor = Object::Conveyor {
+            x: 4,
+            y: 4,
+            subtype: ConveyorSubtype::East,
+        };
+        let other = Object::Conveyor {
+            x: 4,
+            y: 6,
+            subtype: ConveyorSubtype::East,
+        };
+
+        let mut map = Map::new(10, 10, vec![conveyor.clone()]);
+        let index = map.get_object_index(&conveyor.id()).unwrap();
+        assert_eq!(map.get_object_by_index(index), Some(&conveyor));
+
+        map.remove_object(&conveyor).unwrap();
+        map.insert_object(other.clone()).unwrap();
+        let other_index = map.get_object_index(&other.id()).unwrap();
+        assert_eq!(index.index(), other_index.index());
+        assert_eq!(map.get_object_by_index(index), None);
+        assert_eq!(map.get_object_by_index(other_index), Some(&other));
+
+        let mut layered = Map::from_map(&Arc::new(map));
+        layered.insert_object(conveyor.clone()).unwrap();
+        let layered_index = layered.get_object_index(&conveyor.id()).unwrap();
+        assert_ne!(layered_index.index(), other_index.index());
+        assert_eq!(layered.get_object_by_index(other_index), Some(&other));
+        assert_eq!(layered.num_object_indices(), 2);
+    }
+
+    #[test]
+    fn adjacent_cells_match_neighbouring_cells() {
+        let deposit = Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+            subtype: 0,
+        };
+        let mine = Object::Mine {
+            x: 3,
+            y: 0,
+            subtype: MineSubtype::East,
+        };
+        let conveyor = Object::Conveyor {
+            x: 7,
+            y: 2,
+            subtype: ConveyorSubtype::South,
+        };
+        let crossing = Object::Conveyor {
+            x: 6,
+            y: 2,
+            subtype: ConveyorSubtype::LongEast,
+        };
+        let combiner = Object::Combiner {
+            x: 4,
+            y: 6,
+            subtype: CombinerSubtype::West,
+        };
+
+        let map = Map::new(10, 10, vec![deposit, mine]);
+        let mut layered = Map::from_map(&Arc::new(map));
+        layered
+            .try_insert_objects(vec![conveyor.clone(), crossing, combiner])
+            .unwrap();
+        layered.remove_object(&conveyor).unwrap();
+
+        for map in [&layered, &layered.flatten()] {
+            for x in -1..=10 {
+                for y in -1..=10 {
+                    let mut expected: Vec<Point> = neighbours(x, y)
+                        .into_iter()
+                        .filter(|(nx, ny)| {
+                            matches!(
+                                map.get_cell(*nx, *ny),
+                                Some(ObjectCell::Ingress { .. } | ObjectCell::Egress { .. })
+                            )
+                        })
+                        .collect();
+                    let mut adjacent: Vec<Point> =
+                        map.adjacent_cells(x, y).iter().map(|(p, _)| p).collect();
+                    expected.sort_unstable();
+                    adjacent.sort_unstable();
+                    assert_eq!(adjacent, expected, "at {:?}", (x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn audit_reports_objects_inserted_against_the_rules() {
+        let deposit = Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+            subtype: 0,
+        };
+        // ingress touches the deposit
+        let touching = Object::Conveyor {
+            x: 2,
+            y: 0,
+            subtype: ConveyorSubtype::East,
+        };
+        let conveyor = Object::Conveyor {
+            x: 5,
+            y: 5,
+            subtype: ConveyorSubtype::East,
+        };
+        // occupies the conveyor's inner cell
+        let overlapping = Object::Combiner {
+            x: 5,
+            y: 5,
+            subtype: CombinerSubtype::South,
+        };
+
+        let map = Map::new(10, 10, vec![deposit, conveyor]);
+        assert!(map.audit().is_empty());
+
+        let mut layered = Map::from_map(&Arc::new(map));
+        layered.insert_object_unchecked(touching.clone());
+        layered.insert_object_unchecked(overlapping.clone());
+        let violations: Vec<Object> = layered
+            .audit()
+            .into_iter()
+            .map(|violation| violation.object)
+            .collect();
+        assert_eq!(violations, vec![touching, overlapping]);
+    }
+
+    #[test]
+    fn from_ascii() {
+        let objects = vec![
+            Object::Deposit {
+                x: 0,
+                y: 0,
+                width: 3,
+                height: 4,
+                subtype: 2,
+            },
+            Object::Obstacle {
+                x: 4,
+                y: 1,
+                width: 2,
+                height: 3,
+            },
+            Object::Factory {
+                x: 7,
+                y: 5,
+                subtype: 1,
+            },
+        ];
+        let map = Map::new(12, 10, objects.clone());
+
+        let parsed = Map::from_ascii(&map.to_string()).unwrap();
+        assert_eq!((12, 10), (parsed.width(), parsed.height()));
+        assert_eq!(objects, parsed.get_objects().cloned().collect::<Vec<_>>());
+
+        let sketch = "
+            -----..
+            -333-..
+            -----XX
+        ";
+        let sketch: String = sketch
+            .lines()
+            .map(|line| line.trim().to_string() + "\n")
+            .collect();
+        let parsed = Map::from_ascii(&sketch).unwrap();
+        assert_eq!((7, 3), (parsed.width(), parsed.height()));
+        assert_eq!(2, parsed.get_objects().count());
+
+        // conveyors cannot be told apart from other objects
+        assert!(Map::from_ascii("+O-\n...\n").is_err());
+        assert!(Map::from_ascii("...\n..\n").is_err());
+    }
 }
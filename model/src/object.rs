@@ -1,8 +1,9 @@
-use core::panic;
-
 use serde::{Deserialize, Serialize};
 
-use crate::coord::Point;
+use crate::{
+    coord::Point,
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
+};
 
 /// Object's x or y
 pub type Coord = i8;
@@ -16,9 +17,35 @@ pub type Subtype = u8;
 /// A unique ID that identifies an object
 pub type ObjectID = u64;
 
+/// Index of an object in a [Map](crate::map::Map)'s object arena
+///
+/// Slots of removed objects get reused, so the generation tells apart objects that were stored
+/// at the same slot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectIndex {
+    index: u32,
+    generation: u32,
+}
+
+impl ObjectIndex {
+    pub(crate) fn new(index: u32, generation: u32) -> Self {
+        ObjectIndex { index, generation }
+    }
+
+    /// The arena slot, that is unique among all objects currently stored on a map
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Object {
     #[serde(rename = "obstacle")]
     Obstacle {
@@ -39,7 +66,7 @@ pub enum Object {
     Mine {
         x: Coord,
         y: Coord,
-        subtype: Subtype,
+        subtype: MineSubtype,
     },
     #[serde(rename = "factory")]
     Factory {
@@ -51,118 +78,214 @@ pub enum Object {
     Conveyor {
         x: Coord,
         y: Coord,
-        subtype: Subtype,
+        subtype: ConveyorSubtype,
     },
     #[serde(rename = "combiner")]
     Combiner {
         x: Coord,
         y: Coord,
-        subtype: Subtype,
+        subtype: CombinerSubtype,
     },
 }
 
 impl Object {
     /// Creates a mine with the given subtype whose egress is at the specified location
-    pub fn mine_with_subtype_and_egress_at(subtype: u8, egress_position: Point) -> Object {
+    pub fn mine_with_subtype_and_egress_at(subtype: MineSubtype, egress_position: Point) -> Object {
         let (x, y) = egress_position;
         match subtype {
-            0 => Object::Mine {
+            MineSubtype::East => Object::Mine {
                 x: x - 2,
                 y: y - 1,
                 subtype,
             },
-            1 => Object::Mine {
+            MineSubtype::South => Object::Mine {
                 x,
                 y: y - 2,
                 subtype,
             },
-            2 => Object::Mine {
+            MineSubtype::West => Object::Mine {
                 x: x + 1,
                 y,
                 subtype,
             },
-            3 => Object::Mine {
+            MineSubtype::North => Object::Mine {
                 x: x - 1,
                 y: y + 1,
                 subtype,
             },
-            _ => panic!("Invalid mine subtype {}", subtype),
         }
     }
 
     /// Creates a conveyor with the given subtype whose egress is at the specified location
-    pub fn conveyor_with_subtype_and_egress_at(subtype: u8, egress_position: Point) -> Object {
+    pub fn conveyor_with_subtype_and_egress_at(
+        subtype: ConveyorSubtype,
+        egress_position: Point,
+    ) -> Object {
         let (x, y) = egress_position;
         match subtype {
-            0 => Object::Conveyor {
+            ConveyorSubtype::East => Object::Conveyor {
                 x: x - 1,
                 y,
                 subtype,
             },
-            1 => Object::Conveyor {
+            ConveyorSubtype::South => Object::Conveyor {
                 x,
                 y: y - 1,
                 subtype,
             },
-            2 => Object::Conveyor {
+            ConveyorSubtype::West => Object::Conveyor {
                 x: x + 1,
                 y,
                 subtype,
             },
-            3 => Object::Conveyor {
+            ConveyorSubtype::North => Object::Conveyor {
                 x,
                 y: y + 1,
                 subtype,
             },
-            4 => Object::Conveyor {
+            ConveyorSubtype::LongEast => Object::Conveyor {
                 x: x - 2,
                 y,
                 subtype,
             },
-            5 => Object::Conveyor {
+            ConveyorSubtype::LongSouth => Object::Conveyor {
                 x,
                 y: y - 2,
                 subtype,
             },
-            6 => Object::Conveyor {
+            ConveyorSubtype::LongWest => Object::Conveyor {
                 x: x + 1,
                 y,
                 subtype,
             },
-            7 => Object::Conveyor {
+            ConveyorSubtype::LongNorth => Object::Conveyor {
                 x,
                 y: y + 1,
                 subtype,
             },
-            _ => panic!("Invalid conveyor subtype {}", subtype),
         }
     }
 
     /// Creates a combiner with the given subtype whose egress is at the specified location
-    pub fn combiner_with_subtype_and_egress_at(subtype: u8, egress_position: Point) -> Object {
+    pub fn combiner_with_subtype_and_egress_at(
+        subtype: CombinerSubtype,
+        egress_position: Point,
+    ) -> Object {
         let (x, y) = egress_position;
         match subtype {
-            0 => Object::Combiner {
+            CombinerSubtype::East => Object::Combiner {
                 x: x - 1,
                 y,
                 subtype,
             },
-            1 => Object::Combiner {
+            CombinerSubtype::South => Object::Combiner {
                 x,
                 y: y - 1,
                 subtype,
             },
-            2 => Object::Combiner {
+            CombinerSubtype::West => Object::Combiner {
                 x: x + 1,
                 y,
                 subtype,
             },
-            3 => Object::Combiner {
+            CombinerSubtype::North => Object::Combiner {
                 x,
                 y: y + 1,
                 subtype,
             },
-            _ => panic!("Invalid combiner subtype {}", subtype),
+        }
+    }
+
+    /// Creates a mine with the given subtype whose ingress is at the specified location
+    pub fn mine_with_subtype_and_ingress_at(
+        subtype: MineSubtype,
+        ingress_position: Point,
+    ) -> Object {
+        let (dx, dy) = Object::mine_with_subtype_and_egress_at(subtype, (0, 0))
+            .ingress()
+            .unwrap();
+        Object::mine_with_subtype_and_egress_at(
+            subtype,
+            (ingress_position.0 - dx, ingress_position.1 - dy),
+        )
+    }
+
+    /// Creates a conveyor with the given subtype whose ingress is at the specified location
+    pub fn conveyor_with_subtype_and_ingress_at(
+        subtype: ConveyorSubtype,
+        ingress_position: Point,
+    ) -> Object {
+        let (dx, dy) = Object::conveyor_with_subtype_and_egress_at(subtype, (0, 0))
+            .ingress()
+            .unwrap();
+        Object::conveyor_with_subtype_and_egress_at(
+            subtype,
+            (ingress_position.0 - dx, ingress_position.1 - dy),
+        )
+    }
+
+    /// Creates a factory with the given subtype, the middle cell of whose `side` border is the
+    /// specified ingress
+    ///
+    /// E.g. with [Direction::West], resources can be passed to the factory from the cell west of
+    /// `ingress_position`.
+    pub fn factory_with_ingress_at(
+        subtype: Subtype,
+        ingress_position: Point,
+        side: Direction,
+    ) -> Object {
+        let (x, y) = ingress_position;
+        let (x, y) = match side {
+            Direction::East => (x - 4, y - 2),
+            Direction::South => (x - 2, y - 4),
+            Direction::West => (x, y - 2),
+            Direction::North => (x - 2, y),
+        };
+        Object::Factory { x, y, subtype }
+    }
+
+    /// Creates all factories with the given subtype that have an ingress at the specified location
+    pub fn factories_with_ingress_at(subtype: Subtype, ingress_position: Point) -> Vec<Object> {
+        let (x, y) = ingress_position;
+        (0..5)
+            .flat_map(|dx| (0..5).map(move |dy| (dx, dy)))
+            .filter(|&(dx, dy)| dx == 0 || dx == 4 || dy == 0 || dy == 4)
+            .map(|(dx, dy)| Object::Factory {
+                x: x - dx,
+                y: y - dy,
+                subtype,
+            })
+            .collect()
+    }
+
+    /// Creates a deposit with the given subtype and size, the middle cell of whose `side` border
+    /// is the specified egress
+    ///
+    /// E.g. with [Direction::East], a mine can take resources from the deposit at the cell east of
+    /// `egress_position`.
+    pub fn deposit_with_egress_at(
+        subtype: Subtype,
+        width: Length,
+        height: Length,
+        egress_position: Point,
+        side: Direction,
+    ) -> Object {
+        let (x, y) = egress_position;
+        // offsets of the last and the middle column / row
+        let (last_x, last_y) = (width as Coord - 1, height as Coord - 1);
+        let (middle_x, middle_y) = (width as Coord / 2, height as Coord / 2);
+        let (x, y) = match side {
+            Direction::East => (x - last_x, y - middle_y),
+            Direction::South => (x - middle_x, y - last_y),
+            Direction::West => (x, y - middle_y),
+            Direction::North => (x - middle_x, y),
+        };
+        Object::Deposit {
+            x,
+            y,
+            width,
+            height,
+            subtype,
         }
     }
 
@@ -220,6 +343,16 @@ impl Object {
         }
     }
 
+    /// Amount of resources this object holds at the start of a simulation
+    ///
+    /// Only deposits hold resources (5 units per cell).
+    pub fn initial_resources(&self) -> u32 {
+        match self {
+            Object::Deposit { width, height, .. } => *width as u32 * *height as u32 * 5,
+            _ => 0,
+        }
+    }
+
     pub fn kind(&self) -> ObjectType {
         match self {
             Object::Obstacle { .. } => ObjectType::Obstacle,
@@ -235,49 +368,114 @@ impl Object {
         match self {
             Object::Obstacle { .. } => None,
             Object::Deposit { subtype, .. } => Some(*subtype),
-            Object::Mine { subtype, .. } => Some(*subtype),
+            Object::Mine { subtype, .. } => Some(*subtype as Subtype),
             Object::Factory { subtype, .. } => Some(*subtype),
-            Object::Conveyor { subtype, .. } => Some(*subtype),
-            Object::Combiner { subtype, .. } => Some(*subtype),
+            Object::Conveyor { subtype, .. } => Some(*subtype as Subtype),
+            Object::Combiner { subtype, .. } => Some(*subtype as Subtype),
         }
     }
 
+    /// The cell resources enter this object at (for objects with a single ingress)
+    ///
+    /// None for objects without or with multiple ingresses (see [Object::ingresses]).
     pub fn ingress(&self) -> Option<Point> {
         match self {
-            Object::Mine { x, y, subtype: 0 } => Some((x - 1, y + 1)),
-            Object::Mine { x, y, subtype: 1 } => Some((*x, y - 1)),
-            Object::Mine { x, y, subtype: 2 } => Some((x + 2, *y)),
-            Object::Mine { x, y, subtype: 3 } => Some((x + 1, y + 2)),
-
-            Object::Conveyor { x, y, subtype: 0 } => Some((x - 1, *y)),
-            Object::Conveyor { x, y, subtype: 1 } => Some((*x, y - 1)),
-            Object::Conveyor { x, y, subtype: 2 } => Some((x + 1, *y)),
-            Object::Conveyor { x, y, subtype: 3 } => Some((*x, y + 1)),
-            Object::Conveyor { x, y, subtype: 4 } => Some((x - 1, *y)),
-            Object::Conveyor { x, y, subtype: 5 } => Some((*x, y - 1)),
-            Object::Conveyor { x, y, subtype: 6 } => Some((x + 2, *y)),
-            Object::Conveyor { x, y, subtype: 7 } => Some((*x, y + 2)),
-
-            Object::Deposit { .. } => None,
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::East,
+            } => Some((x - 1, y + 1)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::South,
+            } => Some((*x, y - 1)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::West,
+            } => Some((x + 2, *y)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::North,
+            } => Some((x + 1, y + 2)),
 
-            Object::Obstacle { .. } => None,
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::East,
+            } => Some((x - 1, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::South,
+            } => Some((*x, y - 1)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::West,
+            } => Some((x + 1, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::North,
+            } => Some((*x, y + 1)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongEast,
+            } => Some((x - 1, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongSouth,
+            } => Some((*x, y - 1)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongWest,
+            } => Some((x + 2, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongNorth,
+            } => Some((*x, y + 2)),
+
+            Object::Deposit { .. } | Object::Obstacle { .. } => None,
 
-            _ => todo!(),
+            Object::Factory { .. } | Object::Combiner { .. } => None,
         }
     }
 
     pub fn ingresses(&self) -> Vec<Point> {
         match self {
-            Object::Combiner { x, y, subtype: 0 } => {
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::East,
+            } => {
                 vec![(x - 1, y - 1), (x - 1, *y), (x - 1, y + 1)]
             }
-            Object::Combiner { x, y, subtype: 1 } => {
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::South,
+            } => {
                 vec![(x - 1, y - 1), (*x, y - 1), (x + 1, y - 1)]
             }
-            Object::Combiner { x, y, subtype: 2 } => {
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::West,
+            } => {
                 vec![(x + 1, y - 1), (x + 1, *y), (x + 1, y + 1)]
             }
-            Object::Combiner { x, y, subtype: 3 } => {
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::North,
+            } => {
                 vec![(x - 1, y + 1), (*x, y + 1), (x + 1, y + 1)]
             }
 
@@ -302,33 +500,99 @@ impl Object {
         }
     }
 
-    //FIXME: rename -> egress
+    /// The cell resources leave this object at (for objects with a single egress)
+    ///
+    /// None for objects without or with multiple egresses (see [Object::egresses]).
     pub fn egress(&self) -> Option<Point> {
         match self {
-            Object::Mine { x, y, subtype: 0 } => Some((x + 2, y + 1)),
-            Object::Mine { x, y, subtype: 1 } => Some((*x, y + 2)),
-            Object::Mine { x, y, subtype: 2 } => Some((x - 1, *y)),
-            Object::Mine { x, y, subtype: 3 } => Some((x + 1, y - 1)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::East,
+            } => Some((x + 2, y + 1)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::South,
+            } => Some((*x, y + 2)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::West,
+            } => Some((x - 1, *y)),
+            Object::Mine {
+                x,
+                y,
+                subtype: MineSubtype::North,
+            } => Some((x + 1, y - 1)),
 
-            Object::Conveyor { x, y, subtype: 0 } => Some((x + 1, *y)),
-            Object::Conveyor { x, y, subtype: 1 } => Some((*x, y + 1)),
-            Object::Conveyor { x, y, subtype: 2 } => Some((x - 1, *y)),
-            Object::Conveyor { x, y, subtype: 3 } => Some((*x, y - 1)),
-            Object::Conveyor { x, y, subtype: 4 } => Some((x + 2, *y)),
-            Object::Conveyor { x, y, subtype: 5 } => Some((*x, y + 2)),
-            Object::Conveyor { x, y, subtype: 6 } => Some((x - 1, *y)),
-            Object::Conveyor { x, y, subtype: 7 } => Some((*x, y - 1)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::East,
+            } => Some((x + 1, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::South,
+            } => Some((*x, y + 1)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::West,
+            } => Some((x - 1, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::North,
+            } => Some((*x, y - 1)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongEast,
+            } => Some((x + 2, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongSouth,
+            } => Some((*x, y + 2)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongWest,
+            } => Some((x - 1, *y)),
+            Object::Conveyor {
+                x,
+                y,
+                subtype: ConveyorSubtype::LongNorth,
+            } => Some((*x, y - 1)),
 
-            Object::Combiner { x, y, subtype: 0 } => Some((x + 1, *y)),
-            Object::Combiner { x, y, subtype: 1 } => Some((*x, y + 1)),
-            Object::Combiner { x, y, subtype: 2 } => Some((x - 1, *y)),
-            Object::Combiner { x, y, subtype: 3 } => Some((*x, y - 1)),
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::East,
+            } => Some((x + 1, *y)),
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::South,
+            } => Some((*x, y + 1)),
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::West,
+            } => Some((x - 1, *y)),
+            Object::Combiner {
+                x,
+                y,
+                subtype: CombinerSubtype::North,
+            } => Some((*x, y - 1)),
 
-            _ => todo!(),
+            Object::Obstacle { .. } | Object::Deposit { .. } | Object::Factory { .. } => None,
         }
     }
 
-    //FIXME: rename -> egresses
+    /// All cells resources leave this object at
     pub fn egresses(&self) -> Vec<Point> {
         match self {
             Object::Deposit {
@@ -353,571 +617,338 @@ impl Object {
         }
     }
 
-    /// Calculates the fields occupied by this object
-    pub fn get_cells(&self) -> Vec<(Point, ObjectCell)> {
-        use Object::*;
-        use ObjectCell::*;
-
-        let id = self.id();
+    /// Direction this object's egress points to (for mines, conveyors and combiners)
+    pub fn direction(&self) -> Option<Direction> {
+        match self {
+            Object::Mine { subtype, .. } => Some(subtype.direction()),
+            Object::Conveyor { subtype, .. } => Some(subtype.direction()),
+            Object::Combiner { subtype, .. } => Some(subtype.direction()),
+            _ => None,
+        }
+    }
 
+    /// Returns this object, turned by 90° clockwise around its anchor (`x`, `y`)
+    ///
+    /// Mines, conveyors and combiners keep their kind (i.e. conveyors keep their length). All other
+    /// objects cannot be turned and are returned unchanged.
+    pub fn rotated(&self) -> Object {
         match *self {
-            Obstacle {
+            Object::Mine { x, y, subtype } => Object::Mine {
                 x,
                 y,
-                width,
-                height,
-            } => {
-                let mut cells = Vec::new();
-                for px in x..(x + width as Coord) {
-                    for py in y..(y + height as Coord) {
-                        cells.push((
-                            (px, py),
-                            Inner {
-                                kind: ObjectType::Obstacle,
-                                subtype: None,
-                            },
-                        ));
-                    }
-                }
-                cells
-            }
-            Deposit {
+                subtype: subtype.rotated(),
+            },
+            Object::Conveyor { x, y, subtype } => Object::Conveyor {
                 x,
                 y,
-                width,
-                height,
-                subtype,
+                subtype: subtype.rotated(),
+            },
+            Object::Combiner { x, y, subtype } => Object::Combiner {
+                x,
+                y,
+                subtype: subtype.rotated(),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// Returns this object, moved by `dx` and `dy`
+    pub fn translated(&self, dx: Coord, dy: Coord) -> Object {
+        let mut object = self.clone();
+        match object {
+            Object::Obstacle {
+                ref mut x,
+                ref mut y,
+                ..
+            }
+            | Object::Deposit {
+                ref mut x,
+                ref mut y,
+                ..
+            }
+            | Object::Mine {
+                ref mut x,
+                ref mut y,
+                ..
+            }
+            | Object::Factory {
+                ref mut x,
+                ref mut y,
+                ..
+            }
+            | Object::Conveyor {
+                ref mut x,
+                ref mut y,
+                ..
+            }
+            | Object::Combiner {
+                ref mut x,
+                ref mut y,
+                ..
             } => {
-                let mut cells = Vec::with_capacity(25);
-                for px in x..(x + width as Coord) {
-                    for py in y..(y + height as Coord) {
-                        if px == x
-                            || px == (x + width as Coord - 1)
-                            || py == y
-                            || py == (y + height as Coord - 1)
-                        {
-                            cells.push((
-                                (px, py),
-                                Egress {
-                                    kind: ObjectType::Deposit,
-                                    id,
-                                },
-                            ));
-                        } else {
-                            cells.push((
-                                (px, py),
-                                Inner {
-                                    kind: ObjectType::Deposit,
-                                    subtype: Some(subtype),
-                                },
-                            ));
-                        };
-                    }
-                }
-                cells
+                *x += dx;
+                *y += dy;
             }
-            Factory { x, y, subtype } => {
-                let mut cells = Vec::with_capacity(25);
-                for px in x..(x + 5) {
-                    for py in y..(y + 5) {
-                        if px == x || px == (x + 4) || py == y || py == (y + 4) {
-                            cells.push((
-                                (px, py),
-                                Ingress {
-                                    kind: ObjectType::Factory,
-                                    id,
-                                },
-                            ));
-                        } else {
-                            cells.push((
-                                (px, py),
-                                Inner {
-                                    kind: ObjectType::Factory,
-                                    subtype: Some(subtype),
-                                },
-                            ));
-                        };
+        }
+        object
+    }
+
+    /// Smallest rectangle containing all cells of this object, as its top left and bottom right
+    /// (inclusive) corners
+    pub fn bounding_box(&self) -> (Point, Point) {
+        use Object::*;
+
+        let (x, y) = self.coords();
+        let (left, top, right, bottom) = match *self {
+            Obstacle { width, height, .. } | Deposit { width, height, .. } => {
+                (0, 0, width as Coord - 1, height as Coord - 1)
+            }
+            Factory { .. } => (0, 0, 4, 4),
+            Mine { subtype, .. } => match subtype {
+                MineSubtype::East | MineSubtype::West => (-1, 0, 2, 1),
+                MineSubtype::South | MineSubtype::North => (0, -1, 1, 2),
+            },
+            Conveyor { subtype, .. } => match subtype {
+                ConveyorSubtype::East | ConveyorSubtype::West => (-1, 0, 1, 0),
+                ConveyorSubtype::South | ConveyorSubtype::North => (0, -1, 0, 1),
+                ConveyorSubtype::LongEast | ConveyorSubtype::LongWest => (-1, 0, 2, 0),
+                ConveyorSubtype::LongSouth | ConveyorSubtype::LongNorth => (0, -1, 0, 2),
+            },
+            Combiner { .. } => (-1, -1, 1, 1),
+        };
+        // saturate, so objects anchored near the coordinate limits stay outside of any map
+        (
+            (x.saturating_add(left), y.saturating_add(top)),
+            (x.saturating_add(right), y.saturating_add(bottom)),
+        )
+    }
+
+    /// Checks if this object shares any cell with `other`
+    ///
+    /// Unlike [Map::can_insert_object](crate::map::Map::can_insert_object), this does not allow
+    /// conveyors to cross each other.
+    pub fn overlaps(&self, other: &Object) -> bool {
+        let ((left, top), (right, bottom)) = self.bounding_box();
+        let ((other_left, other_top), (other_right, other_bottom)) = other.bounding_box();
+        if right < other_left || other_right < left || bottom < other_top || other_bottom < top {
+            return false;
+        }
+
+        let inside = |((left, top), (right, bottom)): (Point, Point), (x, y): Point| {
+            left <= x && x <= right && top <= y && y <= bottom
+        };
+        match (self.sparse_points(), other.sparse_points()) {
+            (None, None) => true,
+            (Some(points), None) => points.iter().any(|&p| inside(other.bounding_box(), p)),
+            (None, Some(points)) => points.iter().any(|&p| inside(self.bounding_box(), p)),
+            (Some(points), Some(other_points)) => points.iter().any(|p| other_points.contains(p)),
+        }
+    }
+
+    /// The points of objects that do not fill their bounding box (i.e. mines and combiners)
+    ///
+    /// Mines occupy six cells only, so their root cell is repeated.
+    fn sparse_points(&self) -> Option<[Point; 7]> {
+        let (x, y) = self.coords();
+        let offsets = match *self {
+            Object::Mine { subtype, .. } => {
+                let (ingress, egress) = match subtype {
+                    MineSubtype::East => ((-1, 1), (2, 1)),
+                    MineSubtype::South => ((0, -1), (0, 2)),
+                    MineSubtype::West => ((2, 0), (-1, 0)),
+                    MineSubtype::North => ((1, 2), (1, -1)),
+                };
+                [(0, 0), (1, 0), (0, 1), (1, 1), ingress, egress, (0, 0)]
+            }
+            Object::Combiner { subtype, .. } => {
+                let mut offsets = [(0, 0), (-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0)];
+                for _ in 0..subtype as Subtype {
+                    for (dx, dy) in offsets.iter_mut() {
+                        (*dx, *dy) = (-*dy, *dx);
                     }
                 }
-                cells
+                offsets
             }
-            Mine { x, y, subtype } => {
-                if subtype == 0 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x - 1, y + 1),
-                            Ingress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 2, y + 1),
-                            Egress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 1 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y - 1),
-                            Ingress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                        (
-                            (x, y + 2),
-                            Egress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 2 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x - 1, y),
-                            Egress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 2, y),
-                            Ingress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 3 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y - 1),
-                            Egress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 1, y + 2),
-                            Ingress {
-                                kind: ObjectType::Mine,
-                                id,
-                            },
-                        ),
-                    ]
-                } else {
-                    panic!("Invalid mine subtype: {}", subtype);
-                }
+            _ => return None,
+        };
+        Some(offsets.map(|(dx, dy)| (x + dx, y + dy)))
+    }
+
+    /// Iterates the fields occupied by this object, when stored at `index` of a map
+    ///
+    /// Unlike [Object::get_cells], this does not allocate.
+    pub fn cells(&self, index: ObjectIndex) -> impl Iterator<Item = (Point, ObjectCell)> {
+        use ObjectCell::*;
+
+        // obstacles, deposits and factories fill their bounding box and only differ at its border
+        let rectangle = match *self {
+            Object::Obstacle { .. } => {
+                let inner = Inner {
+                    kind: ObjectType::Obstacle,
+                    subtype: None,
+                    index,
+                };
+                Some((inner.clone(), inner))
             }
-            Combiner { x, y, subtype } => {
-                let mut points = vec![
-                    (
-                        (0, 0),
-                        Inner {
-                            kind: ObjectType::Combiner,
-                            subtype: Some(subtype),
-                        },
-                    ), // root cell
-                    (
-                        (-1, -1),
+            Object::Deposit { subtype, .. } => Some((
+                Egress {
+                    kind: ObjectType::Deposit,
+                    index,
+                },
+                Inner {
+                    kind: ObjectType::Deposit,
+                    subtype: Some(subtype),
+                    index,
+                },
+            )),
+            Object::Factory { subtype, .. } => Some((
+                Ingress {
+                    kind: ObjectType::Factory,
+                    index,
+                },
+                Inner {
+                    kind: ObjectType::Factory,
+                    subtype: Some(subtype),
+                    index,
+                },
+            )),
+            _ => None,
+        };
+        let ((left, top), (right, bottom)) = self.bounding_box();
+        let rectangle_cells = rectangle.into_iter().flat_map(move |(border, inner)| {
+            (left..=right).flat_map(move |px| {
+                let (border, inner) = (border.clone(), inner.clone());
+                (top..=bottom).map(move |py| {
+                    let cell = if px == left || px == right || py == top || py == bottom {
+                        border.clone()
+                    } else {
+                        inner.clone()
+                    };
+                    ((px, py), cell)
+                })
+            })
+        });
+
+        rectangle_cells.chain(self.building_cells(index).into_iter().flatten())
+    }
+
+    /// The fields occupied by a mine, conveyor or combiner (none for other objects)
+    fn building_cells(&self, index: ObjectIndex) -> [Option<(Point, ObjectCell)>; 7] {
+        use ObjectCell::*;
+
+        let inner = |kind, subtype| Inner {
+            kind,
+            subtype: Some(subtype),
+            index,
+        };
+        let cells: [Option<(Point, ObjectCell)>; 7] = match *self {
+            Object::Mine { subtype, .. } => {
+                let (ingress, egress) = match subtype {
+                    MineSubtype::East => ((-1, 1), (2, 1)),
+                    MineSubtype::South => ((0, -1), (0, 2)),
+                    MineSubtype::West => ((2, 0), (-1, 0)),
+                    MineSubtype::North => ((1, 2), (1, -1)),
+                };
+                let inner = inner(ObjectType::Combiner, subtype as Subtype);
+                [
+                    Some(((0, 0), inner.clone())),
+                    Some(((1, 0), inner.clone())),
+                    Some(((0, 1), inner.clone())),
+                    Some(((1, 1), inner)),
+                    Some((
+                        ingress,
                         Ingress {
-                            kind: ObjectType::Combiner,
-                            id,
+                            kind: ObjectType::Mine,
+                            index,
                         },
-                    ),
-                    (
-                        (-1, 0),
-                        Ingress {
-                            kind: ObjectType::Combiner,
-                            id,
+                    )),
+                    Some((
+                        egress,
+                        Egress {
+                            kind: ObjectType::Mine,
+                            index,
                         },
-                    ),
-                    (
-                        (-1, 1),
+                    )),
+                    None,
+                ]
+            }
+            Object::Conveyor { subtype, .. } => {
+                let (second_inner, ingress, egress) = match subtype {
+                    ConveyorSubtype::East => (None, (-1, 0), (1, 0)),
+                    ConveyorSubtype::South => (None, (0, -1), (0, 1)),
+                    ConveyorSubtype::West => (None, (1, 0), (-1, 0)),
+                    ConveyorSubtype::North => (None, (0, 1), (0, -1)),
+                    ConveyorSubtype::LongEast => (Some((1, 0)), (-1, 0), (2, 0)),
+                    ConveyorSubtype::LongSouth => (Some((0, 1)), (0, -1), (0, 2)),
+                    ConveyorSubtype::LongWest => (Some((1, 0)), (2, 0), (-1, 0)),
+                    ConveyorSubtype::LongNorth => (Some((0, 1)), (0, 2), (0, -1)),
+                };
+                let inner = inner(ObjectType::Conveyor, subtype as Subtype);
+                [
+                    Some(((0, 0), inner.clone())),
+                    second_inner.map(|point| (point, inner)),
+                    Some((
+                        ingress,
                         Ingress {
-                            kind: ObjectType::Combiner,
-                            id,
-                        },
-                    ),
-                    (
-                        (0, -1),
-                        Inner {
-                            kind: ObjectType::Combiner,
-                            subtype: Some(subtype),
+                            kind: ObjectType::Conveyor,
+                            index,
                         },
-                    ),
-                    (
-                        (0, 1),
-                        Inner {
-                            kind: ObjectType::Combiner,
-                            subtype: Some(subtype),
-                        },
-                    ),
-                    (
-                        (1, 0),
+                    )),
+                    Some((
+                        egress,
                         Egress {
-                            kind: ObjectType::Combiner,
-                            id,
+                            kind: ObjectType::Conveyor,
+                            index,
                         },
-                    ),
+                    )),
+                    None,
+                    None,
+                    None,
+                ]
+            }
+            Object::Combiner { subtype, .. } => {
+                let inner = inner(ObjectType::Combiner, subtype as Subtype);
+                let ingress = Ingress {
+                    kind: ObjectType::Combiner,
+                    index,
+                };
+                let egress = Egress {
+                    kind: ObjectType::Combiner,
+                    index,
+                };
+                let mut cells = [
+                    ((0, 0), inner.clone()), // root cell
+                    ((-1, -1), ingress.clone()),
+                    ((-1, 0), ingress.clone()),
+                    ((-1, 1), ingress),
+                    ((0, -1), inner.clone()),
+                    ((0, 1), inner),
+                    ((1, 0), egress),
                 ];
-
-                for _ in 0..subtype {
-                    for ((x, y), _) in points.iter_mut() {
-                        let tmp = *y;
-                        *y = *x;
-                        *x = -tmp;
+                for _ in 0..subtype as Subtype {
+                    for ((dx, dy), _) in cells.iter_mut() {
+                        (*dx, *dy) = (-*dy, *dx);
                     }
                 }
-
-                points
-                    .into_iter()
-                    .map(|((dx, dy), cell)| (((x as Coord + dx), (y as Coord + dy)), cell))
-                    .collect()
-            }
-            Conveyor { x, y, subtype } => {
-                if subtype == 0 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x - 1, y),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 1 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y - 1),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 2 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x - 1, y),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 3 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y - 1),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 4 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x - 1, y),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 2, y),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 5 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y - 1),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x, y + 2),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 6 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x + 1, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x - 1, y),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x + 2, y),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else if subtype == 7 {
-                    vec![
-                        (
-                            (x, y),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y + 1),
-                            Inner {
-                                kind: ObjectType::Combiner,
-                                subtype: Some(subtype),
-                            },
-                        ),
-                        (
-                            (x, y - 1),
-                            Egress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                        (
-                            (x, y + 2),
-                            Ingress {
-                                kind: ObjectType::Conveyor,
-                                id,
-                            },
-                        ),
-                    ]
-                } else {
-                    panic!("Invalid conveyor subtype: {}", subtype);
-                }
+                cells.map(Some)
             }
-        }
+            _ => Default::default(),
+        };
+
+        let (x, y) = self.coords();
+        cells.map(|cell| cell.map(|((dx, dy), cell)| ((x + dx, y + dy), cell)))
+    }
+
+    /// Calculates the points occupied by this object
+    pub fn get_points(&self) -> Vec<Point> {
+        self.cells(ObjectIndex::default())
+            .map(|(point, _)| point)
+            .collect()
+    }
+
+    /// Calculates the fields occupied by this object, when stored at `index` of a map
+    pub fn get_cells(&self, index: ObjectIndex) -> Vec<(Point, ObjectCell)> {
+        self.cells(index).collect()
     }
 }
 
@@ -931,6 +962,51 @@ pub enum ObjectType {
     Combiner,
 }
 
+/// Direction resources flow through an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    East,
+    South,
+    West,
+    North,
+}
+
+impl Direction {
+    /// Direction of a mine, conveyor or combiner with the given subtype
+    ///
+    /// The direction is the same for short and long conveyors (e.g. subtype 0 and 4).
+    pub fn from_subtype(subtype: Subtype) -> Direction {
+        match subtype % 4 {
+            0 => Direction::East,
+            1 => Direction::South,
+            2 => Direction::West,
+            _ => Direction::North,
+        }
+    }
+
+    /// Offset of a single step into this direction
+    pub fn offset(&self) -> Point {
+        match self {
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+            Direction::North => (0, -1),
+        }
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Direction::East => "east",
+            Direction::South => "south",
+            Direction::West => "west",
+            Direction::North => "north",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 impl From<ObjectType> for String {
     fn from(kind: ObjectType) -> Self {
         match kind {
@@ -948,18 +1024,39 @@ impl From<ObjectType> for String {
 pub enum ObjectCell {
     Egress {
         kind: ObjectType,
-        id: ObjectID,
+        index: ObjectIndex,
     },
     Ingress {
         kind: ObjectType,
-        id: ObjectID,
+        index: ObjectIndex,
     },
     Inner {
         kind: ObjectType,
         subtype: Option<u8>,
+        index: ObjectIndex,
     },
 }
 
+impl ObjectCell {
+    /// Type of the object this cell belongs to
+    pub fn kind(&self) -> ObjectType {
+        match self {
+            ObjectCell::Egress { kind, .. }
+            | ObjectCell::Ingress { kind, .. }
+            | ObjectCell::Inner { kind, .. } => kind.clone(),
+        }
+    }
+
+    /// Index of the object this cell belongs to
+    pub fn index(&self) -> ObjectIndex {
+        match self {
+            ObjectCell::Egress { index, .. }
+            | ObjectCell::Ingress { index, .. }
+            | ObjectCell::Inner { index, .. } => *index,
+        }
+    }
+}
+
 impl From<&ObjectCell> for char {
     fn from(cell: &ObjectCell) -> char {
         match cell {
@@ -972,10 +1069,12 @@ impl From<&ObjectCell> for char {
             ObjectCell::Inner {
                 kind: ObjectType::Factory,
                 subtype: Some(st),
+                ..
             } => char::from_digit(*st as u32, 10).unwrap(),
             ObjectCell::Inner {
                 kind: ObjectType::Deposit,
                 subtype: Some(st),
+                ..
             } => char::from_digit(*st as u32, 10).unwrap(),
             ObjectCell::Inner { .. } => 'O',
         }
@@ -997,7 +1096,10 @@ mod test {
             height: height as u8,
             subtype: 0,
         };
-        let cells: HashMap<Point, ObjectCell> = object.get_cells().into_iter().collect();
+        let cells: HashMap<Point, ObjectCell> = object
+            .get_cells(ObjectIndex::default())
+            .into_iter()
+            .collect();
 
         for x in 0..=4 {
             assert!(matches!(cells[&(x, 0)], ObjectCell::Egress { .. }));
@@ -1015,4 +1117,241 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn bounding_box_and_overlaps() {
+        let mut objects = vec![
+            Object::Obstacle {
+                x: 8,
+                y: 9,
+                width: 3,
+                height: 2,
+            },
+            Object::Deposit {
+                x: 9,
+                y: 8,
+                width: 2,
+                height: 3,
+                subtype: 0,
+            },
+            Object::Factory {
+                x: 7,
+                y: 8,
+                subtype: 0,
+            },
+        ];
+        for subtype in MineSubtype::ALL {
+            objects.push(Object::Mine {
+                x: 10,
+                y: 10,
+                subtype,
+            });
+        }
+        for subtype in ConveyorSubtype::ALL {
+            objects.push(Object::Conveyor {
+                x: 10,
+                y: 10,
+                subtype,
+            });
+        }
+        for subtype in CombinerSubtype::ALL {
+            objects.push(Object::Combiner {
+                x: 10,
+                y: 10,
+                subtype,
+            });
+        }
+
+        for object in objects.iter() {
+            let points = object.get_points();
+            let left = points.iter().map(|p| p.0).min().unwrap();
+            let top = points.iter().map(|p| p.1).min().unwrap();
+            let right = points.iter().map(|p| p.0).max().unwrap();
+            let bottom = points.iter().map(|p| p.1).max().unwrap();
+            assert_eq!(
+                object.bounding_box(),
+                ((left, top), (right, bottom)),
+                "{:?}",
+                object
+            );
+
+            for other in objects.iter() {
+                for (dx, dy) in (-5..=5).flat_map(|dx| (-5..=5).map(move |dy| (dx, dy))) {
+                    let other = other.translated(dx, dy);
+                    let expected = other.get_points().iter().any(|p| points.contains(p));
+                    assert_eq!(
+                        object.overlaps(&other),
+                        expected,
+                        "{:?} {:?}",
+                        object,
+                        other
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn objects_with_ingress_at() {
+        for subtype in MineSubtype::ALL {
+            let mine = Object::mine_with_subtype_and_ingress_at(subtype, (10, 10));
+            assert_eq!(mine.ingress(), Some((10, 10)));
+        }
+
+        for subtype in ConveyorSubtype::ALL {
+            let conveyor = Object::conveyor_with_subtype_and_ingress_at(subtype, (10, 10));
+            assert_eq!(conveyor.ingress(), Some((10, 10)));
+        }
+
+        let factory = Object::Factory {
+            x: 0,
+            y: 0,
+            subtype: 0,
+        };
+        assert_eq!(factory.ingress(), None);
+        assert_eq!(factory.egress(), None);
+
+        for side in [
+            Direction::East,
+            Direction::South,
+            Direction::West,
+            Direction::North,
+        ] {
+            // the cell next to the border, from which resources are passed in / taken out
+            let (dx, dy) = side.offset();
+            let outside = (10 + dx, 10 + dy);
+
+            let factory = Object::factory_with_ingress_at(0, (10, 10), side);
+            assert!(factory.ingresses().contains(&(10, 10)));
+            assert!(!factory.get_points().contains(&outside));
+
+            let deposit = Object::deposit_with_egress_at(1, 4, 3, (10, 10), side);
+            assert!(deposit.egresses().contains(&(10, 10)));
+            assert!(!deposit.get_points().contains(&outside));
+        }
+
+        let factories = Object::factories_with_ingress_at(0, (10, 10));
+        assert_eq!(factories.len(), 16);
+        for factory in factories {
+            assert!(factory.ingresses().contains(&(10, 10)));
+        }
+    }
+
+    #[test]
+    fn direction_points_from_ingress_to_egress() {
+        let objects = MineSubtype::ALL
+            .into_iter()
+            .map(|subtype| Object::Mine {
+                x: 10,
+                y: 10,
+                subtype,
+            })
+            .chain(
+                ConveyorSubtype::ALL
+                    .into_iter()
+                    .map(|subtype| Object::Conveyor {
+                        x: 10,
+                        y: 10,
+                        subtype,
+                    }),
+            );
+
+        for object in objects {
+            let (ix, iy) = object.ingress().unwrap();
+            let (ex, ey) = object.egress().unwrap();
+            let (dx, dy) = object.direction().unwrap().offset();
+            assert_eq!(((ex - ix).signum(), (ey - iy).signum()), (dx, dy));
+        }
+
+        for subtype in CombinerSubtype::ALL {
+            let combiner = Object::Combiner {
+                x: 10,
+                y: 10,
+                subtype,
+            };
+            let (ex, ey) = combiner.egress().unwrap();
+            assert_eq!(combiner.direction().unwrap().offset(), (ex - 10, ey - 10));
+        }
+
+        assert_eq!(
+            Object::Factory {
+                x: 0,
+                y: 0,
+                subtype: 0
+            }
+            .direction(),
+            None
+        );
+    }
+
+    #[test]
+    fn rotated_and_translated_objects() {
+        let objects = MineSubtype::ALL
+            .into_iter()
+            .map(|subtype| Object::Mine {
+                x: 10,
+                y: 10,
+                subtype,
+            })
+            .chain(
+                ConveyorSubtype::ALL
+                    .into_iter()
+                    .map(|subtype| Object::Conveyor {
+                        x: 10,
+                        y: 10,
+                        subtype,
+                    }),
+            )
+            .chain(
+                CombinerSubtype::ALL
+                    .into_iter()
+                    .map(|subtype| Object::Combiner {
+                        x: 10,
+                        y: 10,
+                        subtype,
+                    }),
+            );
+
+        for object in objects {
+            let rotated = object.rotated();
+            assert_eq!(rotated.kind(), object.kind());
+            assert_eq!(rotated.coords(), object.coords());
+            let (dx, dy) = object.direction().unwrap().offset();
+            // turning clockwise maps (dx, dy) to (-dy, dx)
+            assert_eq!(rotated.direction().unwrap().offset(), (-dy, dx));
+            assert_eq!(rotated.rotated().rotated().rotated(), object);
+            if let (Object::Conveyor { subtype, .. }, Object::Conveyor { subtype: other, .. }) =
+                (&object, &rotated)
+            {
+                assert_eq!(subtype.is_long(), other.is_long());
+            }
+
+            let translated = object.translated(2, -3);
+            assert_eq!(translated.coords(), (12, 7));
+            let (ex, ey) = object.egress().unwrap();
+            assert_eq!(translated.egress(), Some((ex + 2, ey - 3)));
+        }
+
+        let factory = Object::Factory {
+            x: 0,
+            y: 0,
+            subtype: 1,
+        };
+        assert_eq!(factory.rotated(), factory);
+    }
+
+    #[test]
+    fn subtypes_are_serialized_as_numbers() {
+        let conveyor = Object::Conveyor {
+            x: 1,
+            y: 2,
+            subtype: ConveyorSubtype::LongSouth,
+        };
+        let json = serde_json::to_string(&conveyor).unwrap();
+        assert_eq!(json, r#"{"type":"conveyor","x":1,"y":2,"subtype":5}"#);
+        assert_eq!(serde_json::from_str::<Object>(&json).unwrap(), conveyor);
+
+        let invalid = r#"{"type":"mine","x":1,"y":2,"subtype":4}"#;
+        assert!(serde_json::from_str::<Object>(invalid).is_err());
+    }
 }
@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 use serde_json;
 
-use crate::object::Object;
+use crate::{map::Map, object::Object};
 
-#[derive(Deserialize, Serialize, Default, Debug, Clone)]
+#[derive(Deserialize, Serialize, Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Solution(pub Vec<Object>);
 
 impl Solution {
@@ -13,9 +14,22 @@ impl Solution {
         serde_json::from_str(&s)
     }
 
+    pub fn from_json_string(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
     pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
+
+    /// Sorts the objects canonically and removes duplicate placements
+    ///
+    /// Solutions that place the same objects are equal (and hash equally) after normalization,
+    /// regardless of the order in which the objects were placed.
+    pub fn normalize(&mut self) {
+        self.0.sort();
+        self.0.dedup();
+    }
 }
 
 impl<T> From<T> for Solution
@@ -26,3 +40,14 @@ where
         Solution(objects.into_iter().collect())
     }
 }
+
+impl From<&Map> for Solution {
+    /// Creates a solution from all objects of `map` that are no landscape objects (deposits and
+    /// obstacles)
+    fn from(map: &Map) -> Self {
+        map.get_objects()
+            .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
+            .cloned()
+            .into()
+    }
+}
@@ -0,0 +1,32 @@
+//! Handling of malformed input
+//!
+//! Input that breaks the rules of the contest (e.g. a factory of an unknown product) is a bug
+//! while developing, but must not crash a binary that is fed by the contest judge. In strict mode
+//! such input panics where it is detected, otherwise it is turned into an error or a score of 0.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STRICT: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+
+/// Process wide toggle between panicking on malformed input and handling it gracefully
+///
+/// Strict mode is enabled by default in debug builds.
+pub struct StrictMode;
+
+impl StrictMode {
+    pub fn set_enabled(enabled: bool) {
+        STRICT.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled() -> bool {
+        STRICT.load(Ordering::Relaxed)
+    }
+
+    /// Panics with `message` in strict mode, otherwise returns it as error
+    pub fn fail<T>(message: String) -> Result<T, String> {
+        if StrictMode::is_enabled() {
+            panic!("{}", message);
+        }
+        Err(message)
+    }
+}
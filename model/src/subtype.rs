@@ -0,0 +1,128 @@
+//! Typed subtypes of rotatable objects
+//!
+//! The subtypes are (de)serialized as their numeric value, so the JSON format stays the same.
+//! Subtypes that do not exist are rejected while parsing, instead of causing panics later on.
+
+use serde::{Deserialize, Serialize};
+
+use crate::object::{Direction, Subtype};
+
+/// Implements the conversions from and to the numeric subtype
+macro_rules! numeric_subtype {
+    ($name: ident, $kind: expr, [$($variant: ident),+]) => {
+        impl $name {
+            /// All subtypes, ordered by their numeric value
+            pub const ALL: [$name; [$($name::$variant),+].len()] = [$($name::$variant),+];
+
+            /// Direction the egress points to
+            pub fn direction(&self) -> Direction {
+                Direction::from_subtype(*self as Subtype)
+            }
+        }
+
+        impl TryFrom<Subtype> for $name {
+            type Error = String;
+
+            fn try_from(subtype: Subtype) -> Result<Self, Self::Error> {
+                $name::ALL
+                    .get(subtype as usize)
+                    .copied()
+                    .ok_or_else(|| format!("Invalid {} subtype {}", $kind, subtype))
+            }
+        }
+
+        impl From<$name> for Subtype {
+            fn from(subtype: $name) -> Self {
+                subtype as Subtype
+            }
+        }
+
+        #[cfg(feature = "schema")]
+        impl schemars::JsonSchema for $name {
+            fn schema_name() -> String {
+                String::from(stringify!($name))
+            }
+
+            fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+                let mut schema = Subtype::json_schema(gen).into_object();
+                schema.number().maximum = Some(($name::ALL.len() - 1) as f64);
+                schema.into()
+            }
+        }
+    };
+}
+
+/// Subtype of a mine, named by the direction of its egress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Subtype", into = "Subtype")]
+#[repr(u8)]
+pub enum MineSubtype {
+    East = 0,
+    South = 1,
+    West = 2,
+    North = 3,
+}
+
+numeric_subtype!(MineSubtype, "mine", [East, South, West, North]);
+
+/// Subtype of a conveyor, named by the direction of its egress
+///
+/// Short conveyors occupy 3 cells, long ones 4 cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Subtype", into = "Subtype")]
+#[repr(u8)]
+pub enum ConveyorSubtype {
+    East = 0,
+    South = 1,
+    West = 2,
+    North = 3,
+    LongEast = 4,
+    LongSouth = 5,
+    LongWest = 6,
+    LongNorth = 7,
+}
+
+numeric_subtype!(
+    ConveyorSubtype,
+    "conveyor",
+    [East, South, West, North, LongEast, LongSouth, LongWest, LongNorth]
+);
+
+/// Subtype of a combiner, named by the direction of its egress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Subtype", into = "Subtype")]
+#[repr(u8)]
+pub enum CombinerSubtype {
+    East = 0,
+    South = 1,
+    West = 2,
+    North = 3,
+}
+
+numeric_subtype!(CombinerSubtype, "combiner", [East, South, West, North]);
+
+impl MineSubtype {
+    /// The subtype turned by 90° clockwise
+    pub fn rotated(&self) -> MineSubtype {
+        MineSubtype::ALL[(*self as usize + 1) % 4]
+    }
+}
+
+impl ConveyorSubtype {
+    pub fn is_long(&self) -> bool {
+        *self as Subtype >= 4
+    }
+
+    /// The subtype turned by 90° clockwise, keeping the conveyor's length
+    pub fn rotated(&self) -> ConveyorSubtype {
+        let subtype = *self as usize;
+        ConveyorSubtype::ALL[subtype - subtype % 4 + (subtype + 1) % 4]
+    }
+}
+
+impl CombinerSubtype {
+    /// The subtype turned by 90° clockwise
+    pub fn rotated(&self) -> CombinerSubtype {
+        CombinerSubtype::ALL[(*self as usize + 1) % 4]
+    }
+}
@@ -1,13 +1,20 @@
-use serde::{Deserialize, Serialize};
+use fxhash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
 use serde_json;
 
-use crate::object::Object;
+use crate::{
+    analysis::NUM_RESOURCE_TYPES,
+    object::{Object, Subtype},
+};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Task {
     pub width: u8,
     pub height: u8,
     pub objects: Vec<Object>,
+    /// Products with unique subtypes, each needing exactly [NUM_RESOURCE_TYPES] resource amounts
+    #[serde(deserialize_with = "deserialize_products")]
     pub products: Vec<Product>,
     pub turns: u32,
     pub time: Option<u32>, //TODO: check if this is not optional
@@ -20,12 +27,55 @@ impl Task {
         serde_json::from_str(&s)
     }
 
+    pub fn from_json_string(s: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(s)
+    }
+
     pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
+
+    /// Lookup of this task's products by subtype
+    pub fn product_catalog(&self) -> ProductCatalog<'_> {
+        ProductCatalog::new(&self.products)
+    }
+}
+
+/// Deserializes products, rejects malformed recipes and pads their resources to
+/// [NUM_RESOURCE_TYPES] entries
+fn deserialize_products<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Product>, D::Error> {
+    let mut products = Vec::<Product>::deserialize(deserializer)?;
+    let mut subtypes = HashSet::default();
+    for product in products.iter_mut() {
+        if product.resources.len() > NUM_RESOURCE_TYPES {
+            return Err(D::Error::custom(format!(
+                "product {} needs {} resource types, but only {} exist",
+                product.subtype,
+                product.resources.len(),
+                NUM_RESOURCE_TYPES
+            )));
+        }
+        if product.points == 0 {
+            return Err(D::Error::custom(format!(
+                "product {} is worth no points",
+                product.subtype
+            )));
+        }
+        if !subtypes.insert(product.subtype) {
+            return Err(D::Error::custom(format!(
+                "product {} is defined more than once",
+                product.subtype
+            )));
+        }
+        product.resources.resize(NUM_RESOURCE_TYPES, 0);
+    }
+    Ok(products)
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Product {
     #[serde(rename = "type")]
     pub kind: String,
@@ -33,3 +83,71 @@ pub struct Product {
     pub resources: Vec<u32>,
     pub points: u32,
 }
+
+/// Lookup of products by subtype
+///
+/// Products are neither ordered nor numbered by subtype (and the solver shuffles them), so they must
+/// never be indexed by subtype.
+#[derive(Debug, Clone, Default)]
+pub struct ProductCatalog<'t> {
+    by_subtype: HashMap<Subtype, &'t Product>,
+}
+
+impl<'t> ProductCatalog<'t> {
+    pub fn new(products: &'t [Product]) -> Self {
+        ProductCatalog {
+            by_subtype: products
+                .iter()
+                .map(|product| (product.subtype, product))
+                .collect(),
+        }
+    }
+
+    /// The product of `subtype`
+    pub fn by_subtype(&self, subtype: Subtype) -> Option<&'t Product> {
+        self.by_subtype.get(&subtype).copied()
+    }
+
+    /// The product `factory` produces, or None if `factory` is no factory
+    pub fn of_factory(&self, factory: &Object) -> Option<&'t Product> {
+        match factory {
+            Object::Factory { subtype, .. } => self.by_subtype(*subtype),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task_with_products(products: &str) -> Result<Task, serde_json::Error> {
+        Task::from_json_string(&format!(
+            r#"{{"width":10,"height":10,"objects":[],"products":{},"turns":10}}"#,
+            products
+        ))
+    }
+
+    #[test]
+    fn normalizes_and_validates_products() {
+        let task = task_with_products(
+            r#"[{"type":"product","subtype":3,"resources":[1,2],"points":5},
+                {"type":"product","subtype":1,"resources":[0,0,0,0,0,0,0,4],"points":1}]"#,
+        )
+        .unwrap();
+        assert_eq!(task.products[0].resources, vec![1, 2, 0, 0, 0, 0, 0, 0]);
+        let catalog = task.product_catalog();
+        assert_eq!(catalog.by_subtype(1).map(|product| product.points), Some(1));
+        assert_eq!(catalog.by_subtype(3).map(|product| product.points), Some(5));
+        assert!(catalog.by_subtype(0).is_none());
+
+        for products in [
+            r#"[{"type":"product","subtype":0,"resources":[1,0,0,0,0,0,0,0,1],"points":5}]"#,
+            r#"[{"type":"product","subtype":0,"resources":[1],"points":0}]"#,
+            r#"[{"type":"product","subtype":0,"resources":[1],"points":5},
+                {"type":"product","subtype":0,"resources":[2],"points":5}]"#,
+        ] {
+            assert!(task_with_products(products).is_err(), "{}", products);
+        }
+    }
+}
@@ -2,4 +2,25 @@ use clap::Parser;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-pub(crate) struct Args {}
+pub(crate) struct Args {
+    #[arg(
+        long,
+        help = "Compare the input against the solution in the given file and print the difference"
+    )]
+    pub diff: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "diff",
+        help = "Read a map drawn as ASCII (as printed by default) and print it as task (without products)"
+    )]
+    pub parse: bool,
+
+    #[cfg(feature = "image")]
+    #[arg(long, help = "Write the map as png image to the given path")]
+    pub png: Option<String>,
+
+    #[cfg(feature = "image")]
+    #[arg(long, default_value_t = 8, help = "Size of a single cell in pixels")]
+    pub scale: u32,
+}
@@ -0,0 +1,73 @@
+//! Colored rendering of the difference between two maps
+
+use std::collections::HashSet;
+
+use model::{
+    coord::Point,
+    map::{DiffEntry, Map},
+    object::Coord,
+};
+
+const COLOR_ADDED: &str = "\x1b[32m";
+const COLOR_REMOVED: &str = "\x1b[31m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Renders `after` with the cells of added objects colored green and the cells of removed
+/// objects (taken from `before`) colored red, followed by a list of all changed objects
+pub(crate) fn render(before: &Map, after: &Map) -> String {
+    let diff = before.diff(after);
+
+    let mut added_cells: HashSet<Point> = HashSet::new();
+    let mut removed_cells: HashSet<Point> = HashSet::new();
+    for entry in diff.iter() {
+        match entry {
+            DiffEntry::Added(object) => added_cells.extend(object.get_points()),
+            DiffEntry::Removed(object) => removed_cells.extend(object.get_points()),
+        }
+    }
+
+    let mut out = String::new();
+
+    out.push_str("   ");
+    for i in 0..after.width() {
+        out.push_str(&format!("{}", i / 10));
+    }
+    out.push_str("\n   ");
+    for i in 0..after.width() {
+        out.push_str(&format!("{}", i % 10));
+    }
+    out.push('\n');
+
+    for y in 0..after.height() as Coord {
+        out.push_str(&format!("{:0>2} ", y));
+        for x in 0..after.width() as Coord {
+            let (color, cell) = if added_cells.contains(&(x, y)) {
+                (Some(COLOR_ADDED), after.get_cell(x, y))
+            } else if removed_cells.contains(&(x, y)) && after.get_cell(x, y).is_none() {
+                (Some(COLOR_REMOVED), before.get_cell(x, y))
+            } else {
+                (None, after.get_cell(x, y))
+            };
+            let c = cell.map(|cell| cell.into()).unwrap_or('.');
+            match color {
+                Some(color) => out.push_str(&format!("{}{}{}", color, c, COLOR_RESET)),
+                None => out.push(c),
+            }
+        }
+        out.push('\n');
+    }
+
+    out.push('\n');
+    for entry in diff.iter() {
+        match entry {
+            DiffEntry::Added(object) => {
+                out.push_str(&format!("{}+ {:?}{}\n", COLOR_ADDED, object, COLOR_RESET))
+            }
+            DiffEntry::Removed(object) => {
+                out.push_str(&format!("{}- {:?}{}\n", COLOR_REMOVED, object, COLOR_RESET))
+            }
+        }
+    }
+
+    out
+}
@@ -1,16 +1,72 @@
 mod cli;
+mod diff;
+#[cfg(feature = "image")]
+mod png;
 
-use model::{input::read_input_from_stdin, map::Map, object::Object};
+use clap::Parser;
+
+use cli::Args;
+use model::{
+    input::{read_input_from_file, read_input_from_stdin},
+    map::Map,
+    solution::Solution,
+    task::Task,
+};
 
 fn main() {
+    let args = Args::parse();
+    if args.parse {
+        parse_ascii();
+        return;
+    }
+
     let (task, solution) = read_input_from_stdin().unwrap();
+    let map = build_map(task, solution);
+
+    if let Some(path) = args.diff {
+        let (task, solution) = read_input_from_file(&path).unwrap();
+        let before = build_map(task, solution);
+        print!("{}", diff::render(&before, &map));
+        return;
+    }
+
+    #[cfg(feature = "image")]
+    if let Some(path) = args.png {
+        png::render(&map, args.scale)
+            .save(&path)
+            .unwrap_or_else(|e| panic!("Cannot write image to {}: {}", path, e));
+        return;
+    }
+
+    println!("{}", map);
+}
+
+fn build_map(task: Task, solution: Option<Solution>) -> Map {
     let solution = solution.unwrap_or_default();
 
     let mut objects = Vec::with_capacity(task.objects.len() + solution.0.len());
-    objects.extend(task.objects.into_iter().map(Object::from));
-    objects.extend(solution.0.into_iter().map(Object::from));
+    objects.extend(task.objects);
+    objects.extend(solution.0);
 
-    let map = Map::new(task.width, task.height, objects);
+    Map::new(task.width, task.height, objects)
+}
 
-    println!("{}", map);
+/// Prints the map drawn as ASCII on stdin as task with the drawn objects
+///
+/// Products and turns are left empty, so they can be filled in by hand.
+fn parse_ascii() {
+    let ascii = std::io::read_to_string(std::io::stdin()).expect("Cannot read stdin");
+    let map = Map::from_ascii(&ascii).unwrap_or_else(|e| {
+        eprintln!("Cannot parse map: {}", e);
+        std::process::exit(1);
+    });
+    let task = Task {
+        width: map.width(),
+        height: map.height(),
+        objects: map.get_objects().cloned().collect(),
+        products: vec![],
+        turns: 0,
+        time: None,
+    };
+    println!("{}", task.to_json_string().unwrap());
 }
@@ -0,0 +1,90 @@
+//! Raster image export of maps
+
+use image::{Rgb, RgbImage};
+use model::{
+    map::Map,
+    object::{Object, ObjectCell},
+};
+
+/// Colors of deposits and factories, indexed by subtype
+const SUBTYPE_COLORS: [[u8; 3]; 8] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+];
+
+const BACKGROUND_COLOR: [u8; 3] = [255, 255, 255];
+const OBSTACLE_COLOR: [u8; 3] = [64, 64, 64];
+const MINE_COLOR: [u8; 3] = [139, 69, 19];
+const CONVEYOR_COLOR: [u8; 3] = [160, 160, 160];
+const COMBINER_COLOR: [u8; 3] = [100, 100, 160];
+const INGRESS_MARKER_COLOR: [u8; 3] = [255, 255, 255];
+const EGRESS_MARKER_COLOR: [u8; 3] = [0, 0, 0];
+
+/// Renders `map` into an image, where each cell is drawn as a `scale` x `scale` square
+///
+/// Ingresses are marked by a white, egresses by a black square in the cell's center.
+pub(crate) fn render(map: &Map, scale: u32) -> RgbImage {
+    let mut image = RgbImage::from_pixel(
+        map.width() as u32 * scale,
+        map.height() as u32 * scale,
+        Rgb(BACKGROUND_COLOR),
+    );
+
+    for (index, object) in map.get_indexed_objects() {
+        let color = object_color(object);
+        for ((x, y), cell) in object.cells(index) {
+            if x < 0 || y < 0 || x as u8 >= map.width() || y as u8 >= map.height() {
+                continue;
+            }
+
+            let (px, py) = (x as u32 * scale, y as u32 * scale);
+            fill_square(&mut image, px, py, scale, color);
+
+            let marker_color = match cell {
+                ObjectCell::Ingress { .. } => Some(INGRESS_MARKER_COLOR),
+                ObjectCell::Egress { .. } => Some(EGRESS_MARKER_COLOR),
+                ObjectCell::Inner { .. } => None,
+            };
+            if let Some(marker_color) = marker_color {
+                let marker_size = (scale / 3).max(1);
+                let offset = (scale - marker_size) / 2;
+                fill_square(
+                    &mut image,
+                    px + offset,
+                    py + offset,
+                    marker_size,
+                    marker_color,
+                );
+            }
+        }
+    }
+
+    image
+}
+
+fn object_color(object: &Object) -> [u8; 3] {
+    match object {
+        Object::Obstacle { .. } => OBSTACLE_COLOR,
+        Object::Deposit { subtype, .. } => SUBTYPE_COLORS[*subtype as usize % 8],
+        Object::Factory { subtype, .. } => {
+            SUBTYPE_COLORS[*subtype as usize % 8].map(|channel| channel / 2)
+        }
+        Object::Mine { .. } => MINE_COLOR,
+        Object::Conveyor { .. } => CONVEYOR_COLOR,
+        Object::Combiner { .. } => COMBINER_COLOR,
+    }
+}
+
+fn fill_square(image: &mut RgbImage, x: u32, y: u32, size: u32, color: [u8; 3]) {
+    for dx in 0..size {
+        for dy in 0..size {
+            image.put_pixel(x + dx, y + dy, Rgb(color));
+        }
+    }
+}
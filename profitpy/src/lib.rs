@@ -0,0 +1,100 @@
+//! Python bindings of the simulator and the solver
+//!
+//! Build with [maturin](https://www.maturin.rs/) (`maturin develop -m profitpy/Cargo.toml`) and use
+//! from python:
+//! ```python
+//! import profitpy
+//! solution = profitpy.solve(task_json, 10, seed=42)
+//! profitpy.simulate(task_json, solution)["score"]
+//! ```
+
+use std::{thread, time::Duration};
+
+use model::{map::Map, solution::Solution, task::Task};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+use simulator::generate_map;
+use solver::{run_solver, RunOptions, SolverConfig};
+
+/// Simulates the solution `solution_json` of the task `task_json`
+///
+/// Returns a dict with the score, the turn the score was reached, warnings and statistics of each
+/// product.
+#[pyfunction]
+fn simulate<'py>(
+    py: Python<'py>,
+    task_json: &str,
+    solution_json: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let task = parse_task(task_json)?;
+    let solution = Solution::from_json_string(solution_json)
+        .map_err(|e| PyValueError::new_err(format!("Invalid solution: {}", e)))?;
+
+    let map = generate_map(&task, &solution).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let result = py.allow_threads(|| simulator::simulate(&task, &map, true));
+
+    let products = PyList::empty(py);
+    for stats in result.products.iter() {
+        let product = PyDict::new(py);
+        product.set_item("subtype", stats.subtype)?;
+        product.set_item("produced", stats.produced)?;
+        product.set_item("points", stats.points)?;
+        product.set_item("last_turn", stats.last_turn)?;
+        products.append(product)?;
+    }
+
+    let warnings: Vec<String> = result.warnings.iter().map(ToString::to_string).collect();
+
+    let dict = PyDict::new(py);
+    dict.set_item("score", result.score)?;
+    dict.set_item("turn", result.turn)?;
+    dict.set_item("warnings", warnings)?;
+    dict.set_item("products", products)?;
+    Ok(dict)
+}
+
+/// Solves the task `task_json` within `seconds` seconds (using all cores)
+///
+/// Returns the best solution found as JSON (an empty solution, if none was found).
+#[pyfunction]
+#[pyo3(signature = (task_json, seconds, seed=None))]
+fn solve(py: Python<'_>, task_json: &str, seconds: f64, seed: Option<u64>) -> PyResult<String> {
+    let task = parse_task(task_json)?;
+    let runtime = Duration::try_from_secs_f64(seconds)
+        .map_err(|e| PyValueError::new_err(format!("Invalid runtime: {}", e)))?;
+
+    let solution = py.allow_threads(|| {
+        let map = Map::new(task.width, task.height, task.objects.to_vec());
+        let num_threads = thread::available_parallelism()
+            .map(|i| i.get())
+            .unwrap_or(1);
+        run_solver(
+            &task,
+            &map,
+            &SolverConfig::default(),
+            num_threads,
+            Some(runtime),
+            seed,
+            RunOptions::default(),
+        )
+        .map(|result| Solution::from(&result.map))
+        .unwrap_or_default()
+    });
+
+    Ok(solution.to_json_string().unwrap())
+}
+
+fn parse_task(task_json: &str) -> PyResult<Task> {
+    Task::from_json_string(task_json)
+        .map_err(|e| PyValueError::new_err(format!("Invalid task: {}", e)))
+}
+
+#[pymodule]
+fn profitpy(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(simulate, module)?)?;
+    module.add_function(wrap_pyfunction!(solve, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use solver::strategy::Strategy;
+
+use crate::{history, DEFAULT_NUM_THREADS, DEFAULT_RUNTIME_IN_SECS};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub(crate) struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+// Arguments of a qa run (if no subcommand is given)
+#[derive(clap::Args, Debug)]
+pub(crate) struct RunArgs {
+    #[arg(
+        long,
+        num_args = 1..,
+        help = "Task files or directories of task files to run [default: the qa tasks of inputs/]"
+    )]
+    pub tasks: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma separated seeds to run each task with [default: 10 fixed seeds]"
+    )]
+    pub seeds: Vec<u64>,
+
+    #[arg(
+        long,
+        default_value_t = DEFAULT_RUNTIME_IN_SECS,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Runtime per task and seed in seconds"
+    )]
+    pub time: u64,
+
+    #[arg(long, default_value_t = DEFAULT_NUM_THREADS, help = "Number of solver threads")]
+    pub threads: usize,
+
+    #[arg(
+        long,
+        help = "Max runtime per task and seed in seconds, before the solver is stopped [default: 5 times --time plus 10]"
+    )]
+    pub timeout: Option<u64>,
+
+    #[arg(long, help = "Only run tasks whose file name contains this text")]
+    pub filter: Option<String>,
+
+    #[arg(
+        long,
+        help = "Results file to compare the run against [default: the results of the last run]"
+    )]
+    pub baseline: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Search solver parameters that perform best on the qa tasks
+    Tune(TuneArgs),
+    /// Print the score trends of all former qa runs and report regressions of the latest run
+    History(HistoryArgs),
+    /// Run each search strategy on the same tasks and seeds and print them ranked
+    Compare(CompareArgs),
+    /// Run a single task and seed and print the result (used internally to isolate runs)
+    #[command(hide = true)]
+    RunTask(RunTaskArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct TuneArgs {
+    #[arg(long, value_enum, default_value_t = SearchMode::Random, help = "Search strategy")]
+    pub mode: SearchMode,
+
+    #[arg(
+        long,
+        default_value_t = 20,
+        help = "Number of parameter sets to evaluate (random search only)"
+    )]
+    pub samples: usize,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Runtime per task and seed in seconds"
+    )]
+    pub time: u64,
+
+    #[arg(long, default_value_t = 3, help = "Number of seeds to run per task")]
+    pub seeds: usize,
+
+    #[arg(long, help = "Seed for sampling parameter sets")]
+    pub seed: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct RunTaskArgs {
+    #[arg(long)]
+    pub task: String,
+
+    #[arg(long)]
+    pub seed: u64,
+
+    #[arg(long)]
+    pub time: u64,
+
+    #[arg(long)]
+    pub threads: usize,
+
+    #[arg(long, value_enum, default_value_t = Strategy::default())]
+    pub strategy: Strategy,
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct HistoryArgs {
+    #[arg(
+        long,
+        default_value_t = history::DEFAULT_REGRESSION_THRESHOLD,
+        help = "Max drop of a task's average score (in percent) compared to the best former run"
+    )]
+    pub threshold: f32,
+}
+
+#[derive(clap::Args, Debug)]
+pub(crate) struct CompareArgs {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma separated strategies to compare [default: all strategies]"
+    )]
+    pub strategies: Vec<Strategy>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(ValueEnum, Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SearchMode {
+    Grid,
+    Random,
+}
@@ -0,0 +1,150 @@
+//! Head-to-head comparison of the solver's search strategies
+
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use model::{analysis::score_upper_bound, task::Task};
+use solver::strategy::Strategy;
+
+use crate::{
+    cli::CompareArgs, isolation, seeds, stats::Summary, strategy_name, task_name, task_paths,
+    OUT_DIR_PATH,
+};
+
+/// Results of a single strategy on all tasks
+#[derive(Serialize)]
+struct StrategyResult {
+    strategy: Strategy,
+    /// Score of each seed by task name (0 for seeds whose run failed or found no solution)
+    scores: BTreeMap<String, Vec<f32>>,
+    /// Ratio of the average score to the upper bound of the score, averaged over all tasks
+    average_ratio: f32,
+    /// Number of tasks on which no other strategy has a higher average score
+    wins: usize,
+}
+
+/// Runs all strategies on the same tasks, seeds and runtime and prints them ranked by their
+/// average ratio of score to upper bound
+pub(crate) fn compare(args: &CompareArgs) {
+    let strategies = if args.strategies.is_empty() {
+        Strategy::value_variants().to_vec()
+    } else {
+        args.strategies.clone()
+    };
+    let seeds = seeds(&args.run);
+
+    let mut tasks: Vec<(String, String, u32)> = vec![];
+    for path in task_paths(&args.run) {
+        match Task::from_json_file(&path) {
+            Ok(task) => tasks.push((task_name(&path).to_string(), path, score_upper_bound(&task))),
+            Err(e) => eprintln!("Skipping {}: {}", path, e),
+        }
+    }
+
+    let mut results: Vec<StrategyResult> = vec![];
+    for &strategy in strategies.iter() {
+        println!("Running strategy {}", strategy_name(strategy));
+        let mut scores = BTreeMap::new();
+        for (name, path, _) in tasks.iter() {
+            let task_scores = seeds
+                .iter()
+                .map(
+                    |&seed| match isolation::run_isolated(path, seed, strategy, &args.run) {
+                        Ok(result) => result.map(|result| result.score).unwrap_or(0.0),
+                        Err(reason) => {
+                            println!("\tFAILURE: {} (seed {}): {}", name, seed, reason);
+                            0.0
+                        }
+                    },
+                )
+                .collect();
+            scores.insert(name.clone(), task_scores);
+        }
+        results.push(StrategyResult {
+            strategy,
+            scores,
+            average_ratio: 0.0,
+            wins: 0,
+        });
+    }
+
+    for (name, _, upper_bound) in tasks.iter() {
+        let averages: Vec<f32> = results
+            .iter()
+            .map(|result| average(&result.scores[name]))
+            .collect();
+        let best = averages.iter().copied().fold(0.0, f32::max);
+        for (result, average) in results.iter_mut().zip(averages) {
+            result.average_ratio += average / (*upper_bound).max(1) as f32;
+            if average >= best {
+                result.wins += 1;
+            }
+        }
+    }
+    for result in results.iter_mut() {
+        result.average_ratio /= tasks.len().max(1) as f32;
+    }
+
+    results.sort_by(|a, b| {
+        b.average_ratio
+            .total_cmp(&a.average_ratio)
+            .then(b.wins.cmp(&a.wins))
+    });
+
+    print_matrix(&tasks, &results);
+
+    let result_str = serde_json::ser::to_string_pretty(&results).unwrap();
+    std::fs::create_dir_all(OUT_DIR_PATH).expect("Cannot create out dir");
+    std::fs::write(format!("{}compare.json", OUT_DIR_PATH), result_str)
+        .expect("Cannot write comparison to file");
+}
+
+/// Prints the mean and standard deviation of the scores of each task (rows) and strategy
+/// (columns), followed by the ranking of the strategies
+fn print_matrix(tasks: &[(String, String, u32)], results: &[StrategyResult]) {
+    let name_width = tasks
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+
+    println!();
+    print!("{:<width$}", "task", width = name_width);
+    for result in results {
+        print!(" {:>18}", strategy_name(result.strategy));
+    }
+    println!(" {:>11}", "upper bound");
+
+    for (name, _, upper_bound) in tasks {
+        print!("{:<width$}", name, width = name_width);
+        for result in results {
+            let summary = Summary::new(&result.scores[name])
+                .map(|summary| summary.to_string())
+                .unwrap_or_else(|| String::from("-"));
+            print!(" {:>18}", summary);
+        }
+        println!(" {:>11}", upper_bound);
+    }
+
+    println!();
+    println!(
+        "{:>4} {:<12} {:>14} {:>5}",
+        "rank", "strategy", "% upper bound", "wins"
+    );
+    for (rank, result) in results.iter().enumerate() {
+        println!(
+            "{:>4} {:<12} {:>14.2} {:>5}",
+            rank + 1,
+            strategy_name(result.strategy),
+            100.0 * result.average_ratio,
+            result.wins
+        );
+    }
+}
+
+fn average(values: &[f32]) -> f32 {
+    values.iter().fold(0.0, |a, b| a + b) / values.len().max(1) as f32
+}
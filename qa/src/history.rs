@@ -0,0 +1,148 @@
+//! Analysis of the qa results of all former runs
+
+use std::{collections::BTreeMap, fs, time::SystemTime};
+
+use crate::{cli::HistoryArgs, TestResults, OUT_DIR_PATH};
+
+/// Max relative drop (in percent) of a task's average score, compared to the best historical run,
+/// before a regression is reported
+pub(crate) const DEFAULT_REGRESSION_THRESHOLD: f32 = 5.0;
+
+/// Files in the qa out dir that do not contain the results of a single commit
+const NON_COMMIT_FILES: [&str; 3] = ["current.json", "last.json", "tune.json"];
+
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Average score of a task at a single commit
+struct DataPoint {
+    commit: String,
+    score: f32,
+}
+
+/// Prints the score history of all tasks and reports regressions of the latest run
+pub(crate) fn history(args: &HistoryArgs) {
+    let results = load_history();
+    match results.last() {
+        Some(latest) => {
+            print_trends(&results);
+            println!();
+            report_regressions(&results, &latest.commit, args.threshold);
+        }
+        None => println!("No qa results found in {}", OUT_DIR_PATH),
+    }
+}
+
+/// Reports tasks whose average score at `commit` dropped more than `threshold` percent compared
+/// to the best historical commit
+///
+/// Returns `true` if any regression was found.
+pub(crate) fn report_regressions(results: &[TestResults], commit: &str, threshold: f32) -> bool {
+    let series = build_series(results);
+    let mut regression = false;
+
+    for (task, data_points) in series.iter() {
+        let current = match data_points.iter().rev().find(|p| p.commit == commit) {
+            Some(current) => current,
+            None => continue,
+        };
+        let best = data_points
+            .iter()
+            .filter(|p| p.commit != commit)
+            .max_by(|a, b| a.score.total_cmp(&b.score));
+
+        if let Some(best) = best {
+            if best.score <= 0.0 {
+                continue;
+            }
+            let change = (current.score - best.score) / best.score * 100.0;
+            if change < -threshold {
+                println!(
+                    "REGRESSION: {}: average score {:.2} is {:.2}% below best {:.2} (commit {})",
+                    task, current.score, -change, best.score, best.commit
+                );
+                regression = true;
+            }
+        }
+    }
+
+    if !regression {
+        println!(
+            "No regressions (threshold {:.2}%) compared to {} former commit(s)",
+            threshold,
+            results.iter().filter(|r| r.commit != commit).count()
+        );
+    }
+
+    regression
+}
+
+/// Loads the results of all commits, ordered by the time they were written
+pub(crate) fn load_history() -> Vec<TestResults> {
+    let entries = match fs::read_dir(OUT_DIR_PATH) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    let mut results: Vec<(SystemTime, TestResults)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.ends_with(".json") && !NON_COMMIT_FILES.contains(&name.as_ref())
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok()?;
+            let file = fs::File::open(entry.path()).ok()?;
+            let result: TestResults = serde_json::de::from_reader(file).ok()?;
+            Some((modified, result))
+        })
+        .collect();
+
+    results.sort_by_key(|(modified, _)| *modified);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Builds a time series of average scores per task
+fn build_series(results: &[TestResults]) -> BTreeMap<String, Vec<DataPoint>> {
+    let mut series: BTreeMap<String, Vec<DataPoint>> = BTreeMap::new();
+    for result in results {
+        for (task, metric) in result.results.iter() {
+            if let Some(metric) = metric {
+                series.entry(task.clone()).or_default().push(DataPoint {
+                    commit: result.commit.clone(),
+                    score: metric.average.score,
+                });
+            }
+        }
+    }
+    series
+}
+
+/// Prints a sparkline of the average score per task
+fn print_trends(results: &[TestResults]) {
+    println!("Average score over {} commit(s)", results.len());
+    for (task, data_points) in build_series(results) {
+        let min = data_points.iter().map(|p| p.score).fold(f32::MAX, f32::min);
+        let max = data_points.iter().map(|p| p.score).fold(f32::MIN, f32::max);
+        let sparkline: String = data_points
+            .iter()
+            .map(|p| {
+                if max > min {
+                    let idx = ((p.score - min) / (max - min) * (SPARK_CHARS.len() - 1) as f32)
+                        .round() as usize;
+                    SPARK_CHARS[idx]
+                } else {
+                    SPARK_CHARS[SPARK_CHARS.len() - 1]
+                }
+            })
+            .collect();
+        println!(
+            "{:<28} {} ({:.2} .. {:.2}, latest {:.2})",
+            task,
+            sparkline,
+            min,
+            max,
+            data_points.last().map(|p| p.score).unwrap_or(0.0)
+        );
+    }
+}
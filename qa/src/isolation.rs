@@ -0,0 +1,105 @@
+//! Runs single task/seed combinations in child processes
+//!
+//! A panic or hang of the solver only fails the affected combination, instead of the whole qa run.
+
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use model::{map::Map, task::Task};
+use solver::{run_solver, strategy::Strategy, RunOptions, SolverConfig};
+
+use crate::{
+    cli::{RunArgs, RunTaskArgs},
+    strategy_name, TestResult,
+};
+
+/// Interval in which a child process is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Runs the solver on the task at `path` with `seed` and `strategy` in a child process
+///
+/// Returns Ok(None) if the solver did not find any solution and Err(reason) if the child process
+/// failed or did not finish within the timeout.
+pub(crate) fn run_isolated(
+    path: &str,
+    seed: u64,
+    strategy: Strategy,
+    args: &RunArgs,
+) -> Result<Option<TestResult>, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Cannot find qa binary: {}", e))?;
+    let mut child = Command::new(exe)
+        .args(["run-task", "--task", path])
+        .args(["--seed", &seed.to_string()])
+        .args(["--time", &args.time.to_string()])
+        .args(["--threads", &args.threads.to_string()])
+        .args(["--strategy", &strategy_name(strategy)])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot start child process: {}", e))?;
+
+    let timeout = Duration::from_secs(args.timeout.unwrap_or(5 * args.time + 10));
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() > deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Timeout after {}s", timeout.as_secs()));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("Cannot wait for child process: {}", e)),
+        }
+    };
+
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    if let Some(mut out) = child.stdout.take() {
+        let _ = out.read_to_string(&mut stdout);
+    }
+    if let Some(mut err) = child.stderr.take() {
+        let _ = err.read_to_string(&mut stderr);
+    }
+
+    if !status.success() {
+        // the panic message follows the line with the panic's location
+        let lines: Vec<&str> = stderr.lines().collect();
+        let message = match lines.iter().position(|line| line.contains("panicked")) {
+            Some(index) => lines[index..lines.len().min(index + 2)].join(" "),
+            None => lines.last().copied().unwrap_or_default().to_string(),
+        };
+        return Err(format!("Solver failed ({}): {}", status, message.trim()));
+    }
+
+    serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Cannot parse result of child process: {}", e))
+}
+
+/// Entry point of a child process: runs the solver once and prints the result as JSON
+pub(crate) fn run_task(args: &RunTaskArgs) {
+    let task = Task::from_json_file(&args.task)
+        .unwrap_or_else(|e| panic!("Could not read task {}: {}", args.task, e));
+    let map = Map::new(task.width, task.height, task.objects.to_vec());
+
+    let result = run_solver(
+        &task,
+        &map,
+        &SolverConfig {
+            strategy: args.strategy,
+            ..SolverConfig::default()
+        },
+        args.threads,
+        Some(Duration::from_secs(args.time)),
+        Some(args.seed),
+        RunOptions::default(),
+    )
+    .map(|r| TestResult::from(&r.result));
+
+    println!("{}", serde_json::to_string(&result).unwrap());
+}
@@ -1,16 +1,28 @@
+mod cli;
+mod compare;
+mod history;
+mod isolation;
+mod stats;
+mod tune;
+
+use clap::{Parser, ValueEnum};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, time::Duration};
+use std::collections::BTreeMap;
 
-use model::{map::Map, object::Object, task::Task};
+use cli::{Args, Command, RunArgs};
+use model::{analysis::score_upper_bound, task::Task};
 use simulator::SimulatorResult;
-use solver::run::run_solver;
+use solver::strategy::Strategy;
+use stats::{Significance, Summary};
 
-const SEEDS: [u64; 10] = [
+/// Seeds each task is run with, if no seeds are given
+const DEFAULT_SEEDS: [u64; 10] = [
     32491274, 923410234, 12375320, 1238493, 593810, 7382934, 3920134, 4742810, 123648, 83047,
 ];
-const NUM_THREADS: usize = 8;
-const RUNTIME_IN_SECS: u64 = 2;
-const TASKS: [&str; 7] = [
+pub(crate) const DEFAULT_NUM_THREADS: usize = 8;
+pub(crate) const DEFAULT_RUNTIME_IN_SECS: u64 = 2;
+/// Tasks that are run, if no tasks are given
+const DEFAULT_TASKS: [&str; 7] = [
     concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/001.task.json"),
     concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/002.task.json"),
     concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/003.task.json"),
@@ -29,91 +41,187 @@ macro_rules! OUT_DIR_NAME {
     };
 }
 
-macro_rules! run_task {
-    ($path: expr) => {{
-        let task = Task::from_json_file($path).expect("Could not read task {$path}");
+pub(crate) const OUT_DIR_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../target/",
+    OUT_DIR_NAME!(),
+    "/"
+);
+
+/// Runs the task at `path` once per seed (each in a separate process) and aggregates the results
+///
+/// Seeds whose run failed are recorded as failures and count as a score of 0.
+fn run_task(path: &str, task: &Task, seeds: &[u64], args: &RunArgs) -> Option<TestResultMetric> {
+    let mut failures = vec![];
+    let mut scores = vec![];
+    let results = seeds
+        .iter()
+        .filter_map(|&seed| {
+            let result = match isolation::run_isolated(path, seed, Strategy::default(), args) {
+                Ok(result) => result,
+                Err(reason) => {
+                    failures.push(Failure { seed, reason });
+                    None
+                }
+            };
+            scores.push(result.as_ref().map(|r| r.score).unwrap_or(0.0));
+            result
+        })
+        .collect::<Vec<TestResult>>();
 
-        let map = Map::new(
-            task.width,
-            task.height,
-            task.objects.iter().cloned().map(Object::from).collect(),
-        );
+    let score_best = results.iter().map(|o| o.score).fold(0.0, f32::max);
+    let turn_best = results.iter().map(|o| o.turn).reduce(f32::max);
+    let turn_best = turn_best.unwrap_or(u32::MAX as f32);
 
-        let results = SEEDS
+    let score_worst = results.iter().map(|o| o.score).reduce(f32::min);
+    let score_worst = score_worst.unwrap_or(0.0);
+    let turn_worst = results.iter().map(|o| o.turn).reduce(f32::min);
+    let turn_worst = turn_worst.unwrap_or(u32::MAX as f32);
+
+    let score_sum = results.iter().map(|o| o.score).fold(0.0, |a, b| a + b);
+    let turn_sum = results.iter().map(|o| o.turn).fold(0.0, |a, b| a + b);
+    let score_avg = score_sum / seeds.len() as f32;
+    let turn_avg = turn_sum / seeds.len() as f32;
+
+    Some(TestResultMetric {
+        upper_bound: score_upper_bound(task),
+        best: TestResult {
+            score: score_best,
+            turn: turn_best,
+        },
+        worst: TestResult {
+            score: score_worst,
+            turn: turn_worst,
+        },
+        average: TestResult {
+            score: score_avg,
+            turn: turn_avg,
+        },
+        turns: results.iter().map(|o| o.turn).collect(),
+        scores,
+        failures,
+    })
+}
+
+/// Paths of all task files to run
+///
+/// Directories are replaced by the json files they contain. Only tasks whose file name contains
+/// the filter are returned.
+fn task_paths(args: &RunArgs) -> Vec<String> {
+    let paths: Vec<String> = if args.tasks.is_empty() {
+        DEFAULT_TASKS.iter().map(|path| path.to_string()).collect()
+    } else {
+        args.tasks
             .iter()
-            .filter_map(|seed| {
-                run_solver(
-                    &task,
-                    &map,
-                    NUM_THREADS,
-                    Duration::from_secs(RUNTIME_IN_SECS),
-                    Some(*seed),
-                )
-                .map(|r| r.result)
+            .flat_map(|path| match std::fs::read_dir(path) {
+                Ok(entries) => {
+                    let mut paths: Vec<String> = entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| entry.path().to_string_lossy().to_string())
+                        .filter(|path| path.ends_with(".json"))
+                        .collect();
+                    paths.sort();
+                    paths
+                }
+                Err(_) => vec![path.clone()],
             })
-            .collect::<Vec<SimulatorResult>>();
-
-        let score_best = results.iter().map(|o| o.score).max().unwrap_or(0) as f32;
-        let turn_best = results.iter().map(|o| o.turn).max().unwrap_or(u32::MAX) as f32;
-
-        let score_worst = results.iter().map(|o| o.score).min().unwrap_or(0) as f32;
-        let turn_worst = results.iter().map(|o| o.turn).min().unwrap_or(u32::MAX) as f32;
-
-        let score_sum: u32 = results.iter().map(|o| o.score).sum();
-        let turn_sum: u32 = results.iter().map(|o| o.turn).sum();
-        let score_avg = score_sum as f32 / SEEDS.len() as f32;
-        let turn_avg = turn_sum as f32 / SEEDS.len() as f32;
-
-        Some(TestResultMetric {
-            best: TestResult {
-                score: score_best,
-                turn: turn_best,
-            },
-            worst: TestResult {
-                score: score_worst,
-                turn: turn_worst,
-            },
-            average: TestResult {
-                score: score_avg,
-                turn: turn_avg,
-            },
+            .collect()
+    };
+
+    paths
+        .into_iter()
+        .filter(|path| match args.filter {
+            Some(ref filter) => task_name(path).contains(filter.as_str()),
+            None => true,
         })
-    }};
+        .collect()
+}
+
+/// Seeds each task is run with
+fn seeds(args: &RunArgs) -> Vec<u64> {
+    if args.seeds.is_empty() {
+        DEFAULT_SEEDS.to_vec()
+    } else {
+        args.seeds.clone()
+    }
+}
+
+/// Name of a strategy as given on the command line
+pub(crate) fn strategy_name(strategy: Strategy) -> String {
+    strategy
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Name of a task in the results, i.e. its file name
+pub(crate) fn task_name(path: &str) -> &str {
+    path.split_terminator('/').next_back().unwrap()
+}
+
+fn read_results(path: &str) -> Option<TestResults> {
+    std::fs::File::open(path)
+        .map_err(|_| "cannot open result")
+        .and_then(|file| serde_json::de::from_reader(file).map_err(|_| "cannot parse result"))
+        .ok()
 }
 
 fn main() {
+    let args = Args::parse();
+
+    match args.command {
+        Some(Command::Tune(tune_args)) => tune::tune(&tune_args),
+        Some(Command::History(history_args)) => history::history(&history_args),
+        Some(Command::Compare(compare_args)) => compare::compare(&compare_args),
+        Some(Command::RunTask(run_task_args)) => isolation::run_task(&run_task_args),
+        None => run_qa(&args.run),
+    }
+}
+
+/// Runs all qa tasks with the default solver config and compares the results to the last run (or
+/// the baseline) and to the best of all former runs
+fn run_qa(args: &RunArgs) {
     let commit = String::from(env!("GIT_HASH"));
-    let out_dir_path = concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/../target/",
-        OUT_DIR_NAME!(),
-        "/"
-    );
+    let out_dir_path = OUT_DIR_PATH;
     let out_file_path = format!("{}current.json", out_dir_path);
     let commit_file_path = format!("{}{}.json", out_dir_path, commit);
     let last_file_path = format!("{}last.json", out_dir_path);
 
-    let last_result: Option<TestResults> = std::fs::File::open(&out_file_path)
-        .map_err(|_| "cannot open last result")
-        .and_then(|file| serde_json::de::from_reader(file).map_err(|_| "cannot parse last result"))
-        .ok();
-
+    let last_result = read_results(&out_file_path);
     if last_result.is_some() {
         std::fs::rename(&out_file_path, last_file_path).expect("Cannot move old result file");
     }
 
+    let last_result = match args.baseline {
+        Some(ref path) => Some(
+            read_results(path)
+                .unwrap_or_else(|| panic!("Cannot read baseline results from {}", path)),
+        ),
+        None => last_result,
+    };
+
+    let seeds = seeds(args);
+
     let mut test_results = TestResults {
-        seeds: SEEDS.to_vec(),
-        time_per_task: RUNTIME_IN_SECS,
-        cores: NUM_THREADS,
+        seeds: seeds.clone(),
+        time_per_task: args.time,
+        cores: args.threads,
         results: BTreeMap::new(),
-        commit,
+        commit: commit.clone(),
     };
 
-    for task in TASKS {
-        let task_name = task.split_terminator('/').last().unwrap();
-        let result = run_task!(task);
-        test_results.results.insert(String::from(task_name), result);
+    for path in task_paths(args) {
+        let task = match Task::from_json_file(&path) {
+            Ok(task) => task,
+            Err(e) => {
+                eprintln!("Skipping {}: {}", path, e);
+                continue;
+            }
+        };
+        let result = run_task(&path, &task, &seeds, args);
+        test_results
+            .results
+            .insert(String::from(task_name(&path)), result);
     }
 
     let result_str = serde_json::ser::to_string_pretty(&test_results).unwrap();
@@ -121,6 +229,28 @@ fn main() {
     std::fs::write(out_file_path, &result_str).expect("Cannot write results to file");
     std::fs::write(commit_file_path, &result_str).expect("Cannot write results to file");
 
+    println!("Average score / upper bound");
+    for (name, result) in test_results.results.iter() {
+        if let Some(result) = result {
+            println!(
+                "\t{}: {:.2}/{} ({:.2}%)",
+                name,
+                result.average.score,
+                result.upper_bound,
+                100.0 * result.average.score / result.upper_bound.max(1) as f32
+            );
+        }
+    }
+    for (name, result) in test_results.results.iter() {
+        for failure in result.iter().flat_map(|result| result.failures.iter()) {
+            println!(
+                "\tFAILURE: {} (seed {}): {}",
+                name, failure.seed, failure.reason
+            );
+        }
+    }
+    println!();
+
     if let Some(last_results) = last_result {
         let mut warning = false;
         if last_results.seeds != test_results.seeds {
@@ -145,25 +275,7 @@ fn main() {
                 match (last_result, &result) {
                     (Some(a), Some(b)) => {
                         println!("{}", name);
-                        for (metric, a, b) in [
-                            ("best", &a.best, &b.best),
-                            ("worst", &a.worst, &b.worst),
-                            ("average", &a.average, &b.average),
-                        ] {
-                            let score_change = (b.score - a.score) / a.score;
-                            let turn_change = (b.turn - a.turn) / a.turn;
-
-                            println!(
-                                "\t{}:\n\t\tScore: {:.2}%\t({:.2} -> {:.2})\n\t\tTurns: {:.2}%\t({:.2} -> {:.2})",
-                                metric,
-                                score_change * 100f32,
-                                a.score,
-                                b.score,
-                                turn_change,
-                                a.turn,
-                                b.turn,
-                            );
-                        }
+                        print_comparison(a, b);
                     }
                     (Some(_), None) => {
                         println!("{}: NO RESULTS", name);
@@ -186,7 +298,58 @@ fn main() {
                 }
             }
         }
+        println!();
+    }
+
+    history::report_regressions(
+        &history::load_history(),
+        &commit,
+        history::DEFAULT_REGRESSION_THRESHOLD,
+    );
+}
+
+/// Prints the change of a task's results from `a` to `b`
+///
+/// Changes are only reported as significant, if the 95% confidence intervals of the results
+/// across all seeds do not overlap. Results of runs that did not record the results of single
+/// seeds are compared by their averages.
+fn print_comparison(a: &TestResultMetric, b: &TestResultMetric) {
+    for (metric, a_values, b_values, a_average, b_average, higher_is_better) in [
+        (
+            "Score",
+            &a.scores,
+            &b.scores,
+            a.average.score,
+            b.average.score,
+            true,
+        ),
+        (
+            "Turns",
+            &a.turns,
+            &b.turns,
+            a.average.turn,
+            b.average.turn,
+            false,
+        ),
+    ] {
+        match (Summary::new(a_values), Summary::new(b_values)) {
+            (Some(a_summary), Some(b_summary)) => println!(
+                "\t{}: {} -> {} ({})",
+                metric,
+                a_summary,
+                b_summary,
+                Significance::of(&a_summary, &b_summary, higher_is_better)
+            ),
+            _ => println!(
+                "\t{}: {:.2} -> {:.2} (averages only)",
+                metric, a_average, b_average
+            ),
+        }
     }
+    println!(
+        "\tBest/worst score: {:.2}/{:.2} -> {:.2}/{:.2}",
+        a.best.score, a.worst.score, b.best.score, b.worst.score
+    );
 }
 
 #[derive(Serialize, Deserialize)]
@@ -200,13 +363,31 @@ struct TestResults {
 
 #[derive(Clone, Serialize, Deserialize)]
 struct TestResultMetric {
+    /// see [score_upper_bound]
+    #[serde(default)]
+    upper_bound: u32,
     best: TestResult,
     worst: TestResult,
     average: TestResult,
+    /// Score of each seed (0 for seeds without a solution)
+    #[serde(default)]
+    scores: Vec<f32>,
+    /// Turn of each seed that found a solution
+    #[serde(default)]
+    turns: Vec<f32>,
+    /// Seeds whose run failed
+    #[serde(default)]
+    failures: Vec<Failure>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Failure {
+    seed: u64,
+    reason: String,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
-struct TestResult {
+pub(crate) struct TestResult {
     score: f32,
     turn: f32,
 }
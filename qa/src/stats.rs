@@ -0,0 +1,88 @@
+//! Statistics for comparing the results of qa runs across seeds
+
+use std::fmt::Display;
+
+/// z-value of a two-sided 95% confidence interval
+const Z_95: f32 = 1.96;
+
+/// Mean and spread of a sample of values (i.e. the scores of a task for all seeds)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Summary {
+    pub mean: f32,
+    /// Sample standard deviation
+    pub std_dev: f32,
+    pub n: usize,
+}
+
+impl Summary {
+    /// Returns None for an empty sample
+    pub fn new(values: &[f32]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let n = values.len();
+        let mean = values.iter().sum::<f32>() / n as f32;
+        let variance = if n > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (n - 1) as f32
+        } else {
+            0.0
+        };
+
+        Some(Summary {
+            mean,
+            std_dev: variance.sqrt(),
+            n,
+        })
+    }
+
+    /// 95% confidence interval of the mean
+    pub fn confidence_interval(&self) -> (f32, f32) {
+        let margin = Z_95 * self.std_dev / (self.n as f32).sqrt();
+        (self.mean - margin, self.mean + margin)
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.2} ± {:.2}", self.mean, self.std_dev)
+    }
+}
+
+/// Outcome of comparing two samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Significance {
+    Better,
+    Worse,
+    /// The confidence intervals of both samples overlap, so the difference may be noise
+    Insignificant,
+}
+
+impl Significance {
+    /// Compares the sample `after` to `before`, where `higher_is_better` tells the direction of
+    /// an improvement
+    pub fn of(before: &Summary, after: &Summary, higher_is_better: bool) -> Significance {
+        let (before_low, before_high) = before.confidence_interval();
+        let (after_low, after_high) = after.confidence_interval();
+
+        let increased = after_low > before_high;
+        let decreased = after_high < before_low;
+        match (increased, decreased) {
+            (true, _) if higher_is_better => Significance::Better,
+            (true, _) => Significance::Worse,
+            (_, true) if higher_is_better => Significance::Worse,
+            (_, true) => Significance::Better,
+            _ => Significance::Insignificant,
+        }
+    }
+}
+
+impl Display for Significance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Significance::Better => "significantly better",
+            Significance::Worse => "SIGNIFICANTLY WORSE",
+            Significance::Insignificant => "not significant",
+        })
+    }
+}
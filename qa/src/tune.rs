@@ -0,0 +1,176 @@
+//! Hyperparameter search for [SolverConfig]
+
+use std::{collections::BTreeMap, time::Duration};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::Serialize;
+
+use model::{map::Map, task::Task};
+use solver::{run_solver, RunOptions, SolverConfig};
+
+use crate::{
+    cli::{SearchMode, TuneArgs},
+    task_name, DEFAULT_NUM_THREADS, DEFAULT_SEEDS, DEFAULT_TASKS, OUT_DIR_PATH,
+};
+
+const NUM_MAX_FACTORY_PLACEMENTS: [u32; 3] = [10, 20, 40];
+const PROBABILITY_FACTORY_SKIP: [(u32, u32); 3] = [(1, 20), (1, 10), (1, 5)];
+const NUM_PATHS_PER_FACTORY_AND_RESOURCE: [u32; 3] = [3, 5, 10];
+const NUM_ADDITIONAL_PATHS_PER_FACTORY_AND_RESOURCE: [u32; 3] = [3, 5, 10];
+const NUM_PATH_COMBINING_ITERATIONS: [u32; 3] = [1, 2, 4];
+const NUM_ADDITIONAL_PATH_FAILURES_PER_FACTORY: [u32; 3] = [5, 10, 20];
+const BIDIRECTIONAL_PATH_SEARCH: [bool; 2] = [false, true];
+
+/// Seed used for sampling parameter sets, if none is given
+const DEFAULT_SAMPLING_SEED: u64 = 2023;
+
+/// Result of evaluating a single parameter set on all tasks
+#[derive(Serialize)]
+struct TuneResult {
+    config: SolverConfig,
+    average_score: f32,
+    average_scores: BTreeMap<String, f32>,
+}
+
+/// Evaluates multiple solver configs on all qa tasks and prints them ranked by average score
+pub(crate) fn tune(args: &TuneArgs) {
+    let tasks: Vec<(String, Task, Map)> = DEFAULT_TASKS
+        .iter()
+        .map(|path| {
+            let name = task_name(path).to_string();
+            let task = Task::from_json_file(path).expect("Could not read task");
+            let map = Map::new(task.width, task.height, task.objects.to_vec());
+            (name, task, map)
+        })
+        .collect();
+    let seeds = &DEFAULT_SEEDS[..args.seeds.min(DEFAULT_SEEDS.len())];
+    let runtime = Duration::from_secs(args.time);
+
+    let configs = match args.mode {
+        SearchMode::Grid => grid(),
+        SearchMode::Random => {
+            let mut configs = grid();
+            let mut rng = StdRng::seed_from_u64(args.seed.unwrap_or(DEFAULT_SAMPLING_SEED));
+            configs.shuffle(&mut rng);
+            configs.truncate(args.samples);
+            configs
+        }
+    };
+
+    let mut results = Vec::with_capacity(configs.len());
+    for (i, config) in configs.into_iter().enumerate() {
+        println!("Evaluating parameter set #{}: {:?}", i + 1, config);
+        results.push(evaluate(config, &tasks, seeds, runtime));
+    }
+
+    results.sort_by(|a, b| b.average_score.total_cmp(&a.average_score));
+
+    println!();
+    println!(
+        "{:>4} {:>10} {:>10} {:>6} {:>6} {:>10} {:>10} {:>9} {:>6}",
+        "rank",
+        "avg score",
+        "placements",
+        "skip",
+        "paths",
+        "add. paths",
+        "combining",
+        "failures",
+        "bidir."
+    );
+    for (rank, result) in results.iter().enumerate() {
+        let config = &result.config;
+        println!(
+            "{:>4} {:>10.2} {:>10} {:>6} {:>6} {:>10} {:>10} {:>9} {:>6}",
+            rank + 1,
+            result.average_score,
+            config.num_max_factory_placements,
+            format!(
+                "{}/{}",
+                config.probability_factory_skip.0, config.probability_factory_skip.1
+            ),
+            config.num_paths_per_factory_and_resource,
+            config.num_additional_paths_per_factory_and_resource,
+            config.num_path_combining_iterations,
+            config.num_additional_path_failures_per_factory,
+            config.bidirectional_path_search,
+        );
+    }
+
+    let result_str = serde_json::ser::to_string_pretty(&results).unwrap();
+    std::fs::create_dir_all(OUT_DIR_PATH).expect("Cannot create out dir");
+    std::fs::write(format!("{}tune.json", OUT_DIR_PATH), result_str)
+        .expect("Cannot write tuning results to file");
+}
+
+/// Runs `config` on all tasks and seeds and averages the achieved scores
+fn evaluate(
+    config: SolverConfig,
+    tasks: &[(String, Task, Map)],
+    seeds: &[u64],
+    runtime: Duration,
+) -> TuneResult {
+    let average_scores: BTreeMap<String, f32> = tasks
+        .iter()
+        .map(|(name, task, map)| {
+            let score_sum: u32 = seeds
+                .iter()
+                .filter_map(|seed| {
+                    run_solver(
+                        task,
+                        map,
+                        &config,
+                        DEFAULT_NUM_THREADS,
+                        Some(runtime),
+                        Some(*seed),
+                        RunOptions::default(),
+                    )
+                    .map(|r| r.result.score)
+                })
+                .sum();
+            (name.clone(), score_sum as f32 / seeds.len() as f32)
+        })
+        .collect();
+
+    let average_score = average_scores.values().sum::<f32>() / average_scores.len() as f32;
+
+    TuneResult {
+        config,
+        average_score,
+        average_scores,
+    }
+}
+
+/// Creates all combinations of the candidate parameter values
+fn grid() -> Vec<SolverConfig> {
+    let mut configs = Vec::new();
+    for num_max_factory_placements in NUM_MAX_FACTORY_PLACEMENTS {
+        for probability_factory_skip in PROBABILITY_FACTORY_SKIP {
+            for num_paths_per_factory_and_resource in NUM_PATHS_PER_FACTORY_AND_RESOURCE {
+                for num_additional_paths_per_factory_and_resource in
+                    NUM_ADDITIONAL_PATHS_PER_FACTORY_AND_RESOURCE
+                {
+                    for num_path_combining_iterations in NUM_PATH_COMBINING_ITERATIONS {
+                        for num_additional_path_failures_per_factory in
+                            NUM_ADDITIONAL_PATH_FAILURES_PER_FACTORY
+                        {
+                            for bidirectional_path_search in BIDIRECTIONAL_PATH_SEARCH {
+                                configs.push(SolverConfig {
+                                    num_max_factory_placements,
+                                    probability_factory_skip,
+                                    num_paths_per_factory_and_resource,
+                                    num_additional_paths_per_factory_and_resource,
+                                    num_path_combining_iterations,
+                                    num_additional_path_failures_per_factory,
+                                    bidirectional_path_search,
+                                    ..SolverConfig::default()
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    configs
+}
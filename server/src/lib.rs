@@ -0,0 +1,119 @@
+//! Minimal HTTP service that solves POSTed tasks
+//!
+//! The service is independent of the actual solver, which is passed to [serve] as a function.
+//!
+//! # Endpoints
+//! - `POST /solve[?time=<seconds>|time_ms=<milliseconds>]` with a task as body responds with the
+//!   solution found within the requested time (or the task's time, if none is requested)
+//! - `GET /metrics` responds with the metrics passed to [serve] (e.g. in the Prometheus text
+//!   format). Since requests are handled one after another, metrics are not available while a
+//!   task is solved.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use model::{solution::Solution, task::Task};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Interval in which `stop` is checked while waiting for requests
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runtime of a task that neither the request nor the task itself specifies
+const DEFAULT_RUNTIME: Duration = Duration::from_secs(100);
+
+/// Answers requests on `addr` until `stop` is set
+///
+/// Requests are handled one after another, as `solve` is expected to use all available cores.
+pub fn serve(
+    addr: &str,
+    stop: &AtomicBool,
+    solve: impl Fn(&Task, Duration) -> Solution,
+    metrics: impl Fn() -> String,
+) -> Result<(), String> {
+    let server = Server::http(addr).map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+
+    while !stop.load(Ordering::Acquire) {
+        let mut request = match server.recv_timeout(STOP_CHECK_INTERVAL) {
+            Ok(Some(request)) => request,
+            Ok(None) => continue,
+            Err(e) => return Err(format!("Cannot receive request: {}", e)),
+        };
+
+        if request.url() == "/metrics" && *request.method() == Method::Get {
+            let response = Response::from_string(metrics()).with_header(
+                Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap(),
+            );
+            if let Err(e) = request.respond(response) {
+                eprintln!("Cannot send response: {}", e);
+            }
+            continue;
+        }
+
+        let response = match parse_request(&mut request) {
+            Ok((task, runtime)) => {
+                Response::from_string(solve(&task, runtime).to_json_string().unwrap())
+                    .with_header(json_content_type())
+            }
+            Err((status, message)) => Response::from_string(message).with_status_code(status),
+        };
+
+        if let Err(e) = request.respond(response) {
+            eprintln!("Cannot send response: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn json_content_type() -> Header {
+    Header::from_bytes("Content-Type", "application/json").unwrap()
+}
+
+/// Parses a request into the task to solve and the requested runtime
+///
+/// Returns the response status and message, if the request is invalid.
+fn parse_request(request: &mut Request) -> Result<(Task, Duration), (u16, String)> {
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    if path != "/solve" {
+        return Err((404, format!("Unknown path {}", path)));
+    }
+    if *request.method() != Method::Post {
+        return Err((405, String::from("Tasks must be POSTed")));
+    }
+
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| (400, format!("Cannot read request body: {}", e)))?;
+    let task = Task::from_json_string(&body).map_err(|e| (400, format!("Invalid task: {}", e)))?;
+    let runtime = match parse_runtime(query).map_err(|e| (400, e))? {
+        Some(runtime) => runtime,
+        None => task
+            .time
+            .map(|time| Duration::from_secs(time as u64))
+            .unwrap_or(DEFAULT_RUNTIME),
+    };
+
+    Ok((task, runtime))
+}
+
+/// Parses the runtime from the parameters `time` (seconds) or `time_ms` (milliseconds) of `query`
+fn parse_runtime(query: &str) -> Result<Option<Duration>, String> {
+    let mut runtime = None;
+    for parameter in query.split('&').filter(|parameter| !parameter.is_empty()) {
+        let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+        let value: u64 = value
+            .parse()
+            .map_err(|_| format!("Invalid value of parameter {}: {}", key, value))?;
+        runtime = match key {
+            "time" => Some(Duration::from_secs(value)),
+            "time_ms" => Some(Duration::from_millis(value)),
+            _ => return Err(format!("Unknown parameter {}", key)),
+        };
+    }
+    Ok(runtime)
+}
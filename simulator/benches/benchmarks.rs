@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use model::input::read_input_from_file;
+use simulator::{generate_map, simulate};
+
+/// Numbers of turns every solution is simulated for
+const TURNS: [u32; 3] = [25, 50, 100];
+
+macro_rules! simulate_solution {
+    ($criterion: ident, $path: expr, $name: expr) => {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/", $path);
+        let (mut task, solution) = read_input_from_file(path).unwrap();
+        let map = generate_map(&task, &solution.unwrap()).unwrap();
+
+        let mut group = $criterion.benchmark_group(concat!("simulate ", $name));
+        for turns in TURNS {
+            task.turns = turns;
+            group.bench_with_input(BenchmarkId::from_parameter(turns), &task, |b, task| {
+                b.iter(|| simulate(task, &map, true))
+            });
+        }
+        group.finish();
+    };
+}
+
+fn simulate_task_001(c: &mut Criterion) {
+    simulate_solution!(c, "inputs/best_001.json", "task 001");
+}
+
+fn simulate_task_002(c: &mut Criterion) {
+    simulate_solution!(c, "inputs/best_002.json", "task 002");
+}
+
+fn simulate_task_003(c: &mut Criterion) {
+    simulate_solution!(c, "inputs/best_003.json", "task 003");
+}
+
+fn simulate_task_004(c: &mut Criterion) {
+    simulate_solution!(c, "inputs/best_004.json", "task 004");
+}
+
+fn simulate_task_xxl_001(c: &mut Criterion) {
+    simulate_solution!(c, "inputs/best_xxl_001.json", "task xxl_001");
+}
+
+criterion_group!(
+    simulator_benches,
+    simulate_task_001,
+    simulate_task_002,
+    simulate_task_003,
+    simulate_task_004,
+    simulate_task_xxl_001
+);
+criterion_main!(simulator_benches);
@@ -0,0 +1,132 @@
+//! Simulation of all solutions in a directory (see `--batch`)
+
+use std::{fs, path::Path};
+
+use rayon::prelude::*;
+
+use model::{input::read_input_from_file, solution::Solution};
+use simulator::{find_dead_ends, generate_map, simulate};
+
+const TASK_SUFFIX: &str = ".task.json";
+const SOLUTION_SUFFIX: &str = ".solution.json";
+
+/// Outcome of simulating a single solution
+struct Row {
+    task: String,
+    solution: String,
+    /// Score and turn, or why the solution was not simulated
+    result: Result<(u32, u32), String>,
+    num_warnings: usize,
+}
+
+/// Simulates every `<name>[.<anything>].solution.json` in `dir` on `<name>.task.json` (in parallel)
+/// and prints a table of the results
+///
+/// Returns Err, if the directory cannot be read.
+pub(crate) fn run_batch(dir: &str) -> Result<(), String> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .map_err(|e| format!("Cannot read directory {}: {}", dir, e))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    files.sort();
+
+    let pairs: Vec<(&str, &str)> = files
+        .iter()
+        .filter_map(|file| file.strip_suffix(TASK_SUFFIX).map(|name| (file, name)))
+        .flat_map(|(task, name)| {
+            files
+                .iter()
+                .filter(move |file| {
+                    file.ends_with(SOLUTION_SUFFIX)
+                        && (file.as_str() == format!("{}{}", name, SOLUTION_SUFFIX)
+                            || file.starts_with(&format!("{}.", name)))
+                })
+                .map(move |solution| (task.as_str(), solution.as_str()))
+        })
+        .collect();
+
+    let rows: Vec<Row> = pairs
+        .par_iter()
+        .map(|&(task, solution)| simulate_pair(Path::new(dir), task, solution))
+        .collect();
+
+    print_table(&rows);
+    Ok(())
+}
+
+fn simulate_pair(dir: &Path, task_file: &str, solution_file: &str) -> Row {
+    let mut row = Row {
+        task: task_file.to_string(),
+        solution: solution_file.to_string(),
+        result: Err(String::new()),
+        num_warnings: 0,
+    };
+
+    let task_path = dir.join(task_file);
+    let task = match read_input_from_file(&task_path.to_string_lossy()) {
+        Ok((task, _)) => task,
+        Err(e) => {
+            row.result = Err(format!("invalid task: {}", e));
+            return row;
+        }
+    };
+    let solution = match fs::read_to_string(dir.join(solution_file))
+        .map_err(|e| e.to_string())
+        .and_then(|content| Solution::from_json_string(&content).map_err(|e| e.to_string()))
+    {
+        Ok(solution) => solution,
+        Err(e) => {
+            row.result = Err(format!("unreadable solution: {}", e));
+            return row;
+        }
+    };
+    let map = match generate_map(&task, &solution) {
+        Ok(map) => map,
+        Err(e) => {
+            row.result = Err(e.to_string());
+            return row;
+        }
+    };
+
+    let result = simulate(&task, &map, true);
+    row.num_warnings = result.warnings.len() + find_dead_ends(&task, &map).len();
+    row.result = Ok((result.score, result.turn));
+    row
+}
+
+fn print_table(rows: &[Row]) {
+    let width = |name: &str, column: fn(&Row) -> &str| {
+        rows.iter()
+            .map(|row| column(row).len())
+            .max()
+            .unwrap_or(0)
+            .max(name.len())
+    };
+    let task_width = width("task", |row| &row.task);
+    let solution_width = width("solution", |row| &row.solution);
+
+    println!(
+        "{:<task_width$} {:<solution_width$} {:>6} {:>5} {:>8}",
+        "task", "solution", "score", "turn", "warnings"
+    );
+    for row in rows {
+        match &row.result {
+            Ok((score, turn)) => println!(
+                "{:<task_width$} {:<solution_width$} {:>6} {:>5} {:>8}",
+                row.task, row.solution, score, turn, row.num_warnings
+            ),
+            Err(e) => println!(
+                "{:<task_width$} {:<solution_width$} {}",
+                row.task, row.solution, e
+            ),
+        }
+    }
+
+    let num_valid = rows.iter().filter(|row| row.result.is_ok()).count();
+    println!(
+        "\n{} solution(s), {} simulated, {} invalid",
+        rows.len(),
+        num_valid,
+        rows.len() - num_valid
+    );
+}
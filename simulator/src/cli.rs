@@ -1,8 +1,82 @@
 use clap::Parser;
+use common::logging::LogLevel;
+
+/// Format of the frames written by `--frames`
+#[cfg(feature = "frames")]
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum FramesFormat {
+    Ascii,
+    Svg,
+}
+
+/// Format of the report printed by `--timeline`
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum TimelineFormat {
+    Text,
+    Json,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
     #[arg(short, long, help = "Supress printing steps")]
     pub quiet: bool,
+
+    #[arg(
+        long,
+        default_value = "info",
+        help = "Max level of log messages printed to stderr (steps are logged as info)"
+    )]
+    pub log_level: LogLevel,
+
+    #[arg(long, help = "Print log messages as JSON lines")]
+    pub log_json: bool,
+
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Panic on malformed input instead of scoring it with 0 (default in debug builds, disable with --strict=false)"
+    )]
+    pub strict: Option<bool>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Simulate each *.solution.json in the directory on the *.task.json of the same name and print a summary table"
+    )]
+    pub batch: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "batch",
+        help = "Simulate the task / cli file, then read objects to add or remove from stdin and print the score after each change"
+    )]
+    pub what_if: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["batch", "what_if"],
+        help = "Print when each factory produced, idled or waited on resources after the result"
+    )]
+    pub timeline: Option<TimelineFormat>,
+
+    #[cfg(feature = "frames")]
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["batch", "what_if"],
+        help = "Write one rendered frame per turn and an index.json into the directory"
+    )]
+    pub frames: Option<String>,
+
+    #[cfg(feature = "frames")]
+    #[arg(
+        long,
+        default_value = "ascii",
+        help = "Format of the frames written by --frames"
+    )]
+    pub frames_format: FramesFormat,
 }
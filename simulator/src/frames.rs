@@ -0,0 +1,175 @@
+//! Export of one rendered frame per turn of a simulation, e.g. to assemble an animation
+
+use std::{fmt::Write, fs, path::Path};
+
+use model::{
+    map::Map,
+    object::{Coord, Object, ObjectType},
+    task::Task,
+};
+use serde_json::json;
+
+use crate::{pretty_format_resources, simulate_with_observer, SimulatorResult, TurnState};
+
+/// Size of a cell in SVG frames, in pixels
+const SVG_CELL_SIZE: u32 = 10;
+
+/// Format of the exported frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// The map as printed by the printer, where cells of objects that hold resources are
+    /// replaced by `*`, followed by the resources each of these objects holds
+    Ascii,
+    /// The map as colored squares, where cells of objects that hold resources are highlighted
+    Svg,
+}
+
+impl FrameFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Ascii => "txt",
+            FrameFormat::Svg => "svg",
+        }
+    }
+}
+
+/// Simulates `map` and writes one frame per turn into `dir`, plus an `index.json` that lists the
+/// frames with the turn and score they show
+pub fn export_frames(
+    task: &Task,
+    map: &Map,
+    dir: &str,
+    format: FrameFormat,
+) -> Result<SimulatorResult, String> {
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+
+    let mut frames = vec![];
+    let mut error = None;
+    let (result, _) = simulate_with_observer(task, map, true, &mut |state| {
+        if error.is_some() {
+            return;
+        }
+        let file = format!("{:04}.{}", state.turn, format.extension());
+        let frame = match format {
+            FrameFormat::Ascii => render_ascii(map, state),
+            FrameFormat::Svg => render_svg(map, state),
+        };
+        match fs::write(dir.join(&file), frame) {
+            Ok(_) => frames.push(json!({
+                "turn": state.turn,
+                "score": state.score,
+                "file": file,
+            })),
+            Err(e) => error = Some(format!("Cannot write frame {}: {}", file, e)),
+        }
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    let index = json!({
+        "width": map.width(),
+        "height": map.height(),
+        "turns": task.turns,
+        "format": format.extension(),
+        "frames": frames,
+    });
+    fs::write(dir.join("index.json"), index.to_string())
+        .map_err(|e| format!("Cannot write index: {}", e))?;
+
+    Ok(result)
+}
+
+/// Objects that transport resources and hold any at the end of the turn
+fn loaded_objects<'m>(
+    map: &'m Map,
+    state: &'m TurnState,
+) -> impl Iterator<Item = (&'m Object, &'m [u32; 8])> {
+    map.get_indexed_objects().filter_map(|(index, object)| {
+        let holdings = &state.holdings[index.index()];
+        let transports = matches!(
+            object.kind(),
+            ObjectType::Mine | ObjectType::Conveyor | ObjectType::Combiner
+        );
+        (transports && holdings.iter().any(|&amount| amount > 0)).then_some((object, holdings))
+    })
+}
+
+fn render_ascii(map: &Map, state: &TurnState) -> String {
+    let mut rows: Vec<Vec<char>> = (0..map.height() as Coord)
+        .map(|y| {
+            (0..map.width() as Coord)
+                .map(|x| map.get_cell(x, y).map(char::from).unwrap_or('.'))
+                .collect()
+        })
+        .collect();
+    for (object, _) in loaded_objects(map, state) {
+        for ((x, y), _) in object.cells(Default::default()) {
+            rows[y as usize][x as usize] = '*';
+        }
+    }
+
+    let mut frame = format!("Turn {}, score {}\n", state.turn, state.score);
+    for row in rows {
+        frame.extend(row);
+        frame.push('\n');
+    }
+    for (object, holdings) in loaded_objects(map, state) {
+        let (x, y) = object.coords();
+        let _ = writeln!(
+            frame,
+            "({}, {}) holds [{}]",
+            x,
+            y,
+            pretty_format_resources(holdings)
+        );
+    }
+    frame
+}
+
+fn render_svg(map: &Map, state: &TurnState) -> String {
+    let mut frame = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+        map.width() as u32 * SVG_CELL_SIZE,
+        map.height() as u32 * SVG_CELL_SIZE + 2 * SVG_CELL_SIZE,
+    );
+    let rect = |frame: &mut String, (x, y): (Coord, Coord), color: &str| {
+        let _ = write!(
+            frame,
+            r#"<rect x="{}" y="{}" width="{size}" height="{size}" fill="{}"/>"#,
+            x as u32 * SVG_CELL_SIZE,
+            y as u32 * SVG_CELL_SIZE,
+            color,
+            size = SVG_CELL_SIZE,
+        );
+    };
+    frame.push_str(r#"<rect width="100%" height="100%" fill="white"/>"#);
+    for object in map.get_objects() {
+        let color = match object.kind() {
+            ObjectType::Obstacle => "dimgray",
+            ObjectType::Deposit => "peru",
+            ObjectType::Factory => "steelblue",
+            ObjectType::Mine => "darkkhaki",
+            ObjectType::Conveyor => "silver",
+            ObjectType::Combiner => "plum",
+        };
+        for (point, _) in object.cells(Default::default()) {
+            rect(&mut frame, point, color);
+        }
+    }
+    for (object, _) in loaded_objects(map, state) {
+        for (point, _) in object.cells(Default::default()) {
+            rect(&mut frame, point, "orange");
+        }
+    }
+    let _ = write!(
+        frame,
+        r#"<text x="2" y="{}" font-size="{}">Turn {}, score {}</text></svg>"#,
+        map.height() as u32 * SVG_CELL_SIZE + SVG_CELL_SIZE + SVG_CELL_SIZE / 2,
+        SVG_CELL_SIZE,
+        state.turn,
+        state.score,
+    );
+    frame
+}
@@ -0,0 +1,120 @@
+//! Conditions that hold after every turn of a correct simulation
+//!
+//! Checking them takes time linear in the number of objects per turn, so they are only checked in
+//! debug builds (see [simulate_with_throughput](crate::simulate_with_throughput)).
+
+use model::{analysis::NUM_RESOURCE_TYPES, object::Object, task::Product};
+
+use crate::FlowGraph;
+
+/// Resource bookkeeping of a simulation, that is checked against the simulation's state
+pub(crate) struct Invariants {
+    /// Total amount of each resource type in all deposits before the first turn
+    initial: [u32; NUM_RESOURCE_TYPES],
+    /// Resource types each object can ever receive (as bit set), by the object's index
+    receivable: Vec<u8>,
+    /// Amount of each resource type consumed by factories so far
+    consumed: [u32; NUM_RESOURCE_TYPES],
+}
+
+impl Invariants {
+    /// `resources` are the resources left in each deposit before the first turn
+    pub(crate) fn new(graph: &FlowGraph, resources: &[u32]) -> Invariants {
+        let mut initial = [0; NUM_RESOURCE_TYPES];
+        let mut receivable = vec![0u8; graph.objects.len()];
+        for &(index, _) in graph.deposits.iter() {
+            let resource = deposit_resource(graph.object(index));
+            initial[resource] += resources[index.index()];
+            receivable[index.index()] = 1 << resource;
+        }
+
+        // objects receive the resources their suppliers can receive; cycles need several passes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for index in 0..graph.objects.len() {
+                let (start, end) = graph.suppliers[index];
+                let mask = graph.supplier_list[start as usize..end as usize]
+                    .iter()
+                    .fold(receivable[index], |mask, supplier| {
+                        mask | receivable[supplier.index()]
+                    });
+                if mask != receivable[index] {
+                    receivable[index] = mask;
+                    changed = true;
+                }
+            }
+        }
+
+        Invariants {
+            initial,
+            receivable,
+            consumed: [0; NUM_RESOURCE_TYPES],
+        }
+    }
+
+    /// Records that a factory produced `product`
+    pub(crate) fn consume(&mut self, product: &Product) {
+        for (resource, amount) in product.resources.iter().enumerate() {
+            self.consumed[resource] += amount;
+        }
+    }
+
+    /// Panics with the first violated invariant
+    ///
+    /// - Every unit of a resource is either left in a deposit, held by an object or consumed
+    /// - No object holds a resource type that none of its suppliers can pass on
+    pub(crate) fn check(
+        &self,
+        turn: u32,
+        graph: &FlowGraph,
+        resources: &[u32],
+        resource_distribution: &[[u32; NUM_RESOURCE_TYPES]],
+    ) {
+        let mut in_deposits = [0; NUM_RESOURCE_TYPES];
+        for &(index, _) in graph.deposits.iter() {
+            in_deposits[deposit_resource(graph.object(index))] += resources[index.index()];
+        }
+
+        let mut in_transit = [0; NUM_RESOURCE_TYPES];
+        for (index, held) in resource_distribution.iter().enumerate() {
+            for (resource, &amount) in held.iter().enumerate() {
+                if amount == 0 {
+                    continue;
+                }
+                in_transit[resource] += amount;
+                if self.receivable[index] & (1 << resource) == 0 {
+                    let object = graph.objects[index].unwrap();
+                    panic!(
+                        "Turn {}: {:?} at {:?} holds {} unit(s) of resource {}, which it can never receive",
+                        turn,
+                        object.kind(),
+                        object.coords(),
+                        amount,
+                        resource
+                    );
+                }
+            }
+        }
+
+        for resource in 0..NUM_RESOURCE_TYPES {
+            let total = in_deposits[resource] + in_transit[resource] + self.consumed[resource];
+            assert_eq!(
+                total, self.initial[resource],
+                "Turn {}: amount of resource {} is not conserved ({} in deposits, {} in transit, {} consumed, {} initially)",
+                turn,
+                resource,
+                in_deposits[resource],
+                in_transit[resource],
+                self.consumed[resource],
+                self.initial[resource]
+            );
+        }
+    }
+}
+
+fn deposit_resource(deposit: &Object) -> usize {
+    deposit
+        .subtype()
+        .expect("Invalid deposit: must have subtype") as usize
+}
@@ -1,23 +1,99 @@
-use std::{cell::RefCell, collections::VecDeque};
+use std::{collections::VecDeque, fmt::Display};
 
 use fxhash::FxHashMap as HashMap;
 use fxhash::FxHashSet as HashSet;
 
 use model::{
-    coord::neighbours,
-    map::Map,
-    object::{Object, ObjectCell, ObjectID},
+    analysis::NUM_RESOURCE_TYPES,
+    error::ModelError,
+    map::{Map, Violation},
+    object::{Object, ObjectID, ObjectIndex},
     solution::Solution,
-    task::{Product, Task},
+    strict::StrictMode,
+    task::Task,
 };
+use tracing::{debug_span, info, warn};
+
+use crate::invariants::Invariants;
+
+#[cfg(feature = "frames")]
+pub mod frames;
+mod invariants;
+pub mod timeline;
 
 /// Result of simulating a mpa
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// Results are compared (and equal) by their score and turn only, not by their warnings and
+/// statistics.
+#[derive(Clone, Debug, Eq)]
 pub struct SimulatorResult {
     /// The final score
     pub score: u32,
     /// The turn, the final score was reached
     pub turn: u32,
+    /// Suspicious configurations found on the simulated map
+    pub warnings: Vec<SimulationWarning>,
+    /// Statistics of each product of the task (in the task's order)
+    pub products: Vec<ProductStats>,
+}
+
+/// Statistics about a single product during a simulation
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProductStats {
+    /// The product's subtype
+    pub subtype: u8,
+    /// Number of units produced by all factories of the product
+    pub produced: u32,
+    /// Points contributed to the score
+    pub points: u32,
+    /// The turn the last unit was produced (0 if none was produced)
+    pub last_turn: u32,
+}
+
+/// A configuration that is legal, but makes the simulated score misleading
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulationWarning {
+    /// Objects whose egresses feed each other's ingresses in a loop (in order of the flow), so
+    /// resources may circulate forever instead of reaching a factory
+    Cycle(Vec<Object>),
+    /// A mine that is not next to a deposit of a resource any product needs
+    UselessMine(Object),
+    /// Objects whose resources never reach a factory (in order of the flow)
+    DanglingChain(Vec<Object>),
+}
+
+impl Display for SimulationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationWarning::Cycle(objects) => {
+                let coords = objects
+                    .iter()
+                    .map(|object| format!("{:?}", object.coords()))
+                    .collect::<Vec<String>>()
+                    .join(" -> ");
+                write!(f, "resources circulate in a cycle: {}", coords)
+            }
+            SimulationWarning::UselessMine(mine) => write!(
+                f,
+                "mine at {:?} is not next to a deposit of a resource any product needs",
+                mine.coords()
+            ),
+            SimulationWarning::DanglingChain(objects) => {
+                let coords = objects
+                    .iter()
+                    .map(|object| format!("{:?}", object.coords()))
+                    .collect::<Vec<String>>()
+                    .join(" -> ");
+                write!(f, "resources never reach a factory: {}", coords)
+            }
+        }
+    }
+}
+
+impl PartialEq for SimulatorResult {
+    fn eq(&self, other: &Self) -> bool {
+        self.score == other.score && self.turn == other.turn
+    }
 }
 
 impl PartialOrd for SimulatorResult {
@@ -45,47 +121,123 @@ impl Ord for SimulatorResult {
 
 /// Runs a simulation of a task and a given solution map
 pub fn simulate(task: &Task, map: &Map, quiet: bool) -> SimulatorResult {
-    let products_by_type = task
-        .products
-        .iter()
-        .map(|product| (product.subtype, product))
-        .collect::<HashMap<u8, &Product>>();
+    simulate_with_throughput(task, map, quiet).0
+}
+
+/// Runs a simulation of a task and a given solution map
+///
+/// Additionally returns the total amount of resources each object received during the simulation.
+/// Objects that never received any resources are not contained in the returned map.
+pub fn simulate_with_throughput(
+    task: &Task,
+    map: &Map,
+    quiet: bool,
+) -> (SimulatorResult, HashMap<ObjectID, u32>) {
+    simulate_with_observer(task, map, quiet, &mut |_| {})
+}
+
+/// Simulates the first `turn_limit` turns (at most all turns of the task) quietly and returns the
+/// score after each of them
+///
+/// The score after turn `t` is at index `t - 1`, so of two solutions with the same final score, the
+/// one whose trajectory is higher reaches its score earlier. Empty if the map cannot be simulated.
+pub fn simulate_until(task: &Task, map: &Map, turn_limit: u32) -> Vec<u32> {
+    let task = Task {
+        turns: turn_limit.min(task.turns),
+        ..task.clone()
+    };
+    let mut scores = Vec::with_capacity(task.turns as usize);
+    simulate_with_observer(&task, map, true, &mut |state| scores.push(state.score));
+    scores
+}
+
+/// State of a simulation at the end of a turn
+pub struct TurnState<'s> {
+    pub turn: u32,
+    /// Score reached so far
+    pub score: u32,
+    /// Amount of each resource every object holds, by the object's (raw) index
+    ///
+    /// Deposits hold the resources they extracted for their mines at the end of the turn.
+    pub holdings: &'s [[u32; 8]],
+    /// Resources left in each deposit, by the deposit's (raw) index
+    pub deposits: &'s [u32],
+    /// Units each factory produced in this turn, by the factory's (raw) index
+    pub produced: &'s [u32],
+}
+
+/// Runs a simulation like [simulate_with_throughput] and passes the state at the end of each turn
+/// to `on_turn`
+pub fn simulate_with_observer(
+    task: &Task,
+    map: &Map,
+    quiet: bool,
+    on_turn: &mut dyn FnMut(&TurnState),
+) -> (SimulatorResult, HashMap<ObjectID, u32>) {
+    let _span = debug_span!("simulation", turns = task.turns).entered();
+
+    let products = task.product_catalog();
 
     let mut score = 0;
 
-    // Map from deposit to its resources
-    let mut resources: HashMap<ObjectID, u32> = map
-        .get_objects()
-        .filter_map(|obj| match obj {
-            Object::Deposit { width, height, .. } => {
-                Some((obj.id(), *width as u32 * *height as u32 * 5))
-            }
-            _ => None,
+    let mut product_stats: Vec<ProductStats> = task
+        .products
+        .iter()
+        .map(|product| ProductStats {
+            subtype: product.subtype,
+            ..Default::default()
         })
         .collect();
 
-    // Map from objectID to amount of resources that object currently holds
-    let mut resource_distribution: HashMap<ObjectID, RefCell<[u32; 8]>> = map
-        .get_objects()
-        .map(|obj| (obj.id(), RefCell::new([0; 8])))
-        .collect();
+    // Map from objectID to total amount of resources that object received
+    let mut throughput: HashMap<ObjectID, u32> = HashMap::default();
 
-    let objects: HashMap<ObjectID, &Object> =
-        map.get_objects().map(|obj| (obj.id(), obj)).collect();
+    // Resources left in each deposit, by object index
+    let mut resources: Vec<u32> = vec![0; map.num_object_indices()];
+    for (index, obj) in map.get_indexed_objects() {
+        if let Object::Deposit { .. } = obj {
+            resources[index.index()] = obj.initial_resources();
+        }
+    }
+
+    // Amount of resources each object currently holds, by object index
+    let mut resource_distribution: Vec<[u32; 8]> = vec![[0; 8]; map.num_object_indices()];
+
+    // Units each factory produced in the current turn, by the factory's index
+    let mut produced: Vec<u32> = vec![0; map.num_object_indices()];
+
+    let objects: Vec<(ObjectIndex, &Object)> = map.get_indexed_objects().collect();
+
+    let warnings = find_cycles(map, &objects);
+
+    let graph = match check_products(task, &objects)
+        .and_then(|_| FlowGraph::new(map, &objects))
+        .or_else(StrictMode::fail)
+    {
+        Ok(graph) => graph,
+        Err(e) => {
+            warn!("Cannot simulate map: {}", e);
+            return (
+                SimulatorResult {
+                    score: 0,
+                    turn: 0,
+                    warnings,
+                    products: vec![],
+                },
+                throughput,
+            );
+        }
+    };
 
     // bfs-queue of all objects. Used to breadth-first search a resource path from factories to deposits
     let all_objects_queue = objects
         .iter()
         .filter(|(_, object)| matches!(*object, Object::Factory { .. }))
-        .map(|(id, object)| (*id, *object))
-        .collect::<VecDeque<(ObjectID, &Object)>>();
+        .map(|(index, _)| *index)
+        .collect::<VecDeque<ObjectIndex>>();
 
-    // all deposits
-    let deposits = objects
-        .iter()
-        .filter(|(_, object)| matches!(object, Object::Deposit { .. }))
-        .map(|(object_id, object)| (*object_id, *object))
-        .collect::<Vec<(ObjectID, &Object)>>();
+    // simulator bugs would silently distort scores, so debug builds check every turn
+    let mut invariants = cfg!(debug_assertions).then(|| Invariants::new(&graph, &resources));
 
     let mut best_turn = 0;
     for turn in 1..=task.turns {
@@ -94,124 +246,84 @@ pub fn simulate(task: &Task, map: &Map, quiet: bool) -> SimulatorResult {
         let mut queue = all_objects_queue.clone();
 
         // try to *pull* resources at ingresses
-        while let Some((object_id, object)) = queue.pop_front() {
+        while let Some(object_index) = queue.pop_front() {
+            let object = graph.object(object_index);
+
             // skip mines - mines dont 'pull' their resources, because deposits push them
             // at the *end of the turn* into the mines
             if matches!(object, Object::Deposit { .. }) {
                 continue;
             }
 
-            // Additional tracking of moved resource; only used for pretty printing
-            let mut resources_incoming = vec![0; 8];
-
-            for (x, y) in object.ingresses().iter() {
-                for (nx, ny) in neighbours(*x, *y) {
-                    if let Some(ObjectCell::Egress {
-                        id: id_outgoing, ..
-                    }) = map.get_cell(nx, ny)
-                    {
-                        // move resources
-                        for (resource_index, value) in resource_distribution
-                            .get(&object_id)
-                            .unwrap()
-                            .borrow_mut()
-                            .iter_mut()
-                            .enumerate()
-                        {
-                            let outgoing_resource =
-                                &mut resource_distribution.get(id_outgoing).unwrap().borrow_mut()
-                                    [resource_index];
-
-                            let amount = match object {
-                                Object::Mine { .. } => (*outgoing_resource).min(3),
-                                _ => *outgoing_resource,
-                            };
-
-                            *value += amount;
-                            *outgoing_resource -= amount;
-                            resources_incoming[resource_index] += amount;
-                        }
+            // Additional tracking of moved resource; used for pretty printing and throughput
+            let mut resources_incoming = [0; 8];
 
-                        // enqueue next object
-                        queue.push_back((*id_outgoing, objects[id_outgoing]));
-                    }
+            for outgoing_index in graph.suppliers(object_index) {
+                // move resources
+                for resource_index in 0..8 {
+                    let outgoing_resource =
+                        resource_distribution[outgoing_index.index()][resource_index];
+
+                    let amount = match object {
+                        Object::Mine { .. } => outgoing_resource.min(3),
+                        _ => outgoing_resource,
+                    };
+
+                    resource_distribution[object_index.index()][resource_index] += amount;
+                    resource_distribution[outgoing_index.index()][resource_index] -= amount;
+                    resources_incoming[resource_index] += amount;
                 }
+
+                // enqueue next object
+                queue.push_back(*outgoing_index);
+            }
+
+            let amount_incoming: u32 = resources_incoming.iter().sum();
+            if amount_incoming > 0 {
+                *throughput.entry(object.id()).or_default() += amount_incoming;
             }
 
             let (x, y) = object.coords();
 
-            if resources_incoming.iter().any(|value| *value > 0) && !quiet {
-                println!(
-                    "{} (start): ({}, {}) accepts [{}], holds [{}]",
+            if amount_incoming > 0 && !quiet {
+                info!(
                     turn,
+                    "(start): ({}, {}) accepts [{}], holds [{}]",
                     x,
                     y,
                     pretty_format_resources(&resources_incoming),
-                    pretty_format_resources(&*resource_distribution[&object_id].borrow()),
+                    pretty_format_resources(&resource_distribution[object_index.index()]),
                 );
             }
         }
 
         // END OF ROUND
 
-        for (deposit_id, deposit) in deposits.iter() {
+        for (deposit_index, num_mines) in graph.deposits.iter() {
+            let deposit = graph.object(*deposit_index);
             let resource_type = deposit
                 .subtype()
                 .expect("Invalid deposit: must have subtype")
                 as usize;
 
-            // Neighbours of a deposit's egresses (that may be ingresses of a mine)
-            let mut visited_cells = HashSet::default();
-
-            for (x, y) in deposit.egresses().iter() {
-                for (nx, ny) in neighbours(*x, *y) {
-                    if visited_cells.contains(&(nx, ny)) {
-                        continue;
-                    }
-
-                    visited_cells.insert((nx, ny));
-
-                    if let Some(ObjectCell::Ingress {
-                        id: id_receiving, ..
-                    }) = map.get_cell(nx, ny)
-                    {
-                        let receiving_object = &objects[id_receiving];
-
-                        if let Object::Mine { .. } = receiving_object {
-                            let amount = resources[deposit_id].min(3);
-                            let deposits_resources =
-                                resource_distribution.get_mut(deposit_id).unwrap();
-                            deposits_resources.borrow_mut()[resource_type] += amount;
-
-                            if let Some(r) = resources.get_mut(deposit_id) {
-                                *r -= amount;
-                            }
-
-                            let coords = deposit.coords();
-
-                            if amount > 0 && !quiet {
-                                println!(
-                                    "{} (end): ({}, {}) takes [{}x{}], [{}x{}] available",
-                                    turn,
-                                    coords.0,
-                                    coords.1,
-                                    amount,
-                                    resource_type,
-                                    resources.get(deposit_id).unwrap(),
-                                    resource_type,
-                                );
-                            }
-                        } else {
-                            #[cfg(debug_assertions)]
-                            {
-                                panic!("Non-Mine object connected to deposit")
-                            }
-                            #[cfg(not(debug_assertions))]
-                            {
-                                return SimulatorResult { score: 0, turn: 0 };
-                            }
-                        }
-                    }
+            for _ in 0..*num_mines {
+                let amount = resources[deposit_index.index()].min(3);
+                resource_distribution[deposit_index.index()][resource_type] += amount;
+                resources[deposit_index.index()] -= amount;
+
+                let coords = deposit.coords();
+
+                if amount > 0 && !quiet {
+                    info!(
+                        turn,
+                        "(end): ({}, {}) takes [{}x{}], [{}x{}] available",
+                        coords.0,
+                        coords.1,
+                        amount,
+                        resource_type,
+                        resources[deposit_index.index()],
+                        resource_type,
+                    );
                 }
             }
         }
@@ -220,29 +332,46 @@ pub fn simulate(task: &Task, map: &Map, quiet: bool) -> SimulatorResult {
             .iter()
             .filter(|(_, object)| matches!(object, Object::Factory { .. }));
 
-        for (factory_id, object) in factories {
+        produced.fill(0);
+        for (factory_index, object) in factories {
             if let Object::Factory { subtype, .. } = object {
-                let factory_resources = resource_distribution.get_mut(factory_id).unwrap();
-                if let Some(&product) = products_by_type.get(subtype) {
+                let factory_resources = &mut resource_distribution[factory_index.index()];
+                if let Some(product) = products.by_subtype(*subtype) {
                     'produce_loop: loop {
                         let can_produce = product.resources.iter().enumerate().all(
                             |(resource_index, resource_amount)| {
-                                factory_resources.borrow_mut()[resource_index] >= *resource_amount
+                                factory_resources[resource_index] >= *resource_amount
                             },
                         );
 
                         if can_produce {
                             score += product.points;
+                            produced[factory_index.index()] += 1;
+                            if let Some(stats) = product_stats
+                                .iter_mut()
+                                .find(|stats| stats.subtype == *subtype)
+                            {
+                                stats.produced += 1;
+                                stats.points += product.points;
+                                stats.last_turn = turn;
+                            }
                             for (resource_index, amount) in product.resources.iter().enumerate() {
-                                factory_resources.borrow_mut()[resource_index] -= amount;
+                                factory_resources[resource_index] -= amount;
+                            }
+                            if let Some(invariants) = &mut invariants {
+                                invariants.consume(product);
                             }
 
                             let (x, y) = object.coords();
 
                             if !quiet {
-                                println!(
-                                    "{} (end): ({}, {}) produces {} ({} points)",
-                                    turn, x, y, subtype, product.points
+                                info!(
+                                    turn,
+                                    "(end): ({}, {}) produces {} ({} points)",
+                                    x,
+                                    y,
+                                    subtype,
+                                    product.points
                                 );
                             }
 
@@ -251,31 +380,342 @@ pub fn simulate(task: &Task, map: &Map, quiet: bool) -> SimulatorResult {
                             break 'produce_loop;
                         }
                     }
-                } else {
-                    panic!(
-                        "no product for subtype {} known but a factory exists",
-                        subtype
-                    );
+                }
+            }
+        }
+
+        if let Some(invariants) = &invariants {
+            invariants.check(turn, &graph, &resources, &resource_distribution);
+        }
+
+        on_turn(&TurnState {
+            turn,
+            score,
+            holdings: &resource_distribution,
+            deposits: &resources,
+            produced: &produced,
+        });
+    }
+
+    (
+        SimulatorResult {
+            score,
+            turn: best_turn,
+            warnings,
+            products: product_stats,
+        },
+        throughput,
+    )
+}
+
+/// Connections between the objects of a map, which do not change during a simulation
+///
+/// Derived once before the first turn, so turns do not have to scan the cells next to ingresses
+/// and egresses.
+struct FlowGraph<'m> {
+    /// Objects by their (raw) index
+    objects: Vec<Option<&'m Object>>,
+    /// Range of each object's suppliers in `supplier_list`, by the object's index
+    ///
+    /// Only objects that (indirectly) supply a factory have suppliers.
+    suppliers: Vec<(u32, u32)>,
+    /// Objects each object pulls resources from, in the order of its ingresses
+    supplier_list: Vec<ObjectIndex>,
+    /// All deposits with the number of mine ingresses they push resources into
+    deposits: Vec<(ObjectIndex, u32)>,
+}
+
+impl<'m> FlowGraph<'m> {
+    /// Fails, if an ingress of an object other than a mine touches a deposit
+    fn new(map: &'m Map, objects: &[(ObjectIndex, &'m Object)]) -> Result<FlowGraph<'m>, String> {
+        let mut graph = FlowGraph {
+            objects: vec![None; map.num_object_indices()],
+            suppliers: vec![(0, 0); map.num_object_indices()],
+            supplier_list: vec![],
+            deposits: vec![],
+        };
+
+        let mut queue = VecDeque::new();
+        for (index, object) in objects.iter() {
+            graph.objects[index.index()] = Some(object);
+            if let Object::Factory { .. } = object {
+                queue.push_back(*index);
+            }
+        }
+
+        // suppliers of all objects reachable from factories
+        let mut visited = vec![false; map.num_object_indices()];
+        while let Some(index) = queue.pop_front() {
+            if std::mem::replace(&mut visited[index.index()], true) {
+                continue;
+            }
+            let start = graph.supplier_list.len() as u32;
+            for (x, y) in graph.object(index).ingresses() {
+                for (_, cell) in map.adjacent_cells(x, y).egresses() {
+                    graph.supplier_list.push(cell.index());
+                    queue.push_back(cell.index());
+                }
+            }
+            graph.suppliers[index.index()] = (start, graph.supplier_list.len() as u32);
+        }
+
+        for (index, object) in objects.iter() {
+            if let Object::Deposit { .. } = object {
+                // neighbours of a deposit's egresses (that must be ingresses of a mine)
+                let mut visited_cells = HashSet::default();
+                for (x, y) in object.egresses() {
+                    for (point, cell) in map.adjacent_cells(x, y).ingresses() {
+                        if !visited_cells.insert(point) {
+                            continue;
+                        }
+                        match map.get_object_by_index(cell.index()) {
+                            Some(Object::Mine { .. }) => {}
+                            other => {
+                                return Err(format!(
+                                    "Non-mine object {:?} connected to deposit {:?}",
+                                    other, object
+                                ))
+                            }
+                        }
+                    }
+                }
+                graph.deposits.push((*index, visited_cells.len() as u32));
+            }
+        }
+
+        Ok(graph)
+    }
+
+    fn object(&self, index: ObjectIndex) -> &'m Object {
+        self.objects[index.index()].unwrap()
+    }
+
+    /// Objects `index` pulls resources from
+    fn suppliers(&self, index: ObjectIndex) -> &[ObjectIndex] {
+        let (start, end) = self.suppliers[index.index()];
+        &self.supplier_list[start as usize..end as usize]
+    }
+}
+
+/// Checks that a product of each factory is known and needs no more than the existing resources
+fn check_products(task: &Task, objects: &[(ObjectIndex, &Object)]) -> Result<(), String> {
+    for product in task.products.iter() {
+        if product.resources.len() > NUM_RESOURCE_TYPES {
+            return Err(format!(
+                "Product {} needs {} resource types, but only {} exist",
+                product.subtype,
+                product.resources.len(),
+                NUM_RESOURCE_TYPES
+            ));
+        }
+    }
+
+    let catalog = task.product_catalog();
+    for (_, object) in objects {
+        if let Object::Factory { subtype, .. } = object {
+            if catalog.by_subtype(*subtype).is_none() {
+                return Err(format!(
+                    "No product for subtype {} known but a factory exists",
+                    subtype
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Estimates the first turn, in which resources extracted along a path of `path_len` objects
+/// (including the mine) can be used by a factory
+///
+/// Deposits hand out resources at the end of the first turn. Afterwards, every object of the path
+/// holds them for one turn, before the next object pulls them. A factory produces at the end of
+/// the turn it received its resources.
+pub fn estimate_delivery_turns(path_len: u32) -> u32 {
+    path_len + 2
+}
+
+/// Finds all cycles of objects, whose egresses feed each other's ingresses
+///
+/// Cycles are reported in a deterministic order, each starting at the object it was entered at.
+fn find_cycles(map: &Map, objects: &[(ObjectIndex, &Object)]) -> Vec<SimulationWarning> {
+    let object_at = |index: ObjectIndex| map.get_object_by_index(index).unwrap();
+
+    let mut warnings = vec![];
+    let mut finished: HashSet<ObjectIndex> = HashSet::default();
+
+    for &(start, _) in objects {
+        if finished.contains(&start) {
+            continue;
+        }
+
+        // iterative dfs; the stack contains the current chain of objects
+        let mut stack: Vec<(ObjectIndex, Vec<ObjectIndex>)> =
+            vec![(start, successors(map, object_at(start)))];
+        while let Some((_, next)) = stack.last_mut() {
+            match next.pop() {
+                Some(index) if finished.contains(&index) => {}
+                Some(index) => match stack.iter().position(|(on_stack, _)| *on_stack == index) {
+                    Some(position) => {
+                        let cycle = stack[position..]
+                            .iter()
+                            .map(|(index, _)| object_at(*index).clone())
+                            .collect();
+                        warnings.push(SimulationWarning::Cycle(cycle));
+                    }
+                    None => stack.push((index, successors(map, object_at(index)))),
+                },
+                None => {
+                    let (index, _) = stack.pop().unwrap();
+                    finished.insert(index);
                 }
             }
         }
     }
 
-    SimulatorResult {
-        score,
-        turn: best_turn,
+    warnings
+}
+
+/// Objects that pull resources from `object`'s egresses
+fn successors(map: &Map, object: &Object) -> Vec<ObjectIndex> {
+    // only these objects have both, ingresses and an egress
+    if !matches!(
+        object,
+        Object::Mine { .. } | Object::Conveyor { .. } | Object::Combiner { .. }
+    ) {
+        return vec![];
     }
+    let mut successors = object
+        .egresses()
+        .into_iter()
+        .flat_map(|(x, y)| {
+            map.adjacent_cells(x, y)
+                .ingresses()
+                .map(|(_, cell)| cell.index())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<ObjectIndex>>();
+    successors.sort_unstable();
+    successors.dedup();
+    successors
+}
+
+/// Objects that push resources into `object`'s ingresses
+fn predecessors(map: &Map, object: &Object) -> Vec<ObjectIndex> {
+    let mut predecessors = object
+        .ingresses()
+        .into_iter()
+        .flat_map(|(x, y)| {
+            map.adjacent_cells(x, y)
+                .egresses()
+                .map(|(_, cell)| cell.index())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<ObjectIndex>>();
+    predecessors.sort_unstable();
+    predecessors.dedup();
+    predecessors
 }
 
-pub fn generate_map(task: &Task, solution: &Solution) -> Map {
-    let mut objects = Vec::with_capacity(task.objects.len() + solution.0.len());
-    objects.extend(task.objects.clone().into_iter().map(Object::from));
-    objects.extend(solution.0.iter().cloned().map(Object::from));
+/// Finds mines and chains of objects that cannot contribute to the score of an imported solution
+///
+/// A mine is useless, if it is not next to a deposit of a resource any product of `task` needs. A
+/// chain dangles, if the resources passed along it never reach a factory. Each dangling chain is
+/// reported once per object it ends at, in the order of the flow. Chains that end in a cycle are
+/// reported as [SimulationWarning::Cycle] by [simulate] instead.
+pub fn find_dead_ends(task: &Task, map: &Map) -> Vec<SimulationWarning> {
+    let object_at = |index: ObjectIndex| map.get_object_by_index(index).unwrap();
+    let is_transport = |object: &Object| {
+        matches!(
+            object,
+            Object::Mine { .. } | Object::Conveyor { .. } | Object::Combiner { .. }
+        )
+    };
+
+    let mut warnings = vec![];
+
+    let is_needed = |resource: u8| {
+        task.products.iter().any(|product| {
+            product
+                .resources
+                .get(resource as usize)
+                .is_some_and(|&amount| amount > 0)
+        })
+    };
+    for mine in map.get_objects() {
+        if !matches!(mine, Object::Mine { .. }) {
+            continue;
+        }
+        let is_useful = predecessors(map, mine)
+            .into_iter()
+            .any(|index| match object_at(index) {
+                Object::Deposit { subtype, .. } => is_needed(*subtype),
+                _ => false,
+            });
+        if !is_useful {
+            warnings.push(SimulationWarning::UselessMine(mine.clone()));
+        }
+    }
+
+    // objects from which resources reach a factory, found backwards from all factories
+    let mut supplies_factory: HashSet<ObjectIndex> = HashSet::default();
+    let mut queue: VecDeque<ObjectIndex> = map
+        .get_indexed_objects()
+        .filter(|(_, object)| matches!(object, Object::Factory { .. }))
+        .map(|(index, _)| index)
+        .collect();
+    while let Some(index) = queue.pop_front() {
+        if supplies_factory.insert(index) {
+            queue.extend(predecessors(map, object_at(index)));
+        }
+    }
+
+    for (end, object) in map.get_indexed_objects() {
+        if !is_transport(object)
+            || supplies_factory.contains(&end)
+            || !successors(map, object).is_empty()
+        {
+            continue;
+        }
+
+        // all objects upstream of a dangling end dangle as well
+        let mut chain = vec![];
+        let mut visited: HashSet<ObjectIndex> = HashSet::default();
+        let mut queue = VecDeque::from([end]);
+        while let Some(index) = queue.pop_front() {
+            let object = object_at(index);
+            if is_transport(object) && visited.insert(index) {
+                chain.push(object.clone());
+                queue.extend(predecessors(map, object));
+            }
+        }
+        chain.reverse();
+        warnings.push(SimulationWarning::DanglingChain(chain));
+    }
+
+    warnings
+}
+
+/// Creates the map of the task's objects and the solution's objects
+///
+/// Fails, if the task is malformed or an object of the solution cannot be placed (see
+/// [Map::can_insert_object]).
+pub fn generate_map(task: &Task, solution: &Solution) -> Result<Map, ModelError> {
+    let mut map = Map::try_new(task.width, task.height, task.objects.to_vec())
+        .map_err(ModelError::InvalidTask)?;
+    for object in solution.0.iter() {
+        map.insert_object(object.clone()).map_err(|reason| {
+            ModelError::InvalidSolution(Violation {
+                object: object.clone(),
+                reason,
+            })
+        })?;
+    }
 
-    Map::new(task.width, task.height, objects)
+    Ok(map)
 }
 
-fn pretty_format_resources(resources: &[u32]) -> String {
+pub(crate) fn pretty_format_resources(resources: &[u32]) -> String {
     resources
         .iter()
         .enumerate()
@@ -1,32 +1,121 @@
+mod batch;
 mod cli;
+mod what_if;
 
 use clap::Parser;
 
-use model::input::read_input_from_stdin;
+use common::logging::init_logging;
+use model::{
+    error::ModelError,
+    input::{read_input_from_file, read_input_from_stdin},
+    strict::StrictMode,
+};
+use serde_json::json;
 
-use cli::Args;
-use simulator::{generate_map, simulate};
+use cli::{Args, TimelineFormat};
+use simulator::{
+    find_dead_ends, generate_map, simulate,
+    timeline::{format_timelines, simulate_with_timelines, timelines_to_json},
+};
 
 fn main() {
     let args = Args::parse();
-    let (task, solution) = read_input_from_stdin().unwrap();
+    init_logging(args.log_level, args.log_json);
+    if let Some(strict) = args.strict {
+        StrictMode::set_enabled(strict);
+    }
+
+    if let Some(dir) = &args.batch {
+        if let Err(e) = batch::run_batch(dir) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let input = match &args.what_if {
+        Some(path) => read_input_from_file(path),
+        None => read_input_from_stdin(),
+    };
+    let (task, solution) = match input.or_else(StrictMode::fail) {
+        Ok(input) => input,
+        Err(e) => {
+            println!("{}", verdict(&ModelError::InvalidTask(e)));
+            std::process::exit(1);
+        }
+    };
     let solution = solution.unwrap_or_default();
 
-    let map = generate_map(&task, &solution);
-    let result = simulate(&task, &map, args.quiet);
+    let map = match generate_map(&task, &solution) {
+        Ok(map) => map,
+        Err(e) => {
+            println!("{}", verdict(&e));
+            std::process::exit(1);
+        }
+    };
+    if args.what_if.is_some() {
+        what_if::run_what_if(&task, map);
+        return;
+    }
+
+    #[cfg(feature = "frames")]
+    if let Some(dir) = &args.frames {
+        use simulator::frames::{export_frames, FrameFormat};
+
+        let format = match args.frames_format {
+            cli::FramesFormat::Ascii => FrameFormat::Ascii,
+            cli::FramesFormat::Svg => FrameFormat::Svg,
+        };
+        match export_frames(&task, &map, dir, format) {
+            Ok(result) => println!("{:?}", result),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let (mut result, timelines) = match args.timeline {
+        Some(_) => simulate_with_timelines(&task, &map, args.quiet),
+        None => (simulate(&task, &map, args.quiet), vec![]),
+    };
+    result.warnings.extend(find_dead_ends(&task, &map));
+    for warning in result.warnings.iter() {
+        eprintln!("Warning: {}", warning);
+    }
     println!("{:?}", result);
+    match args.timeline {
+        Some(TimelineFormat::Text) => print!("{}", format_timelines(&timelines)),
+        Some(TimelineFormat::Json) => println!("{}", timelines_to_json(&timelines)),
+        None => {}
+    }
+}
+
+/// The verdict on input that cannot be simulated, as JSON object
+fn verdict(error: &ModelError) -> serde_json::Value {
+    match error {
+        ModelError::InvalidTask(reason) => json!({
+            "verdict": "invalid task",
+            "reason": reason,
+        }),
+        ModelError::InvalidSolution(violation) => json!({
+            "verdict": "invalid solution",
+            "object": violation.object,
+            "reason": violation.reason,
+        }),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use model::input::read_input_from_file;
 
     macro_rules! test_simulation {
         ($path:expr) => {{
             let cli_path = $path;
             let (task, solution) = read_input_from_file(cli_path).expect("Could not read cli file");
-            let map = generate_map(&task, &solution.unwrap());
+            let map = generate_map(&task, &solution.unwrap()).unwrap();
             simulate(&task, &map, false)
         }};
     }
@@ -49,9 +138,276 @@ mod tests {
         assert_eq!(162, result.score);
     }
 
+    #[test]
+    fn test_product_stats() {
+        let result = test_simulation!("./inputs/test2.json");
+        let stats = result
+            .products
+            .iter()
+            .map(|stats| (stats.subtype, stats.produced, stats.points, stats.last_turn))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![(0, 9, 90, 17), (1, 9, 72, 17)], stats);
+        assert_eq!(
+            result.score,
+            result
+                .products
+                .iter()
+                .map(|stats| stats.points)
+                .sum::<u32>()
+        );
+    }
+
     #[test]
     fn test_task_004() {
         let result = test_simulation!("./inputs/test_task_004.json");
         assert_eq!(240, result.score);
     }
+
+    #[test]
+    fn test_throughput() {
+        let (task, solution) =
+            read_input_from_file("./inputs/test1.json").expect("Could not read cli file");
+        let map = generate_map(&task, &solution.unwrap()).unwrap();
+        let (result, throughput) = simulator::simulate_with_throughput(&task, &map, true);
+
+        assert_eq!(40, result.score);
+        assert!(result.warnings.is_empty());
+        for object in map.get_objects() {
+            let is_landscape = matches!(
+                object,
+                model::object::Object::Deposit { .. } | model::object::Object::Obstacle { .. }
+            );
+            assert_eq!(!is_landscape, throughput.contains_key(&object.id()));
+        }
+    }
+
+    #[test]
+    fn test_timelines() {
+        let (task, solution) =
+            read_input_from_file("./inputs/test2.json").expect("Could not read cli file");
+        let map = generate_map(&task, &solution.unwrap()).unwrap();
+        let (result, timelines) = simulate_with_timelines(&task, &map, true);
+
+        assert_eq!(162, result.score);
+        assert_eq!(
+            result
+                .products
+                .iter()
+                .map(|stats| stats.produced)
+                .sum::<u32>(),
+            timelines
+                .iter()
+                .map(|timeline| timeline.units())
+                .sum::<u32>()
+        );
+        for timeline in timelines.iter() {
+            let turns: Vec<(u32, u32)> = timeline
+                .periods
+                .iter()
+                .map(|period| (period.first_turn, period.last_turn))
+                .collect();
+            assert_eq!(1, turns[0].0);
+            assert_eq!(task.turns, turns[turns.len() - 1].1);
+            assert!(turns.windows(2).all(|w| w[0].1 + 1 == w[1].0));
+        }
+    }
+
+    #[test]
+    fn test_simulate_until() {
+        let (task, solution) =
+            read_input_from_file("./inputs/test2.json").expect("Could not read cli file");
+        let map = generate_map(&task, &solution.unwrap()).unwrap();
+        let result = simulate(&task, &map, true);
+
+        let scores = simulator::simulate_until(&task, &map, u32::MAX);
+        assert_eq!(task.turns as usize, scores.len());
+        assert_eq!(Some(&result.score), scores.last());
+        assert!(scores.windows(2).all(|w| w[0] <= w[1]));
+        // the final score is first reached in the result's turn
+        assert_eq!(result.score, scores[result.turn as usize - 1]);
+        assert!(scores[result.turn as usize - 2] < result.score);
+
+        assert_eq!(scores[..10], simulator::simulate_until(&task, &map, 10));
+    }
+
+    #[test]
+    fn test_invalid_solution() {
+        let (task, solution) =
+            read_input_from_file("./inputs/test1.json").expect("Could not read cli file");
+        let mut solution = solution.unwrap();
+        // overlaps the original
+        let object = solution.0[0].translated(1, 0);
+        solution.0.push(object.clone());
+
+        let Err(ModelError::InvalidSolution(violation)) = generate_map(&task, &solution) else {
+            panic!("Expected an invalid solution");
+        };
+        assert_eq!(object, violation.object);
+        assert_eq!(
+            "invalid solution",
+            verdict(&ModelError::InvalidSolution(violation))["verdict"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_product_scores_zero() {
+        let (mut task, solution) =
+            read_input_from_file("./inputs/test1.json").expect("Could not read cli file");
+        let map = generate_map(&task, &solution.unwrap()).unwrap();
+        task.products.clear();
+
+        StrictMode::set_enabled(false);
+        let result = simulate(&task, &map, true);
+        assert_eq!((0, 0), (result.score, result.turn));
+    }
+
+    #[test]
+    fn test_estimate_delivery_turns() {
+        use model::{
+            object::Object,
+            subtype::{ConveyorSubtype, MineSubtype},
+            task::{Product, Task},
+        };
+        use simulator::estimate_delivery_turns;
+
+        // a single unit of the product can be produced from the deposit
+        let task = Task {
+            width: 20,
+            height: 10,
+            objects: vec![Object::Deposit {
+                x: 0,
+                y: 2,
+                width: 1,
+                height: 1,
+                subtype: 0,
+            }],
+            products: vec![Product {
+                kind: String::from("product"),
+                subtype: 0,
+                resources: vec![3, 0, 0, 0, 0, 0, 0, 0],
+                points: 10,
+            }],
+            turns: 20,
+            time: None,
+        };
+
+        let mine = Object::mine_with_subtype_and_ingress_at(MineSubtype::East, (1, 2));
+        let (x, y) = mine.egress().unwrap();
+        let conveyor =
+            Object::conveyor_with_subtype_and_ingress_at(ConveyorSubtype::East, (x + 1, y));
+        for path in [vec![mine.clone()], vec![mine, conveyor]] {
+            let (x, y) = path.last().unwrap().egress().unwrap();
+            let mut objects = task.objects.clone();
+            objects.push(Object::Factory {
+                x: x + 1,
+                y: y - 2,
+                subtype: 0,
+            });
+            let num_objects = path.len() as u32;
+            objects.extend(path);
+
+            let map = model::map::Map::new(task.width, task.height, objects);
+            let result = simulate(&task, &map, true);
+            assert_eq!(10, result.score);
+            assert_eq!(estimate_delivery_turns(num_objects), result.turn);
+        }
+    }
+
+    #[test]
+    fn test_conveyor_cycle() {
+        use model::{object::Object, subtype::ConveyorSubtype, task::Task};
+        use simulator::SimulationWarning;
+
+        let task = Task {
+            width: 10,
+            height: 10,
+            objects: vec![],
+            products: vec![],
+            turns: 10,
+            time: None,
+        };
+        let conveyors = vec![
+            Object::Conveyor {
+                x: 2,
+                y: 1,
+                subtype: ConveyorSubtype::East,
+            },
+            Object::Conveyor {
+                x: 4,
+                y: 2,
+                subtype: ConveyorSubtype::South,
+            },
+            Object::Conveyor {
+                x: 3,
+                y: 4,
+                subtype: ConveyorSubtype::West,
+            },
+            Object::Conveyor {
+                x: 1,
+                y: 3,
+                subtype: ConveyorSubtype::North,
+            },
+        ];
+        let map = model::map::Map::new(10, 10, conveyors.clone());
+        let result = simulate(&task, &map, true);
+
+        assert_eq!(1, result.warnings.len());
+        let SimulationWarning::Cycle(cycle) = &result.warnings[0] else {
+            panic!("Expected a cycle, got {:?}", result.warnings);
+        };
+        assert_eq!(4, cycle.len());
+        assert!(conveyors.iter().all(|conveyor| cycle.contains(conveyor)));
+    }
+
+    #[test]
+    fn test_dead_ends() {
+        use model::{
+            object::Object,
+            subtype::{ConveyorSubtype, MineSubtype},
+            task::{Product, Task},
+        };
+        use simulator::SimulationWarning;
+
+        let task = Task {
+            width: 20,
+            height: 10,
+            objects: vec![Object::Deposit {
+                x: 0,
+                y: 2,
+                width: 1,
+                height: 1,
+                subtype: 0,
+            }],
+            products: vec![Product {
+                kind: String::from("product"),
+                subtype: 0,
+                resources: vec![3, 0, 0, 0, 0, 0, 0, 0],
+                points: 10,
+            }],
+            turns: 20,
+            time: None,
+        };
+
+        // a chain from the deposit, that ends without a factory, and a mine far from any deposit
+        let mine = Object::mine_with_subtype_and_ingress_at(MineSubtype::East, (1, 2));
+        let (x, y) = mine.egress().unwrap();
+        let conveyor =
+            Object::conveyor_with_subtype_and_ingress_at(ConveyorSubtype::East, (x + 1, y));
+        let lonely_mine = Object::mine_with_subtype_and_ingress_at(MineSubtype::East, (12, 7));
+        let mut objects = task.objects.clone();
+        objects.extend([mine.clone(), conveyor.clone(), lonely_mine.clone()]);
+        let map = model::map::Map::new(task.width, task.height, objects);
+
+        let warnings = find_dead_ends(&task, &map);
+        assert_eq!(3, warnings.len());
+        assert!(warnings.contains(&SimulationWarning::UselessMine(lonely_mine.clone())));
+        assert!(warnings.contains(&SimulationWarning::DanglingChain(vec![mine, conveyor])));
+        assert!(warnings.contains(&SimulationWarning::DanglingChain(vec![lonely_mine])));
+
+        // a solution without dead ends
+        let (task, solution) =
+            read_input_from_file("./inputs/test1.json").expect("Could not read cli file");
+        let map = generate_map(&task, &solution.unwrap()).unwrap();
+        assert!(find_dead_ends(&task, &map).is_empty());
+    }
 }
@@ -0,0 +1,201 @@
+//! Timeline of each factory's production during a simulation
+//!
+//! Splits the turns of each factory into periods in which it produced, idled or waited on
+//! resources, which answers questions like "why did production stop at turn 37?" at a glance.
+
+use std::fmt::Write;
+
+use model::{
+    map::Map,
+    object::{Object, ObjectIndex, Subtype},
+    task::Task,
+};
+use serde_json::{json, Value};
+
+use crate::{simulate_with_observer, SimulatorResult};
+
+/// Max number of characters of a factory's chart, so each character covers multiple turns in long
+/// simulations
+const MAX_CHART_WIDTH: u32 = 80;
+
+/// What a factory did in a turn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FactoryState {
+    /// Produced at least one unit
+    Producing,
+    /// Held no resources at all
+    Idle,
+    /// Held resources, but not enough of the given resource types to produce
+    Waiting(Vec<Subtype>),
+}
+
+/// Consecutive turns in which a factory was in the same state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Period {
+    pub first_turn: u32,
+    pub last_turn: u32,
+    pub state: FactoryState,
+    /// Units produced during the period
+    pub units: u32,
+}
+
+/// Periods of a single factory, covering all simulated turns
+#[derive(Debug, Clone)]
+pub struct FactoryTimeline {
+    pub factory: Object,
+    pub periods: Vec<Period>,
+}
+
+impl FactoryTimeline {
+    /// Units produced during the whole simulation
+    pub fn units(&self) -> u32 {
+        self.periods.iter().map(|period| period.units).sum()
+    }
+
+    fn push(&mut self, turn: u32, state: FactoryState, units: u32) {
+        match self.periods.last_mut() {
+            Some(period) if period.state == state => {
+                period.last_turn = turn;
+                period.units += units;
+            }
+            _ => self.periods.push(Period {
+                first_turn: turn,
+                last_turn: turn,
+                state,
+                units,
+            }),
+        }
+    }
+
+    /// One character per turn (or per range of turns, for long simulations)
+    fn chart(&self) -> String {
+        let turns = self.periods.last().map_or(0, |period| period.last_turn);
+        let turns_per_char = turns.div_ceil(MAX_CHART_WIDTH).max(1);
+        (0..turns.div_ceil(turns_per_char))
+            .map(|i| {
+                let (first, last) = (i * turns_per_char + 1, (i + 1) * turns_per_char);
+                let states = self
+                    .periods
+                    .iter()
+                    .filter(|period| period.first_turn <= last && first <= period.last_turn)
+                    .map(|period| &period.state);
+                states.fold('.', |c, state| match state {
+                    FactoryState::Producing => '#',
+                    FactoryState::Waiting(_) if c != '#' => '-',
+                    _ => c,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Simulates `map` like [crate::simulate] and records the timeline of each factory
+pub fn simulate_with_timelines(
+    task: &Task,
+    map: &Map,
+    quiet: bool,
+) -> (SimulatorResult, Vec<FactoryTimeline>) {
+    let products = task.product_catalog();
+    let factories: Vec<(ObjectIndex, &Object)> = map
+        .get_indexed_objects()
+        .filter(|(_, object)| matches!(object, Object::Factory { .. }))
+        .collect();
+    let mut timelines: Vec<FactoryTimeline> = factories
+        .iter()
+        .map(|(_, factory)| FactoryTimeline {
+            factory: (*factory).clone(),
+            periods: vec![],
+        })
+        .collect();
+
+    let (result, _) = simulate_with_observer(task, map, quiet, &mut |state| {
+        for ((index, factory), timeline) in factories.iter().zip(timelines.iter_mut()) {
+            let units = state.produced[index.index()];
+            let holdings = &state.holdings[index.index()];
+            let factory_state = if units > 0 {
+                FactoryState::Producing
+            } else if holdings.iter().all(|&amount| amount == 0) {
+                FactoryState::Idle
+            } else {
+                let missing = products
+                    .of_factory(factory)
+                    .into_iter()
+                    .flat_map(|product| product.resources.iter().enumerate())
+                    .filter(|&(resource, &amount)| holdings[resource] < amount)
+                    .map(|(resource, _)| resource as Subtype)
+                    .collect();
+                FactoryState::Waiting(missing)
+            };
+            timeline.push(state.turn, factory_state, units);
+        }
+    });
+
+    (result, timelines)
+}
+
+/// Formats `timelines` as chart with the periods of each factory below
+pub fn format_timelines(timelines: &[FactoryTimeline]) -> String {
+    let mut out = String::from("# producing, - waiting on resources, . idle\n");
+    for timeline in timelines {
+        let (x, y) = timeline.factory.coords();
+        let _ = writeln!(
+            out,
+            "factory of product {} at ({}, {}): {} units",
+            timeline.factory.subtype().unwrap_or_default(),
+            x,
+            y,
+            timeline.units()
+        );
+        let _ = writeln!(out, "  |{}|", timeline.chart());
+        for period in timeline.periods.iter() {
+            let turns = if period.first_turn == period.last_turn {
+                format!("turn {}", period.first_turn)
+            } else {
+                format!("turns {}-{}", period.first_turn, period.last_turn)
+            };
+            let _ = match &period.state {
+                FactoryState::Producing => {
+                    writeln!(out, "  {}: producing {} unit(s)", turns, period.units)
+                }
+                FactoryState::Idle => writeln!(out, "  {}: idle", turns),
+                FactoryState::Waiting(missing) => {
+                    writeln!(out, "  {}: waiting on {:?}", turns, missing)
+                }
+            };
+        }
+    }
+    out
+}
+
+/// `timelines` as JSON array
+pub fn timelines_to_json(timelines: &[FactoryTimeline]) -> Value {
+    let timelines: Vec<Value> = timelines
+        .iter()
+        .map(|timeline| {
+            let periods: Vec<Value> = timeline
+                .periods
+                .iter()
+                .map(|period| {
+                    let (state, missing) = match &period.state {
+                        FactoryState::Producing => ("producing", vec![]),
+                        FactoryState::Idle => ("idle", vec![]),
+                        FactoryState::Waiting(missing) => ("waiting", missing.clone()),
+                    };
+                    json!({
+                        "first_turn": period.first_turn,
+                        "last_turn": period.last_turn,
+                        "state": state,
+                        "units": period.units,
+                        "missing": missing,
+                    })
+                })
+                .collect();
+            json!({
+                "factory": timeline.factory,
+                "units": timeline.units(),
+                "periods": periods,
+            })
+        })
+        .collect();
+    Value::Array(timelines)
+}
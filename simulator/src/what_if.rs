@@ -0,0 +1,155 @@
+//! Exploration of changes to a solution (see `--what-if`)
+//!
+//! Objects are added to or removed from the map of the solution one command at a time, and the
+//! score is reported after each change. Changes are applied to the same map, so only the
+//! simulation itself is repeated.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use model::{
+    map::Map,
+    object::{Coord, Object},
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
+    task::Task,
+};
+use simulator::simulate;
+
+const HELP: &str = "\
+Commands:
+  add mine|factory|conveyor|combiner <x> <y> <subtype>
+  remove <x> <y>     remove the object occupying the cell
+  help               print this help
+  quit               exit";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Add(Object),
+    Remove(Coord, Coord),
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// Parses a single line of input
+    ///
+    /// Returns Ok(None) for empty lines.
+    fn parse(line: &str) -> Result<Option<Command>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let command = match words.as_slice() {
+            [] => return Ok(None),
+            ["add", kind, x, y, subtype] => {
+                let (x, y, subtype) = (parse(x)?, parse(y)?, parse::<u8>(subtype)?);
+                Command::Add(match *kind {
+                    "mine" => Object::Mine {
+                        x,
+                        y,
+                        subtype: MineSubtype::try_from(subtype)?,
+                    },
+                    "factory" => Object::Factory { x, y, subtype },
+                    "conveyor" => Object::Conveyor {
+                        x,
+                        y,
+                        subtype: ConveyorSubtype::try_from(subtype)?,
+                    },
+                    "combiner" => Object::Combiner {
+                        x,
+                        y,
+                        subtype: CombinerSubtype::try_from(subtype)?,
+                    },
+                    _ => return Err(format!("Cannot add objects of kind '{}'", kind)),
+                })
+            }
+            ["remove", x, y] => Command::Remove(parse(x)?, parse(y)?),
+            ["help"] => Command::Help,
+            ["quit" | "exit"] => Command::Quit,
+            _ => return Err(format!("Invalid command '{}' (see `help`)", line.trim())),
+        };
+
+        Ok(Some(command))
+    }
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number '{}'", value))
+}
+
+/// Reads commands from stdin and prints the score of `map` after each change, compared to the
+/// score of the initial map
+pub(crate) fn run_what_if(task: &Task, mut map: Map) {
+    let initial = simulate(task, &map, true).score;
+    println!("Score {} (initial)", initial);
+
+    let interactive = io::stdin().is_terminal();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            print!("> ");
+            io::stdout().flush().unwrap();
+        }
+
+        let line = match lines.next() {
+            Some(line) => line.expect("Cannot read from stdin"),
+            None => break,
+        };
+
+        let changed = match Command::parse(&line) {
+            Ok(Some(Command::Add(object))) => map
+                .insert_object(object.clone())
+                .map_err(|e| format!("Cannot add {:?}: {}", object, e)),
+            Ok(Some(Command::Remove(x, y))) => match map.object_at(x, y).cloned() {
+                Some(Object::Deposit { .. } | Object::Obstacle { .. }) => {
+                    Err(format!("Cannot remove the task's object at ({}, {})", x, y))
+                }
+                Some(object) => map.remove_object(&object),
+                None => Err(format!("No object at ({}, {})", x, y)),
+            },
+            Ok(Some(Command::Help)) => {
+                println!("{}", HELP);
+                continue;
+            }
+            Ok(Some(Command::Quit)) => break,
+            Ok(None) => continue,
+            Err(e) => Err(e),
+        };
+
+        match changed {
+            Ok(()) => {
+                let result = simulate(task, &map, true);
+                println!(
+                    "Score {} at turn {} ({:+})",
+                    result.score,
+                    result.turn,
+                    result.score as i64 - initial as i64
+                );
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            Command::parse("add conveyor 10 12 3"),
+            Ok(Some(Command::Add(Object::Conveyor {
+                x: 10,
+                y: 12,
+                subtype: ConveyorSubtype::North,
+            })))
+        );
+        assert_eq!(
+            Command::parse("remove 10 12"),
+            Ok(Some(Command::Remove(10, 12)))
+        );
+        assert_eq!(Command::parse(" "), Ok(None));
+        assert!(Command::parse("add obstacle 1 1 1").is_err());
+        assert!(Command::parse("add mine 1 1 4").is_err());
+        assert!(Command::parse("remove 1").is_err());
+    }
+}
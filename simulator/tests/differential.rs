@@ -0,0 +1,163 @@
+//! Compares the optimized simulator with the [reference] simulator on random small maps
+//!
+//! Maps are generated from fixed seeds, so failures are reproducible; a failure prints the seed,
+//! map and products. Set `DIFFERENTIAL_CASES` to check more (or fewer) maps.
+
+mod reference;
+
+use model::{
+    coord::neighbours,
+    map::Map,
+    object::{Object, Subtype},
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
+    task::{Product, Task},
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use simulator::simulate;
+
+const NUM_CASES: u64 = 500;
+/// Number of resource types and products on the random maps
+const NUM_SUBTYPES: Subtype = 2;
+
+/// A random task of up to 20x20 cells, with a few deposits and obstacles, and a map of the task
+/// with objects that are mostly connected to each other
+fn random_map(seed: u64) -> (Task, Map) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (width, height) = (rng.gen_range(10..=20), rng.gen_range(10..=20));
+
+    let products: Vec<Product> = (0..NUM_SUBTYPES)
+        .map(|subtype| {
+            let mut resources = vec![0; NUM_SUBTYPES as usize];
+            resources[subtype as usize] = rng.gen_range(1..=4);
+            if rng.gen_bool(0.3) {
+                resources[rng.gen_range(0..NUM_SUBTYPES as usize)] += rng.gen_range(1..=3);
+            }
+            Product {
+                kind: String::from("product"),
+                subtype,
+                resources,
+                points: rng.gen_range(1..=10),
+            }
+        })
+        .collect();
+
+    let mut map = Map::new(width, height, vec![]);
+    for _ in 0..rng.gen_range(1..=3) {
+        let _ = map.insert_object(Object::Deposit {
+            x: rng.gen_range(0..width as i8),
+            y: rng.gen_range(0..height as i8),
+            width: rng.gen_range(1..=4),
+            height: rng.gen_range(1..=4),
+            subtype: rng.gen_range(0..NUM_SUBTYPES),
+        });
+    }
+    for _ in 0..rng.gen_range(0..=2) {
+        let _ = map.insert_object(Object::Obstacle {
+            x: rng.gen_range(0..width as i8),
+            y: rng.gen_range(0..height as i8),
+            width: rng.gen_range(1..=3),
+            height: rng.gen_range(1..=3),
+        });
+    }
+
+    // objects are attached to random egresses, so resources flow along them
+    for _ in 0..300 {
+        let suppliers: Vec<&Object> = map
+            .get_objects()
+            .filter(|object| !matches!(object, Object::Factory { .. } | Object::Obstacle { .. }))
+            .collect();
+        let Some(supplier) = suppliers.choose(&mut rng) else {
+            break;
+        };
+        let (x, y) = *supplier.egresses().choose(&mut rng).unwrap();
+        let ingress = *neighbours(x, y).choose(&mut rng).unwrap();
+        let is_deposit = matches!(map.object_at(x, y), Some(Object::Deposit { .. }));
+
+        let object = if is_deposit {
+            Object::mine_with_subtype_and_ingress_at(
+                *MineSubtype::ALL.choose(&mut rng).unwrap(),
+                ingress,
+            )
+        } else {
+            match rng.gen_range(0..10) {
+                0..=3 => Object::conveyor_with_subtype_and_ingress_at(
+                    *ConveyorSubtype::ALL.choose(&mut rng).unwrap(),
+                    ingress,
+                ),
+                4..=5 => {
+                    let subtype = *CombinerSubtype::ALL.choose(&mut rng).unwrap();
+                    let (dx, dy) = subtype.direction().offset();
+                    let (dx, dy) = (dx * rng.gen_range(1..=2), dy * rng.gen_range(1..=2));
+                    let sideways = rng.gen_range(-1..=1);
+                    let egress = if dx == 0 {
+                        (ingress.0 + sideways, ingress.1 + dy)
+                    } else {
+                        (ingress.0 + dx, ingress.1 + sideways)
+                    };
+                    Object::combiner_with_subtype_and_egress_at(subtype, egress)
+                }
+                _ => Object::factories_with_ingress_at(rng.gen_range(0..NUM_SUBTYPES), ingress)
+                    .choose(&mut rng)
+                    .unwrap()
+                    .clone(),
+            }
+        };
+        let _ = map.insert_object(object);
+    }
+    // mines far from deposits and loose conveyors, whose resources never arrive anywhere
+    for _ in 0..5 {
+        let (x, y) = (
+            rng.gen_range(0..width as i8),
+            rng.gen_range(0..height as i8),
+        );
+        let _ = map.insert_object(Object::Conveyor {
+            x,
+            y,
+            subtype: *ConveyorSubtype::ALL.choose(&mut rng).unwrap(),
+        });
+    }
+
+    let task = Task {
+        width,
+        height,
+        objects: vec![],
+        products,
+        turns: rng.gen_range(5..=50),
+        time: None,
+    };
+    (task, map)
+}
+
+#[test]
+fn simulators_agree() {
+    let num_cases = std::env::var("DIFFERENTIAL_CASES")
+        .ok()
+        .and_then(|cases| cases.parse().ok())
+        .unwrap_or(NUM_CASES);
+
+    let mut num_scored = 0;
+    for seed in 0..num_cases {
+        let (task, map) = random_map(seed);
+        let result = simulate(&task, &map, true);
+        let expected = reference::simulate(&task, &map);
+        assert_eq!(
+            expected,
+            (result.score, result.turn),
+            "Seed {}: the simulators disagree on (score, turn) of\n{}\n{:?}\n{:?}",
+            seed,
+            map,
+            map.get_objects().collect::<Vec<_>>(),
+            task.products
+        );
+        if result.score > 0 {
+            num_scored += 1;
+        }
+    }
+
+    // random maps that never score would not test much
+    assert!(
+        num_scored * 10 >= num_cases,
+        "Only {} maps scored",
+        num_scored
+    );
+}
@@ -0,0 +1,146 @@
+//! Compares the trace of simulating each input in `inputs/` with its golden file in `golden/`
+//!
+//! A trace consists of all events logged during the simulation (in order) and the result. Run with
+//! `BLESS=1` to write the current traces as golden files, e.g. after an intended change of the
+//! simulation. Review the diff of the golden files before committing them.
+
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use model::input::read_input_from_file;
+use serde_json::Value;
+use simulator::{generate_map, simulate};
+use tracing_subscriber::fmt::MakeWriter;
+
+const INPUTS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/inputs");
+const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+/// Collects everything written by a subscriber
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Buffer {
+    type Writer = Buffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Simulates the cli file at `path` and returns all logged events and the result
+fn trace(path: &Path) -> Value {
+    let (task, solution) = read_input_from_file(path.to_str().unwrap())
+        .unwrap_or_else(|e| panic!("Cannot read {:?}: {}", path, e));
+    let map = generate_map(&task, &solution.unwrap_or_default()).unwrap();
+
+    let buffer = Buffer::default();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .without_time()
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_target(false)
+        .with_max_level(tracing::Level::INFO)
+        .with_writer(buffer.clone())
+        .finish();
+    let result = tracing::subscriber::with_default(subscriber, || simulate(&task, &map, false));
+
+    let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let events: Vec<Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    serde_json::json!({
+        "result": format!("{:?}", result),
+        "events": events,
+    })
+}
+
+#[test]
+fn golden_traces() {
+    let bless = std::env::var_os("BLESS").is_some();
+
+    let mut inputs: Vec<_> = fs::read_dir(INPUTS_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    inputs.sort();
+
+    let mut mismatches = vec![];
+    for input in inputs {
+        let golden_path = Path::new(GOLDEN_DIR).join(input.file_name().unwrap());
+        let trace = trace(&input);
+
+        if bless {
+            fs::create_dir_all(GOLDEN_DIR).unwrap();
+            let mut content = serde_json::to_string_pretty(&trace).unwrap();
+            content.push('\n');
+            fs::write(&golden_path, content).unwrap();
+            continue;
+        }
+
+        let golden: Value = match fs::read_to_string(&golden_path) {
+            Ok(content) => serde_json::from_str(&content).unwrap(),
+            Err(_) => {
+                mismatches.push(format!("{:?}: no golden file", input.file_name().unwrap()));
+                continue;
+            }
+        };
+        if golden != trace {
+            mismatches.push(describe_mismatch(&input, &golden, &trace));
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "Traces differ from the golden files (run with BLESS=1 to update them):\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// The first event (or the result) in which `trace` differs from `golden`
+fn describe_mismatch(input: &Path, golden: &Value, trace: &Value) -> String {
+    let name = input.file_name().unwrap();
+    let events = |trace: &Value| trace["events"].as_array().cloned().unwrap_or_default();
+    let (golden_events, events) = (events(golden), events(trace));
+
+    match golden_events
+        .iter()
+        .zip(events.iter())
+        .position(|(golden, event)| golden != event)
+    {
+        Some(i) => format!(
+            "{:?}: event {} is {}, but {} was expected",
+            name, i, events[i], golden_events[i]
+        ),
+        None if golden_events.len() != events.len() => format!(
+            "{:?}: {} events were logged, but {} were expected",
+            name,
+            events.len(),
+            golden_events.len()
+        ),
+        None => format!(
+            "{:?}: result is {}, but {} was expected",
+            name, trace["result"], golden["result"]
+        ),
+    }
+}
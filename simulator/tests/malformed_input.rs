@@ -0,0 +1,37 @@
+//! Runs the simulator binary on malformed tasks
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use serde_json::Value;
+
+/// A task whose product needs more than 8 resource types
+const TASK: &str = r#"{"width":10,"height":10,"objects":[],"products":[{"type":"product","subtype":0,"resources":[1,0,0,0,0,0,0,0,0,1],"points":10}],"turns":10}"#;
+
+#[test]
+fn prints_invalid_task_verdict() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simulator"))
+        .args(["--strict=false", "--quiet"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(TASK.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(Some(1), output.status.code());
+    let verdict: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!("invalid task", verdict["verdict"]);
+    assert!(verdict["reason"]
+        .as_str()
+        .unwrap()
+        .contains("resource types"));
+}
@@ -0,0 +1,143 @@
+//! A slow, but straightforward simulator that follows the rules of the contest one by one
+//!
+//! It keeps no state between turns other than the resources each object holds, so its result can
+//! be compared with the optimized [simulator::simulate].
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use model::{
+    analysis::NUM_RESOURCE_TYPES,
+    coord::{neighbours, Point},
+    map::Map,
+    object::Object,
+    task::Task,
+};
+
+type Resources = [u32; NUM_RESOURCE_TYPES];
+
+/// Score and turn at which the score was reached
+pub fn simulate(task: &Task, map: &Map) -> (u32, u32) {
+    let objects: Vec<&Object> = map.get_objects().collect();
+
+    // the object each egress belongs to
+    let mut egresses: HashMap<Point, usize> = HashMap::new();
+    for (i, object) in objects.iter().enumerate() {
+        if let Object::Factory { .. } | Object::Obstacle { .. } = object {
+            continue;
+        }
+        for egress in object.egresses() {
+            egresses.insert(egress, i);
+        }
+    }
+
+    // (receiver, supplier) for each ingress next to an egress of another object
+    let mut connections: Vec<(usize, usize)> = vec![];
+    for (receiver, object) in objects.iter().enumerate() {
+        for (x, y) in object.ingresses() {
+            for neighbour in neighbours(x, y) {
+                if let Some(&supplier) = egresses.get(&neighbour) {
+                    connections.push((receiver, supplier));
+                }
+            }
+        }
+    }
+
+    // (mine, supplier) in the order mines pull resources: breadth-first from the factories
+    let mut mine_pulls: Vec<(usize, usize)> = vec![];
+    let mut queue: VecDeque<usize> = (0..objects.len())
+        .filter(|&i| matches!(objects[i], Object::Factory { .. }))
+        .collect();
+    while let Some(receiver) = queue.pop_front() {
+        for &(_, supplier) in connections.iter().filter(|(r, _)| *r == receiver) {
+            if let Object::Mine { .. } = objects[receiver] {
+                mine_pulls.push((receiver, supplier));
+            }
+            if !matches!(objects[supplier], Object::Deposit { .. }) {
+                queue.push_back(supplier);
+            }
+        }
+    }
+
+    // a deposit extracts for each mine ingress next to one of its egresses
+    let mut extractions: Vec<usize> = vec![];
+    for object in objects.iter() {
+        if let Object::Mine { .. } = object {
+            for (x, y) in object.ingresses() {
+                let deposits: HashSet<usize> = neighbours(x, y)
+                    .into_iter()
+                    .filter_map(|neighbour| egresses.get(&neighbour).copied())
+                    .filter(|&supplier| matches!(objects[supplier], Object::Deposit { .. }))
+                    .collect();
+                extractions.extend(deposits);
+            }
+        }
+    }
+
+    let mut remaining: Vec<u32> = objects.iter().map(|o| o.initial_resources()).collect();
+    // deposits hold what they extracted until mines take it
+    let mut held: Vec<Resources> = vec![[0; NUM_RESOURCE_TYPES]; objects.len()];
+
+    let (mut score, mut best_turn) = (0, 0);
+    for turn in 1..=task.turns {
+        // start of the turn: every ingress takes all resources from the egress next to it, except
+        // the ingresses of mines, which take up to 3 units of each resource
+        let before = held.clone();
+        for &(mine, supplier) in mine_pulls.iter() {
+            let amounts = held[supplier].map(|amount| amount.min(3));
+            add(&mut held[mine], &amounts);
+            sub(&mut held[supplier], &amounts);
+        }
+        for &(receiver, supplier) in connections.iter() {
+            if let Object::Mine { .. } = objects[receiver] {
+                continue;
+            }
+            add(&mut held[receiver], &before[supplier]);
+            sub(&mut held[supplier], &before[supplier]);
+        }
+
+        // end of the turn: deposits extract up to 3 units for each mine ingress next to them
+        for &deposit in extractions.iter() {
+            if let Object::Deposit { subtype, .. } = objects[deposit] {
+                let amount = remaining[deposit].min(3);
+                remaining[deposit] -= amount;
+                held[deposit][*subtype as usize] += amount;
+            }
+        }
+
+        // end of the turn: factories produce as often as their resources allow
+        for (i, object) in objects.iter().enumerate() {
+            let Object::Factory { subtype, .. } = object else {
+                continue;
+            };
+            let Some(product) = task.products.iter().find(|p| p.subtype == *subtype) else {
+                continue;
+            };
+            while product
+                .resources
+                .iter()
+                .enumerate()
+                .all(|(resource, &amount)| held[i][resource] >= amount)
+            {
+                for (resource, &amount) in product.resources.iter().enumerate() {
+                    held[i][resource] -= amount;
+                }
+                score += product.points;
+                best_turn = turn;
+            }
+        }
+    }
+
+    (score, best_turn)
+}
+
+fn add(to: &mut Resources, resources: &Resources) {
+    for (to, amount) in to.iter_mut().zip(resources) {
+        *to += amount;
+    }
+}
+
+fn sub(from: &mut Resources, resources: &Resources) {
+    for (from, amount) in from.iter_mut().zip(resources) {
+        *from -= amount;
+    }
+}
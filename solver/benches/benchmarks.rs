@@ -1,6 +1,7 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use model::{map::Map, object::Object, task::Task};
 use rand::{rngs::StdRng, SeedableRng};
+use solver::config::SolverConfig;
 use solver::paths::Paths;
 use solver::solve::Solver;
 use std::time::{Duration, Instant};
@@ -17,7 +18,13 @@ macro_rules! run_task {
             .into_iter()
             .map(|seed| {
                 let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(seed)));
-                Solver::new(&task, &map, rng, Duration::from_secs(1))
+                Solver::new(
+                    &task,
+                    &map,
+                    SolverConfig::default(),
+                    rng,
+                    Duration::from_secs(1),
+                )
             })
             .collect::<Vec<Solver<StdRng>>>();
 
@@ -74,7 +81,7 @@ macro_rules! run_pathfinding {
         let mut path_finders = (0..3)
             .map(|i| {
                 let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(SEEDS[i])));
-                Paths::new(&[$start_point], &deposits[..], &map, rng)
+                Paths::new(&[$start_point], &deposits[..], &map, task.turns, rng)
             })
             .collect::<Vec<Paths<StdRng>>>();
 
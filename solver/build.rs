@@ -0,0 +1,14 @@
+use std::process::Command;
+
+fn main() {
+    // the solver is also built without a git checkout (e.g. in docker), so a missing hash is fine
+    let git_hash = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash);
+}
@@ -0,0 +1,348 @@
+//! Learning of promising factory positions across iterations
+//!
+//! The learned statistics can be shared by all workers of a run and persisted between runs on the
+//! same task (see [SharedStatistics]).
+
+use std::sync::{Arc, Mutex};
+
+use fxhash::FxHashMap as HashMap;
+use model::{
+    coord::Point,
+    object::{Coord, Subtype},
+    task::Task,
+};
+use rand::distributions::WeightedIndex;
+use serde::{Deserialize, Serialize};
+
+/// Learning percent of runs that load statistics, but do not set one, since statistics are of no
+/// use if nothing is learned from them
+pub const DEFAULT_LOADED_LEARNING_PERCENT: u32 = 50;
+
+/// Lower bound of the factor a position's prior weight is scaled by, so no position is ruled out
+/// for good
+const MIN_WEIGHT_FACTOR: f32 = 0.1;
+
+/// Statistics, that the workers of a run start from and record their scores to (see
+/// [PositionBandit::share])
+pub type SharedStatistics = Arc<Mutex<PlacementStatistics>>;
+
+/// Scores of the solutions a single position took part in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Arm {
+    total_score: u64,
+    num_solutions: u32,
+}
+
+impl Arm {
+    fn mean_score(&self) -> Option<f32> {
+        (self.num_solutions > 0).then(|| self.total_score as f32 / self.num_solutions as f32)
+    }
+
+    fn add(&mut self, score: u32) {
+        self.total_score += score as u64;
+        self.num_solutions += 1;
+    }
+}
+
+/// Scores of the solutions each factory position of a task took part in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlacementStatistics {
+    task_id: u64,
+    arms: HashMap<(Subtype, Point), Arm>,
+}
+
+/// A position of [PlacementStatistics], as saved to a file
+#[derive(Serialize, Deserialize)]
+struct PositionEntry {
+    product: Subtype,
+    x: Coord,
+    y: Coord,
+    total_score: u64,
+    num_solutions: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StatisticsFile {
+    task_id: u64,
+    positions: Vec<PositionEntry>,
+}
+
+impl PlacementStatistics {
+    /// Creates empty statistics of `task`
+    pub fn new(task: &Task) -> PlacementStatistics {
+        PlacementStatistics {
+            task_id: task_id(task),
+            arms: HashMap::default(),
+        }
+    }
+
+    /// Reads statistics saved by [PlacementStatistics::save]
+    pub fn load(path: &str) -> Result<PlacementStatistics, String> {
+        let file = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read state {}: {}", path, e))?;
+        let file: StatisticsFile =
+            serde_json::from_str(&file).map_err(|e| format!("Invalid state {}: {}", path, e))?;
+        let arms = file
+            .positions
+            .into_iter()
+            .map(|entry| {
+                let arm = Arm {
+                    total_score: entry.total_score,
+                    num_solutions: entry.num_solutions,
+                };
+                ((entry.product, (entry.x, entry.y)), arm)
+            })
+            .collect();
+        Ok(PlacementStatistics {
+            task_id: file.task_id,
+            arms,
+        })
+    }
+
+    /// Writes these statistics to `path`
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut positions: Vec<PositionEntry> = self
+            .arms
+            .iter()
+            .map(|(&(product, (x, y)), arm)| PositionEntry {
+                product,
+                x,
+                y,
+                total_score: arm.total_score,
+                num_solutions: arm.num_solutions,
+            })
+            .collect();
+        positions.sort_by_key(|entry| (entry.product, entry.x, entry.y));
+        let file = StatisticsFile {
+            task_id: self.task_id,
+            positions,
+        };
+        std::fs::write(path, serde_json::to_string(&file).unwrap())
+            .map_err(|e| format!("Cannot write state {}: {}", path, e))
+    }
+
+    /// Checks if these are statistics of `task`
+    pub fn is_of(&self, task: &Task) -> bool {
+        self.task_id == task_id(task)
+    }
+
+    /// Number of recorded solutions of all positions
+    pub fn num_solutions(&self) -> u64 {
+        self.arms.values().map(|arm| arm.num_solutions as u64).sum()
+    }
+}
+
+/// Identifies a task by its map, products and turns, but not its time
+fn task_id(task: &Task) -> u64 {
+    let task = Task {
+        time: None,
+        ..task.clone()
+    };
+    fxhash::hash64(&task.to_json_string().unwrap())
+}
+
+/// Weights of each product's factory positions, adapted towards the positions whose solutions
+/// scored high (a simple multi-armed bandit)
+///
+/// The weight of a position is its prior weight (see
+/// [factory_position_weights](crate::placement::factory_position_weights)) scaled by how its mean
+/// score compares to the best mean score of all positions of the product. Positions that were not
+/// tried yet keep their prior weight.
+#[derive(Debug, Clone)]
+pub struct PositionBandit {
+    /// Share (0 - 1) of a position's weight, that is determined by its mean score
+    learning_rate: f32,
+    /// Statistics, that scores are recorded to as well
+    shared: Option<SharedStatistics>,
+    positions: Vec<Point>,
+    priors: HashMap<Subtype, Vec<f32>>,
+    arms: HashMap<Subtype, Vec<Arm>>,
+    weights: HashMap<Subtype, WeightedIndex<f32>>,
+}
+
+impl PositionBandit {
+    /// Creates a bandit, whose weights are the `priors` of each product's `positions`, until
+    /// solutions are recorded
+    ///
+    /// A `learning_percent` of 0 keeps the prior weights for good. Products whose priors are all 0
+    /// (or that have no positions at all) get no weights.
+    pub fn new(
+        positions: &[Point],
+        priors: HashMap<Subtype, Vec<f32>>,
+        learning_percent: u32,
+    ) -> PositionBandit {
+        let arms = priors
+            .keys()
+            .map(|&subtype| (subtype, vec![Arm::default(); positions.len()]))
+            .collect();
+        let weights = priors
+            .iter()
+            .filter_map(|(&subtype, priors)| Some((subtype, WeightedIndex::new(priors).ok()?)))
+            .collect();
+        PositionBandit {
+            learning_rate: learning_percent.min(100) as f32 / 100.0,
+            shared: None,
+            positions: positions.to_vec(),
+            priors,
+            arms,
+            weights,
+        }
+    }
+
+    /// Starts from `statistics` and records all further solutions to them as well
+    ///
+    /// Statistics of another task than `task` are ignored.
+    pub fn share(&mut self, task: &Task, statistics: &SharedStatistics) {
+        {
+            let shared = statistics.lock().unwrap();
+            if !shared.is_of(task) {
+                return;
+            }
+            for (subtype, arms) in self.arms.iter_mut() {
+                for (arm, position) in arms.iter_mut().zip(self.positions.iter()) {
+                    *arm = shared
+                        .arms
+                        .get(&(*subtype, *position))
+                        .copied()
+                        .unwrap_or_default();
+                }
+            }
+        }
+        self.shared = Some(Arc::clone(statistics));
+
+        if self.learning_rate > 0.0 {
+            let subtypes: Vec<Subtype> = self.priors.keys().copied().collect();
+            for subtype in subtypes {
+                self.update_weights(subtype);
+            }
+        }
+    }
+
+    /// Current weights of each product's positions
+    pub fn weights(&self) -> &HashMap<Subtype, WeightedIndex<f32>> {
+        &self.weights
+    }
+
+    /// Records the `score` of a solution with factories at the given positions, which are
+    /// identified by the product and the position's index
+    pub fn record(&mut self, positions: &[(Subtype, usize)], score: u32) {
+        if self.learning_rate == 0.0 && self.shared.is_none() {
+            return;
+        }
+
+        let mut subtypes = vec![];
+        for &(subtype, index) in positions {
+            if let Some(arm) = self
+                .arms
+                .get_mut(&subtype)
+                .and_then(|arms| arms.get_mut(index))
+            {
+                arm.add(score);
+                if !subtypes.contains(&subtype) {
+                    subtypes.push(subtype);
+                }
+            }
+        }
+
+        if let Some(shared) = &self.shared {
+            let mut shared = shared.lock().unwrap();
+            for &(subtype, index) in positions {
+                if let Some(&position) = self.positions.get(index) {
+                    shared
+                        .arms
+                        .entry((subtype, position))
+                        .or_default()
+                        .add(score);
+                }
+            }
+        }
+
+        if self.learning_rate > 0.0 {
+            for subtype in subtypes {
+                self.update_weights(subtype);
+            }
+        }
+    }
+
+    fn update_weights(&mut self, subtype: Subtype) {
+        let arms = &self.arms[&subtype];
+        let best_mean_score = arms.iter().filter_map(Arm::mean_score).fold(0.0, f32::max);
+        let weights = self.priors[&subtype]
+            .iter()
+            .zip(arms.iter())
+            .map(|(&prior, arm)| {
+                // positions without any solution are neutral (0.5), unless no position scored yet
+                let reward = match arm.mean_score() {
+                    Some(mean_score) if best_mean_score > 0.0 => mean_score / best_mean_score,
+                    Some(_) => 0.0,
+                    None => 0.5,
+                };
+                let factor = 1.0 + self.learning_rate * 2.0 * (reward - 0.5);
+                prior * factor.max(MIN_WEIGHT_FACTOR)
+            });
+        if let Ok(weights) = WeightedIndex::new(weights) {
+            self.weights.insert(subtype, weights);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{distributions::Distribution, rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn prefers_positions_of_high_scoring_solutions() {
+        let task = Task {
+            width: 10,
+            height: 10,
+            objects: vec![],
+            products: vec![],
+            turns: 10,
+            time: None,
+        };
+        let positions = [(0, 0), (1, 0), (2, 0), (3, 0)];
+        let priors = || [(0, vec![1.0; 4])].into_iter().collect();
+        let samples = |bandit: &PositionBandit| {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut samples = [0; 4];
+            for _ in 0..1000 {
+                samples[bandit.weights()[&0].sample(&mut rng)] += 1;
+            }
+            samples
+        };
+
+        let statistics: SharedStatistics = Arc::new(Mutex::new(PlacementStatistics::new(&task)));
+        let mut bandit = PositionBandit::new(&positions, priors(), 100);
+        bandit.share(&task, &statistics);
+        for _ in 0..5 {
+            bandit.record(&[(0, 0)], 10);
+            bandit.record(&[(0, 1)], 0);
+        }
+        let learned = samples(&bandit);
+        // weights 2.0 (best), 0.1 (no points) and 1.0 (untried)
+        assert!(learned[0] > learned[2] && learned[2] > learned[1]);
+        assert!(learned[1] > 0);
+
+        // a bandit of the next run starts from the saved statistics
+        let statistics = statistics.lock().unwrap().clone();
+        assert_eq!(10, statistics.num_solutions());
+        let path = std::env::temp_dir().join("bandit_statistics.json");
+        let path = path.to_str().unwrap();
+        statistics.save(path).unwrap();
+        let loaded = PlacementStatistics::load(path).unwrap();
+        assert_eq!(statistics, loaded);
+        let mut bandit = PositionBandit::new(&positions, priors(), 100);
+        bandit.share(&task, &Arc::new(Mutex::new(loaded)));
+        assert_eq!(learned, samples(&bandit));
+
+        // statistics of another task are ignored
+        let other_task = Task {
+            turns: 20,
+            ..task.clone()
+        };
+        let mut bandit = PositionBandit::new(&positions, priors(), 100);
+        bandit.share(&other_task, &Arc::new(Mutex::new(statistics)));
+        assert!(bandit.shared.is_none());
+    }
+}
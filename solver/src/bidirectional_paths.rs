@@ -0,0 +1,470 @@
+//! Bidirectional (meet-in-the-middle) path finding algorithm
+//!
+//! Other than [Paths](crate::paths::Paths), partial paths are not only grown from the start points
+//! towards the deposits, but also from the deposits (starting with a mine) towards the start
+//! points. A path is found as soon as both frontiers meet. This keeps both frontiers small on maps
+//! with long corridors between factories and deposits.
+
+use std::{
+    cell::RefCell,
+    collections::{BinaryHeap, VecDeque},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use fxhash::FxHashMap as HashMap;
+use fxhash::FxHashSet as HashSet;
+
+use crate::distances::{get_distances, get_distances_to_points};
+use crate::path::{Path, PathArena, PathID, SegmentIndex};
+use crate::paths::max_path_length_for_turns;
+use model::{
+    coord::{neighbours, Point},
+    map::Map,
+    object::Object,
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
+};
+use rand::Rng;
+
+/// Max time to search for the next path
+const MAX_SEARCH_TIME_IN_MILLIS: u64 = 200;
+
+/// Partial path grown from the start points towards the deposits
+struct BackwardNode {
+    path: SegmentIndex,
+    length: u32,
+    map_ref: Arc<Map>,
+}
+
+/// Partial path grown from a deposit towards the start points
+///
+/// The first object of a chain is always a mine. The ingress of every following object touches
+/// the egress of its predecessor.
+struct ForwardNode {
+    object: Object,
+    predecessor: Option<Rc<ForwardNode>>,
+    length: u32,
+    map_ref: Arc<Map>,
+}
+
+/// The internal search state (= type of search queue elements)
+struct SearchState<N> {
+    /// Distance to the other side's origin
+    distance: u32,
+    length: u32,
+    node: Rc<N>,
+}
+
+impl<N> PartialEq for SearchState<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.distance == other.distance && self.length == other.length
+    }
+}
+
+impl<N> Eq for SearchState<N> {}
+
+impl<N> PartialOrd for SearchState<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for SearchState<N> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .distance
+            .cmp(&self.distance)
+            .then(other.length.cmp(&self.length))
+    }
+}
+
+/// A container for constructing paths between a set of start and target points, searching from
+/// both ends simultaneously
+pub struct BidirectionalPaths<T> {
+    distances_to_deposits: Arc<HashMap<Point, u32>>,
+    distances_to_starts: HashMap<Point, u32>,
+    /// Max number of objects of a path
+    max_path_length: u32,
+    backward_queue: BinaryHeap<SearchState<BackwardNode>>,
+    forward_queue: BinaryHeap<SearchState<ForwardNode>>,
+    /// Partial paths of the backward nodes
+    arena: PathArena,
+    /// Backward nodes by the ingresses of their head
+    backward_nodes_by_head: HashMap<Point, Vec<Rc<BackwardNode>>>,
+    /// Forward nodes by the egress of their last object
+    forward_nodes_by_egress: HashMap<Point, Vec<Rc<ForwardNode>>>,
+    found_paths: VecDeque<Path>,
+    paths_so_far: HashSet<PathID>,
+    rng: Rc<RefCell<T>>,
+}
+
+impl<T: Rng> BidirectionalPaths<T> {
+    /// Creates a new BidirectionalPaths container
+    ///
+    /// # Example
+    /// A container for constructing paths from any point in `starts` to any deposit of `deposits`,
+    /// that deliver resources within `turns` turns
+    /// BidirectionalPaths::new(starts, deposits, map, turns, rng)
+    pub fn new(
+        start_points: &[Point],
+        deposits: &[Object],
+        map: &Map,
+        turns: u32,
+        rng: Rc<RefCell<T>>,
+    ) -> Self {
+        let distances_to_deposits = get_distances(map, deposits);
+        BidirectionalPaths::with_distances(start_points, distances_to_deposits, map, turns, rng)
+    }
+
+    /// Creates a new BidirectionalPaths container, whose targets are the cells with a distance
+    /// of 0 in `distances_to_deposits`
+    pub fn with_distances(
+        start_points: &[Point],
+        distances_to_deposits: Arc<HashMap<Point, u32>>,
+        map: &Map,
+        turns: u32,
+        rng: Rc<RefCell<T>>,
+    ) -> Self {
+        let distances_to_starts = get_distances_to_points(map, start_points);
+
+        let start_distance = start_points
+            .iter()
+            .flat_map(|point| neighbours(point.0, point.1))
+            .filter_map(|point| distances_to_deposits.get(&point))
+            .min()
+            .cloned();
+
+        let mut paths = BidirectionalPaths {
+            distances_to_deposits,
+            distances_to_starts,
+            max_path_length: start_distance
+                .map(|d| ((d / 3) + 10).min(max_path_length_for_turns(turns)))
+                .unwrap_or(0),
+            backward_queue: BinaryHeap::new(),
+            forward_queue: BinaryHeap::new(),
+            arena: PathArena::new(),
+            backward_nodes_by_head: HashMap::default(),
+            forward_nodes_by_egress: HashMap::default(),
+            found_paths: VecDeque::new(),
+            paths_so_far: HashSet::default(),
+            rng,
+        };
+
+        let start_distance = match start_distance {
+            Some(distance) => distance,
+            None => return paths,
+        };
+
+        let map_ref = Arc::new(map.clone());
+
+        for &ingress in start_points {
+            let path = paths.arena.start(vec![ingress]);
+            paths.add_backward_node(
+                start_distance,
+                BackwardNode {
+                    path,
+                    length: 0,
+                    map_ref: Arc::clone(&map_ref),
+                },
+            );
+        }
+
+        let mut mine_ingresses: Vec<Point> = paths
+            .distances_to_deposits
+            .iter()
+            .filter(|(_, distance)| **distance == 0)
+            .map(|(point, _)| *point)
+            .collect();
+        mine_ingresses.sort_unstable();
+
+        for mine_ingress in mine_ingresses {
+            for mine_subtype in MineSubtype::ALL {
+                let mine = Object::mine_with_subtype_and_ingress_at(mine_subtype, mine_ingress);
+                if map_ref.can_insert_object(&mine).is_ok() {
+                    paths.add_forward_node(mine, None, &map_ref);
+                }
+            }
+        }
+
+        paths
+    }
+
+    /// Adds a partial path (grown from the start points) to the search
+    fn add_backward_node(&mut self, distance: u32, node: BackwardNode) {
+        let node = Rc::new(node);
+
+        for head in self.arena.heads(node.path) {
+            for neighbour in neighbours(head.0, head.1) {
+                if let Some(forward_nodes) = self.forward_nodes_by_egress.get(&neighbour) {
+                    for forward_node in forward_nodes {
+                        if let Some(path) = self.join(&node, forward_node) {
+                            self.found_paths.push_back(path);
+                        }
+                    }
+                }
+            }
+
+            self.backward_nodes_by_head
+                .entry(head)
+                .or_default()
+                .push(Rc::clone(&node));
+        }
+
+        self.backward_queue.push(SearchState {
+            distance,
+            length: node.length,
+            node,
+        });
+    }
+
+    /// Adds a partial path (grown from a deposit) that ends with `object` to the search
+    fn add_forward_node(
+        &mut self,
+        object: Object,
+        predecessor: Option<Rc<ForwardNode>>,
+        map_ref: &Arc<Map>,
+    ) {
+        let egress = object.egress().unwrap();
+        let distance = match self.distances_to_starts.get(&egress) {
+            Some(distance) => distance.saturating_add(self.rng.borrow_mut().gen_range(0..=10)),
+            None => return,
+        };
+
+        let mut new_map_ref = Map::from_map(map_ref);
+        new_map_ref.insert_object_unchecked(object.clone());
+
+        let node = Rc::new(ForwardNode {
+            length: predecessor.as_ref().map(|p| p.length).unwrap_or(0) + 1,
+            object,
+            predecessor,
+            map_ref: Arc::new(new_map_ref),
+        });
+
+        for neighbour in neighbours(egress.0, egress.1) {
+            if let Some(backward_nodes) = self.backward_nodes_by_head.get(&neighbour) {
+                for backward_node in backward_nodes {
+                    if let Some(path) = self.join(backward_node, &node) {
+                        self.found_paths.push_back(path);
+                    }
+                }
+            }
+        }
+
+        self.forward_nodes_by_egress
+            .entry(egress)
+            .or_default()
+            .push(Rc::clone(&node));
+
+        self.forward_queue.push(SearchState {
+            distance,
+            length: node.length,
+            node,
+        });
+    }
+
+    /// Tries to join two partial paths, whose ends touch each other, into a complete path
+    fn join(&self, backward: &BackwardNode, forward: &Rc<ForwardNode>) -> Option<Path> {
+        if backward.length + forward.length > self.max_path_length {
+            return None;
+        }
+
+        let mut map = Map::from_map(&backward.map_ref);
+        let mut path = self.arena.path(backward.path);
+        let mut node = Some(forward);
+        while let Some(current) = node {
+            if map.contains_object(&current.object.id()) {
+                return None;
+            }
+            map.insert_object(current.object.clone()).ok()?;
+            path.append(current.object.clone());
+            node = current.predecessor.as_ref();
+        }
+
+        if self.paths_so_far.contains(&path.id()) {
+            return None;
+        }
+
+        Some(path)
+    }
+
+    /// Grows the partial path with the least distance to the deposits by one object
+    fn expand_backward(&mut self, state: SearchState<BackwardNode>) {
+        let node = state.node;
+        if node.length >= self.max_path_length {
+            return;
+        }
+
+        for (x, y) in self.arena.heads(node.path) {
+            let free_neighbours = neighbours(x, y)
+                .into_iter()
+                .filter(|(x, y)| node.map_ref.is_empty_at(*x, *y))
+                .collect::<Vec<Point>>();
+
+            for (nx, ny) in free_neighbours {
+                let conveyors = ConveyorSubtype::ALL
+                    .into_iter()
+                    .rev()
+                    .map(|subtype| Object::conveyor_with_subtype_and_egress_at(subtype, (nx, ny)));
+                let combiners = CombinerSubtype::ALL
+                    .into_iter()
+                    .map(|subtype| Object::combiner_with_subtype_and_egress_at(subtype, (nx, ny)));
+
+                for object in conveyors.chain(combiners) {
+                    if node.map_ref.can_insert_object(&object).is_err() {
+                        continue;
+                    }
+
+                    let distance = object
+                        .ingresses()
+                        .iter()
+                        .filter_map(|ingress| self.distances_to_deposits.get(ingress))
+                        .min()
+                        .cloned();
+
+                    if let Some(distance) = distance {
+                        let distance =
+                            distance.saturating_add(self.rng.borrow_mut().gen_range(0..=10));
+                        let mut new_map_ref = Map::from_map(&node.map_ref);
+                        new_map_ref.insert_object_unchecked(object.clone());
+
+                        let path = self.arena.append(object, node.path);
+                        self.add_backward_node(
+                            distance,
+                            BackwardNode {
+                                path,
+                                length: node.length + 1,
+                                map_ref: Arc::new(new_map_ref),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Grows the partial path with the least distance to the start points by one object
+    fn expand_forward(&mut self, state: SearchState<ForwardNode>) {
+        let node = state.node;
+        if node.length >= self.max_path_length {
+            return;
+        }
+
+        let (x, y) = node.object.egress().unwrap();
+        let free_neighbours = neighbours(x, y)
+            .into_iter()
+            .filter(|(x, y)| node.map_ref.is_empty_at(*x, *y))
+            .collect::<Vec<Point>>();
+
+        for (nx, ny) in free_neighbours {
+            for conveyor_subtype in ConveyorSubtype::ALL.into_iter().rev() {
+                let conveyor =
+                    Object::conveyor_with_subtype_and_ingress_at(conveyor_subtype, (nx, ny));
+                if node.map_ref.can_insert_object(&conveyor).is_ok() {
+                    self.add_forward_node(conveyor, Some(Rc::clone(&node)), &node.map_ref);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Rng> Iterator for BidirectionalPaths<T> {
+    type Item = Path;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let timer = Instant::now();
+
+        loop {
+            while let Some(path) = self.found_paths.pop_front() {
+                if self.paths_so_far.insert(path.id()) {
+                    return Some(path);
+                }
+            }
+
+            if timer.elapsed() > Duration::from_millis(MAX_SEARCH_TIME_IN_MILLIS) {
+                return None;
+            }
+
+            // always grow the smaller frontier
+            let expand_backward = match (self.backward_queue.len(), self.forward_queue.len()) {
+                (0, 0) => return None,
+                (0, _) => false,
+                (_, 0) => true,
+                (backward, forward) => backward <= forward,
+            };
+
+            if expand_backward {
+                let state = self.backward_queue.pop().unwrap();
+                self.expand_backward(state);
+            } else {
+                let state = self.forward_queue.pop().unwrap();
+                self.expand_forward(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use model::task::Task;
+    use rand::{rngs::StdRng, SeedableRng};
+    use simulator::simulate;
+
+    use super::*;
+    use crate::{config::SolverConfig, solve::Solver};
+
+    const TASKS: [&str; 3] = [
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/long_path_001.json"),
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../inputs/long_path_20_25.json"
+        ),
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../inputs/long_path_20_40.json"
+        ),
+    ];
+
+    #[test]
+    fn finds_insertable_paths_to_deposit() {
+        let task = Task::from_json_file(TASKS[0]).unwrap();
+        let map = Map::from(&task);
+        let deposits: Vec<Object> = map
+            .get_objects()
+            .filter(|object| matches!(object, Object::Deposit { .. }))
+            .cloned()
+            .collect();
+        let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(1)));
+        let paths: Vec<Path> =
+            BidirectionalPaths::new(&[(25, 5)], &deposits, &map, task.turns, rng)
+                .take(3)
+                .collect();
+
+        assert!(!paths.is_empty());
+        for path in paths {
+            assert!(path.deposit(&deposits).is_some());
+            let mut map = map.clone();
+            for object in path.objects() {
+                map.insert_object(object.clone()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn solves_long_path_tasks() {
+        for path in TASKS {
+            let task = Task::from_json_file(path).unwrap();
+            let map = Map::from(&task);
+            let config = SolverConfig {
+                bidirectional_path_search: true,
+                ..SolverConfig::default()
+            };
+            let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(1)));
+            let mut solver = Solver::new(&task, &map, config, rng, Duration::from_secs(1));
+
+            let (result, solution) = solver.next().expect("No solution found");
+            assert!(result.score > 0, "{}", path);
+            assert_eq!(simulate(&task, &solution, true).score, result.score);
+        }
+    }
+}
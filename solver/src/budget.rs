@@ -0,0 +1,190 @@
+//! Scaling of the search effort per iteration to the characteristics of a task's map
+
+use model::{
+    map::Map,
+    object::{Object, ObjectType},
+    task::Task,
+};
+
+use crate::config::SolverConfig;
+
+/// Number of cells of a map, on which the default config spends the intended effort
+const REFERENCE_NUM_CELLS: f32 = 50.0 * 50.0;
+
+/// Bounds of the factor by which the effort is scaled
+const MIN_BUDGET_FACTOR: f32 = 0.5;
+const MAX_BUDGET_FACTOR: f32 = 4.0;
+
+/// Characteristics of a map, that determine how hard it is to connect factories to deposits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapProfile {
+    pub num_cells: u32,
+    /// Share of cells that are not occupied by any object
+    pub free_cell_ratio: f32,
+    /// Share of cells that are occupied by obstacles
+    pub obstacle_ratio: f32,
+    /// Mean distance of the deposits to their center, relative to the map's mean side length
+    pub deposit_spread: f32,
+    pub num_products: u32,
+}
+
+impl MapProfile {
+    pub fn new(task: &Task, map: &Map) -> MapProfile {
+        let num_cells = map.width() as u32 * map.height() as u32;
+        let (mut num_occupied, mut num_obstacle_cells) = (0, 0);
+        for (point, _) in map.iter_cells() {
+            num_occupied += 1;
+            if map
+                .object_at(point.0, point.1)
+                .is_some_and(|object| object.kind() == ObjectType::Obstacle)
+            {
+                num_obstacle_cells += 1;
+            }
+        }
+
+        let centers: Vec<(f32, f32)> = map
+            .get_objects()
+            .filter(|object| matches!(object, Object::Deposit { .. }))
+            .map(|deposit| {
+                let ((left, top), (right, bottom)) = deposit.bounding_box();
+                (
+                    (left as f32 + right as f32) / 2.0,
+                    (top as f32 + bottom as f32) / 2.0,
+                )
+            })
+            .collect();
+        let deposit_spread = if centers.is_empty() {
+            0.0
+        } else {
+            let n = centers.len() as f32;
+            let (cx, cy) = centers
+                .iter()
+                .fold((0.0, 0.0), |(cx, cy), (x, y)| (cx + x / n, cy + y / n));
+            let mean_distance = centers
+                .iter()
+                .map(|(x, y)| (x - cx).abs() + (y - cy).abs())
+                .sum::<f32>()
+                / n;
+            mean_distance / ((map.width() as f32 + map.height() as f32) / 2.0)
+        };
+
+        MapProfile {
+            num_cells,
+            free_cell_ratio: 1.0 - num_occupied as f32 / num_cells.max(1) as f32,
+            obstacle_ratio: num_obstacle_cells as f32 / num_cells.max(1) as f32,
+            deposit_spread,
+            num_products: task.products.len() as u32,
+        }
+    }
+
+    /// Factor by which the number of paths and retries per iteration are scaled
+    ///
+    /// Larger maps, fewer free cells (obstacles counting twice as much as deposits) and deposits
+    /// spread over the map need more paths to be tried, while tiny maps are solved just as well
+    /// with less effort per iteration.
+    pub fn budget_factor(&self) -> f32 {
+        let size = (self.num_cells as f32 / REFERENCE_NUM_CELLS).sqrt();
+        let occupancy = 1.0 + (1.0 - self.free_cell_ratio) + self.obstacle_ratio;
+        let spread = 0.5 + self.deposit_spread;
+        (size * occupancy * spread).clamp(MIN_BUDGET_FACTOR, MAX_BUDGET_FACTOR)
+    }
+
+    /// Scales the search effort of `config` by [MapProfile::budget_factor]
+    ///
+    /// Path combinations are additionally scaled by the number of products, whose paths compete
+    /// for space.
+    pub fn scale(&self, config: SolverConfig) -> SolverConfig {
+        let factor = self.budget_factor();
+        let product_factor = 1.0 + 0.25 * self.num_products.saturating_sub(1) as f32;
+        SolverConfig {
+            num_path_combining_iterations: scaled(
+                config.num_path_combining_iterations,
+                factor * product_factor,
+            ),
+            num_paths_per_factory_and_resource: scaled(
+                config.num_paths_per_factory_and_resource,
+                factor,
+            ),
+            num_additional_paths_per_factory_and_resource: scaled(
+                config.num_additional_paths_per_factory_and_resource,
+                factor,
+            ),
+            num_combiner_merge_attempts: scaled(config.num_combiner_merge_attempts, factor),
+            ..config
+        }
+    }
+}
+
+/// `value` scaled by `factor`, but at least 1 unless `value` is 0
+fn scaled(value: u32, factor: f32) -> u32 {
+    if value == 0 {
+        0
+    } else {
+        ((value as f32 * factor).round() as u32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scales_with_map_size_and_obstacles() {
+        let task = |width: u8, height: u8, objects: Vec<Object>| Task {
+            width,
+            height,
+            objects,
+            products: vec![],
+            turns: 10,
+            time: None,
+        };
+        let profile = |task: &Task| {
+            MapProfile::new(
+                task,
+                &Map::new(task.width, task.height, task.objects.clone()),
+            )
+        };
+        let deposits = |size: i8| {
+            vec![
+                Object::Deposit {
+                    x: 0,
+                    y: 0,
+                    width: 2,
+                    height: 2,
+                    subtype: 0,
+                },
+                Object::Deposit {
+                    x: size - 2,
+                    y: size - 2,
+                    width: 2,
+                    height: 2,
+                    subtype: 0,
+                },
+            ]
+        };
+
+        let tiny = profile(&task(10, 10, deposits(10)));
+        let large = profile(&task(100, 100, deposits(100)));
+        let mut maze_objects = deposits(100);
+        maze_objects.extend((0..40).map(|i| Object::Obstacle {
+            x: 2 * i + 10,
+            y: 10,
+            width: 1,
+            height: 80,
+        }));
+        let maze = profile(&task(100, 100, maze_objects));
+
+        assert!(tiny.free_cell_ratio > 0.9);
+        assert!(maze.obstacle_ratio > 0.3);
+        assert_eq!(MIN_BUDGET_FACTOR, tiny.budget_factor());
+        assert!(tiny.budget_factor() < large.budget_factor());
+        assert!(large.budget_factor() < maze.budget_factor());
+
+        let config = SolverConfig::default();
+        let scaled = maze.scale(config);
+        assert!(
+            scaled.num_paths_per_factory_and_resource > config.num_paths_per_factory_and_resource
+        );
+        assert!(tiny.scale(config).num_path_combining_iterations >= 1);
+    }
+}
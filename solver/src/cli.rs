@@ -1,5 +1,14 @@
+use std::time::SystemTime;
+
 use clap::clap_derive::ValueEnum;
 use clap::Parser;
+use common::logging::LogLevel;
+use solver::{
+    bandit::DEFAULT_LOADED_LEARNING_PERCENT, config::SolverConfig, objective::Objective,
+    rng::RngType, strategy::Strategy,
+};
+
+use crate::deadline::parse_deadline;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -7,30 +16,249 @@ pub(crate) struct Args {
     #[arg(long, help = "Runtime in seconds")]
     pub time: Option<u64>,
 
+    #[arg(long, conflicts_with = "time", help = "Runtime in milliseconds")]
+    pub time_ms: Option<u64>,
+
+    #[arg(
+        long,
+        value_parser = parse_deadline,
+        help = "Point in time (RFC 3339 or milliseconds since the unix epoch) at which the solution must be printed"
+    )]
+    pub deadline: Option<SystemTime>,
+
+    #[arg(
+        long,
+        help = "Time in milliseconds that is reserved for accumulating and printing the result"
+    )]
+    pub safety_margin_ms: Option<u64>,
+
     #[arg(long, help = "Number of cores to use")]
     pub cores: Option<usize>,
 
     #[arg(long, help = "Output format")]
     pub out: Option<OutputFormat>,
 
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Additionally write the task with all placed objects (cli format) to the given file"
+    )]
+    pub emit_cli: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Additionally write the solution (solution format) to the given file"
+    )]
+    pub emit_solution: Option<String>,
+
     #[arg(long, help = "Seed for rng")]
     pub seed: Option<u64>,
 
-    #[arg(long, help = "Print additional solution stats")]
+    #[arg(long, help = "Print additional solution stats to stderr")]
     pub stats: bool,
 
-    #[arg(long, help = "Print final result as map")]
+    #[arg(long, help = "Print final result as map to stderr")]
     pub print: bool,
+
+    #[arg(
+        long,
+        default_value = "warn",
+        help = "Max level of log messages printed to stderr (debug and trace require a debug build)"
+    )]
+    pub log_level: LogLevel,
+
+    #[arg(long, help = "Print log messages as JSON lines")]
+    pub log_json: bool,
+
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Panic on malformed tasks instead of printing an empty solution (default in debug builds, disable with --strict=false)"
+    )]
+    pub strict: Option<bool>,
+
+    #[arg(long, help = "Periodically print the solver's progress to stderr")]
+    pub progress: bool,
+
+    #[arg(
+        long,
+        help = "Simulate the serialized solution again before printing it and abort if its score differs"
+    )]
+    pub verify: bool,
+
+    #[arg(long, help = "Criterion by which the best solution is chosen")]
+    pub objective: Option<Objective>,
+
+    #[arg(long, help = "Strategy by which solutions are searched")]
+    pub strategy: Option<Strategy>,
+
+    #[arg(
+        long,
+        help = "Percentage of the runtime the portfolio strategy spends on greedy construction"
+    )]
+    pub portfolio_greedy_percent: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Let threads start searching from the best solution found by any thread"
+    )]
+    pub warm_start: bool,
+
+    #[cfg(feature = "pin-threads")]
+    #[arg(long, help = "Pin each worker thread to its own core")]
+    pub pin_threads: bool,
+
+    #[arg(
+        long,
+        help = "Search the first paths of all factories of an iteration in parallel"
+    )]
+    pub parallel_path_search: bool,
+
+    #[arg(
+        long,
+        help = "Search paths from factories and deposits simultaneously, until both searches meet"
+    )]
+    pub bidirectional_path_search: bool,
+
+    #[arg(
+        long,
+        help = "Prefer additional paths to deposits that no path is attached to yet"
+    )]
+    pub diversify_deposits: bool,
+
+    #[arg(
+        long,
+        help = "Max number of mines per deposit, before paths are built to other deposits of the same resource"
+    )]
+    pub max_mines_per_deposit: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Scale the number of paths and retries per iteration to the size and obstacle density of the map"
+    )]
+    pub adaptive_budget: bool,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Percentage (0-100) of the factory position weights that is learned from the scores of former iterations"
+    )]
+    pub factory_position_learning: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the learned factory position statistics to the given file after the run"
+    )]
+    pub save_state: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Start from the factory position statistics of a former run on the same task (see --save-state); implies --factory-position-learning 50 unless given"
+    )]
+    pub load_state: Option<String>,
+
+    #[arg(long, help = "Random number generator to use")]
+    pub rng: Option<RngType>,
+
+    #[arg(
+        long,
+        help = "Record all seeds of the run to the given file, so it can be replayed"
+    )]
+    pub record: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write metrics of every iteration (time, failed path searches, simulation time, score) as CSV to the given file"
+    )]
+    pub telemetry: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "record",
+        help = "Replay the run recorded in the given file (ignores --cores, --seed and the solver config)"
+    )]
+    pub replay: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "THREAD:ITERATION",
+        value_parser = parse_iteration,
+        requires = "seed",
+        conflicts_with_all = ["record", "replay"],
+        help = "Repeat a single iteration of a former run single-threaded (requires the --seed, --time and solver config of the former run)"
+    )]
+    pub reproduce: Option<(usize, usize)>,
+
+    #[cfg(feature = "server")]
+    #[arg(
+        long,
+        conflicts_with_all = ["record", "replay", "reproduce"],
+        help = "Serve solutions of POSTed tasks via HTTP on the given address (e.g. 127.0.0.1:8080)"
+    )]
+    pub serve: Option<String>,
 }
 
 impl Args {
     pub fn output_format(&self) -> OutputFormat {
         self.out.clone().unwrap_or(OutputFormat::Solution)
     }
+
+    pub fn config(&self) -> SolverConfig {
+        SolverConfig {
+            objective: self.objective.unwrap_or_default(),
+            strategy: self.strategy.unwrap_or_default(),
+            portfolio_greedy_percent: self
+                .portfolio_greedy_percent
+                .unwrap_or(SolverConfig::default().portfolio_greedy_percent),
+            warm_start: self.warm_start,
+            #[cfg(feature = "pin-threads")]
+            pin_threads: self.pin_threads,
+            parallel_path_search: self.parallel_path_search,
+            bidirectional_path_search: self.bidirectional_path_search,
+            diversify_deposits: self.diversify_deposits,
+            max_mines_per_deposit: self.max_mines_per_deposit,
+            adaptive_budget: self.adaptive_budget,
+            factory_position_learning_percent: self.factory_position_learning.unwrap_or(match self
+                .load_state
+            {
+                Some(_) => DEFAULT_LOADED_LEARNING_PERCENT,
+                None => 0,
+            }),
+            rng: self.rng.unwrap_or_default(),
+            safety_margin_ms: self.safety_margin_ms,
+            pareto: self.output_format() == OutputFormat::Pareto,
+            ..SolverConfig::default()
+        }
+    }
+}
+
+/// Parses the thread and iteration index of `--reproduce`
+fn parse_iteration(value: &str) -> Result<(usize, usize), String> {
+    let (thread, iteration) = value
+        .split_once(':')
+        .ok_or_else(|| format!("Expected THREAD:ITERATION, got '{}'", value))?;
+    let parse = |value: &str| {
+        value
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid number '{}'", value))
+    };
+    Ok((parse(thread)?, parse(iteration)?))
 }
 
 #[derive(ValueEnum, Debug, Clone, PartialEq, Eq)]
 pub enum OutputFormat {
     Cli,
     Solution,
+    /// Solution, with a human readable table of all placed objects on stderr
+    Placements,
+    /// JSON array of all solutions that are not dominated in score, turn and number of objects
+    Pareto,
+    /// Solution with the solver version, git hash, seed, runtime, score and turn of the run
+    Envelope,
 }
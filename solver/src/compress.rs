@@ -0,0 +1,262 @@
+//! Post-processing that replaces chains of short conveyors by fewer long conveyors
+
+use model::{
+    coord::Point,
+    map::Map,
+    object::{Object, ObjectCell, ObjectType},
+    subtype::ConveyorSubtype,
+    task::Task,
+};
+use simulator::{simulate, SimulatorResult};
+
+/// Number of cells a short conveyor spans in its direction
+const SHORT_LENGTH: u32 = 3;
+
+/// Number of cells a long conveyor spans in its direction
+const LONG_LENGTH: u32 = 4;
+
+/// Rewrites chains of conveyors with the same direction into the minimal number of conveyors
+///
+/// A chain is only rewritten, if the new conveyors can be placed and simulating the map neither
+/// lowers its score nor delays the turn the score is reached. Returns the result of the
+/// (possibly) rewritten map.
+pub fn compress_conveyors(
+    task: &Task,
+    map: &Map,
+    result: SimulatorResult,
+) -> (SimulatorResult, Map) {
+    let mut map = map.clone();
+    let mut result = result;
+
+    for chain in conveyor_chains(&map) {
+        let length: u32 = chain.iter().map(conveyor_length).sum();
+        let (num_short, num_long) = match split_length(length) {
+            Some(split) if ((split.0 + split.1) as usize) < chain.len() => split,
+            _ => continue,
+        };
+
+        // subtypes of short and long conveyors with the direction of the chain
+        let subtype = conveyor_subtype(&chain[0]) as usize % 4;
+        let short = ConveyorSubtype::ALL[subtype];
+        let long = ConveyorSubtype::ALL[subtype + 4];
+        let (dx, dy) = short.direction().offset();
+
+        let mut ingress = chain[0].ingress().unwrap();
+        let mut replacement = Vec::with_capacity((num_short + num_long) as usize);
+        for subtype in (0..num_long)
+            .map(|_| long)
+            .chain((0..num_short).map(|_| short))
+        {
+            let conveyor = Object::conveyor_with_subtype_and_ingress_at(subtype, ingress);
+            let (x, y) = conveyor.egress().unwrap();
+            ingress = (x + dx, y + dy);
+            replacement.push(conveyor);
+        }
+
+        for conveyor in chain.iter() {
+            map.remove_object(conveyor).unwrap();
+        }
+
+        if map.try_insert_objects(replacement.clone()).is_ok() {
+            let new_result = simulate(task, &map, true);
+            if new_result.score >= result.score && new_result.turn <= result.turn {
+                result = new_result;
+                continue;
+            }
+            for conveyor in replacement.iter() {
+                map.remove_object(conveyor).unwrap();
+            }
+        }
+
+        map.try_insert_objects(chain).unwrap();
+    }
+
+    (result, map)
+}
+
+/// Finds all maximal chains of at least two conveyors that have the same direction and pass
+/// resources on to each other
+fn conveyor_chains(map: &Map) -> Vec<Vec<Object>> {
+    let mut chains = vec![];
+    for conveyor in map.get_objects() {
+        if conveyor.kind() != ObjectType::Conveyor || predecessor(map, conveyor).is_some() {
+            continue;
+        }
+
+        let mut chain = vec![conveyor.clone()];
+        while let Some(next) = successor(map, chain.last().unwrap()) {
+            chain.push(next.clone());
+        }
+
+        if chain.len() > 1 {
+            chains.push(chain);
+        }
+    }
+    chains
+}
+
+/// The conveyor with the same direction, whose ingress is next to the egress of `conveyor`
+fn successor<'a>(map: &'a Map, conveyor: &Object) -> Option<&'a Object> {
+    let (x, y) = conveyor.egress().unwrap();
+    let (dx, dy) = conveyor.direction().unwrap().offset();
+    conveyor_with_cell_at(map, (x + dx, y + dy), conveyor, |cell| {
+        matches!(cell, ObjectCell::Ingress { .. })
+    })
+}
+
+/// The conveyor with the same direction, whose egress is next to the ingress of `conveyor`
+fn predecessor<'a>(map: &'a Map, conveyor: &Object) -> Option<&'a Object> {
+    let (x, y) = conveyor.ingress().unwrap();
+    let (dx, dy) = conveyor.direction().unwrap().offset();
+    conveyor_with_cell_at(map, (x - dx, y - dy), conveyor, |cell| {
+        matches!(cell, ObjectCell::Egress { .. })
+    })
+}
+
+fn conveyor_with_cell_at<'a>(
+    map: &'a Map,
+    (x, y): Point,
+    conveyor: &Object,
+    is_cell: impl Fn(&ObjectCell) -> bool,
+) -> Option<&'a Object> {
+    let cell = map.get_cell(x, y)?;
+    if cell.kind() != ObjectType::Conveyor || !is_cell(cell) {
+        return None;
+    }
+    map.object_at(x, y)
+        .filter(|other| other.direction() == conveyor.direction())
+}
+
+fn conveyor_subtype(conveyor: &Object) -> ConveyorSubtype {
+    match conveyor {
+        Object::Conveyor { subtype, .. } => *subtype,
+        _ => unreachable!("{:?} is no conveyor", conveyor),
+    }
+}
+
+fn conveyor_length(conveyor: &Object) -> u32 {
+    if conveyor_subtype(conveyor).is_long() {
+        LONG_LENGTH
+    } else {
+        SHORT_LENGTH
+    }
+}
+
+/// Splits `length` cells into the minimal number of (short, long) conveyors
+fn split_length(length: u32) -> Option<(u32, u32)> {
+    (0..=length / LONG_LENGTH)
+        .rev()
+        .map(|num_long| (length - num_long * LONG_LENGTH, num_long))
+        .find(|(rest, _)| rest % SHORT_LENGTH == 0)
+        .map(|(rest, num_long)| (rest / SHORT_LENGTH, num_long))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use model::{subtype::MineSubtype, task::Product};
+
+    #[test]
+    fn splits_lengths_into_fewest_conveyors() {
+        let expected = [
+            (3, Some((1, 0))),
+            (4, Some((0, 1))),
+            (5, None),
+            (6, Some((2, 0))),
+            (7, Some((1, 1))),
+            (8, Some((0, 2))),
+            (9, Some((3, 0))),
+            (10, Some((2, 1))),
+            (11, Some((1, 2))),
+            (12, Some((0, 3))),
+        ];
+        for (length, split) in expected {
+            assert_eq!(split, split_length(length), "length {}", length);
+        }
+    }
+
+    /// A mine that supplies a factory through a chain of 4 short conveyors (12 cells), plus
+    /// `objects`
+    fn chain_map(objects: Vec<Object>) -> (Task, Map) {
+        let mut landscape = vec![Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 3,
+            subtype: 0,
+        }];
+        landscape.extend(objects);
+        let task = Task {
+            width: 25,
+            height: 10,
+            objects: landscape.clone(),
+            products: vec![Product {
+                kind: String::from("product"),
+                subtype: 0,
+                resources: vec![1, 0, 0, 0, 0, 0, 0, 0],
+                points: 1,
+            }],
+            turns: 50,
+            time: None,
+        };
+
+        let mut objects = landscape;
+        objects.push(Object::Mine {
+            x: 2,
+            y: 0,
+            subtype: MineSubtype::East,
+        });
+        for i in 0..4 {
+            objects.push(Object::conveyor_with_subtype_and_ingress_at(
+                ConveyorSubtype::East,
+                (5 + 3 * i, 1),
+            ));
+        }
+        objects.push(Object::Factory {
+            x: 17,
+            y: 0,
+            subtype: 0,
+        });
+        (task.clone(), Map::new(task.width, task.height, objects))
+    }
+
+    fn num_conveyors(map: &Map) -> usize {
+        map.get_objects()
+            .filter(|object| object.kind() == ObjectType::Conveyor)
+            .count()
+    }
+
+    #[test]
+    fn compresses_chain_of_short_conveyors() {
+        let (task, map) = chain_map(vec![]);
+        let result = simulate(&task, &map, true);
+        assert_eq!(15, result.score);
+
+        let (compressed_result, compressed) = compress_conveyors(&task, &map, result.clone());
+        assert_eq!(3, num_conveyors(&compressed));
+        assert_eq!(compressed_result, simulate(&task, &compressed, true));
+        assert_eq!(result.score, compressed_result.score);
+        // resources pass fewer conveyors, so they arrive earlier
+        assert!(compressed_result.turn < result.turn);
+    }
+
+    #[test]
+    fn keeps_chain_that_cannot_be_replaced() {
+        // next to the ingress of the second long conveyor of the replacement, but only next to an
+        // inner cell of the chain
+        let deposit = Object::Deposit {
+            x: 9,
+            y: 2,
+            width: 1,
+            height: 1,
+            subtype: 1,
+        };
+        let (task, map) = chain_map(vec![deposit]);
+        let result = simulate(&task, &map, true);
+
+        let (compressed_result, compressed) = compress_conveyors(&task, &map, result.clone());
+        assert_eq!(4, num_conveyors(&compressed));
+        assert_eq!(result, compressed_result);
+        assert_eq!(map.get_objects().count(), compressed.get_objects().count());
+    }
+}
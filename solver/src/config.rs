@@ -0,0 +1,163 @@
+//! Tunable parameters of the [Solver](crate::solve::Solver)
+
+use serde::{Deserialize, Serialize};
+
+use crate::{objective::Objective, rng::RngType, strategy::Strategy};
+
+/// Hyperparameters that control how a [Solver](crate::solve::Solver) explores the search space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolverConfig {
+    /// Number of times a factory location is tried.
+    /// If no location can be found a whole new iteration starts
+    pub num_max_factory_placements: u32,
+
+    /// Chance that a single factory will be skipped during placement
+    pub probability_factory_skip: (u32, u32),
+
+    /// Max number of factories per product
+    ///
+    /// Additional factories are only placed, if a product's resources are spread over multiple
+    /// deposits.
+    pub max_factories_per_product: u32,
+
+    /// Chance that an additional factory for a product will be placed
+    pub probability_additional_factory: (u32, u32),
+
+    /// Number of paths to try (calculate) per factory and resource type
+    pub num_paths_per_factory_and_resource: u32,
+
+    /// Number of additional paths to try (calculate) per factory and resource type
+    pub num_additional_paths_per_factory_and_resource: u32,
+
+    /// Number of path combinations to try during one iteration
+    pub num_path_combining_iterations: u32,
+
+    /// Number of failed additional paths (per placed factory) before an iteration stops building
+    /// additional paths
+    pub num_additional_path_failures_per_factory: u32,
+
+    /// Number of trunk conveyors that are tried to be replaced by a combiner, if no separate path
+    /// for a resource can be built
+    pub num_combiner_merge_attempts: u32,
+
+    /// Search paths from factories and deposits simultaneously (see
+    /// [BidirectionalPaths](crate::bidirectional_paths::BidirectionalPaths))
+    pub bidirectional_path_search: bool,
+
+    /// Search the first paths of all factories and resources of an iteration in parallel, before
+    /// they are built one after another
+    #[serde(default)]
+    pub parallel_path_search: bool,
+
+    /// Search additional paths to deposits, that no path is attached to yet, before all other
+    /// deposits of a resource
+    #[serde(default)]
+    pub diversify_deposits: bool,
+
+    /// Max number of mines per deposit, beyond which no further paths are built to it, but to
+    /// other deposits of the same resource
+    ///
+    /// If `None`, deposits only count as saturated once their mines extract all of their
+    /// resources in time.
+    #[serde(default)]
+    pub max_mines_per_deposit: Option<u32>,
+
+    /// Scale the number of paths and retries per iteration to the size, obstacle density and
+    /// deposit spread of the task's map (see [MapProfile](crate::budget::MapProfile))
+    #[serde(default)]
+    pub adaptive_budget: bool,
+
+    /// Let paths start at paths of other factories that produce the same product, instead of
+    /// always building a separate path to each factory
+    pub reuse_routes: bool,
+
+    /// Replace chains of short conveyors by fewer long conveyors in found solutions (see
+    /// [compress_conveyors](crate::compress::compress_conveyors))
+    #[serde(default)]
+    pub compress_conveyors: bool,
+
+    /// Number of iterations in which a factory placement, that led to a solution without any
+    /// points, is not tried again (see [TabuList](crate::tabu::TabuList))
+    ///
+    /// 0 disables skipping placements.
+    #[serde(default)]
+    pub tabu_tenure: u32,
+
+    /// Percentage (0 - 100) of a factory position's weight, that is learned from the scores of the
+    /// solutions it took part in (see [PositionBandit](crate::bandit::PositionBandit))
+    ///
+    /// 0 keeps the distance-based weights for the whole run.
+    #[serde(default)]
+    pub factory_position_learning_percent: u32,
+
+    /// Let the workers of a multi-threaded run start from the best solution found by any worker
+    /// (see [SearchStrategy::warm_start](crate::strategy::SearchStrategy::warm_start))
+    ///
+    /// [Strategy::Portfolio] always warm-starts its workers.
+    #[serde(default)]
+    pub warm_start: bool,
+
+    /// Pin each worker thread of a multi-threaded run to a core and let it work on its own copy of
+    /// the map (only with the feature `pin-threads`)
+    #[serde(default)]
+    pub pin_threads: bool,
+
+    /// Criterion by which the best solution is chosen
+    pub objective: Objective,
+
+    /// Strategy by which solutions are searched
+    pub strategy: Strategy,
+
+    /// Percentage of the runtime that [Strategy::Portfolio] spends on its greedy phase
+    ///
+    /// 0 skips the greedy phase.
+    #[serde(default)]
+    pub portfolio_greedy_percent: u32,
+
+    /// Algorithm of the random number generator
+    pub rng: RngType,
+
+    /// Time in milliseconds that is reserved at the end of a run for accumulating and printing
+    /// the result
+    ///
+    /// If `None`, a tenth of the runtime (but at least 0.5s and at most 1.5s) is reserved.
+    pub safety_margin_ms: Option<u64>,
+
+    /// Collect all solutions that are not dominated in score, turn and number of objects (see
+    /// [ParetoFront](crate::pareto::ParetoFront)), instead of only the best one
+    #[serde(default)]
+    pub pareto: bool,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            num_max_factory_placements: 20,
+            probability_factory_skip: (1, 10),
+            max_factories_per_product: 3,
+            probability_additional_factory: (1, 2),
+            num_paths_per_factory_and_resource: 5,
+            num_additional_paths_per_factory_and_resource: 5,
+            num_path_combining_iterations: 2,
+            num_additional_path_failures_per_factory: 10,
+            num_combiner_merge_attempts: 3,
+            bidirectional_path_search: false,
+            parallel_path_search: false,
+            diversify_deposits: false,
+            max_mines_per_deposit: None,
+            adaptive_budget: false,
+            reuse_routes: true,
+            compress_conveyors: true,
+            tabu_tenure: 50,
+            factory_position_learning_percent: 0,
+            warm_start: false,
+            pin_threads: false,
+            objective: Objective::ScoreThenTurn,
+            strategy: Strategy::Iterative,
+            portfolio_greedy_percent: 10,
+            rng: RngType::Std,
+            safety_margin_ms: None,
+            pareto: false,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Parsing of the `--deadline` argument
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Parses a point in time given as milliseconds since the unix epoch or as RFC 3339 timestamp
+/// (e.g. `2023-01-15T12:00:00.500+01:00`)
+pub(crate) fn parse_deadline(input: &str) -> Result<SystemTime, String> {
+    let input = input.trim();
+    if !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit()) {
+        let millis: u64 = input
+            .parse()
+            .map_err(|_| format!("Timestamp out of range: {}", input))?;
+        return Ok(UNIX_EPOCH + Duration::from_millis(millis));
+    }
+
+    parse_rfc3339(input).ok_or_else(|| format!("Cannot parse '{}' as RFC 3339 timestamp", input))
+}
+
+fn parse_rfc3339(input: &str) -> Option<SystemTime> {
+    let bytes = input.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || !matches!(bytes[10], b'T' | b't' | b' ')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return None;
+    }
+
+    let number = |from: usize, to: usize| -> Option<i64> {
+        let digits = input.get(from..to)?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    };
+
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    // leap seconds are allowed by RFC 3339
+    if second > 60 {
+        return None;
+    }
+
+    let mut rest = &input[19..];
+    let mut nanos = 0u32;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let num_digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if num_digits == 0 {
+            return None;
+        }
+        for (i, digit) in fraction.bytes().take(num_digits.min(9)).enumerate() {
+            nanos += (digit - b'0') as u32 * 10u32.pow(8 - i as u32);
+        }
+        rest = &fraction[num_digits..];
+    }
+
+    let offset_in_secs = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let hours = number(input.len() - 5, input.len() - 3)?;
+            let minutes = number(input.len() - 2, input.len())?;
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'+' {
+                offset
+            } else {
+                -offset
+            }
+        }
+        _ => return None,
+    };
+
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second
+        - offset_in_secs;
+    let secs = u64::try_from(secs).ok()?;
+
+    Some(UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+/// Number of days since 1970-01-01 of the given date (proleptic gregorian calendar)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -2,81 +2,292 @@
 
 use std::{
     collections::VecDeque,
-    hash::{Hash, Hasher},
+    hash::Hasher,
+    num::NonZeroUsize,
     sync::{Arc, Mutex},
 };
 
 use fxhash::FxHashMap as HashMap;
 use fxhash::FxHashSet as HashSet;
+use fxhash::FxHasher;
 use lazy_static::lazy_static;
+use lru::LruCache;
 use model::{
     coord::{neighbours, Point},
     map::Map,
-    object::Object,
+    object::{Object, ObjectID, ObjectIndex, Subtype},
 };
 
 /// Maximum number of cache entries (50_000 entries ~ 10Mb)
 ///
-/// If maximum is reached, every second entry will be evicted.
+/// If maximum is reached, the least recently used entry will be evicted.
 const NUM_MAX_CACHE_ENTRIES: usize = 50_000;
 
-/// Map from (hash(map), hash(deposits)) => distance map
-type DistanceCache = HashMap<(u64, u64), Arc<HashMap<Point, u32>>>;
+/// Key of a cached distance map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct DistanceCacheKey {
+    width: u8,
+    height: u8,
+    /// see [Map::zobrist_hash]
+    map_hash: u64,
+    /// see [deposit_set_id]
+    deposits_id: u64,
+}
+
+type DistanceCache = LruCache<DistanceCacheKey, Arc<HashMap<Point, u32>>>;
 
 lazy_static! {
-    static ref DISTANCES_CACHE: Mutex<DistanceCache> = Default::default();
+    static ref DISTANCES_CACHE: Mutex<DistanceCache> = Mutex::new(LruCache::new(
+        NonZeroUsize::new(NUM_MAX_CACHE_ENTRIES).unwrap()
+    ));
 }
 
 /// Create a map of shortest distances to given deposits from all empty points on map
 ///
 /// Returns map as Arc because it may be read from a cache
 pub(crate) fn get_distances(map: &Map, deposits: &[Object]) -> Arc<HashMap<Point, u32>> {
-    let map_hash = {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        map.hash(&mut hasher);
-        hasher.finish()
-    };
-    let deposits_hash = {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        deposits.hash(&mut hasher);
-        hasher.finish()
+    let key = DistanceCacheKey {
+        width: map.width(),
+        height: map.height(),
+        map_hash: map.zobrist_hash(),
+        deposits_id: deposit_set_id(deposits),
     };
 
-    let mut cache = DISTANCES_CACHE.lock().unwrap();
+    if let Some(distances) = DISTANCES_CACHE.lock().unwrap().get(&key) {
+        return Arc::clone(distances);
+    }
 
-    if cache.len() > NUM_MAX_CACHE_ENTRIES {
-        let mut keys_to_remove: Vec<(u64, u64)> = Vec::with_capacity(NUM_MAX_CACHE_ENTRIES / 2 + 2);
-        for (idx, (k, _)) in cache.iter().enumerate() {
-            if idx % 2 == 0 {
-                keys_to_remove.push(*k);
-            }
+    // calculate w/o holding the lock, so other threads are not blocked in the meantime
+    let distances = Arc::new(create_distances_to_deposits(map, deposits));
+    DISTANCES_CACHE
+        .lock()
+        .unwrap()
+        .put(key, Arc::clone(&distances));
+
+    distances
+}
+
+/// Distance maps to the deposits of each resource type (see [get_distances])
+///
+/// The maps are calculated once per task and shared by all workers through the cache. Once objects
+/// are inserted into the map, a worker copies the map and only recalculates the cells affected by
+/// them (see [update_distances]), and only when the map of a resource type is requested.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DistancesByType {
+    distances: HashMap<Subtype, ResourceDistances>,
+}
+
+#[derive(Debug, Clone)]
+struct ResourceDistances {
+    /// Egresses of all deposits of the resource type
+    origins: Arc<HashSet<Point>>,
+    distances: Arc<HashMap<Point, u32>>,
+    /// Cells of objects inserted since the distances were calculated
+    pending: Vec<Point>,
+}
+
+impl DistancesByType {
+    pub(crate) fn new(map: &Map, deposits_by_type: &HashMap<Subtype, Vec<Object>>) -> Self {
+        let distances = deposits_by_type
+            .iter()
+            .map(|(&resource, deposits)| {
+                let origins: HashSet<Point> = deposits.iter().flat_map(Object::egresses).collect();
+                (
+                    resource,
+                    ResourceDistances {
+                        origins: Arc::new(origins),
+                        distances: get_distances(map, deposits),
+                        pending: vec![],
+                    },
+                )
+            })
+            .collect();
+        DistancesByType { distances }
+    }
+
+    /// Distance map to all deposits of `resource` on `map`
+    ///
+    /// `map` must be the map the distances were calculated for, including all objects inserted
+    /// since (see [DistancesByType::insert_objects]).
+    pub(crate) fn get(&mut self, map: &Map, resource: Subtype) -> Option<Arc<HashMap<Point, u32>>> {
+        let entry = self.distances.get_mut(&resource)?;
+        if !entry.pending.is_empty() {
+            let distances = Arc::make_mut(&mut entry.distances);
+            update_distances(distances, map, &entry.origins, &entry.pending);
+            entry.pending.clear();
         }
-        for key in keys_to_remove.into_iter() {
-            cache.remove(&key);
+        Some(Arc::clone(&entry.distances))
+    }
+
+    /// Records that `objects` were inserted into the map
+    pub(crate) fn insert_objects<'o>(&mut self, objects: impl IntoIterator<Item = &'o Object>) {
+        let points: Vec<Point> = objects
+            .into_iter()
+            .flat_map(|object| object.cells(ObjectIndex::default()))
+            .map(|(point, _)| point)
+            .collect();
+        for entry in self.distances.values_mut() {
+            entry.pending.extend(points.iter().cloned());
         }
     }
+}
 
-    let distances = cache
-        .entry((map_hash, deposits_hash))
-        .or_insert_with(|| Arc::new(create_distances(map, deposits)));
+/// Removes all entries from the distances cache
+///
+/// Should be called before solving a new task, so entries of former tasks do not occupy the cache.
+pub(crate) fn clear_distances_cache() {
+    DISTANCES_CACHE.lock().unwrap().clear();
+}
 
-    Arc::clone(distances)
+/// An id of a set of deposits, independent of the deposits' order
+fn deposit_set_id(deposits: &[Object]) -> u64 {
+    let mut ids: Vec<ObjectID> = deposits.iter().map(|deposit| deposit.id()).collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut hasher = FxHasher::default();
+    for id in ids {
+        hasher.write_u64(id);
+    }
+    hasher.finish()
+}
+
+/// Create a map of shortest distances to given points from all empty points on map
+///
+/// Other than [get_distances], the result is not cached.
+pub(crate) fn get_distances_to_points(map: &Map, points: &[Point]) -> HashMap<Point, u32> {
+    create_distances(map, points.iter().cloned())
 }
 
 /// Create a map of shortest distances to given deposits from all reachable points on map
-fn create_distances(map: &Map, deposits: &[Object]) -> HashMap<Point, u32> {
+fn create_distances_to_deposits(map: &Map, deposits: &[Object]) -> HashMap<Point, u32> {
+    create_distances(map, deposits.iter().flat_map(|deposit| deposit.egresses()))
+}
+
+/// Updates `distances` to `origins` (see [create_distances]) after objects were inserted into
+/// `map` at `points`
+///
+/// Inserted objects can only block cells, so distances only increase. The cells whose distance
+/// increases are those, whose shortest paths all lead across `points`. Only these cells are
+/// removed and filled again from the unaffected cells around them, instead of recalculating the
+/// whole map. Removing objects is not supported, since it may decrease distances elsewhere.
+pub(crate) fn update_distances(
+    distances: &mut HashMap<Point, u32>,
+    map: &Map,
+    origins: &HashSet<Point>,
+    points: &[Point],
+) {
+    let is_start = |(x, y): Point| {
+        neighbours(x, y)
+            .iter()
+            .any(|neighbour| origins.contains(neighbour))
+    };
+
+    // Affected cells are removed from `distances` right away. Cells are visited by increasing
+    // distance, so whether a cell still has a predecessor is only checked once all affected cells
+    // of lower distance are removed.
+    let mut affected: Vec<Point> = vec![];
+    let mut queue = BucketQueue::default();
+    for point in points {
+        if let Some(distance) = distances.remove(point) {
+            affected.push(*point);
+            queue.push(distance, *point);
+        }
+    }
+    while let Some((distance, (x, y))) = queue.pop() {
+        for next in neighbours(x, y) {
+            if distances.get(&next) != Some(&(distance + 1)) {
+                continue;
+            }
+            let has_predecessor = neighbours(next.0, next.1)
+                .iter()
+                .any(|predecessor| distances.get(predecessor) == Some(&distance));
+            if !has_predecessor {
+                distances.remove(&next);
+                affected.push(next);
+                queue.push(distance + 1, next);
+            }
+        }
+    }
+
+    // A passable cell without distance next to an affected cell must have been affected itself,
+    // since it was reachable before. So filling these cells cannot reach any other cells.
+    let is_passable =
+        |(x, y): Point| !is_start((x, y)) && (map.is_empty_at(x, y) || map.is_crossable_at(x, y));
+    for &(x, y) in affected.iter() {
+        let distance = if is_start((x, y)) {
+            map.is_empty_at(x, y).then_some(0)
+        } else if is_passable((x, y)) {
+            neighbours(x, y)
+                .iter()
+                .filter_map(|neighbour| distances.get(neighbour))
+                .min()
+                .map(|distance| distance + 1)
+        } else {
+            None
+        };
+        if let Some(distance) = distance {
+            queue.push(distance, (x, y));
+        }
+    }
+    while let Some((distance, (x, y))) = queue.pop() {
+        if distances.get(&(x, y)).is_some_and(|&old| old <= distance) {
+            continue;
+        }
+        distances.insert((x, y), distance);
+        for next in neighbours(x, y) {
+            if distances.get(&next).is_none_or(|&old| old > distance + 1) && is_passable(next) {
+                queue.push(distance + 1, next);
+            }
+        }
+    }
+}
+
+/// Queue of points, that pops points by increasing distance
+///
+/// Points must not be pushed with a lower distance than the last popped point.
+#[derive(Default)]
+struct BucketQueue {
+    buckets: Vec<Vec<Point>>,
+    current: usize,
+}
+
+impl BucketQueue {
+    fn push(&mut self, distance: u32, point: Point) {
+        let distance = distance as usize;
+        if self.buckets.len() <= distance {
+            self.buckets.resize_with(distance + 1, Vec::new);
+        }
+        self.current = self.current.min(distance);
+        self.buckets[distance].push(point);
+    }
+
+    fn pop(&mut self) -> Option<(u32, Point)> {
+        while self.current < self.buckets.len() {
+            if let Some(point) = self.buckets[self.current].pop() {
+                return Some((self.current as u32, point));
+            }
+            self.current += 1;
+        }
+        None
+    }
+}
+
+/// Create a map of shortest distances to given origins from all reachable points on map
+///
+/// All empty neighbours of an origin have a distance of 0. Inner cells of conveyors are
+/// traversed as well, because they can be crossed by other conveyors.
+fn create_distances(map: &Map, origins: impl Iterator<Item = Point>) -> HashMap<Point, u32> {
     let mut distances: HashMap<Point, u32> = HashMap::default();
     let mut queue: VecDeque<(u32, Point)> = VecDeque::new();
     let mut visited: HashSet<Point> = HashSet::default();
 
-    for deposit in deposits {
-        for egress in deposit.egresses() {
-            for position in neighbours(egress.0, egress.1) {
-                if !visited.contains(&position) {
-                    visited.insert(position);
-                    if map.is_empty_at(position.0, position.1) {
-                        queue.push_back((0, position));
-                    }
+    for origin in origins {
+        for position in neighbours(origin.0, origin.1) {
+            if !visited.contains(&position) {
+                visited.insert(position);
+                if map.is_empty_at(position.0, position.1) {
+                    queue.push_back((0, position));
                 }
             }
         }
@@ -90,7 +301,9 @@ fn create_distances(map: &Map, deposits: &[Object]) -> HashMap<Point, u32> {
         for position in neighbours(x, y) {
             if !visited.contains(&position) {
                 visited.insert(position);
-                if map.is_empty_at(position.0, position.1) {
+                if map.is_empty_at(position.0, position.1)
+                    || map.is_crossable_at(position.0, position.1)
+                {
                     queue.push_back((distance + 1, position));
                 }
             }
@@ -99,3 +312,67 @@ fn create_distances(map: &Map, deposits: &[Object]) -> HashMap<Point, u32> {
 
     distances
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use model::subtype::ConveyorSubtype;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    #[test]
+    fn updated_distances_equal_recalculated_ones() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let deposits = vec![
+                Object::Deposit {
+                    x: rng.gen_range(0..5),
+                    y: rng.gen_range(0..20),
+                    width: 3,
+                    height: 3,
+                    subtype: 0,
+                },
+                Object::Deposit {
+                    x: rng.gen_range(15..22),
+                    y: rng.gen_range(0..20),
+                    width: 3,
+                    height: 3,
+                    subtype: 0,
+                },
+            ];
+            let mut map = Map::new(25, 25, deposits.clone());
+            let origins: HashSet<Point> = deposits.iter().flat_map(Object::egresses).collect();
+            let mut distances = create_distances_to_deposits(&map, &deposits);
+
+            for step in 0..60 {
+                let (x, y) = (rng.gen_range(0..25), rng.gen_range(0..25));
+                let object = if rng.gen_bool(0.3) {
+                    Object::Obstacle {
+                        x,
+                        y,
+                        width: rng.gen_range(1..=3),
+                        height: rng.gen_range(1..=3),
+                    }
+                } else {
+                    let subtype = *ConveyorSubtype::ALL.choose(&mut rng).unwrap();
+                    Object::Conveyor { x, y, subtype }
+                };
+                if map.insert_object(object.clone()).is_err() {
+                    continue;
+                }
+                let points: Vec<Point> = object
+                    .cells(ObjectIndex::default())
+                    .map(|(point, _)| point)
+                    .collect();
+                update_distances(&mut distances, &map, &origins, &points);
+                assert_eq!(
+                    create_distances_to_deposits(&map, &deposits),
+                    distances,
+                    "seed {}, step {}, after inserting {:?}",
+                    seed,
+                    step,
+                    object
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Solution wrapped with the metadata of the run that found it (see `--out envelope`)
+
+use std::time::Duration;
+
+use model::solution::Solution;
+use serde::{Deserialize, Serialize};
+use simulator::SimulatorResult;
+
+/// Version of the envelope's format, increased on every incompatible change
+pub(crate) const ENVELOPE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Envelope {
+    pub envelope_version: u32,
+    /// Version of the solver binary
+    pub solver_version: String,
+    /// Commit the solver was built from ('unknown' if built outside of a git checkout)
+    pub git_hash: String,
+    /// Seed of the run (None for solutions that cannot be reproduced, e.g. replayed runs)
+    pub seed: Option<u64>,
+    pub runtime_ms: u64,
+    pub score: u32,
+    /// Turn at which the score was reached
+    pub turn: u32,
+    pub solution: Solution,
+}
+
+impl Envelope {
+    pub(crate) fn new(
+        solution: Solution,
+        result: &SimulatorResult,
+        seed: Option<u64>,
+        runtime: Duration,
+    ) -> Envelope {
+        Envelope {
+            envelope_version: ENVELOPE_VERSION,
+            solver_version: String::from(env!("CARGO_PKG_VERSION")),
+            git_hash: String::from(env!("GIT_HASH")),
+            seed,
+            runtime_ms: runtime.as_millis() as u64,
+            score: result.score,
+            turn: result.turn,
+            solution,
+        }
+    }
+}
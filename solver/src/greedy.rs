@@ -0,0 +1,198 @@
+//! Deterministic greedy construction of a single solution
+//!
+//! Products are handled in the order of their points. Each product's factory is placed at the free
+//! position closest to its deposits (by the true distances around everything built before, each
+//! resource weighted by its amount in the recipe) and connected by [build_paths] right away, which
+//! routes the first path found per resource and adds further paths in the order of their expected
+//! marginal gain. A factory that does not add any points is removed and the next best position is
+//! tried instead.
+//!
+//! Only the path search itself draws random numbers (to break ties), so a seeded run always
+//! constructs the same solution. The result is a fast baseline, e.g. for runs with a tiny time
+//! budget.
+
+use std::{cell::RefCell, cmp::Reverse, rc::Rc, sync::Arc, time::Duration};
+
+use fxhash::FxHashMap as HashMap;
+
+use model::{
+    coord::Point,
+    map::Map,
+    object::{Object, Subtype},
+    task::{Product, Task},
+};
+use rand::Rng;
+use simulator::SimulatorResult;
+
+use crate::{
+    config::SolverConfig,
+    distances::DistancesByType,
+    placement::factory_distance,
+    solve::{build_paths, Solver},
+    strategy::SearchStrategy,
+    telemetry::simulate_timed,
+};
+
+/// A solver that constructs a single solution greedily
+pub struct GreedySolver<'a, T> {
+    /// Provides the factory positions, deposits and configuration of the task
+    base: Solver<'a, T>,
+    /// Set once the solution was constructed
+    exhausted: bool,
+    #[allow(unused)] //only used if feature 'stats' is active
+    num_solutions: usize,
+}
+
+impl<'a, T> GreedySolver<'a, T> {
+    #[cfg(feature = "stats")]
+    /// Returns the total number of solutions produced so far
+    pub fn get_num_solutions(&self) -> usize {
+        self.num_solutions
+    }
+}
+
+impl<'a, T: Rng> GreedySolver<'a, T> {
+    /// Creates a new solver for the given task / map
+    pub fn new(
+        task: &'a Task,
+        map: &'a Map,
+        config: SolverConfig,
+        rng: Rc<RefCell<T>>,
+        max_iteration_time: Duration,
+    ) -> GreedySolver<'a, T> {
+        GreedySolver {
+            base: Solver::new(task, map, config, rng, max_iteration_time),
+            exhausted: false,
+            num_solutions: 0,
+        }
+    }
+
+    fn construct(&self) -> Option<(SimulatorResult, Map)> {
+        let base = &self.base;
+        let mut map = base.original_map.clone();
+        let mut distances = base.distances_by_type.clone();
+
+        // the most valuable products are placed first, so they get the best positions
+        let mut products: Vec<&Product> = base.products.iter().collect();
+        products.sort_by_key(|product| (Reverse(product.points), product.subtype));
+
+        let mut result: Option<SimulatorResult> = None;
+        for product in products {
+            let (_, positions) = &base.best_factory_positions_by_factory_subtype[&product.subtype];
+            let mut tried_positions = vec![];
+            for _ in 0..base.config.num_max_factory_placements {
+                let factory = match best_factory(
+                    &map,
+                    &mut distances,
+                    product,
+                    positions,
+                    &tried_positions,
+                ) {
+                    Some(factory) => factory,
+                    None => break,
+                };
+                tried_positions.push(factory.coords());
+
+                // the paths of a factory are built right away, so later factories are placed
+                // around them
+                let mut next_map = map.clone();
+                let mut next_distances = distances.clone();
+                let mut factory_ids = vec![factory.id()];
+                next_distances.insert_objects([&factory]);
+                next_map.insert_object(factory).ok()?;
+                if !build_paths(
+                    base.task,
+                    &mut next_map,
+                    &mut factory_ids,
+                    &base.deposits_by_type,
+                    &mut next_distances,
+                    &base.config,
+                    &base.rng,
+                ) {
+                    continue;
+                }
+
+                // the factory is kept, if it adds any points
+                let next_result = simulate_timed(base.task, &next_map);
+                if next_result.score > result.as_ref().map_or(0, |result| result.score) {
+                    map = next_map;
+                    distances = next_distances;
+                    result = Some(next_result);
+                    break;
+                }
+            }
+        }
+
+        result.map(|result| (result, map))
+    }
+}
+
+impl<'a, T: Rng> Iterator for GreedySolver<'a, T> {
+    type Item = (SimulatorResult, Map);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if std::mem::replace(&mut self.exhausted, true) {
+            return None;
+        }
+
+        #[cfg(feature = "stats")]
+        {
+            self.num_solutions += 1;
+        }
+
+        self.construct()
+    }
+}
+
+impl<'a, T: Rng> SearchStrategy for GreedySolver<'a, T> {
+    /// Checks if the solution was constructed already, so no further solutions will follow
+    fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+/// The factory of `product` at the free position of `positions` (except `excluded`) with the
+/// lowest penalty
+///
+/// The penalty of a position is the sum of its distances to the nearest deposit of each resource,
+/// weighted by the amount of the resource the product needs. Returns None, if no position is left
+/// or none reaches all resources of the product.
+fn best_factory(
+    map: &Map,
+    distances: &mut DistancesByType,
+    product: &Product,
+    positions: &[Point],
+    excluded: &[Point],
+) -> Option<Object> {
+    let distances: Vec<(u32, Arc<HashMap<Point, u32>>)> = product
+        .resources
+        .iter()
+        .enumerate()
+        .filter(|&(_, &amount)| amount > 0)
+        .map(|(resource, &amount)| Some((amount, distances.get(map, resource as Subtype)?)))
+        .collect::<Option<_>>()?;
+
+    let penalty = |position: Point| -> Option<u32> {
+        distances
+            .iter()
+            .try_fold(0, |penalty, (amount, distances)| {
+                Some(penalty + amount * factory_distance(distances, position)?)
+            })
+    };
+
+    positions
+        .iter()
+        .filter(|position| !excluded.contains(position))
+        .filter_map(|&position| Some((penalty(position)?, position)))
+        .map(|(penalty, (x, y))| {
+            let factory = Object::Factory {
+                x,
+                y,
+                subtype: product.subtype,
+            };
+            (penalty, factory)
+        })
+        .filter(|(_, factory)| map.can_insert_object(factory).is_ok())
+        .min_by_key(|(penalty, factory)| (*penalty, factory.coords()))
+        .map(|(_, factory)| factory)
+}
@@ -1,5 +1,33 @@
+//! Solver for the construction site problem
+//!
+//! Most callers only need [run_solver], which runs solvers on multiple threads and returns the
+//! best solution found. A single-threaded [Solver] can also be iterated directly; each item is a
+//! solution with a positive score.
+
+pub mod bandit;
+pub mod bidirectional_paths;
+pub mod budget;
+pub mod compress;
+pub mod config;
 mod distances;
+pub mod greedy;
+pub mod mcts;
+pub mod objective;
+pub mod pareto;
 mod path;
 pub mod paths;
+pub mod placement;
+pub mod progress;
+pub mod prune;
+pub mod record;
+pub mod rng;
 pub mod run;
 pub mod solve;
+pub mod strategy;
+pub mod tabu;
+pub mod telemetry;
+
+pub use config::SolverConfig;
+pub use run::{run_solver, RunOptions, RunnerResult, SolutionOrigin};
+pub use simulator::SimulatorResult;
+pub use solve::Solver;
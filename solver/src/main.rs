@@ -1,36 +1,127 @@
 use clap::Parser;
 use cli::Args;
-use common::{debug, release};
-use model::{input::read_input_from_stdin, map::Map, object::Object, solution::Solution};
+use common::{logging::init_logging, release};
+use model::{
+    analysis::score_upper_bound, input::read_inputs_from_stdin, map::Map, solution::Solution,
+    strict::StrictMode, task::Task,
+};
 use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
     thread,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
 };
+use tracing::debug;
 
-use crate::cli::OutputFormat;
-use solver::run::run_solver;
+use crate::{cli::OutputFormat, envelope::Envelope};
+use simulator::SimulatorResult;
+use solver::{
+    bandit::{PlacementStatistics, SharedStatistics},
+    progress::Progress,
+    record::{DecisionLog, Recorder},
+    run::{replay_solver, reproduce_iteration, run_solver, RunOptions},
+    telemetry::Telemetry,
+};
 
 mod cli;
+mod deadline;
+mod envelope;
+mod placements;
+mod verify;
+
+/// Interval in which the progress is printed (if `--progress` is given)
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Set once SIGINT / SIGTERM is received, so that the best solution found so far is printed
+static STOP: AtomicBool = AtomicBool::new(false);
 
 fn main() {
     let now = Instant::now();
     let args = Args::parse();
+    init_logging(args.log_level, args.log_json);
+    if let Some(strict) = args.strict {
+        StrictMode::set_enabled(strict);
+    }
+
+    ctrlc::set_handler(|| {
+        // a second signal aborts immediately
+        if STOP.swap(true, Ordering::AcqRel) {
+            std::process::exit(130);
+        }
+        eprintln!("Interrupted, stopping solver");
+    })
+    .expect("Cannot install signal handler");
+
+    #[cfg(feature = "server")]
+    if let Some(addr) = &args.serve {
+        serve(&args, addr);
+        return;
+    }
+
+    let tasks: Vec<Task> = match read_inputs_from_stdin().or_else(StrictMode::fail) {
+        Ok(inputs) => inputs.into_iter().map(|(task, _)| task).collect(),
+        Err(e) => {
+            eprintln!("Invalid task: {}", e);
+            release!("{}", Solution::default().to_json_string().unwrap());
+            return;
+        }
+    };
+
+    if tasks.len() > 1
+        && (args.record.is_some() || args.replay.is_some() || args.reproduce.is_some())
+    {
+        eprintln!("Recording, replaying and reproducing is only supported for a single task");
+        std::process::exit(2);
+    }
 
-    let (task, _) = read_input_from_stdin().unwrap();
+    if tasks.len() > 1 && (args.emit_cli.is_some() || args.emit_solution.is_some()) {
+        eprintln!("Emitting files is only supported for a single task");
+        std::process::exit(2);
+    }
 
-    let map = Map::new(
-        task.width,
-        task.height,
-        task.objects.iter().cloned().map(Object::from).collect(),
-    );
+    if tasks.len() > 1 && (args.save_state.is_some() || args.load_state.is_some()) {
+        eprintln!("Saving and loading state is only supported for a single task");
+        std::process::exit(2);
+    }
 
-    let runtime = {
-        let runtime_in_secs = args.time.unwrap_or(task.time.unwrap_or(100) as u64);
-        Duration::from_secs(runtime_in_secs) - now.elapsed()
+    // Total time for all tasks, measured from the start of the program
+    let budget = {
+        let budget = match (args.time_ms, args.time) {
+            (Some(runtime_in_millis), _) => Duration::from_millis(runtime_in_millis),
+            (None, Some(runtime_in_secs)) => Duration::from_secs(runtime_in_secs),
+            (None, None) => tasks
+                .iter()
+                .map(|task| Duration::from_secs(task.time.unwrap_or(100) as u64))
+                .sum(),
+        };
+        match args.deadline {
+            Some(deadline) => {
+                let time_until_deadline = deadline
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default();
+                // the deadline is absolute, so startup time is already accounted for
+                budget.min(time_until_deadline + now.elapsed())
+            }
+            None => budget,
+        }
     };
 
-    debug!("Time bound {}s", runtime.as_secs());
+    let num_threads = num_threads(&args);
+    let telemetry = args.telemetry.as_deref().map(create_telemetry);
 
+    // tasks are solved one after another, each with an equal share of the remaining time
+    for (i, task) in tasks.iter().enumerate() {
+        let num_remaining_tasks = (tasks.len() - i) as u32;
+        let runtime = budget.saturating_sub(now.elapsed()) / num_remaining_tasks;
+        solve(&args, task, runtime, num_threads, telemetry.as_ref());
+    }
+}
+
+/// Number of threads the solver uses
+fn num_threads(args: &Args) -> usize {
     let num_threads = args.cores.unwrap_or_else(|| {
         thread::available_parallelism()
             .map(|i| i.get())
@@ -39,43 +130,346 @@ fn main() {
 
     debug!("Using {} thread(s)", num_threads);
 
-    let result = run_solver(&task, &map, num_threads, runtime, args.seed);
+    num_threads
+}
+
+/// Answers HTTP requests on `addr` with solutions of the POSTed tasks, until SIGINT / SIGTERM
+#[cfg(feature = "server")]
+fn serve(args: &Args, addr: &str) {
+    let num_threads = num_threads(args);
+    let config = args.config();
+    let telemetry = match &args.telemetry {
+        Some(path) => create_telemetry(path),
+        None => Telemetry::default(),
+    };
+
+    eprintln!("Listening on {}", addr);
+    let solve = |task: &Task, runtime| {
+        let map = match task_map(task) {
+            Some(map) => map,
+            None => return Solution::default(),
+        };
+        let options = RunOptions {
+            cancel: Some(&STOP),
+            telemetry: Some(&telemetry),
+            ..RunOptions::default()
+        };
+        run_solver(
+            task,
+            &map,
+            &config,
+            num_threads,
+            Some(runtime),
+            args.seed,
+            options,
+        )
+        .map(|result| Solution::from(&result.map))
+        .unwrap_or_default()
+    };
+    let served = server::serve(addr, &STOP, solve, || telemetry.to_prometheus());
+
+    if let Err(e) = served {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Solves `task` within `runtime` and prints the solution
+fn solve(
+    args: &Args,
+    task: &Task,
+    runtime: Duration,
+    num_threads: usize,
+    telemetry: Option<&Telemetry>,
+) {
+    let time_start = Instant::now();
+    let map = match task_map(task) {
+        Some(map) => map,
+        None => {
+            release!("{}", Solution::default().to_json_string().unwrap());
+            return;
+        }
+    };
+
+    debug!("Time bound {:.3}s", runtime.as_secs_f32());
+
+    let config = args.config();
+
+    // runs without --seed are seeded randomly, but an envelope must name a seed that reproduces it
+    let seed = match args.seed {
+        None if args.output_format() == OutputFormat::Envelope => Some(rand::random()),
+        seed => seed,
+    };
+
+    let recorder = args.record.as_ref().map(|path| {
+        Recorder::create(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    });
+
+    let statistics: Option<SharedStatistics> = (args.save_state.is_some()
+        || args.load_state.is_some())
+    .then(|| Arc::new(Mutex::new(load_state(args, task))));
+
+    let options = RunOptions {
+        cancel: Some(&STOP),
+        recorder: recorder.as_ref(),
+        telemetry,
+        statistics: statistics.as_ref(),
+        ..RunOptions::default()
+    };
+
+    let result = if let Some(path) = &args.replay {
+        let log = DecisionLog::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        debug!("Replaying {} worker(s)", log.seeds_by_worker.len());
+        replay_solver(task, &map, &log)
+    } else if let Some((thread, iteration)) = args.reproduce {
+        reproduce_iteration(
+            task,
+            &map,
+            &config,
+            seed.expect("clap requires --seed for --reproduce"),
+            runtime,
+            thread,
+            iteration,
+        )
+    } else if args.progress {
+        let progress = Progress::new(num_threads, runtime);
+        let (sender, receiver) = mpsc::channel::<()>();
+        thread::scope(|scope| {
+            scope.spawn(|| print_progress(&progress, receiver));
+            let options = RunOptions {
+                progress: Some(&progress),
+                ..options
+            };
+            let result = run_solver(
+                task,
+                &map,
+                &config,
+                num_threads,
+                Some(runtime),
+                seed,
+                options,
+            );
+            // stops printing
+            drop(sender);
+            result
+        })
+    } else {
+        run_solver(
+            task,
+            &map,
+            &config,
+            num_threads,
+            Some(runtime),
+            seed,
+            options,
+        )
+    };
+
+    if let (Some(path), Some(statistics)) = (&args.save_state, &statistics) {
+        if let Err(e) = statistics.lock().unwrap().save(path) {
+            eprintln!("{}", e);
+        }
+    }
 
     if let Some(result) = result {
         #[cfg(feature = "stats")]
         {
-            println!(
+            eprintln!(
                 "Calculated {} solutions per second",
                 result.solutions_per_second
             );
         }
 
         if cfg!(debug_assertions) || args.stats {
-            println!("{:?}", result.result);
+            eprintln!("{:?}", result.result);
+            eprintln!("Found in iteration {}", result.origin);
+            let upper_bound = score_upper_bound(task);
+            eprintln!(
+                "Score/upper bound: {}/{} ({:.2}%)",
+                result.result.score,
+                upper_bound,
+                100.0 * result.result.score as f32 / upper_bound.max(1) as f32
+            );
         }
 
         if args.print {
-            println!("{}", result.map);
+            eprintln!("{}", result.map);
         }
 
-        if cfg!(debug_assertions) || args.output_format() == OutputFormat::Cli {
-            /* allow explicit cloning of task to make clear, that we *do not* change the original
-             * task, but just a copy in order to print the solution
-             */
-            #[allow(clippy::redundant_clone)]
-            let mut task = task.clone();
-            task.objects = result.map.get_objects().cloned().collect();
-            println!("{}", task.to_json_string().unwrap());
+        if let Err(e) = emit_files(args, task, &result.map) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+
+        let output_format = if cfg!(debug_assertions) {
+            OutputFormat::Cli
         } else {
-            let objects = result
-                .map
-                .get_objects()
-                .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
-                .cloned();
-            println!("{}", Solution::from(objects).to_json_string().unwrap());
+            args.output_format()
+        };
+
+        if output_format == OutputFormat::Pareto {
+            print_pareto(args, task, &result.pareto);
+            return;
         }
+
+        let output = if output_format == OutputFormat::Cli {
+            cli_output(task, &result.map)
+        } else if output_format == OutputFormat::Envelope {
+            // a replayed run is not reproducible by its seed
+            let seed = seed.filter(|_| args.replay.is_none());
+            let solution = Solution::from(&result.map);
+            let envelope = Envelope::new(solution, &result.result, seed, time_start.elapsed());
+            serde_json::to_string(&envelope).unwrap()
+        } else {
+            Solution::from(&result.map).to_json_string().unwrap()
+        };
+
+        if args.verify {
+            if let Err(e) = verify::verify(task, &output, &output_format, &result.result) {
+                eprintln!("Verification failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+
+        if output_format == OutputFormat::Placements {
+            eprint!("{}", placements::placements_table(&result.map));
+        }
+
+        println!("{}", output);
     } else {
         debug!("No solution found");
         release!("{}", Solution::default().to_json_string().unwrap());
     }
 }
+
+/// The telemetry sink given by `--telemetry`; exits if the file cannot be written
+fn create_telemetry(path: &str) -> Telemetry {
+    Telemetry::create_csv(path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+/// The statistics given by `--load-state`, or empty statistics if there are none for `task`
+///
+/// A missing file is not an error, so the first run of a session can load from and save to the
+/// same file.
+fn load_state(args: &Args, task: &Task) -> PlacementStatistics {
+    let path = match &args.load_state {
+        Some(path) if std::path::Path::new(path).exists() => path,
+        _ => return PlacementStatistics::new(task),
+    };
+    match PlacementStatistics::load(path) {
+        Ok(statistics) if statistics.is_of(task) => {
+            debug!(
+                "Loaded {} solutions from state {}",
+                statistics.num_solutions(),
+                path
+            );
+            statistics
+        }
+        Ok(_) => {
+            eprintln!("Ignoring state {} of another task", path);
+            PlacementStatistics::new(task)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The task with all objects of `map`, in cli format
+fn cli_output(task: &Task, map: &Map) -> String {
+    /* allow explicit cloning of task to make clear, that we *do not* change the original
+     * task, but just a copy in order to print the solution
+     */
+    #[allow(clippy::redundant_clone)]
+    let mut task = task.clone();
+    task.objects = map.get_objects().cloned().collect();
+    task.to_json_string().unwrap()
+}
+
+/// Writes the solution on `map` to the files given by `--emit-cli` and `--emit-solution`
+fn emit_files(args: &Args, task: &Task, map: &Map) -> Result<(), String> {
+    if let Some(path) = &args.emit_cli {
+        std::fs::write(path, cli_output(task, map))
+            .map_err(|e| format!("Cannot write {}: {}", path, e))?;
+    }
+    if let Some(path) = &args.emit_solution {
+        let solution = Solution::from(map).to_json_string().unwrap();
+        std::fs::write(path, solution).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+/// Creates the map of the task's objects
+///
+/// Returns None (unless in strict mode), if the task is malformed.
+fn task_map(task: &Task) -> Option<Map> {
+    Map::try_new(task.width, task.height, task.objects.to_vec())
+        .or_else(StrictMode::fail)
+        .map_err(|e| eprintln!("Invalid task: {}", e))
+        .ok()
+}
+
+/// Prints all solutions of the Pareto front as JSON array of their score, turn and objects
+fn print_pareto(args: &Args, task: &Task, pareto: &[(SimulatorResult, Map)]) {
+    let mut solutions = vec![];
+    for (result, map) in pareto {
+        let solution = Solution::from(map);
+        if args.verify {
+            let output = solution.to_json_string().unwrap();
+            if let Err(e) = verify::verify(task, &output, &OutputFormat::Pareto, result) {
+                eprintln!("Verification failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        solutions.push(serde_json::json!({
+            "score": result.score,
+            "turn": result.turn,
+            "objects": solution.0.len(),
+            "solution": solution,
+        }));
+    }
+    println!("{}", serde_json::to_string(&solutions).unwrap());
+}
+
+/// Prints `progress` to stderr every [PROGRESS_INTERVAL] until `stop` is disconnected
+fn print_progress(progress: &Progress, stop: Receiver<()>) {
+    while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(PROGRESS_INTERVAL) {
+        let best_score = progress
+            .best_score()
+            .map(|score| score.to_string())
+            .unwrap_or_else(|| String::from("-"));
+        eprintln!(
+            "[{:>6.1}s] {:.1} solutions/s, best score {}, {:.1}s remaining",
+            progress.elapsed().as_secs_f32(),
+            progress.solutions_per_second(),
+            best_score,
+            progress.time_remaining().as_secs_f32(),
+        );
+        for (i, thread) in progress.threads().iter().enumerate() {
+            eprintln!(
+                "\t#{}: {} ({} solutions, best score {})",
+                i,
+                if thread.is_finished() {
+                    "finished"
+                } else {
+                    "searching"
+                },
+                thread.num_solutions(),
+                thread
+                    .best_score()
+                    .map(|score| score.to_string())
+                    .unwrap_or_else(|| String::from("-")),
+            );
+        }
+    }
+}
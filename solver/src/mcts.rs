@@ -0,0 +1,338 @@
+//! Monte-Carlo tree search over factory placements
+//!
+//! Every node of the search tree represents a partial layout, in which the factories of the first
+//! `depth` products have been placed (or skipped). A rollout completes the layout by placing the
+//! remaining factories at random, builds paths to the deposits (like [Solver] does) and scores the
+//! layout with [simulate]. Scores are propagated back to the root, so that promising placements
+//! are explored more often.
+
+use std::{cell::RefCell, ops::DerefMut, rc::Rc, time::Duration, time::Instant};
+
+use model::{
+    analysis::score_upper_bound,
+    coord::Point,
+    map::Map,
+    object::{Object, ObjectID},
+    task::{Product, Task},
+};
+use rand::{prelude::Distribution, seq::SliceRandom, Rng};
+use simulator::SimulatorResult;
+
+use crate::{
+    config::SolverConfig,
+    solve::{build_paths, Solver},
+    strategy::SearchStrategy,
+    telemetry::simulate_timed,
+};
+
+/// Number of positions that are sampled as candidates for a factory at a single node
+const NUM_CANDIDATE_POSITIONS: usize = 8;
+
+/// Exploration constant of the UCT formula
+const EXPLORATION: f64 = std::f64::consts::SQRT_2;
+
+/// Placement of the factory of a single product, or `None` if the product is skipped
+type Action = Option<Point>;
+
+/// A partial layout
+struct Node {
+    visits: u32,
+    /// Sum of all (normalized) scores of rollouts through this node
+    total_reward: f64,
+    children: Vec<(Action, Node)>,
+    /// Actions that have not been expanded yet (sampled when the node is entered the first time)
+    untried_actions: Option<Vec<Action>>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Node {
+            visits: 0,
+            total_reward: 0.0,
+            children: Vec::new(),
+            untried_actions: None,
+        }
+    }
+
+    /// Index of the child with the highest upper confidence bound
+    fn best_child(&self) -> Option<usize> {
+        let ln_visits = (self.visits.max(1) as f64).ln();
+        self.children
+            .iter()
+            .map(|(_, child)| {
+                let visits = child.visits.max(1) as f64;
+                child.total_reward / visits + EXPLORATION * (ln_visits / visits).sqrt()
+            })
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    /// Adds a rollout with `reward` to this node and the descendants along `path` (the index of
+    /// the child at each level)
+    fn backpropagate(&mut self, path: &[usize], reward: f64) {
+        let mut node = self;
+        node.visits += 1;
+        node.total_reward += reward;
+        for &index in path {
+            node = &mut node.children[index].1;
+            node.visits += 1;
+            node.total_reward += reward;
+        }
+    }
+}
+
+/// A solver that searches factory placements by Monte-Carlo tree search
+pub struct MctsSolver<'a, T> {
+    /// Provides the factory positions, deposits and configuration of the task
+    base: Solver<'a, T>,
+    root: Node,
+    /// Upper bound of the score, used to normalize rewards
+    max_score: f64,
+    #[allow(unused)] //only used if feature 'stats' is active
+    num_solutions: usize,
+}
+
+impl<'a, T> MctsSolver<'a, T> {
+    #[cfg(feature = "stats")]
+    /// Returns the total number of solutions produced so far
+    pub fn get_num_solutions(&self) -> usize {
+        self.num_solutions
+    }
+}
+
+impl<'a, T: Rng> MctsSolver<'a, T> {
+    /// Creates a new solver for the given task / map
+    pub fn new(
+        task: &'a Task,
+        map: &'a Map,
+        config: SolverConfig,
+        rng: Rc<RefCell<T>>,
+        max_iteration_time: Duration,
+    ) -> MctsSolver<'a, T> {
+        MctsSolver {
+            base: Solver::new(task, map, config, rng, max_iteration_time),
+            root: Node::new(),
+            max_score: score_upper_bound(task).max(1) as f64,
+            num_solutions: 0,
+        }
+    }
+
+    /// Runs a single selection, expansion, rollout and backpropagation step
+    ///
+    /// Returns the completed layout, if its paths could be built.
+    fn iterate(&mut self) -> Option<(SimulatorResult, Map)> {
+        let MctsSolver {
+            base,
+            root,
+            max_score,
+            ..
+        } = self;
+        let products = &base.products;
+
+        let mut map = base.original_map.clone();
+        let mut factory_ids = Vec::new();
+
+        /* SELECTION + EXPANSION */
+        let mut selected: Vec<usize> = Vec::new();
+        let mut node = &mut *root;
+        while selected.len() < products.len() {
+            let product = &products[selected.len()];
+            let untried_actions = node
+                .untried_actions
+                .get_or_insert_with(|| candidate_actions(base, product));
+
+            if let Some(action) = untried_actions.pop() {
+                if let Some(factory_id) = apply(&mut map, product, action) {
+                    factory_ids.extend(factory_id);
+                    node.children.push((action, Node::new()));
+                    selected.push(node.children.len() - 1);
+                    break;
+                }
+                // the factory cannot be placed in this layout
+                continue;
+            }
+
+            match node.best_child() {
+                Some(index) => {
+                    let (action, child) = &mut node.children[index];
+                    // placing the same factories in the same order always succeeds
+                    factory_ids.extend(apply(&mut map, product, *action).unwrap());
+                    selected.push(index);
+                    node = child;
+                }
+                None => break,
+            }
+        }
+
+        /* ROLLOUT */
+        for product in products.iter().skip(selected.len()) {
+            factory_ids.extend(place_randomly(base, &mut map, product));
+        }
+
+        let mut distances = base.distances_by_type.clone();
+        distances.insert_objects(factory_ids.iter().map(|&id| map.get_object(id)));
+
+        let result = if !factory_ids.is_empty()
+            && build_paths(
+                base.task,
+                &mut map,
+                &mut factory_ids,
+                &base.deposits_by_type,
+                &mut distances,
+                &base.config,
+                &base.rng,
+            ) {
+            Some(simulate_timed(base.task, &map))
+        } else {
+            None
+        };
+
+        /* BACKPROPAGATION */
+        let reward = result
+            .as_ref()
+            .map(|result| result.score as f64 / *max_score)
+            .unwrap_or(0.0);
+        root.backpropagate(&selected, reward);
+
+        result.map(|result| (result, map))
+    }
+}
+
+impl<'a, T: Rng> Iterator for MctsSolver<'a, T> {
+    type Item = (SimulatorResult, Map);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time_start = Instant::now();
+
+        while time_start.elapsed() <= self.base.max_iteration_time {
+            let solution = self.iterate();
+
+            #[cfg(feature = "stats")]
+            {
+                self.num_solutions += 1;
+            }
+
+            if let Some((result, map)) = solution {
+                if result.score > 0 {
+                    return Some((result, map));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Samples the actions that are tried for the factory of `product`
+fn candidate_actions<T: Rng>(solver: &Solver<T>, product: &Product) -> Vec<Action> {
+    let (distribution, positions) =
+        &solver.best_factory_positions_by_factory_subtype[&product.subtype];
+    let mut rng = solver.rng.borrow_mut();
+
+    let mut actions = vec![None];
+    for _ in 0..NUM_CANDIDATE_POSITIONS {
+        let action = Some(positions[distribution.sample(rng.deref_mut())]);
+        if !actions.contains(&action) {
+            actions.push(action);
+        }
+    }
+    actions.shuffle(rng.deref_mut());
+
+    actions
+}
+
+/// Places the factory of `product` as described by `action`
+///
+/// Returns `None` if the factory cannot be placed, or `Some(None)` if the product is skipped.
+fn apply(map: &mut Map, product: &Product, action: Action) -> Option<Option<ObjectID>> {
+    match action {
+        Some((x, y)) => {
+            let factory = Object::Factory {
+                x,
+                y,
+                subtype: product.subtype,
+            };
+            let factory_id = factory.id();
+            map.insert_object(factory).ok().map(|_| Some(factory_id))
+        }
+        None => Some(None),
+    }
+}
+
+/// Places the factory of `product` at a random position, just like [Solver] does
+fn place_randomly<T: Rng>(
+    solver: &Solver<T>,
+    map: &mut Map,
+    product: &Product,
+) -> Option<ObjectID> {
+    let config = &solver.config;
+    if solver.rng.borrow_mut().gen_ratio(
+        config.probability_factory_skip.0,
+        config.probability_factory_skip.1,
+    ) {
+        return None;
+    }
+
+    let (distribution, positions) =
+        &solver.best_factory_positions_by_factory_subtype[&product.subtype];
+    for _ in 0..config.num_max_factory_placements {
+        let position = positions[distribution.sample(solver.rng.borrow_mut().deref_mut())];
+        if let Some(factory_id) = apply(map, product, Some(position)).flatten() {
+            return Some(factory_id);
+        }
+    }
+
+    None
+}
+
+/// The statistics of the search tree only hold for rollouts of the tree itself, so an incumbent is
+/// not used.
+impl<'a, T: Rng> SearchStrategy for MctsSolver<'a, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(visits: u32, total_reward: f64) -> Node {
+        Node {
+            visits,
+            total_reward,
+            ..Node::new()
+        }
+    }
+
+    #[test]
+    fn selects_child_by_upper_confidence_bound() {
+        let mut root = node(20, 10.0);
+        root.children = vec![(None, node(10, 2.0)), (None, node(10, 8.0))];
+        assert_eq!(Some(1), root.best_child());
+
+        // a rarely visited child gets a large exploration bonus
+        root.children.push((None, node(1, 0.5)));
+        assert_eq!(Some(2), root.best_child());
+
+        assert_eq!(None, Node::new().best_child());
+    }
+
+    #[test]
+    fn backpropagates_reward_along_path() {
+        let mut root = Node::new();
+        let mut child = Node::new();
+        child.children = vec![(None, Node::new()), (None, Node::new())];
+        root.children = vec![(None, Node::new()), (None, child)];
+
+        root.backpropagate(&[1, 0], 0.5);
+        root.backpropagate(&[1], 0.25);
+
+        assert_eq!((2, 0.75), (root.visits, root.total_reward));
+        assert_eq!(0, root.children[0].1.visits);
+        let child = &root.children[1].1;
+        assert_eq!((2, 0.75), (child.visits, child.total_reward));
+        assert_eq!(
+            (1, 0.5),
+            (child.children[0].1.visits, child.children[0].1.total_reward)
+        );
+        assert_eq!(0, child.children[1].1.visits);
+    }
+}
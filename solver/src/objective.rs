@@ -0,0 +1,61 @@
+//! Criteria by which solutions are compared
+
+use std::cmp::{Ordering, Reverse};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use simulator::SimulatorResult;
+
+/// Criterion by which the best solution of a solver run is chosen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum Objective {
+    /// Highest score first, then earliest turn (the ordering of simulation results)
+    #[default]
+    ScoreThenTurn,
+    /// Earliest turn the final score is reached first, then highest score
+    TurnThenScore,
+    /// Highest score per placed object first, then highest score and earliest turn
+    ScorePerObject,
+}
+
+impl Objective {
+    /// Compares two solutions by their simulation results and their number of placed objects
+    ///
+    /// Greater is better. Among solutions that are equal under this objective, the one with
+    /// fewer objects is better.
+    pub fn compare(
+        &self,
+        (result, num_objects): (&SimulatorResult, usize),
+        (other, other_num_objects): (&SimulatorResult, usize),
+    ) -> Ordering {
+        let ordering = match self {
+            Objective::ScoreThenTurn => result.cmp(other),
+            Objective::TurnThenScore => other
+                .turn
+                .cmp(&result.turn)
+                .then(result.score.cmp(&other.score)),
+            Objective::ScorePerObject => {
+                let score_per_object = result.score as u64 * other_num_objects.max(1) as u64;
+                let other_score_per_object = other.score as u64 * num_objects.max(1) as u64;
+                score_per_object
+                    .cmp(&other_score_per_object)
+                    .then(result.cmp(other))
+            }
+        };
+
+        ordering.then(other_num_objects.cmp(&num_objects))
+    }
+
+    /// Checks if a solution with `result` may be better than a solution with `best`, once
+    /// objects that do not transport any resources are removed from it
+    pub fn may_improve(&self, result: &SimulatorResult, best: &SimulatorResult) -> bool {
+        match self {
+            Objective::ScoreThenTurn => result >= best,
+            Objective::TurnThenScore => {
+                (Reverse(result.turn), result.score) >= (Reverse(best.turn), best.score)
+            }
+            // removing objects raises the score per object, so any solution may be better
+            Objective::ScorePerObject => true,
+        }
+    }
+}
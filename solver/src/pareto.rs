@@ -0,0 +1,96 @@
+//! Solutions that are not dominated by any other solution
+//!
+//! Solutions are compared by their score (higher is better), the turn the score is reached and
+//! their number of placed objects (both lower is better). Depending on the tiebreaks of a contest,
+//! any solution of the front may be the best one.
+
+use model::{map::Map, object::Object};
+use simulator::SimulatorResult;
+
+/// Objectives of a single solution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParetoPoint {
+    pub score: u32,
+    pub turn: u32,
+    pub num_objects: usize,
+}
+
+impl ParetoPoint {
+    pub fn new(result: &SimulatorResult, map: &Map) -> ParetoPoint {
+        let num_objects = map
+            .get_objects()
+            .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
+            .count();
+
+        ParetoPoint {
+            score: result.score,
+            turn: result.turn,
+            num_objects,
+        }
+    }
+
+    /// Checks if this point is at least as good as `other` in every objective
+    pub fn dominates(&self, other: &ParetoPoint) -> bool {
+        self.score >= other.score
+            && self.turn <= other.turn
+            && self.num_objects <= other.num_objects
+    }
+}
+
+/// Set of mutually non-dominated solutions
+///
+/// Of solutions with equal objectives, only the one inserted first is kept.
+#[derive(Default)]
+pub struct ParetoFront {
+    solutions: Vec<(ParetoPoint, SimulatorResult, Map)>,
+}
+
+impl ParetoFront {
+    /// Adds a solution to the front, unless it is dominated by a solution of the front
+    ///
+    /// All solutions of the front that are dominated by the new solution are removed. Returns
+    /// true, if the solution was added.
+    pub fn insert(&mut self, result: SimulatorResult, map: Map) -> bool {
+        let point = ParetoPoint::new(&result, &map);
+        if self.is_dominated(&point) {
+            return false;
+        }
+
+        self.solutions
+            .retain(|(other, _, _)| !point.dominates(other));
+        self.solutions.push((point, result, map));
+        true
+    }
+
+    /// Checks if a solution of the front dominates `point`
+    pub fn is_dominated(&self, point: &ParetoPoint) -> bool {
+        self.solutions
+            .iter()
+            .any(|(other, _, _)| other.dominates(point))
+    }
+
+    pub fn len(&self) -> usize {
+        self.solutions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.solutions.is_empty()
+    }
+
+    /// Returns all solutions of the front, ordered by score (descending), turn and number of
+    /// objects
+    pub fn into_solutions(self) -> Vec<(SimulatorResult, Map)> {
+        let mut solutions = self.solutions;
+        solutions.sort_by_key(|(point, _, _)| {
+            (
+                std::cmp::Reverse(point.score),
+                point.turn,
+                point.num_objects,
+            )
+        });
+        solutions
+            .into_iter()
+            .map(|(_, result, map)| (result, map))
+            .collect()
+    }
+}
@@ -1,60 +1,186 @@
-//! Representation of a single path, as constructed by [Paths]
+//! Representation of a single path, as constructed by [Paths](crate::paths::Paths)
+//!
+//! Path searches grow many partial paths that share their tails. These are stored as segments in
+//! a [PathArena], which refer to their tails by index. Complete paths are copied out of the arena
+//! (see [PathArena::path]), so they own their objects and can be sent between threads.
 
-use std::{borrow::Borrow, rc::Rc};
+use std::hash::Hasher;
 
+use fxhash::FxHasher;
 use model::{
-    coord::Point,
+    coord::{neighbours, Point},
     object::{Object, ObjectType},
 };
 
 pub type PathID = u128;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub enum Path {
-    End { ingresses: Vec<Point> },
-    Segment { object: Object, tail: Rc<Path> },
+/// Calculates a hash-like id of a path, based on its ordered `objects`
+fn path_id<'a>(objects: impl Iterator<Item = &'a Object>) -> PathID {
+    // two independently seeded hashes, so ids of distinct paths practically never collide
+    let mut low = FxHasher::default();
+    let mut high = FxHasher::default();
+    high.write_u64(u64::MAX);
+    for object in objects {
+        low.write_u64(object.id());
+        high.write_u64(object.id());
+    }
+
+    ((high.finish() as u128) << 64) | (low.finish() as u128)
 }
 
-/// A path of objects
-impl Path {
+/// Index of a (partial) path's head segment in a [PathArena]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SegmentIndex(u32);
+
+#[derive(Debug, Clone)]
+enum Segment {
+    End {
+        ingresses: Vec<Point>,
+    },
+    Object {
+        object: Object,
+        tail: SegmentIndex,
+        /// Number of objects of the path ending with this segment
+        len: u32,
+    },
+}
+
+/// Storage of partial paths, which share their tails
+#[derive(Debug, Clone, Default)]
+pub struct PathArena {
+    segments: Vec<Segment>,
+}
+
+impl PathArena {
+    pub fn new() -> Self {
+        PathArena::default()
+    }
+
+    fn push(&mut self, segment: Segment) -> SegmentIndex {
+        self.segments.push(segment);
+        SegmentIndex(self.segments.len() as u32 - 1)
+    }
+
     /// Creates an empty path with given ingresses as heads
-    pub fn from_starting_points(starting_points: Vec<Point>) -> Self {
-        Path::End {
+    pub fn start(&mut self, starting_points: Vec<Point>) -> SegmentIndex {
+        self.push(Segment::End {
             ingresses: starting_points,
+        })
+    }
+
+    /// Appends `object` to the path at `tail` to create a new path
+    pub fn append(&mut self, object: Object, tail: SegmentIndex) -> SegmentIndex {
+        let len = self.len(tail) + 1;
+        self.push(Segment::Object { object, tail, len })
+    }
+
+    /// Number of objects of the path at `head`
+    pub fn len(&self, head: SegmentIndex) -> u32 {
+        match &self.segments[head.0 as usize] {
+            Segment::End { .. } => 0,
+            Segment::Object { len, .. } => *len,
         }
     }
 
-    /// Appends `object` to this path to create a new Path
-    pub fn append(object: Object, tail: &Rc<Path>) -> Path {
-        Path::Segment {
-            object,
-            tail: Rc::clone(tail),
+    /// Returns all ingresses of the head of the path at `head`
+    pub fn heads(&self, head: SegmentIndex) -> Vec<Point> {
+        match &self.segments[head.0 as usize] {
+            Segment::End { ingresses } => ingresses.clone(),
+            Segment::Object { object, .. } => object.ingresses(),
         }
     }
 
-    /// Calculates a hash-like id for this path, based on its objects
-    pub fn id(&self) -> PathID {
-        let mut a = 0u64;
-        let mut b = 0u64;
-        let mut t = false;
-        for object in self.objects() {
-            if t {
-                a ^= object.id();
-            } else {
-                b ^= object.id();
+    /// Calculates a hash-like id for the path at `head` (see [Path::id])
+    pub fn id(&self, head: SegmentIndex) -> PathID {
+        path_id(self.objects(head))
+    }
+
+    /// Return an Iterator over the objects of the path at `head`, starting at its head
+    pub fn objects(&self, head: SegmentIndex) -> impl Iterator<Item = &Object> {
+        let mut index = head;
+        std::iter::from_fn(move || match &self.segments[index.0 as usize] {
+            Segment::End { .. } => None,
+            Segment::Object { object, tail, .. } => {
+                index = *tail;
+                Some(object)
             }
+        })
+    }
 
-            t ^= t;
+    /// Copies the path at `head` out of this arena
+    pub fn path(&self, head: SegmentIndex) -> Path {
+        let mut objects = Vec::with_capacity(self.len(head) as usize);
+        let mut index = head;
+        loop {
+            match &self.segments[index.0 as usize] {
+                Segment::End { ingresses } => {
+                    objects.reverse();
+                    return Path {
+                        starting_points: ingresses.clone(),
+                        objects,
+                    };
+                }
+                Segment::Object { object, tail, .. } => {
+                    objects.push(object.clone());
+                    index = *tail;
+                }
+            }
         }
+    }
+}
 
-        ((a as u128) << 64) | (b as u128)
+/// A path of objects
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Path {
+    starting_points: Vec<Point>,
+    /// Objects from the starting points to the head
+    objects: Vec<Object>,
+}
+
+impl Path {
+    /// Creates an empty path with given ingresses as heads
+    pub fn from_starting_points(starting_points: Vec<Point>) -> Self {
+        Path {
+            starting_points,
+            objects: vec![],
+        }
+    }
+
+    /// Appends `object` to the head of this path
+    pub fn append(&mut self, object: Object) {
+        self.objects.push(object);
+    }
+
+    /// Replaces `object` by `replacement`, returns whether `object` was part of this path
+    pub fn replace(&mut self, object: &Object, replacement: Object) -> bool {
+        match self.objects.iter_mut().find(|o| *o == object) {
+            Some(o) => {
+                *o = replacement;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of objects of this path
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Calculates a hash-like id for this path, based on its objects
+    pub fn id(&self) -> PathID {
+        path_id(self.objects())
     }
 
     /// Returns all ingresses of the path's head
     pub fn heads(&self) -> Vec<Point> {
-        match self {
-            Path::End { ingresses } => ingresses.clone(),
-            Path::Segment { object, .. } => object.ingresses(),
+        match self.objects.last() {
+            None => self.starting_points.clone(),
+            Some(object) => object.ingresses(),
         }
     }
 
@@ -78,69 +204,145 @@ impl Path {
         ingresses
     }
 
-    /// Return an Iterator over this path's objects
-    pub fn objects(&self) -> impl Iterator<Item = &Object> {
-        PathObjects { path: self }
+    /// The deposit of `deposits`, that the mine at this path's head is attached to
+    pub fn deposit<'d>(&self, deposits: &'d [Object]) -> Option<&'d Object> {
+        let mine = self
+            .objects
+            .last()
+            .filter(|object| object.kind() == ObjectType::Mine)?;
+        let (x, y) = mine.ingress()?;
+        neighbours(x, y).into_iter().find_map(|(x, y)| {
+            deposits.iter().find(|deposit| {
+                let ((left, top), (right, bottom)) = deposit.bounding_box();
+                left <= x && x <= right && top <= y && y <= bottom
+            })
+        })
     }
-}
 
-impl From<&Path> for Vec<Object> {
-    fn from(mut path: &Path) -> Self {
-        let mut v = vec![];
+    /// Returns the points this path was started from
+    pub fn starting_points(&self) -> &[Point] {
+        &self.starting_points
+    }
 
-        loop {
-            match path {
-                Path::End { .. } => {
-                    break;
-                }
-                Path::Segment { object, tail } => {
-                    v.push(object.clone());
-                    path = tail.borrow();
-                }
-            }
-        }
+    /// Return an Iterator over this path's objects, starting at its head
+    pub fn objects(&self) -> impl DoubleEndedIterator<Item = &Object> + ExactSizeIterator {
+        self.objects.iter().rev()
+    }
+}
 
-        v
+impl From<&Path> for Vec<Object> {
+    fn from(path: &Path) -> Self {
+        path.objects().cloned().collect()
     }
 }
 
 impl From<Path> for Vec<Object> {
     fn from(mut path: Path) -> Self {
-        let mut v = vec![];
+        path.objects.reverse();
+        path.objects
+    }
+}
 
-        loop {
-            match path {
-                Path::End { .. } => {
-                    break;
-                }
-                Path::Segment { object, tail } => {
-                    v.push(object.clone());
-                    path = Rc::try_unwrap(tail).expect(
-                        "Cannot turn path into objects. Path is still (partially) referenced.",
-                    )
-                }
-            }
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
+    use fxhash::FxHashSet as HashSet;
+    use model::subtype::ConveyorSubtype;
 
-        v
+    fn conveyor(x: i8, y: i8, subtype: ConveyorSubtype) -> Object {
+        Object::Conveyor { x, y, subtype }
     }
-}
 
-/// An iterator of objects along a path
-struct PathObjects<'a> {
-    path: &'a Path,
-}
+    #[test]
+    fn ids_depend_on_order() {
+        let a = conveyor(1, 1, ConveyorSubtype::East);
+        let b = conveyor(3, 1, ConveyorSubtype::East);
+        let c = conveyor(5, 1, ConveyorSubtype::East);
 
-impl<'a> Iterator for PathObjects<'a> {
-    type Item = &'a Object;
+        let mut arena = PathArena::new();
+        let start = arena.start(vec![(0, 1)]);
+        let abc = arena.append(a.clone(), start);
+        let abc = arena.append(b.clone(), abc);
+        let abc = arena.append(c.clone(), abc);
+        let acb = arena.append(a.clone(), start);
+        let acb = arena.append(c.clone(), acb);
+        let acb = arena.append(b.clone(), acb);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.path {
-            Path::End { .. } => None,
-            Path::Segment { object, tail } => {
-                self.path = tail;
-                Some(object)
+        assert_ne!(arena.id(abc), arena.id(acb));
+        // objects that cancelled each other out by XOR
+        let aa = arena.append(a.clone(), start);
+        let aa = arena.append(a, aa);
+        assert_ne!(arena.id(aa), arena.id(start));
+        assert_eq!(arena.id(abc), arena.path(abc).id());
+    }
+
+    #[test]
+    fn arena_paths_share_tails() {
+        let a = conveyor(1, 1, ConveyorSubtype::East);
+        let b = conveyor(3, 1, ConveyorSubtype::East);
+        let c = conveyor(2, 3, ConveyorSubtype::South);
+
+        let mut arena = PathArena::new();
+        let start = arena.start(vec![(0, 1)]);
+        // both paths share the segment of `a`
+        let shared = arena.append(a.clone(), start);
+        let ab = arena.append(b.clone(), shared);
+        let ac = arena.append(c.clone(), shared);
+
+        assert_eq!(0, arena.len(start));
+        assert_eq!(vec![(0, 1)], arena.heads(start));
+        assert_eq!(2, arena.len(ab));
+        assert_eq!(vec![&b, &a], arena.objects(ab).collect::<Vec<_>>());
+        assert_eq!(vec![&c, &a], arena.objects(ac).collect::<Vec<_>>());
+        assert_eq!(b.ingresses(), arena.heads(ab));
+
+        // copies equal paths built by appending objects one after another
+        for (head, objects) in [(ab, [&a, &b]), (ac, [&a, &c])] {
+            let mut expected = Path::from_starting_points(vec![(0, 1)]);
+            for object in objects {
+                expected.append(object.clone());
+            }
+            let path = arena.path(head);
+            assert_eq!(expected, path);
+            assert_eq!(arena.len(head) as usize, path.len());
+            assert_eq!(arena.id(head), path.id());
+            assert_eq!(
+                arena.objects(head).collect::<Vec<_>>(),
+                path.objects().collect::<Vec<_>>()
+            );
+        }
+        assert_ne!(arena.id(ab), arena.id(ac));
+    }
+
+    #[test]
+    fn no_collisions() {
+        let mut arena = PathArena::new();
+        let start = arena.start(vec![(0, 0)]);
+        let mut ids = HashSet::default();
+        let mut num_paths = 0;
+        // all paths of up to 3 conveyors out of 40
+        let objects: Vec<Object> = (0..10)
+            .flat_map(|x| {
+                ConveyorSubtype::ALL[..4]
+                    .iter()
+                    .map(move |&s| conveyor(x, 0, s))
+            })
+            .collect();
+        for first in objects.iter() {
+            let one = arena.append(first.clone(), start);
+            ids.insert(arena.id(one));
+            num_paths += 1;
+            for second in objects.iter() {
+                let two = arena.append(second.clone(), one);
+                ids.insert(arena.id(two));
+                num_paths += 1;
+                for third in objects.iter() {
+                    let three = arena.append(third.clone(), two);
+                    ids.insert(arena.id(three));
+                    num_paths += 1;
+                }
             }
         }
+        assert_eq!(ids.len(), num_paths);
     }
 }
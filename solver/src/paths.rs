@@ -11,14 +11,17 @@ use std::{
 use fxhash::FxHashMap as HashMap;
 use fxhash::FxHashSet as HashSet;
 
+use crate::bidirectional_paths::BidirectionalPaths;
 use crate::distances::get_distances;
-use crate::path::{Path, PathID};
+use crate::path::{Path, PathArena, PathID, SegmentIndex};
 use model::{
     coord::{neighbours, Point},
     map::Map,
     object::Object,
+    subtype::{CombinerSubtype, ConveyorSubtype, MineSubtype},
 };
 use rand::Rng;
+use simulator::estimate_delivery_turns;
 
 /// Max time to search for the next path
 const MAX_SEARCH_TIME_IN_MILLIS: u64 = 200;
@@ -31,12 +34,18 @@ const MAX_SEARCH_TIME_IN_MILLIS: u64 = 200;
 /// will increase overall performance by pruning bad paths early.
 const MAX_STEPS_WITHOUT_IMPROVEMENT: usize = 10;
 
+/// Max number of objects of a path, that can deliver any resources within `turns` turns
+pub fn max_path_length_for_turns(turns: u32) -> u32 {
+    // every additional object delays the delivery by one turn
+    (turns + 1).saturating_sub(estimate_delivery_turns(1))
+}
+
 /// The internal BFS-search state (= type of search queue elements)
 struct PathSearchState {
     start_distance: u32,
     distance: u32,
     path_length: u32,
-    path: Rc<Path>,
+    path: SegmentIndex,
     map_ref: Arc<Map>,
 }
 
@@ -66,7 +75,10 @@ impl Ord for PathSearchState {
 /// A container for constructing paths between a set of start and target points
 pub struct Paths<T> {
     distances_to_deposits: Arc<HashMap<Point, u32>>,
+    /// Max number of objects of a path, so that it still delivers resources in time
+    max_delivery_path_length: u32,
     paths_so_far: HashSet<PathID>,
+    arena: PathArena,
     queue: BinaryHeap<PathSearchState>,
     rng: Rc<RefCell<T>>,
 }
@@ -75,16 +87,28 @@ impl<T: Rng> Paths<T> {
     /// Creates a new Paths container
     ///
     /// # Example
-    /// A container for constructing paths from any point in `starts` to any deposit of `deposits`
-    /// Paths::new(starts, deposits, map, rng)
+    /// A container for constructing paths from any point in `starts` to any deposit of `deposits`,
+    /// that deliver resources within `turns` turns
+    /// Paths::new(starts, deposits, map, turns, rng)
     pub fn new(
         start_points: &[Point],
         deposits: &[Object],
         map: &Map,
+        turns: u32,
         rng: Rc<RefCell<T>>,
     ) -> Self {
-        let distances_to_deposits = get_distances(map, deposits);
+        Paths::with_distances(start_points, get_distances(map, deposits), map, turns, rng)
+    }
 
+    /// Creates a new Paths container, whose targets are the cells with a distance of 0 in
+    /// `distances_to_deposits`
+    pub fn with_distances(
+        start_points: &[Point],
+        distances_to_deposits: Arc<HashMap<Point, u32>>,
+        map: &Map,
+        turns: u32,
+        rng: Rc<RefCell<T>>,
+    ) -> Self {
         let min_distance_to_deposits = |points: &[Point]| {
             points
                 .iter()
@@ -97,16 +121,17 @@ impl<T: Rng> Paths<T> {
 
         let mut queue: BinaryHeap<PathSearchState> = BinaryHeap::new();
 
+        let mut arena = PathArena::new();
         let map_ref = Arc::new(map.clone());
         for &ingress in start_points {
-            let path = Path::from_starting_points(vec![ingress]);
+            let path = arena.start(vec![ingress]);
             let distance = min_distance_to_deposits(&neighbours(ingress.0, ingress.1));
             if let Some(distance) = distance {
                 queue.push(PathSearchState {
                     start_distance: distance,
                     distance,
                     path_length: 0,
-                    path: Rc::new(path),
+                    path,
                     map_ref: Arc::clone(&map_ref),
                 });
             }
@@ -114,7 +139,9 @@ impl<T: Rng> Paths<T> {
 
         Paths {
             distances_to_deposits,
+            max_delivery_path_length: max_path_length_for_turns(turns),
             paths_so_far,
+            arena,
             queue,
             rng,
         }
@@ -127,7 +154,9 @@ impl<T: Rng> Iterator for Paths<T> {
     fn next(&mut self) -> Option<Self::Item> {
         let Paths {
             distances_to_deposits,
+            max_delivery_path_length,
             paths_so_far,
+            arena,
             queue,
             ref rng,
             ..
@@ -175,18 +204,21 @@ impl<T: Rng> Iterator for Paths<T> {
             // Max distance a path's head may have to the target
             let max_path_distance = 2 * start_distance;
             // Max length a path may have
-            let max_path_length = (start_distance / 3) + 10;
+            let max_path_length = ((start_distance / 3) + 10).min(*max_delivery_path_length);
 
             if path_distance > max_path_distance || path_length > max_path_length {
                 continue;
             }
 
-            for (x, y) in path.heads() {
+            for (x, y) in arena.heads(path) {
                 /* LOGIC
                  *  1. check if target is reached by placing a mine
                  *  2. try using long conveyor
                  *  3. try using short conveyor
                  *  4. try using combiner
+                 *
+                 * Conveyors may cross existing conveyors with their inner cells, so the target
+                 * can be reached across other paths' trunks (see [Map::can_insert_object]).
                  */
 
                 let free_neighbours = neighbours(x, y)
@@ -195,7 +227,12 @@ impl<T: Rng> Iterator for Paths<T> {
                     .collect::<Vec<Point>>();
 
                 for (nx, ny) in free_neighbours {
-                    for mine_subtype in 0..=3 {
+                    // a mine is the last object of the path
+                    if path_length + 1 > *max_delivery_path_length {
+                        break;
+                    }
+
+                    for mine_subtype in MineSubtype::ALL {
                         let mine = Object::mine_with_subtype_and_egress_at(mine_subtype, (nx, ny));
                         let mine_ingress = mine.ingress().unwrap();
 
@@ -206,22 +243,21 @@ impl<T: Rng> Iterator for Paths<T> {
                             == 0;
 
                         if mine_reaches_deposit && map_ref.can_insert_object(&mine).is_ok() {
-                            let new_path = Path::append(mine, &path);
-                            let new_path_id = new_path.id();
-                            if paths_so_far.insert(new_path_id) {
-                                return Some(new_path);
+                            let new_path = arena.append(mine, path);
+                            if paths_so_far.insert(arena.id(new_path)) {
+                                return Some(arena.path(new_path));
                             }
                         }
                     }
 
-                    for conveyor_subtype in (0..=7).rev() {
+                    for conveyor_subtype in ConveyorSubtype::ALL.into_iter().rev() {
                         let conveyor =
                             Object::conveyor_with_subtype_and_egress_at(conveyor_subtype, (nx, ny));
                         let ingress = conveyor.ingress().unwrap();
 
                         if map_ref.can_insert_object(&conveyor).is_ok() {
-                            let new_path = Path::append(conveyor.clone(), &path);
                             if let Some(distance) = min_distance_to_deposits(&[ingress]) {
+                                let new_path = arena.append(conveyor.clone(), path);
                                 let mut new_map_ref = Map::from_map(&map_ref);
                                 new_map_ref.insert_object_unchecked(conveyor);
 
@@ -229,21 +265,21 @@ impl<T: Rng> Iterator for Paths<T> {
                                     start_distance,
                                     distance,
                                     path_length: path_length + 1,
-                                    path: Rc::new(new_path),
+                                    path: new_path,
                                     map_ref: Arc::new(new_map_ref),
                                 })
                             }
                         }
                     }
 
-                    for combiner_subtype in 0..=3 {
+                    for combiner_subtype in CombinerSubtype::ALL {
                         let combiner =
                             Object::combiner_with_subtype_and_egress_at(combiner_subtype, (nx, ny));
                         let ingresses = combiner.ingresses();
 
                         if map_ref.can_insert_object(&combiner).is_ok() {
-                            let new_path = Path::append(combiner.clone(), &path);
                             if let Some(distance) = min_distance_to_deposits(&ingresses) {
+                                let new_path = arena.append(combiner.clone(), path);
                                 let mut new_map_ref = Map::from_map(&map_ref);
                                 new_map_ref.insert_object_unchecked(combiner);
 
@@ -251,7 +287,7 @@ impl<T: Rng> Iterator for Paths<T> {
                                     start_distance,
                                     distance,
                                     path_length: path_length + 1,
-                                    path: Rc::new(new_path),
+                                    path: new_path,
                                     map_ref: Arc::new(new_map_ref),
                                 });
                             }
@@ -264,3 +300,65 @@ impl<T: Rng> Iterator for Paths<T> {
         None
     }
 }
+
+/// A path search, either in one or in both directions (see [BidirectionalPaths])
+pub enum PathSearch<T> {
+    Unidirectional(Paths<T>),
+    Bidirectional(BidirectionalPaths<T>),
+}
+
+impl<T: Rng> PathSearch<T> {
+    /// Creates a new path search from any point in `starts` to the deposits of
+    /// `distances_to_deposits` (see [Paths::with_distances]), for paths that deliver resources
+    /// within `turns` turns
+    pub fn new(
+        start_points: &[Point],
+        distances_to_deposits: Arc<HashMap<Point, u32>>,
+        map: &Map,
+        turns: u32,
+        rng: Rc<RefCell<T>>,
+        bidirectional: bool,
+    ) -> Self {
+        if bidirectional {
+            PathSearch::Bidirectional(BidirectionalPaths::with_distances(
+                start_points,
+                distances_to_deposits,
+                map,
+                turns,
+                rng,
+            ))
+        } else {
+            PathSearch::Unidirectional(Paths::with_distances(
+                start_points,
+                distances_to_deposits,
+                map,
+                turns,
+                rng,
+            ))
+        }
+    }
+}
+
+impl<T: Rng> PathSearch<T> {
+    /// Yields each path of this search together with the deposit of `deposits` it ends at
+    pub fn with_deposits(
+        self,
+        deposits: &[Object],
+    ) -> impl Iterator<Item = (Path, Option<&Object>)> {
+        self.map(|path| {
+            let deposit = path.deposit(deposits);
+            (path, deposit)
+        })
+    }
+}
+
+impl<T: Rng> Iterator for PathSearch<T> {
+    type Item = Path;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            PathSearch::Unidirectional(paths) => paths.next(),
+            PathSearch::Bidirectional(paths) => paths.next(),
+        }
+    }
+}
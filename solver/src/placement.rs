@@ -0,0 +1,345 @@
+//! Placement of factories and acceptance of paths
+//!
+//! These are the building blocks [solve](crate::solve) uses to decide where factories go and
+//! which of the found paths are worth inserting into a map.
+
+use fxhash::FxHashMap as HashMap;
+use model::{
+    analysis::MAX_EXTRACTION_PER_TURN,
+    coord::{neighbours, Point},
+    map::Map,
+    object::{Coord, Object, Subtype},
+};
+use rand::distributions::WeightedIndex;
+use simulator::estimate_delivery_turns;
+
+use crate::{path::Path, paths::max_path_length_for_turns};
+
+/// Finds all locations, at which a 5x5 factory could be legally placed
+///
+/// Positions are the factories' top left cells, ordered by row and column. A position is
+/// returned if and only if [Map::can_insert_object] accepts a factory at it, so the placement
+/// rules (including which cells touch a deposit's egress) are only defined there.
+pub fn find_possible_factory_positions(map: &Map) -> Vec<Point> {
+    let width = map.width() as Coord;
+    let height = map.height() as Coord;
+
+    let mut positions = Vec::new();
+    for y in 0..height - 4 {
+        for x in 0..width - 4 {
+            // also rules out a factory that is already placed at this position, which
+            // `can_insert_object` would accept
+            if map.get_cell(x, y).is_some() {
+                continue;
+            }
+
+            let factory = Object::Factory { x, y, subtype: 0 };
+            if map.can_insert_object(&factory).is_ok() {
+                positions.push((x, y));
+            }
+        }
+    }
+
+    positions
+}
+
+/// Weights all factory `positions` by their walking distances to the nearest deposit of each
+/// required resource type
+///
+/// Each of `distances` is a distance map of a single resource type's deposits (see
+/// `get_distances`). The weight of a position is the inverse of its
+/// [factory_position_penalty], so the returned weights are in the order of `positions`.
+pub fn factory_position_weights(
+    positions: &[Point],
+    distances: &[&HashMap<Point, u32>],
+) -> Vec<f32> {
+    positions
+        .iter()
+        .map(|&position| 1f32 / factory_position_penalty(distances, position).max(1) as f32)
+        .collect()
+}
+
+/// Penalty of a factory at `position`, which is the sum of its distances to the deposits of
+/// `distances` plus their deviation from the mean distance
+///
+/// Positions whose resources are close and about equally far away have the lowest penalty.
+/// Resources that cannot be reached count as [UNREACHABLE_DISTANCE].
+pub fn factory_position_penalty(distances: &[&HashMap<Point, u32>], position: Point) -> u32 {
+    // TODO: weight deposit (resource types) by importance for product
+    let distances = distances
+        .iter()
+        .map(|distances| factory_distance(distances, position).unwrap_or(UNREACHABLE_DISTANCE))
+        .collect::<Vec<u32>>();
+
+    if distances.is_empty() {
+        return UNREACHABLE_DISTANCE;
+    }
+
+    let sum = distances.iter().sum::<u32>();
+    let mean_distance = sum / distances.len() as u32;
+    let deviation = distances
+        .iter()
+        .map(|&distance| distance.abs_diff(mean_distance))
+        .sum::<u32>();
+
+    sum + deviation
+}
+
+/// Finds the indices of all `positions` whose factories would overlap the factory at each position
+///
+/// The result is indexed like `positions`. Each entry is sorted and contains the position itself.
+pub fn find_conflicting_positions(positions: &[Point]) -> Vec<Vec<usize>> {
+    let index_by_position: HashMap<Point, usize> = positions
+        .iter()
+        .enumerate()
+        .map(|(index, position)| (*position, index))
+        .collect();
+
+    positions
+        .iter()
+        .map(|&(x, y)| {
+            let mut conflicts: Vec<usize> = (-4..=4)
+                .flat_map(|dx| (-4..=4).map(move |dy| (x + dx, y + dy)))
+                .filter_map(|position| index_by_position.get(&position).cloned())
+                .collect();
+            conflicts.sort_unstable();
+            conflicts
+        })
+        .collect()
+}
+
+/// Sets the weights of the factory positions at `indices` to zero for all products
+///
+/// If all positions of a product would be excluded, its weights are left unchanged.
+pub fn exclude_positions(weights: &mut HashMap<Subtype, WeightedIndex<f32>>, indices: &[usize]) {
+    let zero_weights: Vec<(usize, &f32)> = indices.iter().map(|&index| (index, &0.0)).collect();
+    for weights in weights.values_mut() {
+        let _ = weights.update_weights(&zero_weights);
+    }
+}
+
+/// Distance (penalty) of a factory position, from which a resource type cannot be reached
+///
+/// Exceeds any walking distance on a 100x100 map.
+pub const UNREACHABLE_DISTANCE: u32 = 10_000;
+
+/// Min walking distance from any cell of a factory at `position` to the origins of `distances`
+///
+/// Returns `None` if no cell of the factory is contained in `distances`.
+pub fn factory_distance(distances: &HashMap<Point, u32>, position: Point) -> Option<u32> {
+    let (x, y) = position;
+    (x..x + 5)
+        .flat_map(|px| (y..y + 5).map(move |py| (px, py)))
+        .filter_map(|point| distances.get(&point))
+        .min()
+        .cloned()
+}
+
+/// Estimates the first turn, in which resources transported along `path` can be used by a factory
+///
+/// Besides the path's own objects, all objects between its start and the factory delay the
+/// delivery. Starts that are not (yet) connected to a factory are not taken into account.
+pub fn estimate_path_delivery_turn(map: &Map, path: &Path, turns: u32) -> u32 {
+    let num_objects_downstream = path
+        .starting_points()
+        .iter()
+        .filter_map(|&point| num_objects_to_factory(map, point, max_path_length_for_turns(turns)))
+        .min()
+        .unwrap_or(0);
+    estimate_delivery_turns(path.len() as u32 + num_objects_downstream)
+}
+
+/// Checks if resources transported along `path` can reach a factory within `turns` turns
+///
+/// Paths are only inserted into a map, if they pass this check.
+pub fn delivers_in_time(map: &Map, path: &Path, turns: u32) -> bool {
+    estimate_path_delivery_turn(map, path, turns) <= turns
+}
+
+/// Estimates the amount of resources, that the mines of `path` deliver within `turns` turns
+///
+/// Additionally returns the amount of resources the mines would deliver without any time limit.
+pub fn estimate_deliveries(map: &Map, path: &Path, turns: u32) -> (u32, u32) {
+    let delivery_turn = estimate_path_delivery_turn(map, path, turns);
+    mined_deposits(map, path)
+        .into_iter()
+        .fold((0, 0), |(deliveries, max_deliveries), deposit| {
+            (
+                deliveries + deliverable_resources(deposit, delivery_turn, turns),
+                max_deliveries + deposit.initial_resources(),
+            )
+        })
+}
+
+/// Amount of resources a single mine can deliver from `deposit`, if they are first used by a
+/// factory in turn `delivery_turn`
+pub fn deliverable_resources(deposit: &Object, delivery_turn: u32, turns: u32) -> u32 {
+    let num_delivering_turns = (turns + 1).saturating_sub(delivery_turn);
+    deposit
+        .initial_resources()
+        .min(MAX_EXTRACTION_PER_TURN * num_delivering_turns)
+}
+
+/// Number of objects, that resources entering the map at the ingress at `point` pass before they
+/// reach a factory (not counting the factory)
+///
+/// Returns `None` if no factory is reached within `max_length` objects.
+fn num_objects_to_factory(map: &Map, (x, y): Point, max_length: u32) -> Option<u32> {
+    let mut object = map.object_at(x, y)?;
+    for num_objects in 0..=max_length {
+        if let Object::Factory { .. } = object {
+            return Some(num_objects);
+        }
+
+        let (x, y) = object.egress()?;
+        object = map
+            .adjacent_cells(x, y)
+            .ingresses()
+            .find_map(|(_, cell)| map.get_object_by_index(cell.index()))?;
+    }
+    None
+}
+
+/// Deposits the mines of `path` are attached to (once per mine)
+pub fn mined_deposits<'m>(map: &'m Map, path: &Path) -> Vec<&'m Object> {
+    let mut mined_deposits = vec![];
+    for mine in path
+        .objects()
+        .filter(|object| matches!(object, Object::Mine { .. }))
+    {
+        let mut deposits: Vec<&Object> = mine
+            .ingresses()
+            .into_iter()
+            .flat_map(|(x, y)| neighbours(x, y))
+            .filter_map(|(x, y)| map.object_at(x, y))
+            .filter(|object| matches!(object, Object::Deposit { .. }))
+            .collect();
+        deposits.dedup();
+        mined_deposits.extend(deposits);
+    }
+    mined_deposits
+}
+
+#[cfg(test)]
+mod tests {
+
+    use model::subtype::MineSubtype;
+
+    use super::*;
+
+    #[test]
+    fn factory_positions_can_be_inserted() {
+        assert_eq!(
+            find_possible_factory_positions(&Map::new(10, 10, vec![])).len(),
+            36
+        );
+
+        let map = Map::new(
+            20,
+            15,
+            vec![
+                Object::Deposit {
+                    x: 0,
+                    y: 0,
+                    width: 3,
+                    height: 3,
+                    subtype: 0,
+                },
+                Object::Mine {
+                    x: 4,
+                    y: 1,
+                    subtype: MineSubtype::East,
+                },
+                Object::Obstacle {
+                    x: 10,
+                    y: 6,
+                    width: 2,
+                    height: 5,
+                },
+            ],
+        );
+        let positions = find_possible_factory_positions(&map);
+        assert!(!positions.is_empty());
+        for x in 0..20 {
+            for y in 0..15 {
+                let factory = Object::Factory { x, y, subtype: 0 };
+                assert_eq!(
+                    positions.contains(&(x, y)),
+                    map.can_insert_object(&factory).is_ok(),
+                    "{:?}",
+                    factory
+                );
+            }
+        }
+
+        // only touches the deposit's corner diagonally
+        assert!(positions.contains(&(3, 3)));
+        assert!(!positions.contains(&(3, 2)));
+    }
+
+    #[test]
+    fn positions_close_to_all_resources_are_preferred() {
+        let near: HashMap<Point, u32> = [((0, 0), 2), ((10, 0), 20)].into_iter().collect();
+        let far: HashMap<Point, u32> = [((0, 0), 20), ((10, 0), 2)].into_iter().collect();
+
+        // both resources 11 cells away on average, but one of them much closer
+        let balanced = factory_position_penalty(&[&near, &near], (0, 0));
+        let unbalanced = factory_position_penalty(&[&near, &far], (0, 0));
+        assert_eq!(balanced, 4);
+        assert_eq!(unbalanced, 22 + 18);
+        assert_eq!(
+            factory_position_penalty(&[&near], (5, 5)),
+            UNREACHABLE_DISTANCE
+        );
+    }
+
+    #[test]
+    fn overlapping_positions_conflict() {
+        let positions = [(0, 0), (4, 4), (5, 5), (0, 5)];
+        assert_eq!(
+            find_conflicting_positions(&positions),
+            vec![vec![0, 1], vec![0, 1, 2, 3], vec![1, 2], vec![1, 3]]
+        );
+    }
+
+    #[test]
+    fn paths_are_accepted_if_they_deliver_in_time() {
+        let deposit = Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 3,
+            height: 3,
+            subtype: 0,
+        };
+        let map = Map::new(
+            20,
+            10,
+            vec![
+                deposit.clone(),
+                Object::Factory {
+                    x: 7,
+                    y: 0,
+                    subtype: 0,
+                },
+            ],
+        );
+        let mine = Object::Mine {
+            x: 4,
+            y: 1,
+            subtype: MineSubtype::East,
+        };
+        let mut path = Path::from_starting_points(vec![(7, 2)]);
+        path.append(mine);
+
+        let delivery_turn = estimate_delivery_turns(1);
+        assert_eq!(estimate_path_delivery_turn(&map, &path, 10), delivery_turn);
+        assert!(!delivers_in_time(&map, &path, delivery_turn - 1));
+        assert!(delivers_in_time(&map, &path, delivery_turn));
+
+        assert_eq!(mined_deposits(&map, &path), vec![&deposit]);
+        assert_eq!(
+            estimate_deliveries(&map, &path, delivery_turn + 1),
+            (2 * MAX_EXTRACTION_PER_TURN, deposit.initial_resources())
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Human readable list of placed objects
+
+use model::{map::Map, object::Object};
+
+/// Creates a table of all placed objects (everything but deposits and obstacles), ordered by
+/// kind and position
+pub(crate) fn placements_table(map: &Map) -> String {
+    let mut objects: Vec<&Object> = map
+        .get_objects()
+        .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
+        .collect();
+    objects.sort_by_key(|obj| (kind_order(obj), obj.coords().1, obj.coords().0));
+
+    let mut table = format!(
+        "{:<10} {:>7} {:>3} {:>3}  {}\n",
+        "kind", "subtype", "x", "y", "meaning"
+    );
+    for object in objects {
+        let (x, y) = object.coords();
+        table.push_str(&format!(
+            "{:<10} {:>7} {:>3} {:>3}  {}\n",
+            kind_name(object),
+            object.subtype().unwrap_or(0),
+            x,
+            y,
+            meaning(object)
+        ));
+    }
+    table
+}
+
+fn kind_order(object: &Object) -> u8 {
+    match object {
+        Object::Factory { .. } => 0,
+        Object::Mine { .. } => 1,
+        Object::Conveyor { .. } => 2,
+        Object::Combiner { .. } => 3,
+        Object::Deposit { .. } => 4,
+        Object::Obstacle { .. } => 5,
+    }
+}
+
+fn kind_name(object: &Object) -> &'static str {
+    match object {
+        Object::Factory { .. } => "factory",
+        Object::Mine { .. } => "mine",
+        Object::Conveyor { .. } => "conveyor",
+        Object::Combiner { .. } => "combiner",
+        Object::Deposit { .. } => "deposit",
+        Object::Obstacle { .. } => "obstacle",
+    }
+}
+
+/// Describes what an object's subtype means, e.g. the direction its egress points to
+fn meaning(object: &Object) -> String {
+    let direction = object.direction().map(|direction| direction.to_string());
+
+    match object {
+        Object::Factory { subtype, .. } => format!("produces product {}", subtype),
+        Object::Mine { .. } | Object::Combiner { .. } => {
+            format!("egress {}", direction.unwrap())
+        }
+        Object::Conveyor { subtype, .. } => format!(
+            "{}, egress {}",
+            if subtype.is_long() { "long" } else { "short" },
+            direction.unwrap()
+        ),
+        Object::Deposit { subtype, .. } => format!("resource {}", subtype),
+        Object::Obstacle { .. } => String::new(),
+    }
+}
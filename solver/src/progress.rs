@@ -0,0 +1,92 @@
+//! Live state of a solver run, that can be observed from another thread
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Progress of a whole solver run (see [RunOptions::progress](crate::run::RunOptions::progress))
+pub struct Progress {
+    time_start: Instant,
+    runtime: Duration,
+    threads: Vec<ThreadProgress>,
+}
+
+/// Progress of a single worker thread
+#[derive(Default)]
+pub struct ThreadProgress {
+    num_solutions: AtomicUsize,
+    /// Best score + 1, so 0 can be used as 'no solution yet'
+    best_score: AtomicU32,
+    finished: AtomicBool,
+}
+
+impl Progress {
+    pub fn new(num_threads: usize, runtime: Duration) -> Self {
+        Progress {
+            time_start: Instant::now(),
+            runtime,
+            threads: (0..num_threads.max(1))
+                .map(|_| ThreadProgress::default())
+                .collect(),
+        }
+    }
+
+    /// Progress of each worker thread
+    pub fn threads(&self) -> &[ThreadProgress] {
+        &self.threads
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.time_start.elapsed()
+    }
+
+    pub fn time_remaining(&self) -> Duration {
+        self.runtime.saturating_sub(self.elapsed())
+    }
+
+    /// Number of solutions found by all threads so far
+    pub fn num_solutions(&self) -> usize {
+        self.threads.iter().map(|t| t.num_solutions()).sum()
+    }
+
+    /// Number of solutions found per second by all threads
+    pub fn solutions_per_second(&self) -> f64 {
+        self.num_solutions() as f64 / self.elapsed().as_secs_f64().max(0.001)
+    }
+
+    /// Best score of all threads so far
+    pub fn best_score(&self) -> Option<u32> {
+        self.threads.iter().filter_map(|t| t.best_score()).max()
+    }
+}
+
+impl ThreadProgress {
+    pub(crate) fn record_solution(&self, score: u32) {
+        self.num_solutions.fetch_add(1, Ordering::Relaxed);
+        self.best_score
+            .fetch_max(score.saturating_add(1), Ordering::Relaxed);
+    }
+
+    pub(crate) fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    /// Number of solutions found by this thread so far
+    pub fn num_solutions(&self) -> usize {
+        self.num_solutions.load(Ordering::Relaxed)
+    }
+
+    /// Best score found by this thread so far
+    pub fn best_score(&self) -> Option<u32> {
+        match self.best_score.load(Ordering::Relaxed) {
+            0 => None,
+            score => Some(score - 1),
+        }
+    }
+
+    /// Checks if this thread stopped searching
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
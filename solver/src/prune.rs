@@ -0,0 +1,29 @@
+//! Post-processing of solutions
+
+use model::{
+    map::Map,
+    object::{Object, ObjectType},
+    task::Task,
+};
+use simulator::simulate_with_throughput;
+
+/// Removes all mines, conveyors and combiners that never transport any resources
+///
+/// Such objects do not contribute to the map's score, so removing them does not change the
+/// simulation result.
+pub fn prune_dead_branches(task: &Task, map: &Map) -> Map {
+    let (_, throughput) = simulate_with_throughput(task, map, true);
+
+    let objects: Vec<Object> = map
+        .get_objects()
+        .filter(|object| {
+            !matches!(
+                object.kind(),
+                ObjectType::Mine | ObjectType::Conveyor | ObjectType::Combiner
+            ) || throughput.contains_key(&object.id())
+        })
+        .cloned()
+        .collect();
+
+    Map::new(map.width(), map.height(), objects)
+}
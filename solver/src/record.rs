@@ -0,0 +1,122 @@
+//! Decision logs, which allow to replay solver runs
+//!
+//! Every worker of a run draws a new seed for its random number generator before each solution
+//! it calculates. A decision log contains the run's configuration, followed by all seeds drawn by
+//! each worker (one `<worker> <seed>` line per seed). Replaying those seeds in order reproduces
+//! all random choices (factory positions, path picks, ...) of the worker, independent of the number
+//! of threads. Seeds are written before the solution is calculated, so a log also covers solutions
+//! that caused a panic.
+//!
+//! Calculations that are bounded by time (like the path search) may still stop at different
+//! points if the replay runs faster or slower than the recorded run.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    sync::Mutex,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::SolverConfig;
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    config: SolverConfig,
+    max_iteration_time_ms: u64,
+}
+
+/// Writes the seeds of a solver run to a decision log
+pub struct Recorder {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Creates an empty decision log at `path`
+    pub fn create(path: &str) -> Result<Recorder, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Cannot create decision log {}: {}", path, e))?;
+
+        Ok(Recorder {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Writes the parameters of the recorded run
+    ///
+    /// Must be called once, before any seed is recorded.
+    pub(crate) fn start(&self, config: &SolverConfig, max_iteration_time: Duration) {
+        let header = Header {
+            config: *config,
+            max_iteration_time_ms: max_iteration_time.as_millis() as u64,
+        };
+
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, &header).expect("Cannot write decision log");
+        writeln!(writer)
+            .and_then(|_| writer.flush())
+            .expect("Cannot write decision log");
+    }
+
+    /// Appends a seed drawn by `worker`
+    pub(crate) fn record(&self, worker: usize, seed: u64) {
+        let mut writer = self.writer.lock().unwrap();
+        writeln!(writer, "{} {}", worker, seed)
+            .and_then(|_| writer.flush())
+            .expect("Cannot write decision log");
+    }
+}
+
+/// A decision log read from a file
+pub struct DecisionLog {
+    pub config: SolverConfig,
+    pub max_iteration_time: Duration,
+    /// All seeds drawn by each worker, in order
+    pub seeds_by_worker: Vec<Vec<u64>>,
+}
+
+impl DecisionLog {
+    /// Reads the decision log at `path`
+    pub fn load(path: &str) -> Result<DecisionLog, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Cannot open decision log {}: {}", path, e))?;
+        let mut lines = BufReader::new(file).lines();
+
+        let header = lines
+            .next()
+            .ok_or_else(|| format!("Decision log {} is empty", path))?
+            .map_err(|e| e.to_string())?;
+        let header: Header = serde_json::from_str(&header)
+            .map_err(|e| format!("Invalid decision log header: {}", e))?;
+
+        let mut seeds_by_worker: Vec<Vec<u64>> = Vec::new();
+        for (line_number, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let entry = line
+                .split_once(' ')
+                .and_then(|(worker, seed)| Some((worker.parse().ok()?, seed.parse().ok()?)));
+            let (worker, seed): (usize, u64) = match entry {
+                Some(entry) => entry,
+                None => {
+                    return Err(format!(
+                        "Invalid decision log entry in line {}: '{}'",
+                        line_number + 2,
+                        line
+                    ))
+                }
+            };
+
+            if seeds_by_worker.len() <= worker {
+                seeds_by_worker.resize(worker + 1, Vec::new());
+            }
+            seeds_by_worker[worker].push(seed);
+        }
+
+        Ok(DecisionLog {
+            config: header.config,
+            max_iteration_time: Duration::from_millis(header.max_iteration_time_ms),
+            seeds_by_worker,
+        })
+    }
+}
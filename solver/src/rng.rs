@@ -0,0 +1,88 @@
+//! Random number generators a solver run can use
+
+use clap::ValueEnum;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+/// Algorithm of the random number generator used by a solver
+///
+/// The sequence of [StdRng] may change between versions of `rand`, so the same seed does not
+/// necessarily reproduce the same solution after an update. The other generators are stable.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum RngType {
+    /// `rand`'s standard generator
+    #[default]
+    Std,
+    /// Xoshiro256++
+    Xoshiro,
+    /// PCG (64 bit MCG variant)
+    Pcg,
+}
+
+/// A random number generator of one of the [RngType]s
+///
+/// Dispatching by an enum instead of a trait object keeps the hot calls (e.g. `gen_range` during
+/// path search) cheap.
+#[allow(clippy::large_enum_variant)] // there is only a single generator per worker
+pub enum SolverRng {
+    Std(StdRng),
+    Xoshiro(Xoshiro256PlusPlus),
+    Pcg(Pcg64Mcg),
+}
+
+impl SolverRng {
+    /// Creates a generator of type `rng_type` from `seed`, or from system entropy if no seed is
+    /// given
+    pub fn new(rng_type: RngType, seed: Option<u64>) -> Self {
+        fn create<R: SeedableRng>(seed: Option<u64>) -> R {
+            match seed {
+                Some(seed) => R::seed_from_u64(seed),
+                None => R::from_entropy(),
+            }
+        }
+
+        match rng_type {
+            RngType::Std => SolverRng::Std(create(seed)),
+            RngType::Xoshiro => SolverRng::Xoshiro(create(seed)),
+            RngType::Pcg => SolverRng::Pcg(create(seed)),
+        }
+    }
+}
+
+impl RngCore for SolverRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            SolverRng::Std(rng) => rng.next_u32(),
+            SolverRng::Xoshiro(rng) => rng.next_u32(),
+            SolverRng::Pcg(rng) => rng.next_u32(),
+        }
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            SolverRng::Std(rng) => rng.next_u64(),
+            SolverRng::Xoshiro(rng) => rng.next_u64(),
+            SolverRng::Pcg(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            SolverRng::Std(rng) => rng.fill_bytes(dest),
+            SolverRng::Xoshiro(rng) => rng.fill_bytes(dest),
+            SolverRng::Pcg(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            SolverRng::Std(rng) => rng.try_fill_bytes(dest),
+            SolverRng::Xoshiro(rng) => rng.try_fill_bytes(dest),
+            SolverRng::Pcg(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
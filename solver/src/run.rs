@@ -1,232 +1,916 @@
 //! Higher level runner function for a [Solver]
 
-use crate::solve::Solver;
-use common::debug;
-use model::{map::Map, task::Task};
-use rand::{rngs::StdRng, SeedableRng};
+use crate::{
+    bandit::SharedStatistics,
+    compress::compress_conveyors,
+    config::SolverConfig,
+    distances::clear_distances_cache,
+    objective::Objective,
+    pareto::ParetoFront,
+    progress::{Progress, ThreadProgress},
+    prune::prune_dead_branches,
+    record::{DecisionLog, Recorder},
+    rng::SolverRng,
+    strategy::{SearchStrategy, Strategy, StrategySolver},
+    telemetry::{take_iteration_metrics, Telemetry},
+};
+use fxhash::FxHashSet as HashSet;
+use model::{map::Map, object::Object, solution::Solution, task::Task};
+use rand::RngCore;
 use simulator::SimulatorResult;
 use std::{
     cell::RefCell,
+    fmt::Display,
+    ops::Range,
     rc::Rc,
-    sync::{mpsc, Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Mutex, RwLock,
+    },
     thread,
     time::{Duration, Instant},
 };
+use tracing::{debug, debug_span};
+
+/// Max time generating a single solution may take, if the runtime is not bounded
+///
+/// Cancellation is checked in between solutions, so this bounds the time until a cancelled run
+/// stops if no solutions are found.
+const MAX_ITERATION_TIME_UNBOUNDED: Duration = Duration::from_secs(1);
+
+/// The iteration of a worker that found a solution
+///
+/// Given the same task, config and `--seed`, [reproduce_iteration] computes the solution again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolutionOrigin {
+    pub thread: usize,
+    /// Index of the iteration within its thread, starting at 0
+    pub iteration: usize,
+    /// Seed the solver's random number generator was seeded with for this iteration
+    pub seed: u64,
+}
+
+impl Display for SolutionOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{} (seed {})", self.thread, self.iteration, self.seed)
+    }
+}
+
+/// A solution found by a worker, together with its origin
+type Found = (SimulatorResult, Map, SolutionOrigin);
 
 #[cfg(not(feature = "stats"))]
 pub struct RunnerResult {
     pub result: SimulatorResult,
     pub map: Map,
+    pub origin: SolutionOrigin,
+    /// All non-dominated solutions found, if [SolverConfig::pareto] is set
+    pub pareto: Vec<(SimulatorResult, Map)>,
 }
 
 #[cfg(feature = "stats")]
 pub struct RunnerResult {
     pub result: SimulatorResult,
     pub map: Map,
+    pub origin: SolutionOrigin,
+    /// All non-dominated solutions found, if [SolverConfig::pareto] is set
+    pub pareto: Vec<(SimulatorResult, Map)>,
     pub solutions_per_second: u128,
 }
 
+/// Receiver of improving solutions of a run (see [RunOptions::on_improvement])
+pub type OnImprovement<'a> = &'a mut dyn FnMut(&SimulatorResult, &Solution);
+
+/// Optional hooks of a run (see [run_solver])
+#[derive(Default)]
+pub struct RunOptions<'a> {
+    /// Stops the run once set (e.g. by a signal handler or another thread)
+    ///
+    /// The flag is only read, so it is still unset if the run ends on its own.
+    pub cancel: Option<&'a AtomicBool>,
+    /// Records the progress of the run, so it can be observed from another thread
+    ///
+    /// It must have been created for the run's number of threads.
+    pub progress: Option<&'a Progress>,
+    /// Records the seeds of all workers, so the run can be replayed (see [replay_solver])
+    pub recorder: Option<&'a Recorder>,
+    /// Records metrics of every iteration
+    pub telemetry: Option<&'a Telemetry>,
+    /// Factory position statistics the workers learn from (see [SharedStatistics])
+    pub statistics: Option<&'a SharedStatistics>,
+    /// Receives every solution that is strictly better than all solutions found before, as soon
+    /// as it is found, so the caller always has a valid solution at hand
+    pub on_improvement: Option<OnImprovement<'a>>,
+}
+
 /// Executes a solver on the given task
+///
+/// The run ends once `runtime` is over or [RunOptions::cancel] is set. Without a runtime, the
+/// caller is responsible for stopping the run by setting [RunOptions::cancel]. The best solution
+/// found until then is returned.
+///
+/// Objects of the resulting map (and of solutions passed to [RunOptions::on_improvement]) that do
+/// not transport any resources are removed. Solutions are compared by the config's [Objective].
+/// Among solutions that are equal under that objective, the one using the fewest objects is
+/// chosen.
 pub fn run_solver(
     task: &Task,
     map: &Map,
+    config: &SolverConfig,
     num_threads: usize,
-    runtime: Duration,
+    runtime: Option<Duration>,
     seed: Option<u64>,
+    options: RunOptions,
 ) -> Option<RunnerResult> {
-    if num_threads == 1 {
-        run_solver_single_threaded(task, map, runtime, seed)
+    let RunOptions {
+        cancel,
+        progress,
+        recorder,
+        telemetry,
+        statistics,
+        mut on_improvement,
+    } = options;
+    debug_assert!(
+        runtime.is_some() || cancel.is_some(),
+        "A run without runtime must be cancellable"
+    );
+    let not_cancelled = AtomicBool::new(false);
+    let run = Run {
+        task,
+        map,
+        config,
+        seed,
+        max_iteration_time: runtime.map_or(MAX_ITERATION_TIME_UNBOUNDED, |runtime| runtime / 2),
+        deadline: runtime.map(|runtime| {
+            Deadline::new(runtime, config.safety_margin_ms.map(Duration::from_millis))
+        }),
+        cancel: cancel.unwrap_or(&not_cancelled),
+        progress,
+        recorder,
+        telemetry,
+        statistics,
+        replay: None,
+        num_solutions: AtomicUsize::new(0),
+        time_start: Instant::now(),
+    };
+    clear_distances_cache();
+
+    if let Some(recorder) = recorder {
+        recorder.start(config, run.max_iteration_time);
+    }
+    if let Some(telemetry) = telemetry {
+        telemetry.start();
+        // metrics of iterations of former runs on this thread were never taken
+        take_iteration_metrics();
+    }
+
+    let mut on_improvement = |result: &SimulatorResult, map: &Map| {
+        if let Some(on_improvement) = on_improvement.as_mut() {
+            on_improvement(result, &Solution::from(map));
+        }
+    };
+    let mut accumulator = Accumulator::new(config, &mut on_improvement);
+    if config.strategy == Strategy::Portfolio {
+        run.portfolio(num_threads, &mut accumulator);
+    } else if num_threads == 1 {
+        run.single(&mut accumulator);
     } else {
-        run_solver_multi_threaded(task, map, num_threads, runtime, seed)
+        run.parallel(0..num_threads, &mut accumulator);
+    }
+
+    if let Some(telemetry) = telemetry {
+        telemetry.flush();
     }
+    accumulator.into_result(&run.num_solutions, run.time_start)
 }
 
-fn run_solver_single_threaded(
-    task: &Task,
-    map: &Map,
-    runtime: Duration,
+/// A run of one or more workers on a task (see [run_solver])
+struct Run<'a> {
+    task: &'a Task,
+    map: &'a Map,
+    config: &'a SolverConfig,
     seed: Option<u64>,
-) -> Option<RunnerResult> {
-    let time_start = Instant::now();
-    let time_for_accumulation =
-        (runtime / 10).clamp(Duration::from_millis(500), Duration::from_millis(1500));
+    /// Max time generating a single solution may take
+    max_iteration_time: Duration,
+    deadline: Option<Deadline>,
+    /// Set by the caller to stop the run (only read)
+    cancel: &'a AtomicBool,
+    progress: Option<&'a Progress>,
+    recorder: Option<&'a Recorder>,
+    telemetry: Option<&'a Telemetry>,
+    statistics: Option<&'a SharedStatistics>,
+    /// Log of a former run, whose seeds are used instead of drawing new ones
+    replay: Option<&'a DecisionLog>,
+    num_solutions: AtomicUsize,
+    time_start: Instant,
+}
 
-    let mut result: Option<(SimulatorResult, Map)> = None;
-    let rng = match seed {
-        Some(seed) => StdRng::seed_from_u64(seed),
-        _ => StdRng::from_entropy(),
-    };
-    // Max time generating a single solution must take
-    let max_iteration_time = runtime / 2;
-    let mut solver = Solver::new(task, map, Rc::new(RefCell::new(rng)), max_iteration_time);
-
-    let mut next_solution_estimate = RollingAverage::new();
-    let mut last_solution = Instant::now();
-    for solution in solver.by_ref() {
-        let now = Instant::now();
-        next_solution_estimate.add(now.duration_since(last_solution));
-        last_solution = now;
-
-        result = match result {
-            None => Some(solution),
-            Some(result) if solution.0 > result.0 => Some(solution),
-            _ => result,
+/// State of workers that run in parallel
+struct Shared {
+    /// Best solution of all workers
+    incumbent: Incumbent,
+    /// Set once the solutions of the workers are no longer received
+    finished: AtomicBool,
+}
+
+impl<'a> Run<'a> {
+    /// Runs worker 0 on the current thread
+    fn single(&self, accumulator: &mut Accumulator) {
+        self.worker(
+            0,
+            self.map,
+            self.deadline.as_ref(),
+            self.progress.map(|progress| &progress.threads()[0]),
+            None,
+            |solution| accumulator.add(solution),
+        );
+    }
+
+    /// Executes the [Strategy::Portfolio] strategy
+    ///
+    /// First, worker 0 constructs greedy solutions on the current thread for
+    /// [SolverConfig::portfolio_greedy_percent] of the runtime (or until it runs out of
+    /// solutions). Afterwards, the search workers 1 to `num_threads` run in parallel,
+    /// warm-starting from the best solution found so far. Since warm starts depend on the timing
+    /// of the workers, replaying a recorded run does not necessarily find the same solutions.
+    fn portfolio(&self, num_threads: usize, accumulator: &mut Accumulator) {
+        if self.config.portfolio_greedy_percent > 0 {
+            let _span = debug_span!("worker", worker = 0).entered();
+            let greedy_deadline = self
+                .deadline
+                .as_ref()
+                .map(|deadline| deadline.share(self.config.portfolio_greedy_percent));
+            self.worker(
+                0,
+                self.map,
+                greedy_deadline.as_ref(),
+                None,
+                None,
+                |solution| accumulator.add(solution),
+            );
+        }
+
+        if !self.cancel.load(Ordering::Acquire) {
+            self.parallel(1..num_threads + 1, accumulator);
+        }
+    }
+
+    /// Runs the workers with the indices of `threads` in parallel and passes their solutions to
+    /// `accumulator`
+    ///
+    /// The workers share the best solution found by any of them (see [Incumbent]), starting with
+    /// the best solution of `accumulator`.
+    fn parallel(&self, threads: Range<usize>, accumulator: &mut Accumulator) {
+        let (sender, receiver) = mpsc::channel::<Found>();
+
+        // hashes of all solutions sent by any worker, so that layouts found by several workers
+        // are only considered once
+        let sent_solutions: Mutex<HashSet<u64>> = Mutex::default();
+
+        let shared = Shared {
+            // starting with the best solution of earlier phases of the run
+            incumbent: Incumbent::new(
+                accumulator
+                    .best
+                    .as_ref()
+                    .map(|(result, map, _)| (result.clone(), map.clone())),
+            ),
+            finished: AtomicBool::new(false),
         };
 
-        if time_start.elapsed() + time_for_accumulation + next_solution_estimate.get() * 5 > runtime
-        {
-            break;
+        thread::scope(|scope| {
+            for i_thread in threads.clone() {
+                debug!("Starting thread #{}", i_thread);
+
+                let sender = sender.clone();
+                let shared = &shared;
+                let sent_solutions = &sent_solutions;
+                let progress = self
+                    .progress
+                    .and_then(|progress| progress.threads().get(i_thread - threads.start));
+                scope.spawn(move || {
+                    let _span = debug_span!("worker", worker = i_thread).entered();
+                    #[cfg(feature = "pin-threads")]
+                    if self.config.pin_threads {
+                        pin_thread(i_thread);
+                    }
+                    // workers share the immutable base map, unless they are pinned: then each
+                    // one works on its own copy, which is allocated close to its core
+                    let local_map;
+                    let map = if self.config.pin_threads {
+                        local_map = self.map.clone();
+                        &local_map
+                    } else {
+                        self.map
+                    };
+                    self.worker(
+                        i_thread,
+                        map,
+                        self.deadline.as_ref(),
+                        progress,
+                        Some(shared),
+                        |solution| {
+                            if !sent_solutions
+                                .lock()
+                                .unwrap()
+                                .insert(solution_hash(&solution.1))
+                            {
+                                return;
+                            }
+                            sender.send(solution).expect(
+                                "Could not send solution from worker thread to main thread",
+                            );
+                        },
+                    );
+                });
+            }
+
+            debug!("Workers started");
+            // drop sender, so receiving results will terminate after all workers are finished
+            drop(sender);
+
+            loop {
+                let solution = match &self.deadline {
+                    Some(deadline) => {
+                        match receiver.recv_timeout(deadline.time_until_accumulation()) {
+                            Ok(solution) => solution,
+                            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                                break
+                            }
+                        }
+                    }
+                    None => match receiver.recv() {
+                        Ok(solution) => solution,
+                        Err(_) => break,
+                    },
+                };
+                accumulator.add(solution);
+            }
+
+            debug!("Stopping workers");
+            shared.finished.store(true, Ordering::Release);
+        });
+
+        debug!("Workers stopped");
+        debug!("Accumulating results");
+
+        while let Ok(solution) = receiver.recv() {
+            accumulator.add(solution);
         }
     }
 
-    #[cfg(feature = "stats")]
-    {
-        let solutions_per_second =
-            1000 * solver.get_num_solutions() as u128 / time_start.elapsed().as_millis();
-        result.map(|(result, map)| RunnerResult {
-            result,
+    /// Runs a single solver on `map` until the run is stopped, `deadline` is (probably) exceeded
+    /// or the worker runs out of seeds
+    ///
+    /// Before every solution, the solver's random number generator is seeded (see [Run::seeds]).
+    /// Passed solutions are tagged with their [SolutionOrigin], `thread` being the index of the
+    /// worker. Every solution that is better (see [SolutionRank]) than all solutions before is
+    /// passed to `on_solution`. If [SolverConfig::pareto] is set, so is every solution that is not
+    /// dominated by a former solution (see [ParetoFront]). Objects of passed solutions that do not
+    /// transport any resources are removed.
+    ///
+    /// If the worker runs in parallel to others, only solutions that are better than their
+    /// [Shared::incumbent] are passed. With [SolverConfig::warm_start] (or [Strategy::Portfolio]),
+    /// the solver warm-starts from every new incumbent before its next solution.
+    fn worker(
+        &self,
+        thread: usize,
+        map: &Map,
+        deadline: Option<&Deadline>,
+        progress: Option<&ThreadProgress>,
+        shared: Option<&Shared>,
+        mut on_solution: impl FnMut(Found),
+    ) {
+        let mut next_seed = self.seeds(thread);
+        let rng = match next_seed() {
+            Some(seed) => Rc::new(RefCell::new(SolverRng::new(self.config.rng, Some(seed)))),
+            None => return,
+        };
+        let mut solver = StrategySolver::new(
+            self.task,
             map,
-            solutions_per_second,
-        })
+            worker_config(self.config, thread),
+            Rc::clone(&rng),
+            self.max_iteration_time,
+        );
+        if let Some(statistics) = self.statistics {
+            solver.share_statistics(statistics);
+        }
+        let mut best_rank: Option<SolutionRank> = None;
+        let mut front = ParetoFront::default();
+        let warm_start = self.config.warm_start || self.config.strategy == Strategy::Portfolio;
+        let incumbent = shared.map(|shared| &shared.incumbent);
+        let mut incumbent_version = 0;
+
+        let mut next_solution_estimate = RollingAverage::new();
+        let mut last_solution = Instant::now();
+        let mut iteration = 0;
+        while !self.is_stopped(shared) {
+            let origin = match next_seed() {
+                Some(seed) => {
+                    *rng.borrow_mut() = SolverRng::new(self.config.rng, Some(seed));
+                    SolutionOrigin {
+                        thread,
+                        iteration,
+                        seed,
+                    }
+                }
+                None => break,
+            };
+            iteration += 1;
+
+            if let Some(incumbent) = incumbent
+                .filter(|_| warm_start)
+                .and_then(|incumbent| incumbent.get_if_newer(&mut incumbent_version))
+            {
+                solver.warm_start(&incumbent);
+            }
+
+            let iteration_start = Instant::now();
+            let solution = solver.next();
+            if let Some(telemetry) = self.telemetry {
+                telemetry.record(
+                    thread,
+                    origin.iteration,
+                    iteration_start.elapsed(),
+                    take_iteration_metrics(),
+                    solution.as_ref().map(|(result, _)| result.score),
+                );
+            }
+
+            let solution = match solution {
+                Some(solution) => solution,
+                None if solver.is_exhausted() => break,
+                // without a deadline, there is no reason to give up
+                None if deadline.is_none() => continue,
+                None => break,
+            };
+
+            let now = Instant::now();
+            next_solution_estimate.add(now.duration_since(last_solution));
+            last_solution = now;
+
+            if self.is_stopped(shared) {
+                break;
+            }
+
+            if let Some(progress) = progress {
+                progress.record_solution(solution.0.score);
+            }
+
+            // solutions with a lower result cannot be better, regardless of their number of objects
+            let is_candidate = match best_rank {
+                Some(ref best_rank) => self
+                    .config
+                    .objective
+                    .may_improve(&solution.0, &best_rank.result),
+                None => true,
+            } && incumbent
+                .is_none_or(|incumbent| incumbent.may_improve(&solution.0, self.config.objective))
+                || self.config.pareto;
+
+            if is_candidate {
+                let (result, map) = post_process(self.task, self.config, solution.0, solution.1);
+                let rank = SolutionRank::new(&result, &map, self.config.objective);
+                let is_improvement = best_rank.is_none() || Some(&rank) > best_rank.as_ref();
+                let extends_front = self.config.pareto && front.insert(result.clone(), map.clone());
+                // solutions that are not better than the ones of other workers are not reported
+                let is_new_incumbent = is_improvement
+                    && incumbent.is_none_or(|incumbent| {
+                        incumbent.update(&result, &map, self.config.objective)
+                    });
+                if is_improvement {
+                    best_rank = Some(rank);
+                }
+                if is_new_incumbent || extends_front {
+                    debug!("Found score {} in iteration {}", result.score, origin);
+                    on_solution((result, map, origin));
+                }
+            }
+
+            if let Some(deadline) = deadline {
+                if deadline.is_exceeded(next_solution_estimate.get()) {
+                    break;
+                }
+            }
+        }
+
+        if let Some(progress) = progress {
+            progress.finish();
+        }
+
+        #[cfg(feature = "stats")]
+        {
+            self.num_solutions
+                .fetch_add(solver.get_num_solutions(), Ordering::AcqRel);
+        }
+    }
+
+    /// Creates the source of seeds for the worker with index `thread`
+    ///
+    /// Seeds are drawn from a generator that is seeded by the run's seed (offset by `thread`) and
+    /// recorded by [Run::recorder] (if given). A replayed run uses the recorded seeds instead.
+    fn seeds(&self, thread: usize) -> Box<dyn FnMut() -> Option<u64> + '_> {
+        match self.replay {
+            Some(log) => {
+                let mut seeds = log.seeds_by_worker[thread].iter().cloned();
+                Box::new(move || seeds.next())
+            }
+            None => Box::new(seeds(
+                self.config,
+                self.seed.map(|seed| seed.wrapping_add(thread as u64)),
+                self.recorder.map(|recorder| (recorder, thread)),
+            )),
+        }
     }
-    #[cfg(not(feature = "stats"))]
-    {
-        result.map(|(result, map)| RunnerResult { result, map })
+
+    /// Checks if the caller cancelled the run or, if `shared` is given, the parallel workers are
+    /// finished
+    fn is_stopped(&self, shared: Option<&Shared>) -> bool {
+        self.cancel.load(Ordering::Acquire)
+            || shared.is_some_and(|shared| shared.finished.load(Ordering::Acquire))
+    }
+}
+
+/// Pins the current thread to the core of the worker with index `thread`
+///
+/// Workers are assigned to the available cores round robin.
+#[cfg(feature = "pin-threads")]
+fn pin_thread(thread: usize) {
+    match core_affinity::get_core_ids() {
+        Some(core_ids) if !core_ids.is_empty() => {
+            let core_id = core_ids[thread % core_ids.len()];
+            if !core_affinity::set_for_current(core_id) {
+                tracing::warn!("Could not pin worker {} to core {}", thread, core_id.id);
+            }
+        }
+        _ => tracing::warn!("Could not determine the cores to pin worker {} to", thread),
     }
 }
 
-fn run_solver_multi_threaded(
+/// Replays the decision log of a former run
+///
+/// The workers of the recorded run are replayed one after another on the current thread, until
+/// all of their seeds are used up. The best solution of all workers is returned. If workers found
+/// equally ranked solutions, the recorded run may have returned another one of them.
+pub fn replay_solver(task: &Task, map: &Map, log: &DecisionLog) -> Option<RunnerResult> {
+    let not_cancelled = AtomicBool::new(false);
+    let run = Run {
+        task,
+        map,
+        config: &log.config,
+        seed: None,
+        max_iteration_time: log.max_iteration_time,
+        deadline: None,
+        cancel: &not_cancelled,
+        progress: None,
+        recorder: None,
+        telemetry: None,
+        statistics: None,
+        replay: Some(log),
+        num_solutions: AtomicUsize::new(0),
+        time_start: Instant::now(),
+    };
+    clear_distances_cache();
+
+    let mut on_improvement = |_: &SimulatorResult, _: &Map| {};
+    let mut accumulator = Accumulator::new(&log.config, &mut on_improvement);
+    for worker in 0..log.seeds_by_worker.len() {
+        let _span = debug_span!("worker", worker).entered();
+        debug!("Replaying worker");
+        run.worker(worker, map, None, None, None, |solution| {
+            accumulator.add(solution)
+        });
+    }
+
+    accumulator.into_result(&run.num_solutions, run.time_start)
+}
+
+/// Computes the solution of a single iteration of a former run again
+///
+/// `seed` and `config` must be the ones of the former run, and `runtime` the runtime of the run
+/// for the iteration's time bound to be the same. All iterations of `thread` up to `iteration`
+/// are repeated on the current thread, since earlier iterations may influence later ones. Returns
+/// None if the iteration did not find a solution.
+pub fn reproduce_iteration(
     task: &Task,
     map: &Map,
-    num_threads: usize,
+    config: &SolverConfig,
+    seed: u64,
     runtime: Duration,
-    seed: Option<u64>,
+    thread: usize,
+    iteration: usize,
 ) -> Option<RunnerResult> {
     let time_start = Instant::now();
-    // Extra time for accumulating gathered solutions
-    //
-    // Estimates have shown that accumulating, and, especially, building and printing the final
-    // result take about 300ms, independent of the problem and solution size.
-    let time_for_accumulation =
-        (runtime / 10).clamp(Duration::from_millis(500), Duration::from_millis(1500));
-
-    #[cfg(feature = "stats")]
-    let num_solutions = Arc::new(std::sync::atomic::AtomicUsize::new(0));
-
-    // Max time generating a single solution must take
-    let max_iteration_time = runtime / 2;
-    let (sender, receiver) = mpsc::channel();
-    let stop_condition = Arc::new(RwLock::new(false));
-
-    thread::scope(|scope| {
-        let task = &task;
-
-        for i_thread in 0..num_threads {
-            let map = map.clone();
-            debug!("Starting thread #{}", i_thread);
-
-            #[cfg(feature = "stats")]
-            let num_solutions = Arc::clone(&num_solutions);
-
-            let sender = sender.clone();
-            let stop_condition = Arc::clone(&stop_condition);
-            scope.spawn(move || {
-                let rng = match seed {
-                    Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(i_thread as u64)),
-                    _ => StdRng::from_entropy(),
-                };
-                let mut solver =
-                    Solver::new(task, &map, Rc::new(RefCell::new(rng)), max_iteration_time);
-                let mut best_solution: Option<(SimulatorResult, Map)> = None;
-
-                let mut next_solution_estimate = RollingAverage::new();
-                let mut last_solution = Instant::now();
-                for solution in solver.by_ref() {
-                    let now = Instant::now();
-                    next_solution_estimate.add(now.duration_since(last_solution));
-                    last_solution = now;
-
-                    if *(*stop_condition).read().unwrap() {
-                        #[cfg(feature = "stats")]
-                        {
-                            num_solutions.fetch_add(
-                                solver.get_num_solutions(),
-                                std::sync::atomic::Ordering::AcqRel,
-                            );
-                        }
+    clear_distances_cache();
 
-                        break;
-                    }
+    let mut next_seed = seeds(config, Some(seed.wrapping_add(thread as u64)), None);
+    let rng = Rc::new(RefCell::new(SolverRng::new(config.rng, next_seed())));
+    let mut solver = StrategySolver::new(
+        task,
+        map,
+        worker_config(config, thread),
+        Rc::clone(&rng),
+        runtime / 2,
+    );
 
-                    best_solution = match best_solution {
-                        None => {
-                            sender.send(solution.clone()).expect(
-                                "Could not send solution from worker thread to main thread",
-                            );
-                            Some(solution)
-                        }
-                        Some((result, _)) if solution.0 > result => {
-                            sender.send(solution.clone()).expect(
-                                "Could not send solution from worker thread to main thread",
-                            );
-                            Some(solution)
-                        }
-                        _ => best_solution,
-                    };
+    let mut solution = None;
+    for i in 0..=iteration {
+        let seed = next_seed().unwrap();
+        debug!("Repeating iteration {}:{} (seed {})", thread, i, seed);
+        *rng.borrow_mut() = SolverRng::new(config.rng, Some(seed));
+        solution = solver.next().map(|(result, map)| {
+            let origin = SolutionOrigin {
+                thread,
+                iteration: i,
+                seed,
+            };
+            let (result, map) = post_process(task, config, result, map);
+            (result, map, origin)
+        });
+    }
 
-                    if time_start.elapsed()
-                        + time_for_accumulation
-                        + next_solution_estimate.get() * 5
-                        > runtime
-                    {
-                        #[cfg(feature = "stats")]
-                        {
-                            num_solutions.fetch_add(
-                                solver.get_num_solutions(),
-                                std::sync::atomic::Ordering::AcqRel,
-                            );
-                        }
+    let mut on_improvement = |_: &SimulatorResult, _: &Map| {};
+    let mut accumulator = Accumulator::new(config, &mut on_improvement);
+    accumulator.add(solution?);
+    accumulator.into_result(&AtomicUsize::new(iteration + 1), time_start)
+}
 
-                        break;
-                    }
-                }
-            });
+/// Creates the source of seeds for a single worker
+///
+/// Seeds are drawn from a generator that is seeded by `seed`. Each drawn seed is recorded by
+/// `recorder` (if given) as seed of the given worker.
+fn seeds<'r>(
+    config: &SolverConfig,
+    seed: Option<u64>,
+    recorder: Option<(&'r Recorder, usize)>,
+) -> impl FnMut() -> Option<u64> + 'r {
+    let mut rng = SolverRng::new(config.rng, seed);
+    move || {
+        let seed = rng.next_u64();
+        if let Some((recorder, worker)) = recorder {
+            recorder.record(worker, seed);
         }
+        Some(seed)
+    }
+}
 
-        debug!("Workers started");
-        thread::sleep(runtime - time_start.elapsed() - time_for_accumulation);
-        debug!("Stopping workers");
-        *(*stop_condition).write().unwrap() = true;
-        debug!("Workers stopped");
-        // drop sender, so receiving results will terminate after last result was read from pipe
-        drop(sender);
-    });
-
-    debug!("Accumulating results");
-
-    let mut result: Option<(SimulatorResult, Map)> = None;
-    while let Ok(solution) = receiver.recv() {
-        result = match result {
-            None => Some(solution),
-            Some(result) if solution.0 > result.0 => Some(solution),
-            _ => result,
+/// The config of the worker with index `thread` (see [Strategy::for_worker])
+fn worker_config(config: &SolverConfig, thread: usize) -> SolverConfig {
+    SolverConfig {
+        strategy: config.strategy.for_worker(thread),
+        ..*config
+    }
+}
+
+/// Removes objects that do not transport any resources from a solution and compresses its
+/// conveyors (if enabled by `config`)
+fn post_process(
+    task: &Task,
+    config: &SolverConfig,
+    result: SimulatorResult,
+    map: Map,
+) -> (SimulatorResult, Map) {
+    // paths are inserted unchecked, so a bug in path search could break placement rules
+    debug_assert_eq!(map.audit(), vec![], "Solution breaks placement rules");
+    let map = prune_dead_branches(task, &map);
+    debug_assert_eq!(result, simulator::simulate(task, &map, true));
+    if config.compress_conveyors {
+        compress_conveyors(task, &map, result)
+    } else {
+        (result, map)
+    }
+}
+
+/// Hash of the normalized [Solution] of `map`
+fn solution_hash(map: &Map) -> u64 {
+    let mut solution = Solution::from(map);
+    solution.normalize();
+    fxhash::hash64(&solution)
+}
+
+/// Best solution (and Pareto front, if [SolverConfig::pareto] is set) of all solutions reported
+/// by workers
+struct Accumulator<'a> {
+    objective: Objective,
+    best: Option<Found>,
+    front: Option<ParetoFront>,
+    on_improvement: &'a mut dyn FnMut(&SimulatorResult, &Map),
+}
+
+impl<'a> Accumulator<'a> {
+    fn new(
+        config: &SolverConfig,
+        on_improvement: &'a mut dyn FnMut(&SimulatorResult, &Map),
+    ) -> Accumulator<'a> {
+        Accumulator {
+            objective: config.objective,
+            best: None,
+            front: config.pareto.then(ParetoFront::default),
+            on_improvement,
+        }
+    }
+
+    fn add(&mut self, solution: Found) {
+        if let Some(front) = &mut self.front {
+            front.insert(solution.0.clone(), solution.1.clone());
+        }
+
+        self.best = match self.best.take() {
+            Some(best)
+                if SolutionRank::new(&solution.0, &solution.1, self.objective)
+                    <= SolutionRank::new(&best.0, &best.1, self.objective) =>
+            {
+                Some(best)
+            }
+            _ => {
+                (self.on_improvement)(&solution.0, &solution.1);
+                Some(solution)
+            }
         };
     }
 
-    #[cfg(feature = "stats")]
-    {
-        let solutions_per_second = (1000
-            * num_solutions.load(std::sync::atomic::Ordering::Acquire) as u128)
-            / time_start.elapsed().as_millis();
-        result.map(|(result, map)| RunnerResult {
-            result,
-            map,
-            solutions_per_second,
-        })
+    #[allow(unused_variables)]
+    fn into_result(self, num_solutions: &AtomicUsize, time_start: Instant) -> Option<RunnerResult> {
+        let (result, map, origin) = self.best?;
+        let pareto = self
+            .front
+            .map(ParetoFront::into_solutions)
+            .unwrap_or_default();
+
+        #[cfg(feature = "stats")]
+        {
+            let solutions_per_second = (1000 * num_solutions.load(Ordering::Acquire) as u128)
+                / time_start.elapsed().as_millis().max(1);
+            Some(RunnerResult {
+                result,
+                map,
+                origin,
+                pareto,
+                solutions_per_second,
+            })
+        }
+        #[cfg(not(feature = "stats"))]
+        {
+            Some(RunnerResult {
+                result,
+                map,
+                origin,
+                pareto,
+            })
+        }
     }
-    #[cfg(not(feature = "stats"))]
-    {
-        result.map(|(result, map)| RunnerResult { result, map })
+}
+
+/// Best solution of all workers of a run, shared between their threads
+struct Incumbent {
+    solution: RwLock<Option<(SimulatorResult, Map)>>,
+    /// Number of updates of `solution`, so workers can check for a new incumbent without locking
+    version: AtomicUsize,
+}
+
+impl Incumbent {
+    fn new(solution: Option<(SimulatorResult, Map)>) -> Incumbent {
+        Incumbent {
+            version: AtomicUsize::new(solution.is_some() as usize),
+            solution: RwLock::new(solution),
+        }
+    }
+
+    /// Checks if a solution with the given result may be better than the incumbent (see
+    /// [Objective::may_improve])
+    fn may_improve(&self, result: &SimulatorResult, objective: Objective) -> bool {
+        match &*self.solution.read().unwrap() {
+            Some((best, _)) => objective.may_improve(result, best),
+            None => true,
+        }
+    }
+
+    /// Replaces the incumbent by the given solution, if it is better (see [SolutionRank])
+    ///
+    /// Returns whether the incumbent was replaced.
+    fn update(&self, result: &SimulatorResult, map: &Map, objective: Objective) -> bool {
+        let mut solution = self.solution.write().unwrap();
+        if let Some((best_result, best_map)) = &*solution {
+            if SolutionRank::new(result, map, objective)
+                <= SolutionRank::new(best_result, best_map, objective)
+            {
+                return false;
+            }
+        }
+        *solution = Some((result.clone(), map.clone()));
+        self.version.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+
+    /// The map of the incumbent, if it was updated since `version`
+    ///
+    /// `version` is set to the current version.
+    fn get_if_newer(&self, version: &mut usize) -> Option<Map> {
+        let current = self.version.load(Ordering::Acquire);
+        if current == *version {
+            return None;
+        }
+        *version = current;
+        self.solution
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|(_, map)| map.clone())
+    }
+}
+
+/// Order of solutions
+///
+/// Solutions are ordered by their [SimulatorResult] and number of placed objects, as defined by
+/// an [Objective].
+struct SolutionRank {
+    result: SimulatorResult,
+    num_objects: usize,
+    objective: Objective,
+}
+
+impl SolutionRank {
+    fn new(result: &SimulatorResult, map: &Map, objective: Objective) -> SolutionRank {
+        let num_objects = map
+            .get_objects()
+            .filter(|obj| !matches!(obj, Object::Deposit { .. } | Object::Obstacle { .. }))
+            .count();
+
+        SolutionRank {
+            result: result.clone(),
+            num_objects,
+            objective,
+        }
+    }
+}
+
+impl PartialEq for SolutionRank {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for SolutionRank {}
+
+impl PartialOrd for SolutionRank {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SolutionRank {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.objective.compare(
+            (&self.result, self.num_objects),
+            (&other.result, other.num_objects),
+        )
+    }
+}
+
+/// Time bounds of a solver run
+struct Deadline {
+    time_start: Instant,
+    runtime: Duration,
+    /// Extra time for accumulating gathered solutions
+    ///
+    /// Estimates have shown that accumulating, and, especially, building and printing the final
+    /// result take about 300ms, independent of the problem and solution size.
+    time_for_accumulation: Duration,
+}
+
+impl Deadline {
+    fn new(runtime: Duration, time_for_accumulation: Option<Duration>) -> Deadline {
+        Deadline {
+            time_start: Instant::now(),
+            runtime,
+            time_for_accumulation: time_for_accumulation.unwrap_or_else(|| {
+                (runtime / 10).clamp(Duration::from_millis(500), Duration::from_millis(1500))
+            }),
+        }
+    }
+
+    /// Time left until gathered solutions must be accumulated
+    fn time_until_accumulation(&self) -> Duration {
+        self.runtime
+            .saturating_sub(self.time_start.elapsed())
+            .saturating_sub(self.time_for_accumulation)
+    }
+
+    /// Deadline after `percent` of the runtime, that leaves no extra time for accumulation
+    fn share(&self, percent: u32) -> Deadline {
+        Deadline {
+            time_start: self.time_start,
+            runtime: self.runtime * percent.min(100) / 100,
+            time_for_accumulation: Duration::ZERO,
+        }
+    }
+
+    /// Checks if the next solution (probably) cannot be calculated in time anymore
+    fn is_exceeded(&self, next_solution_estimate: Duration) -> bool {
+        self.time_start.elapsed() + self.time_for_accumulation + next_solution_estimate * 5
+            > self.runtime
     }
 }
 
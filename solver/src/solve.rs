@@ -2,52 +2,83 @@
 
 use std::{
     cell::RefCell,
+    cmp::Reverse,
     collections::VecDeque,
     ops::DerefMut,
     rc::Rc,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use fxhash::FxHashMap as HashMap;
 
-use common::debug;
 use model::{
+    analysis::{
+        consumable_resources, max_producible, max_producible_by_product, resource_capacities,
+        NUM_RESOURCE_TYPES,
+    },
     coord::Point,
     map::Map,
-    object::{Coord, Object, ObjectCell, ObjectID, Subtype},
-    task::{Product, Task},
+    object::{Object, ObjectID, Subtype},
+    subtype::CombinerSubtype,
+    task::{Product, ProductCatalog, Task},
+};
+use tracing::{debug, debug_span, trace, warn};
+
+use crate::{
+    bandit::{PositionBandit, SharedStatistics},
+    budget::MapProfile,
+    config::SolverConfig,
+    distances::{get_distances, DistancesByType},
+    path::Path,
+    paths::PathSearch,
+    placement::{
+        deliverable_resources, delivers_in_time, estimate_deliveries, estimate_path_delivery_turn,
+        exclude_positions, factory_position_weights, find_conflicting_positions,
+        find_possible_factory_positions, mined_deposits,
+    },
+    rng::SolverRng,
+    strategy::{SearchStrategy, Strategy},
+    tabu::TabuList,
+    telemetry::{record_path_search_failure, simulate_timed},
 };
-
-use crate::{distances::get_distances, path::Path, paths::Paths};
 use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, Rng};
-use simulator::{simulate, SimulatorResult};
-
-/// Number of times a factory location is tried.
-/// If no location can be found a whole new iteration starts
-const NUM_MAX_FACTORY_PLACEMENTS: u32 = 20;
-
-/// Chance that a single factory will be skipped during placement
-const PROBABILITY_FACTORY_SKIP: (u32, u32) = (1, 10);
-
-/// Number of paths to try (calculate) per factory and resource type
-const NUM_PATHS_PER_FACTORY_AND_RESOURCE: u32 = 5;
-
-/// Number of additional paths to try (calculate) per factory and resource type
-const NUM_ADDITION_PATHS_PER_FACTORY_AND_RESOURCE: u32 = 5;
+use rayon::prelude::*;
+use simulator::{estimate_delivery_turns, SimulatorResult};
 
-/// Number of path combinations to try during one iteration
-const NUM_PATH_COMBINING_ITERATIONS: u32 = 2;
+/// Chance that an iteration starts from the factories of the incumbent, if there is one
+const WARM_START_PROBABILITY: (u32, u32) = (1, 2);
 
 /// An iterative best-search solver
 #[derive(Clone)]
 pub struct Solver<'a, T> {
-    task: &'a Task,
-    original_map: &'a Map,
-    deposits_by_type: HashMap<Subtype, Vec<Object>>,
-    products: Vec<Product>,
-    best_factory_positions_by_factory_subtype: HashMap<Subtype, (WeightedIndex<f32>, Vec<Point>)>,
-    rng: Rc<RefCell<T>>,
-    max_iteration_time: Duration,
+    pub(crate) task: &'a Task,
+    pub(crate) original_map: &'a Map,
+    pub(crate) deposits_by_type: HashMap<Subtype, Vec<Object>>,
+    /// Distances to the deposits of each resource type on the original map
+    pub(crate) distances_by_type: DistancesByType,
+    /// Products that can be produced at all
+    pub(crate) products: Vec<Product>,
+    pub(crate) best_factory_positions_by_factory_subtype:
+        HashMap<Subtype, (WeightedIndex<f32>, Vec<Point>)>,
+    /// Indices of all factory positions that overlap the factory position of the same index
+    ///
+    /// All products share the same list of factory positions, so the indices are valid for each
+    /// of them.
+    factory_position_conflicts: Vec<Vec<usize>>,
+    /// Max number of factories that may be placed per product in addition to the first one
+    num_additional_factories_by_product: HashMap<Subtype, u32>,
+    /// Weights of the factory positions of each product, learned from the scores of former
+    /// iterations
+    position_bandit: PositionBandit,
+    /// Factory placements that recently led to solutions without any points
+    tabu_list: TabuList,
+    /// Factories of the best solution of the run, that iterations may start from (see
+    /// [SearchStrategy::warm_start])
+    incumbent_factories: Vec<Object>,
+    pub(crate) config: SolverConfig,
+    pub(crate) rng: Rc<RefCell<T>>,
+    pub(crate) max_iteration_time: Duration,
     #[allow(unused)] //only used if feature 'stats' is active
     num_solutions: usize,
 }
@@ -65,86 +96,133 @@ impl<'a, T: Rng> Solver<'a, T> {
     pub fn new(
         task: &'a Task,
         map: &'a Map,
+        config: SolverConfig,
         rng: Rc<RefCell<T>>,
         max_iteration_time: Duration,
     ) -> Solver<'a, T> {
+        let config = if config.adaptive_budget {
+            let profile = MapProfile::new(task, map);
+            debug!(
+                "Scaling the budget by {:.2} for {:?}",
+                profile.budget_factor(),
+                profile
+            );
+            profile.scale(config)
+        } else {
+            config
+        };
+
         let deposits_by_type: HashMap<u8, Vec<Object>> = {
             let mut deposits: HashMap<u8, Vec<Object>> = HashMap::default();
-            task.objects
-                .iter()
-                .cloned()
-                .map(Object::from)
-                .for_each(|obj| {
-                    if let Object::Deposit { subtype, .. } = obj {
-                        deposits.entry(subtype).or_default().push(obj)
-                    }
-                });
+            task.objects.iter().cloned().for_each(|obj| {
+                if let Object::Deposit { subtype, .. } = obj {
+                    deposits.entry(subtype).or_default().push(obj)
+                }
+            });
 
             deposits
         };
 
-        let deposits_by_product: HashMap<u8, Vec<Object>> = {
-            let mut deposits: HashMap<u8, Vec<Object>> = HashMap::default();
-            task.products.iter().for_each(|product| {
-                product
+        // distances to the nearest deposit of each resource type
+        let mut distances_by_type = DistancesByType::new(map, &deposits_by_type);
+
+        let possible_factory_locations = find_possible_factory_positions(map);
+        let factory_position_conflicts = find_conflicting_positions(&possible_factory_locations);
+
+        let factory_position_priors: HashMap<Subtype, Vec<f32>> = task
+            .products
+            .iter()
+            .map(|product| {
+                let distances = product
                     .resources
                     .iter()
                     .enumerate()
                     .filter(|&(_, &amount)| amount > 0)
-                    .flat_map(|(resource_index, _)| {
-                        deposits_by_type[&(resource_index as u8)].iter()
+                    .filter_map(|(resource_index, _)| {
+                        distances_by_type.get(map, resource_index as Subtype)
                     })
-                    .cloned()
-                    .for_each(|deposit_object| {
-                        deposits
-                            .entry(product.subtype)
-                            .or_default()
-                            .push(deposit_object);
-                    });
-            });
-
-            deposits
-        };
-
-        let possible_factory_locations = find_possible_factory_positions(map);
-
-        let distance_type =
-            /* Value estimated by experimentation
-             * The 'xxl_001' (100x100 map) task needed at least 5 seconds to produce results using
-             * 'ShortestPath'. In such cases, 'Manhattan' should be prefered.
-             */
-            if max_iteration_time.as_millis() / (map.width() as u128 * map.height() as u128) > 2 {
-                DistanceType::ShortestPath
-            } else {
-                DistanceType::Manhattan
-            };
+                    .collect::<Vec<Arc<HashMap<Point, u32>>>>();
+                let distances: Vec<&HashMap<Point, u32>> =
+                    distances.iter().map(Arc::as_ref).collect();
+                let weights = factory_position_weights(&possible_factory_locations, &distances);
+                (product.subtype, weights)
+            })
+            .collect();
         let best_factory_positions_by_factory_subtype: HashMap<
             Subtype,
             (WeightedIndex<f32>, Vec<Point>),
-        > = task
+        > = factory_position_priors
+            .iter()
+            .filter_map(|(&subtype, weights)| {
+                // fails, if the factory fits nowhere
+                let weights = WeightedIndex::new(weights).ok()?;
+                Some((subtype, (weights, possible_factory_locations.clone())))
+            })
+            .collect();
+        let position_bandit = PositionBandit::new(
+            &possible_factory_locations,
+            factory_position_priors,
+            config.factory_position_learning_percent,
+        );
+
+        let num_additional_factories_by_product = task
             .products
             .iter()
             .map(|product| {
-                let factory_type = product.subtype;
-                let deposits = &deposits_by_product[&factory_type];
-                let (probabilities, best_positions) = sort_to_best_positions_by_deposits(
-                    map,
-                    &possible_factory_locations,
-                    deposits,
-                    distance_type,
-                );
-                (factory_type, (probabilities, best_positions))
+                (
+                    product.subtype,
+                    estimate_num_additional_factories(product, &deposits_by_type, &config),
+                )
             })
             .collect();
 
-        let products: Vec<Product> = task.products.to_vec();
+        // there is no use in placing factories for products that cannot be produced at all
+        let max_producible_by_product = max_producible_by_product(task);
+        let products: Vec<Product> = task
+            .products
+            .iter()
+            .filter(|product| {
+                let missing_resources: Vec<usize> = product
+                    .resources
+                    .iter()
+                    .enumerate()
+                    .filter(|&(resource_index, &amount)| {
+                        amount > 0 && !deposits_by_type.contains_key(&(resource_index as Subtype))
+                    })
+                    .map(|(resource_index, _)| resource_index)
+                    .collect();
+                if !missing_resources.is_empty() {
+                    warn!(
+                        "Product {} is not planned, since there are no deposits of its resource(s) {:?}",
+                        product.subtype, missing_resources
+                    );
+                    return false;
+                }
+                if !best_factory_positions_by_factory_subtype.contains_key(&product.subtype) {
+                    warn!(
+                        "Product {} is not planned, since its factory fits nowhere",
+                        product.subtype
+                    );
+                    return false;
+                }
+                max_producible_by_product[&product.subtype] > 0
+            })
+            .cloned()
+            .collect();
 
         Solver {
             task,
             original_map: map,
             deposits_by_type,
+            distances_by_type,
             products,
             best_factory_positions_by_factory_subtype,
+            factory_position_conflicts,
+            num_additional_factories_by_product,
+            position_bandit,
+            tabu_list: TabuList::new(config.tabu_tenure),
+            incumbent_factories: Vec::new(),
+            config,
             rng,
             max_iteration_time,
             num_solutions: 0,
@@ -152,6 +230,20 @@ impl<'a, T: Rng> Solver<'a, T> {
     }
 }
 
+impl<'a, T: Rng> SearchStrategy for Solver<'a, T> {
+    fn warm_start(&mut self, incumbent: &Map) {
+        self.incumbent_factories = incumbent
+            .get_objects()
+            .filter(|object| matches!(object, Object::Factory { .. }))
+            .cloned()
+            .collect();
+    }
+
+    fn share_statistics(&mut self, statistics: &SharedStatistics) {
+        self.position_bandit.share(self.task, statistics);
+    }
+}
+
 impl<'a, T: Rng> Iterator for Solver<'a, T> {
     type Item = (SimulatorResult, Map);
 
@@ -160,8 +252,15 @@ impl<'a, T: Rng> Iterator for Solver<'a, T> {
             task,
             original_map,
             deposits_by_type,
+            distances_by_type,
             products,
             best_factory_positions_by_factory_subtype,
+            factory_position_conflicts,
+            num_additional_factories_by_product,
+            position_bandit,
+            tabu_list,
+            incumbent_factories,
+            config,
             ref rng,
             max_iteration_time,
             ..
@@ -169,7 +268,7 @@ impl<'a, T: Rng> Iterator for Solver<'a, T> {
 
         let time_start = Instant::now();
 
-        debug!("{}", original_map);
+        trace!("{}", original_map);
 
         /*************************************************/
         /* START ITERATING                               */
@@ -177,13 +276,14 @@ impl<'a, T: Rng> Iterator for Solver<'a, T> {
 
         let mut best_solution: Option<(SimulatorResult, Map)> = None;
 
-        #[allow(unused_variables)]
         'iterate: for n_iteration in 1.. {
             if time_start.elapsed() > *max_iteration_time {
                 return None;
             }
 
-            debug!("Starting iteration #{}", n_iteration);
+            let _span = debug_span!("iteration", n = n_iteration).entered();
+            debug!("Starting iteration");
+            tabu_list.tick();
 
             let mut map = original_map.clone();
 
@@ -191,303 +291,157 @@ impl<'a, T: Rng> Iterator for Solver<'a, T> {
             /* PLACE FACTORIES                               */
             /*************************************************/
 
-            let mut factory_ids = Vec::new();
+            // weights of factory positions, from which positions that conflict with already placed
+            // factories are removed
+            let mut factory_position_weights: HashMap<Subtype, WeightedIndex<f32>> =
+                position_bandit.weights().clone();
 
-            // Shuffle products to place factories in different order/priority each iteration
-            products.shuffle(rng.borrow_mut().deref_mut());
+            let mut factory_ids = Vec::new();
+            // products and indices of sampled factory positions, whose solution's score is recorded
+            let mut sampled_positions: Vec<(Subtype, usize)> = Vec::new();
 
-            'factory_placement: for product in products.iter() {
-                // skip a factory with some probability to try solutions where not all factories are used
-                if (**rng)
+            // Some iterations keep the factories of the incumbent (except the skipped ones) and
+            // only build new paths
+            let warm_start = !incumbent_factories.is_empty()
+                && (**rng)
                     .borrow_mut()
-                    .gen_ratio(PROBABILITY_FACTORY_SKIP.0, PROBABILITY_FACTORY_SKIP.1)
-                {
-                    continue 'factory_placement;
+                    .gen_ratio(WARM_START_PROBABILITY.0, WARM_START_PROBABILITY.1);
+            if warm_start {
+                for factory in incumbent_factories.iter() {
+                    if !(**rng).borrow_mut().gen_ratio(
+                        config.probability_factory_skip.0,
+                        config.probability_factory_skip.1,
+                    ) && map.insert_object(factory.clone()).is_ok()
+                    {
+                        factory_ids.push(factory.id());
+                    }
                 }
-
-                let factory_type = product.subtype;
-                let (factory_location_distribution, factory_locations) =
-                    &best_factory_positions_by_factory_subtype[&factory_type];
-
-                for _ in 0..NUM_MAX_FACTORY_PLACEMENTS {
-                    let factory_location = factory_locations
-                        [factory_location_distribution.sample(rng.borrow_mut().deref_mut())];
-
-                    // TODO: check that for each required resource type, a deposit of such type is
-                    // reachable (simple path finding) from this factory location
-                    let factory = Object::Factory {
-                        x: factory_location.0,
-                        y: factory_location.1,
-                        subtype: product.subtype,
-                    };
-                    let factory_id = factory.id();
-
-                    if map.insert_object(factory).is_ok() {
-                        // TODO: update factory_positions weights, so that conflicting positions can not be picked anymore
-                        factory_ids.push(factory_id);
+            } else {
+                // Shuffle products to place factories in different order/priority each iteration
+                products.shuffle(rng.borrow_mut().deref_mut());
+
+                'factory_placement: for product in products.iter() {
+                    // skip a factory with some probability to try solutions where not all factories are used
+                    if (**rng).borrow_mut().gen_ratio(
+                        config.probability_factory_skip.0,
+                        config.probability_factory_skip.1,
+                    ) {
                         continue 'factory_placement;
                     }
-                }
-
-                // TODO: disallow already set factories
-                continue 'iterate;
-            }
-
-            if factory_ids.is_empty() {
-                continue 'iterate;
-            }
-
-            debug!("Factories placed");
-            debug!("{}", map);
-
-            /*************************************************/
-            /* CONSTRUCT INITIAL FACTORY -> DEPOSIT PATHS    */
-            /*************************************************/
-
-            // Map from factory subtype => (map of resource type => built path)
-            let mut built_paths_by_factory: HashMap<Subtype, HashMap<Subtype, Path>> =
-                HashMap::default();
-
-            #[allow(unused_variables)]
-            'combining_paths: for n_combining_paths in 0..NUM_PATH_COMBINING_ITERATIONS {
-                debug!("Combining paths #{}", n_combining_paths);
-
-                //TODO: don't shuffle randomly but by weight
-                factory_ids.shuffle(rng.borrow_mut().deref_mut());
-
-                for &factory_id in factory_ids.iter() {
-                    let factory = map.get_object(factory_id).clone(); //clone, so 'map' is borrowed for the scope of the loop
-                    let subtype = factory.subtype().unwrap();
-                    let product = task // TODO: use lookup table
-                        .products
-                        .iter()
-                        .find(|product| product.subtype == subtype)
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "No product found for subtype {} but a factory is present",
-                                subtype
-                            )
-                        });
-
-                    let mut resources: VecDeque<Subtype> = product
-                        .resources
-                        .iter()
-                        .enumerate()
-                        .filter_map(|(index, amount)| {
-                            if *amount > 0 {
-                                Some(index as Subtype)
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-
-                    resources
-                        .make_contiguous()
-                        .shuffle(rng.borrow_mut().deref_mut());
-
-                    let mut processed_resources: VecDeque<Subtype> = VecDeque::new();
-
-                    let mut paths_by_resource: HashMap<Subtype, Option<Paths<T>>> =
-                        resources.iter().map(|resource| (*resource, None)).collect();
-
-                    let mut built_paths_by_resource: HashMap<Subtype, Path> = HashMap::default();
-
-                    'path_building: while let Some(resource) = resources.pop_front() {
-                        debug!(
-                            "Try to find path from factory {} to resource {}",
-                            factory.subtype().unwrap(),
-                            resource
-                        );
 
-                        /* LOGIC
-                         *  1a. If no path to resource built yet:
-                         *      - Built and store paths for resource, based on already built paths
-                         *      - Choose first valid of such paths
-                         *  1b. Else:
-                         *      - Choose the next valid path from prebuilt paths
-                         *  2. Build and store the choosen path
-                         *  3a. If no path can be choosen:
-                         *      - push back resource and also push top of 'done' stack
-                         *  3b. Else:
-                         *      - pop resource and push it onto 'done' stack
-                         */
-
-                        let available_paths = paths_by_resource
-                            .entry(resource)
-                            .and_modify(|paths| {
-                                if paths.is_none() {
-                                    let start_points = {
-                                        let mut start_points = factory.ingresses().to_vec();
-                                        for path in built_paths_by_resource.values() {
-                                            for ingress in path.all_ingresses() {
-                                                start_points.push(ingress);
-                                            }
-                                        }
-                                        start_points
-                                    };
-                                    *paths = Some(Paths::new(
-                                        &start_points,
-                                        &deposits_by_type[&resource],
-                                        &map,
-                                        Rc::clone(&self.rng),
-                                    ));
-                                }
-                            })
-                            .or_default();
-
-                        if let Some(available_paths) = available_paths {
-                            for path in available_paths
-                                .by_ref()
-                                .take(NUM_PATHS_PER_FACTORY_AND_RESOURCE as usize)
-                            {
-                                if map
-                                    .try_insert_objects(path.objects().cloned().collect())
-                                    .is_ok()
-                                {
-                                    built_paths_by_resource.insert(resource, path);
-                                    processed_resources.push_back(resource);
-                                    continue 'path_building;
-                                }
-                            }
-
-                            break; // go to backtrack
-                        }
-
-                        // backtrack
-                        *available_paths = None;
-                        built_paths_by_resource.remove(&resource);
-
-                        resources.push_front(resource);
-                        if let Some(prior_resource) = processed_resources.pop_back() {
-                            resources.push_front(prior_resource);
-                        } else {
-                            continue 'combining_paths;
+                    let factory_type = product.subtype;
+                    let (_, factory_locations) =
+                        &best_factory_positions_by_factory_subtype[&factory_type];
+
+                    for _ in 0..config.num_max_factory_placements {
+                        let factory_location_index = factory_position_weights[&factory_type]
+                            .sample(rng.borrow_mut().deref_mut());
+                        let factory_location = factory_locations[factory_location_index];
+
+                        // TODO: check that for each required resource type, a deposit of such type is
+                        // reachable (simple path finding) from this factory location
+                        let factory = Object::Factory {
+                            x: factory_location.0,
+                            y: factory_location.1,
+                            subtype: product.subtype,
+                        };
+                        let factory_id = factory.id();
+
+                        if map.insert_object(factory).is_ok() {
+                            exclude_positions(
+                                &mut factory_position_weights,
+                                &factory_position_conflicts[factory_location_index],
+                            );
+                            factory_ids.push(factory_id);
+                            sampled_positions.push((factory_type, factory_location_index));
+                            continue 'factory_placement;
                         }
                     }
 
-                    built_paths_by_factory.insert(subtype, built_paths_by_resource);
-
-                    debug!("Initial paths built");
-                    debug!("{}", map);
+                    // TODO: disallow already set factories
+                    continue 'iterate;
                 }
-
-                // map = work_map;
-                break 'combining_paths;
             }
 
-            if built_paths_by_factory.is_empty() {
-                debug!("Could not build initial paths");
+            if factory_ids.is_empty() {
                 continue 'iterate;
             }
 
-            /*************************************************/
-            /* prepare weights for building additional paths */
-            /*************************************************/
-
-            let mut factory_resource_pairs: Vec<(ObjectID, Subtype)> = Vec::new();
-            let mut factory_resource_weights_raw: Vec<u32> = Vec::new();
-            for &factory_id in factory_ids.iter() {
-                let factory = map.get_object(factory_id);
-                let subtype = factory.subtype().unwrap();
-                let product = &products[subtype as usize];
-                for (resource_index, resource_amount) in product
-                    .resources
-                    .iter()
-                    .enumerate()
-                    .filter_map(|(idx, amount)| {
-                        if *amount > 0 {
-                            Some((idx, amount))
-                        } else {
-                            None
-                        }
-                    })
-                {
-                    let key = (factory_id, resource_index as Subtype);
-                    factory_resource_pairs.push(key);
-                    let weight = resource_amount * product.points;
-                    factory_resource_weights_raw.push(weight);
-                }
-            }
-            let mut factory_resource_weights =
-                WeightedIndex::new(factory_resource_weights_raw.clone())
-                    .expect("Cannot built (factory,resource) weights");
-
-            debug!("Building additional paths");
-
-            /*************************************************/
-            /* BUILD AUXILIARY PATHS                         */
-            /*************************************************/
-
-            // TODO: investigate optimal number of failed tries per factory/resource tuple
-            let max_additional_path_failures = factory_ids.len() * 10;
-            let mut additional_path_failures = 0;
-            'additional_paths: loop {
-                let factory_resource_pair_index =
-                    factory_resource_weights.sample(rng.borrow_mut().deref_mut());
-
-                let (factory_id, resource_index) =
-                    factory_resource_pairs[factory_resource_pair_index];
-                let factory = map.get_object(factory_id);
-
-                debug!(
-                    "Try building path from factory {} to resource {}",
-                    factory.subtype().unwrap(),
-                    resource_index
-                );
-
-                let built_paths_by_resource = built_paths_by_factory
-                    .entry(factory.subtype().unwrap())
-                    .or_default();
-                let start_points = {
-                    let mut start_points = factory.ingresses();
-                    for path in built_paths_by_resource.values() {
-                        for ingress in path.all_ingresses() {
-                            start_points.push(ingress);
-                        }
+            // Place additional factories for products whose resources are spread over multiple
+            // deposits. Other than the first factory of a product, those are optional.
+            let placed_products: Vec<Subtype> = factory_ids
+                .iter()
+                .map(|&factory_id| map.get_object(factory_id).subtype().unwrap())
+                .collect();
+            for product_subtype in placed_products {
+                let (_, factory_locations) =
+                    &best_factory_positions_by_factory_subtype[&product_subtype];
+
+                for _ in 0..num_additional_factories_by_product[&product_subtype] {
+                    if !(**rng).borrow_mut().gen_ratio(
+                        config.probability_additional_factory.0,
+                        config.probability_additional_factory.1,
+                    ) {
+                        continue;
                     }
-                    start_points
-                };
 
-                #[allow(unused_variables)]
-                let mut i = 1;
-                for path in Paths::new(
-                    &start_points,
-                    &deposits_by_type[&resource_index],
-                    &map,
-                    Rc::clone(&self.rng),
-                )
-                .take(NUM_ADDITION_PATHS_PER_FACTORY_AND_RESOURCE as usize)
-                {
-                    debug!("Checking path #{}", i);
-                    i += 1;
-                    if map
-                        .try_insert_objects(path.objects().cloned().collect())
-                        .is_ok()
-                    {
-                        built_paths_by_resource.insert(resource_index, path);
-                        debug!("{}", map);
-                        continue 'additional_paths;
+                    for _ in 0..config.num_max_factory_placements {
+                        let factory_location_index = factory_position_weights[&product_subtype]
+                            .sample(rng.borrow_mut().deref_mut());
+                        let factory_location = factory_locations[factory_location_index];
+                        let factory = Object::Factory {
+                            x: factory_location.0,
+                            y: factory_location.1,
+                            subtype: product_subtype,
+                        };
+                        let factory_id = factory.id();
+
+                        if map.insert_object(factory).is_ok() {
+                            exclude_positions(
+                                &mut factory_position_weights,
+                                &factory_position_conflicts[factory_location_index],
+                            );
+                            factory_ids.push(factory_id);
+                            sampled_positions.push((product_subtype, factory_location_index));
+                            break;
+                        }
                     }
                 }
+            }
 
-                // Reduce weight of current factory,resource tuple
-                let new_weight = &mut factory_resource_weights_raw[factory_resource_pair_index];
-                *new_weight /= 2;
-                let _ = factory_resource_weights
-                    .update_weights(&[(factory_resource_pair_index, new_weight)]);
+            debug!("Factories placed");
+            trace!("{}", map);
 
-                additional_path_failures += 1;
+            if tabu_list.contains(&factory_ids) {
+                debug!("Skipping factory placement of the tabu list");
+                continue 'iterate;
+            }
 
-                if additional_path_failures > max_additional_path_failures {
-                    break 'additional_paths;
-                }
+            let mut distances = distances_by_type.clone();
+            distances.insert_objects(factory_ids.iter().map(|&id| map.get_object(id)));
+
+            if !build_paths(
+                task,
+                &mut map,
+                &mut factory_ids,
+                deposits_by_type,
+                &mut distances,
+                config,
+                rng,
+            ) {
+                tabu_list.insert(&factory_ids);
+                position_bandit.record(&sampled_positions, 0);
+                continue 'iterate;
             }
 
             debug!("Additional paths built");
-            debug!("{}", map);
+            trace!("{}", map);
 
-            let map_score = simulate(task, &map, true);
+            let map_score = simulate_timed(task, &map);
+            if map_score.score == 0 {
+                tabu_list.insert(&factory_ids);
+            }
+            position_bandit.record(&sampled_positions, map_score.score);
 
             #[cfg(feature = "stats")]
             {
@@ -496,14 +450,22 @@ impl<'a, T: Rng> Iterator for Solver<'a, T> {
 
             if let Some((result, _)) = &best_solution {
                 if map_score > *result {
-                    debug!("{:?}", map_score);
-                    debug!("{}", map);
+                    debug!(
+                        score = map_score.score,
+                        turn = map_score.turn,
+                        "Solution found"
+                    );
+                    trace!("{}", map);
                     best_solution = Some((map_score, map));
                     return best_solution;
                 }
             } else if map_score.score > 0 {
-                debug!("{:?}", map_score);
-                debug!("{}", map);
+                debug!(
+                    score = map_score.score,
+                    turn = map_score.turn,
+                    "Solution found"
+                );
+                trace!("{}", map);
                 best_solution = Some((map_score, map));
                 return best_solution;
             };
@@ -513,129 +475,977 @@ impl<'a, T: Rng> Iterator for Solver<'a, T> {
     }
 }
 
-/// Finds all locations, at which a 5x5 factory could be legally placed
-fn find_possible_factory_positions(map: &Map) -> Vec<Point> {
-    let width = map.width() as Coord;
-    let height = map.height() as Coord;
-
-    let free_cells = {
-        let mut v = vec![];
-        for y in 0..height {
-            for x in 0..width {
-                if map.get_cell(x, y).is_none() {
-                    v.push((x, y));
-                }
-            }
-        }
-        v
+/// Builds paths from the factories identified by `factory_ids` to deposits of their resources
+///
+/// First, a path is built for each resource of each factory. Afterwards, additional paths are
+/// built for randomly chosen (factory, resource) pairs, weighted by the points they are expected to
+/// add.
+///
+/// With the [Strategy::Greedy] strategy, factories and resources keep their order and additional
+/// paths are built for the pair with the highest weight instead.
+///
+/// Returns `false` if not a single factory could be connected to its resources.
+pub(crate) fn build_paths<T: Rng>(
+    task: &Task,
+    map: &mut Map,
+    factory_ids: &mut [ObjectID],
+    deposits_by_type: &HashMap<Subtype, Vec<Object>>,
+    distances: &mut DistancesByType,
+    config: &SolverConfig,
+    rng: &Rc<RefCell<T>>,
+) -> bool {
+    /*************************************************/
+    /* CONSTRUCT INITIAL FACTORY -> DEPOSIT PATHS    */
+    /*************************************************/
+
+    let greedy = config.strategy == Strategy::Greedy;
+    let products = task.product_catalog();
+
+    // Map from factory id => (map of resource type => built path)
+    let mut built_paths_by_factory: HashMap<ObjectID, HashMap<Subtype, Path>> = HashMap::default();
+
+    // paths searched in parallel on the map without any paths, which are tried first
+    let mut prefetched_paths = if config.parallel_path_search {
+        search_paths_in_parallel(task, map, &products, factory_ids, distances, config, rng)
+    } else {
+        HashMap::default()
     };
 
-    let mut positions = Vec::new();
+    'combining_paths: for n_combining_paths in 0..config.num_path_combining_iterations {
+        let _span = debug_span!("combining_paths", n = n_combining_paths).entered();
 
-    'lopp_cells: for (x, y) in free_cells {
-        if x + 4 >= width || y + 4 >= height {
-            continue 'lopp_cells;
+        //TODO: don't shuffle randomly but by weight
+        if !greedy {
+            factory_ids.shuffle(rng.borrow_mut().deref_mut());
         }
 
-        let min_x = if x == 0 { 0 } else { x - 1 };
-        let min_y = if y == 0 { 0 } else { y - 1 };
-        for dx in x..x + 5 {
-            for dy in y..y + 5 {
-                if let Some(ObjectCell::Inner { .. }) = map.get_cell(dx, dy) {
-                    continue 'lopp_cells;
+        for &factory_id in factory_ids.iter() {
+            let factory = map.get_object(factory_id).clone(); //clone, so 'map' is borrowed for the scope of the loop
+            let product = factory_product(&products, &factory);
+
+            let mut resources: VecDeque<Subtype> = product
+                .resources
+                .iter()
+                .enumerate()
+                .filter_map(|(index, amount)| {
+                    if *amount > 0 {
+                        Some(index as Subtype)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            if !greedy {
+                resources
+                    .make_contiguous()
+                    .shuffle(rng.borrow_mut().deref_mut());
+            }
+
+            let mut processed_resources: VecDeque<Subtype> = VecDeque::new();
+
+            let mut paths_by_resource: HashMap<Subtype, Option<PathSearch<T>>> =
+                resources.iter().map(|resource| (*resource, None)).collect();
+
+            let mut built_paths_by_resource: HashMap<Subtype, Path> = HashMap::default();
+
+            'path_building: while let Some(resource) = resources.pop_front() {
+                let _span = debug_span!(
+                    "path_search",
+                    factory = factory.subtype().unwrap(),
+                    resource
+                )
+                .entered();
+
+                /* LOGIC
+                 *  1a. If no path to resource built yet:
+                 *      - Built and store paths for resource, based on already built paths
+                 *      - Choose first valid of such paths
+                 *  1b. Else:
+                 *      - Choose the next valid path from prebuilt paths
+                 *  2. Build and store the choosen path
+                 *  3a. If no path can be choosen:
+                 *      - try to merge into an already built path via a combiner
+                 *      - else push back resource and also push top of 'done' stack
+                 *  3b. Else:
+                 *      - pop resource and push it onto 'done' stack
+                 */
+
+                if let Some(paths) = prefetched_paths.remove(&(factory_id, resource)) {
+                    let path = paths.into_iter().find(|path| {
+                        delivers_in_time(map, path, task.turns)
+                            && map
+                                .try_insert_objects(path.objects().cloned().collect())
+                                .is_ok()
+                    });
+                    if let Some(path) = path {
+                        distances.insert_objects(path.objects());
+                        built_paths_by_resource.insert(resource, path);
+                        processed_resources.push_back(resource);
+                        continue 'path_building;
+                    }
+                }
+
+                let available_paths = paths_by_resource
+                    .entry(resource)
+                    .and_modify(|paths| {
+                        if paths.is_none() {
+                            let start_points = {
+                                let mut start_points = factory.ingresses().to_vec();
+                                for path in built_paths_by_resource.values() {
+                                    for ingress in path.all_ingresses() {
+                                        start_points.push(ingress);
+                                    }
+                                }
+                                if config.reuse_routes {
+                                    start_points.extend(shared_route_ingresses(
+                                        map,
+                                        &built_paths_by_factory,
+                                        factory_id,
+                                    ));
+                                }
+                                start_points
+                            };
+                            *paths = Some(PathSearch::new(
+                                &start_points,
+                                distances.get(map, resource).unwrap_or_default(),
+                                map,
+                                task.turns,
+                                Rc::clone(rng),
+                                config.bidirectional_path_search,
+                            ));
+                        }
+                    })
+                    .or_default();
+
+                if let Some(available_paths) = available_paths {
+                    for path in available_paths
+                        .by_ref()
+                        .take(config.num_paths_per_factory_and_resource as usize)
+                    {
+                        if delivers_in_time(map, &path, task.turns)
+                            && map
+                                .try_insert_objects(path.objects().cloned().collect())
+                                .is_ok()
+                        {
+                            distances.insert_objects(path.objects());
+                            built_paths_by_resource.insert(resource, path);
+                            processed_resources.push_back(resource);
+                            continue 'path_building;
+                        }
+                    }
+
+                    let mut trunks: Vec<&mut Path> = built_paths_by_resource.values_mut().collect();
+                    if let Some(path) = merge_into_paths(
+                        map,
+                        &mut trunks,
+                        distances,
+                        resource,
+                        task.turns,
+                        Rc::clone(rng),
+                        config,
+                    ) {
+                        built_paths_by_resource.insert(resource, path);
+                        processed_resources.push_back(resource);
+                        continue 'path_building;
+                    }
+
+                    record_path_search_failure();
+                    break; // go to backtrack
+                }
+
+                // backtrack
+                *available_paths = None;
+                built_paths_by_resource.remove(&resource);
+
+                resources.push_front(resource);
+                if let Some(prior_resource) = processed_resources.pop_back() {
+                    resources.push_front(prior_resource);
+                } else {
+                    continue 'combining_paths;
                 }
             }
+
+            built_paths_by_factory.insert(factory_id, built_paths_by_resource);
+
+            debug!("Initial paths built");
+            trace!("{}", map);
+        }
+
+        // map = work_map;
+        break 'combining_paths;
+    }
+
+    if built_paths_by_factory.is_empty() {
+        debug!("Could not build initial paths");
+        return false;
+    }
+
+    /*************************************************/
+    /* prepare weights for building additional paths */
+    /*************************************************/
+
+    // Resources beyond the consumable amount cannot contribute any points, so no further paths
+    // are built to resources, whose deposits are already mined sufficiently
+    let consumable = consumable_resources(task);
+    let mut deposit_usage = DepositUsage::new(config.max_mines_per_deposit);
+    for path in built_paths_by_factory.values().flat_map(HashMap::values) {
+        deposit_usage.add_path(task, map, path);
+    }
+
+    // Estimated amount of each resource, that the paths of each factory deliver in time
+    let mut deliveries_by_factory: HashMap<ObjectID, [u32; NUM_RESOURCE_TYPES]> =
+        HashMap::default();
+    for (&factory_id, paths) in built_paths_by_factory.iter() {
+        let deliveries = deliveries_by_factory.entry(factory_id).or_default();
+        for (&resource, path) in paths.iter() {
+            deliveries[resource as usize] += estimate_deliveries(map, path, task.turns).0;
+        }
+    }
+
+    let mut factory_resource_pairs: Vec<(ObjectID, Subtype)> = Vec::new();
+    for &factory_id in factory_ids.iter() {
+        let product = factory_product(&products, map.get_object(factory_id));
+        for (resource_index, _) in product
+            .resources
+            .iter()
+            .enumerate()
+            .filter(|(_, &amount)| amount > 0)
+        {
+            factory_resource_pairs.push((factory_id, resource_index as Subtype));
+        }
+    }
+
+    // Additional paths are built for pairs weighted by the score they are expected to add. The
+    // weight of a pair is halved for each failed search.
+    let marginal_gain = |map: &Map,
+                         deliveries_by_factory: &HashMap<ObjectID, [u32; NUM_RESOURCE_TYPES]>,
+                         deposit_usage: &DepositUsage,
+                         built_paths_by_factory: &HashMap<ObjectID, HashMap<Subtype, Path>>,
+                         (factory_id, resource): (ObjectID, Subtype)| {
+        let deposits = deposits_of(deposits_by_type, resource);
+        if deposit_usage.is_resource_saturated(deposits, consumable[resource as usize]) {
+            return 0.0;
         }
-        for dx in min_x..=x + 5 {
-            for dy in [min_y, y + 5] {
-                if let Some(ObjectCell::Egress { .. }) = map.get_cell(dx, dy) {
-                    continue 'lopp_cells;
+        let paths = built_paths_by_factory.get(&factory_id);
+        let delivery_turn = paths
+            .and_then(|paths| paths.get(&resource))
+            .into_iter()
+            .chain(paths.into_iter().flat_map(HashMap::values))
+            .map(|path| estimate_path_delivery_turn(map, path, task.turns))
+            .next()
+            .unwrap_or_else(|| estimate_delivery_turns(1));
+        expected_marginal_gain(
+            factory_product(&products, map.get_object(factory_id)),
+            &deliveries_by_factory
+                .get(&factory_id)
+                .copied()
+                .unwrap_or_default(),
+            resource,
+            deposit_usage.estimate_supply(deposits, delivery_turn, task.turns),
+        )
+    };
+    let mut num_failures = vec![0; factory_resource_pairs.len()];
+    let mut factory_resource_weights_raw: Vec<f32> = factory_resource_pairs
+        .iter()
+        .map(|&pair| {
+            marginal_gain(
+                map,
+                &deliveries_by_factory,
+                &deposit_usage,
+                &built_paths_by_factory,
+                pair,
+            )
+        })
+        .collect();
+    let mut factory_resource_weights = match WeightedIndex::new(&factory_resource_weights_raw) {
+        Ok(weights) => weights,
+        // no additional path is expected to add any points
+        Err(_) => return true,
+    };
+
+    debug!("Building additional paths");
+
+    /*************************************************/
+    /* BUILD AUXILIARY PATHS                         */
+    /*************************************************/
+
+    // TODO: investigate optimal number of failed tries per factory/resource tuple
+    let max_additional_path_failures =
+        factory_ids.len() * config.num_additional_path_failures_per_factory as usize;
+    let mut additional_path_failures = 0;
+    'additional_paths: loop {
+        let factory_resource_pair_index = if greedy {
+            // the first of all pairs with the highest weight
+            let (index, &weight) = factory_resource_weights_raw
+                .iter()
+                .enumerate()
+                .max_by(|(a_index, a), (b_index, b)| a.total_cmp(b).then(b_index.cmp(a_index)))
+                .unwrap();
+            if weight <= 0.0 {
+                break 'additional_paths;
+            }
+            index
+        } else {
+            factory_resource_weights.sample(rng.borrow_mut().deref_mut())
+        };
+
+        let (factory_id, resource_index) = factory_resource_pairs[factory_resource_pair_index];
+        let factory = map.get_object(factory_id);
+
+        let _span = debug_span!(
+            "path_search",
+            factory = factory.subtype().unwrap(),
+            resource = resource_index
+        )
+        .entered();
+
+        let shared_ingresses = if config.reuse_routes {
+            shared_route_ingresses(map, &built_paths_by_factory, factory_id)
+        } else {
+            vec![]
+        };
+        let built_paths_by_resource = built_paths_by_factory.entry(factory_id).or_default();
+        let start_points = {
+            let mut start_points = factory.ingresses();
+            start_points.extend(shared_ingresses);
+            for path in built_paths_by_resource.values() {
+                for ingress in path.all_ingresses() {
+                    start_points.push(ingress);
                 }
             }
-        }
+            start_points
+        };
+
+        let all_deposits = deposits_of(deposits_by_type, resource_index);
+        let deposits: Vec<Object> = all_deposits
+            .iter()
+            .filter(|deposit| !deposit_usage.is_saturated(deposit))
+            .cloned()
+            .collect();
+        // the precalculated distances only apply, if no deposit is excluded
+        let distances_to_deposits = if deposits.len() == all_deposits.len() {
+            distances.get(map, resource_index).unwrap_or_default()
+        } else {
+            get_distances(map, &deposits)
+        };
+
+        // With diversification, deposits without any path are searched first, so all deposits of
+        // the resource get tapped instead of the nearest ones only
+        let unconnected_deposits: Vec<Object> = deposits
+            .iter()
+            .filter(|deposit| !deposit_usage.is_connected(deposit))
+            .cloned()
+            .collect();
+        let diversified_paths = (config.diversify_deposits
+            && !unconnected_deposits.is_empty()
+            && unconnected_deposits.len() < deposits.len())
+        .then(|| {
+            PathSearch::new(
+                &start_points,
+                get_distances(map, &unconnected_deposits),
+                map,
+                task.turns,
+                Rc::clone(rng),
+                config.bidirectional_path_search,
+            )
+            .with_deposits(&unconnected_deposits)
+        });
+
+        // Prefer paths that deliver the most resources in time. Paths whose deliveries are not
+        // limited by the number of turns are tried right away, all others once the search is
+        // exhausted.
+        let num_paths = config.num_additional_paths_per_factory_and_resource as usize;
+        let mut paths = diversified_paths
+            .into_iter()
+            .flatten()
+            .take(num_paths)
+            .chain(
+                PathSearch::new(
+                    &start_points,
+                    distances_to_deposits,
+                    map,
+                    task.turns,
+                    Rc::clone(rng),
+                    config.bidirectional_path_search,
+                )
+                .with_deposits(&deposits)
+                .take(num_paths),
+            );
+        let mut delayed_paths: Vec<(u32, Path, Option<&Object>)> = vec![];
+
+        for i in 1.. {
+            let (path, deposit) = match paths.next() {
+                Some((path, deposit)) => {
+                    let (deliveries, max_deliveries) = estimate_deliveries(map, &path, task.turns);
+                    if deliveries < max_deliveries {
+                        delayed_paths.push((deliveries, path, deposit));
+                        continue;
+                    }
+                    (path, deposit)
+                }
+                None => match delayed_paths
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(index, (deliveries, ..))| (*deliveries, Reverse(*index)))
+                {
+                    Some((index, _)) => {
+                        let (_, path, deposit) = delayed_paths.remove(index);
+                        (path, deposit)
+                    }
+                    None => break,
+                },
+            };
 
-        for dy in min_y..=y + 5 {
-            for dx in [min_x, x + 5] {
-                if let Some(ObjectCell::Egress { .. }) = map.get_cell(dx, dy) {
-                    continue 'lopp_cells;
+            debug!(
+                "Checking path #{} to deposit at {:?}",
+                i,
+                deposit.map(Object::coords)
+            );
+            if delivers_in_time(map, &path, task.turns)
+                && map
+                    .try_insert_objects(path.objects().cloned().collect())
+                    .is_ok()
+            {
+                distances.insert_objects(path.objects());
+                let delivered = deposit_usage.add_path(task, map, &path);
+                deliveries_by_factory.entry(factory_id).or_default()[resource_index as usize] +=
+                    delivered;
+                built_paths_by_resource.insert(resource_index, path);
+                trace!("{}", map);
+
+                // the new path only changes the gains of pairs sharing its factory or resource
+                let updated_weights: Vec<(usize, f32)> = factory_resource_pairs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (id, resource))| *id == factory_id || *resource == resource_index)
+                    .map(|(index, &pair)| {
+                        let gain = marginal_gain(
+                            map,
+                            &deliveries_by_factory,
+                            &deposit_usage,
+                            &built_paths_by_factory,
+                            pair,
+                        );
+                        (index, gain / 2f32.powi(num_failures[index]))
+                    })
+                    .collect();
+                for &(index, weight) in updated_weights.iter() {
+                    factory_resource_weights_raw[index] = weight;
                 }
+                let updated_weights: Vec<(usize, &f32)> = updated_weights
+                    .iter()
+                    .map(|(index, weight)| (*index, weight))
+                    .collect();
+                if factory_resource_weights
+                    .update_weights(&updated_weights)
+                    .is_err()
+                {
+                    debug!("No additional path is expected to add any points");
+                    break 'additional_paths;
+                }
+                continue 'additional_paths;
             }
         }
 
-        positions.push((x, y))
+        record_path_search_failure();
+
+        // Reduce weight of current factory,resource tuple
+        num_failures[factory_resource_pair_index] += 1;
+        let new_weight = &mut factory_resource_weights_raw[factory_resource_pair_index];
+        *new_weight /= 2.0;
+        let _ =
+            factory_resource_weights.update_weights(&[(factory_resource_pair_index, new_weight)]);
+
+        additional_path_failures += 1;
+
+        if additional_path_failures > max_additional_path_failures {
+            break 'additional_paths;
+        }
     }
 
-    positions
+    true
 }
 
-/// Distance calculation type
-#[derive(Copy, Clone)]
-enum DistanceType {
-    /// Use manhattan distance
-    ///
-    /// Ignores buildings already placed on the map but is faster
-    Manhattan,
-    /// Use shortest distance
+/// All deposits of `resource` (none, if the task does not contain any)
+fn deposits_of(deposits_by_type: &HashMap<Subtype, Vec<Object>>, resource: Subtype) -> &[Object] {
+    deposits_by_type
+        .get(&resource)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Resources and mines, that the paths built during an iteration take from each deposit
+struct DepositUsage {
+    /// Amount of resources the mines attached to each deposit can extract in time
+    extraction_by_deposit: HashMap<ObjectID, u32>,
+    /// Number of mines attached to each deposit
+    mines_by_deposit: HashMap<ObjectID, u32>,
+    /// Number of mines, at which a deposit counts as saturated (see
+    /// [SolverConfig::max_mines_per_deposit])
+    max_mines_per_deposit: Option<u32>,
+}
+
+impl DepositUsage {
+    fn new(max_mines_per_deposit: Option<u32>) -> Self {
+        DepositUsage {
+            extraction_by_deposit: HashMap::default(),
+            mines_by_deposit: HashMap::default(),
+            max_mines_per_deposit,
+        }
+    }
+
+    /// Adds the mines of `path` and the amount of resources they can deliver in time to the
+    /// deposits they are attached to
     ///
-    /// Takes more time, especially for large maps but may produce better estimates
-    ShortestPath,
+    /// Returns the total amount of resources added.
+    fn add_path(&mut self, task: &Task, map: &Map, path: &Path) -> u32 {
+        let delivery_turn = estimate_path_delivery_turn(map, path, task.turns);
+        let mut total = 0;
+        for deposit in mined_deposits(map, path) {
+            let amount = deliverable_resources(deposit, delivery_turn, task.turns);
+            *self.extraction_by_deposit.entry(deposit.id()).or_default() += amount;
+            *self.mines_by_deposit.entry(deposit.id()).or_default() += 1;
+            total += amount;
+        }
+        total
+    }
+
+    /// Checks if any path is attached to `deposit`
+    fn is_connected(&self, deposit: &Object) -> bool {
+        self.mines_by_deposit.contains_key(&deposit.id())
+    }
+
+    /// Checks if the mines attached to `deposit` extract all of its resources in time, or if
+    /// the max number of mines is attached to it
+    fn is_saturated(&self, deposit: &Object) -> bool {
+        let extracted = self
+            .extraction_by_deposit
+            .get(&deposit.id())
+            .is_some_and(|&extraction| extraction >= deposit.initial_resources());
+        let max_mines = self.max_mines_per_deposit.is_some_and(|max_mines| {
+            self.mines_by_deposit
+                .get(&deposit.id())
+                .copied()
+                .unwrap_or(0)
+                >= max_mines
+        });
+        extracted || max_mines
+    }
+
+    /// Checks if the mines attached to `deposits` can extract at least `consumable` resources in
+    /// time, or if all of the deposits are saturated
+    fn is_resource_saturated(&self, deposits: &[Object], consumable: u32) -> bool {
+        let extraction: u32 = deposits
+            .iter()
+            .filter_map(|deposit| {
+                let extraction = *self.extraction_by_deposit.get(&deposit.id())?;
+                Some(extraction.min(deposit.initial_resources()))
+            })
+            .sum();
+        extraction >= consumable || deposits.iter().all(|deposit| self.is_saturated(deposit))
+    }
+
+    /// Amount of resources a single new mine at any unsaturated deposit of `deposits` can
+    /// deliver, if they are first used by a factory in turn `delivery_turn`
+    fn estimate_supply(&self, deposits: &[Object], delivery_turn: u32, turns: u32) -> u32 {
+        deposits
+            .iter()
+            .filter(|deposit| !self.is_saturated(deposit))
+            .map(|deposit| {
+                let extraction = self.extraction_by_deposit.get(&deposit.id()).copied();
+                let remaining = deposit
+                    .initial_resources()
+                    .saturating_sub(extraction.unwrap_or(0));
+                remaining.min(deliverable_resources(deposit, delivery_turn, turns))
+            })
+            .max()
+            .unwrap_or(0)
+    }
 }
 
-/// Weights all `positions` by their minimal distance to `deposits`
-fn sort_to_best_positions_by_deposits(
+/// Expected number of points, that delivering `supply` additional units of `resource` to a
+/// factory, which already receives `deliveries`, adds
+///
+/// Units of `product` are counted fractionally, so resources that only complete a unit together
+/// with later deliveries are valued as well.
+fn expected_marginal_gain(
+    product: &Product,
+    deliveries: &[u32; NUM_RESOURCE_TYPES],
+    resource: Subtype,
+    supply: u32,
+) -> f32 {
+    let units = |deliveries: &[u32; NUM_RESOURCE_TYPES]| {
+        product
+            .resources
+            .iter()
+            .zip(deliveries.iter())
+            .filter(|(&amount, _)| amount > 0)
+            .map(|(&amount, &delivered)| delivered as f32 / amount as f32)
+            .fold(f32::INFINITY, f32::min)
+    };
+
+    let mut new_deliveries = *deliveries;
+    new_deliveries[resource as usize] += supply;
+    let gain = units(&new_deliveries) - units(deliveries);
+    if gain.is_finite() {
+        gain * product.points as f32
+    } else {
+        0.0
+    }
+}
+
+/// Searches paths from each factory of `factory_ids` to each of its resources in parallel
+///
+/// All searches run on the current `map`, so their paths may conflict with each other. Each search
+/// uses its own rng, seeded by `rng`.
+fn search_paths_in_parallel<T: Rng>(
+    task: &Task,
     map: &Map,
-    positions: &[Point],
-    deposits: &[Object],
-    distance_type: DistanceType,
-) -> (WeightedIndex<f32>, Vec<Point>) {
-    let positions_with_distances: Vec<(i32, &Point)> = positions
+    products: &ProductCatalog<'_>,
+    factory_ids: &[ObjectID],
+    distances: &mut DistancesByType,
+    config: &SolverConfig,
+    rng: &Rc<RefCell<T>>,
+) -> HashMap<(ObjectID, Subtype), Vec<Path>> {
+    let mut searches = Vec::new();
+    for &factory_id in factory_ids {
+        let factory = map.get_object(factory_id);
+        let product = factory_product(products, factory);
+        for (resource, _) in product
+            .resources
+            .iter()
+            .enumerate()
+            .filter(|(_, &amount)| amount > 0)
+        {
+            let resource = resource as Subtype;
+            if let Some(distances) = distances.get(map, resource) {
+                let seed: u64 = rng.borrow_mut().gen();
+                searches.push((factory_id, resource, factory.ingresses(), distances, seed));
+            }
+        }
+    }
+
+    searches
+        .into_par_iter()
+        .map(|(factory_id, resource, start_points, distances, seed)| {
+            let rng = Rc::new(RefCell::new(SolverRng::new(config.rng, Some(seed))));
+            let paths = PathSearch::new(
+                &start_points,
+                distances,
+                map,
+                task.turns,
+                rng,
+                config.bidirectional_path_search,
+            )
+            .take(config.num_paths_per_factory_and_resource as usize)
+            .collect();
+            ((factory_id, resource), paths)
+        })
+        .collect()
+}
+
+/// The product a factory produces
+fn factory_product<'t>(products: &ProductCatalog<'t>, factory: &Object) -> &'t Product {
+    products.of_factory(factory).unwrap_or_else(|| {
+        panic!(
+            "No product found for subtype {} but a factory is present",
+            factory.subtype().unwrap()
+        )
+    })
+}
+
+/// Estimates how many factories of `product` may be placed in addition to the first one
+///
+/// Additional factories are only worth it, if the product's resources are available from multiple
+/// deposits (which are probably spread over the map) and if the deposits hold enough resources to
+/// produce the product at all.
+fn estimate_num_additional_factories(
+    product: &Product,
+    deposits_by_type: &HashMap<Subtype, Vec<Object>>,
+    config: &SolverConfig,
+) -> u32 {
+    let required_resources = product
+        .resources
         .iter()
-        .map(|position| {
-            // TODO: weight deposit (resource types) by importance for product
-            let distances = deposits
-                .iter()
-                .map(|deposit| match distance_type {
-                    DistanceType::Manhattan => {
-                        let (x, y) = position;
-                        let (dx, dy) = deposit.coords();
-                        (x - dx).abs() as i32 + (y - dy).abs() as i32
-                    }
-                    DistanceType::ShortestPath => {
-                        let distances = get_distances(map, &[deposit.clone()]);
-                        distances
-                            .get(position)
-                            .map(|d| *d as i32)
-                            .unwrap_or(i32::MAX)
-                    }
-                })
-                .collect::<Vec<i32>>();
+        .enumerate()
+        .filter(|(_, &amount)| amount > 0);
+
+    let mut num_deposits = u32::MAX;
+    for (resource_index, _) in required_resources {
+        let deposits = deposits_by_type
+            .get(&(resource_index as Subtype))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        num_deposits = num_deposits.min(deposits.len() as u32);
+    }
 
-            let sum = distances.iter().sum::<i32>();
-            let mean_distance = sum / distances.len() as i32;
-            let deviation = distances
-                .iter()
-                .map(|&i| (i - mean_distance).abs())
-                .sum::<i32>();
+    let capacities = resource_capacities(deposits_by_type.values().flatten());
+    let num_producible = max_producible(product, &capacities);
+
+    if num_producible == 0 || num_deposits == u32::MAX {
+        return 0;
+    }
 
-            let distance = sum + deviation;
+    num_deposits
+        .min(num_producible)
+        .min(config.max_factories_per_product)
+        .saturating_sub(1)
+}
 
-            (distance, position)
+/// Ingresses of all paths built for other factories that produce the same product as the factory
+/// identified by `factory_id`
+///
+/// Resources delivered to any of those factories are worth the same, so new paths may start at
+/// these ingresses instead of building a separate path all the way to the factory.
+fn shared_route_ingresses(
+    map: &Map,
+    built_paths_by_factory: &HashMap<ObjectID, HashMap<Subtype, Path>>,
+    factory_id: ObjectID,
+) -> Vec<Point> {
+    let product = map.get_object(factory_id).subtype();
+    built_paths_by_factory
+        .iter()
+        .filter(|(&other_id, _)| {
+            other_id != factory_id && map.get_object(other_id).subtype() == product
         })
-        .collect();
+        .flat_map(|(&other_id, paths)| {
+            map.get_object(other_id)
+                .ingresses()
+                .into_iter()
+                .chain(paths.values().flat_map(Path::all_ingresses))
+        })
+        .collect()
+}
 
-    let probabilites: Vec<f32> = positions_with_distances
+/// Tries to build a path to deposits of `resource` that merges into one of `trunks`
+///
+/// To do so, a short conveyor of a trunk is replaced by a combiner of the same direction. Because
+/// both share the same egress and the combiner's middle ingress equals the conveyor's ingress,
+/// the trunk's flow stays intact while the combiner's side ingresses can be used as start points
+/// for the new path. This allows building paths in narrow corridors, where no separate path fits.
+/// However, the combiner covers more cells than the conveyor, so it is only inserted in place of
+/// the conveyor if it does not collide with other objects. Otherwise the conveyor is kept.
+///
+/// On success the combiner and the new path are inserted into `map` (and recorded in `distances`),
+/// the combiner replaces the conveyor in its trunk and the new path is returned. Otherwise `map`
+/// and `trunks` are left unchanged.
+fn merge_into_paths<T: Rng>(
+    map: &mut Map,
+    trunks: &mut [&mut Path],
+    distances: &mut DistancesByType,
+    resource: Subtype,
+    turns: u32,
+    rng: Rc<RefCell<T>>,
+    config: &SolverConfig,
+) -> Option<Path> {
+    let mut splices: Vec<(usize, Object, Object)> = trunks
         .iter()
-        .map(|(distance, _)| 1f32 / (*distance).max(1) as f32)
+        .enumerate()
+        .flat_map(|(trunk, path)| path.objects().map(move |object| (trunk, object)))
+        .filter_map(|(trunk, object)| match *object {
+            // a combiner has the same egress and middle ingress as a short conveyor with the same
+            // direction
+            Object::Conveyor { x, y, subtype } if !subtype.is_long() => Some((
+                trunk,
+                object.clone(),
+                Object::Combiner {
+                    x,
+                    y,
+                    subtype: CombinerSubtype::ALL[subtype as usize],
+                },
+            )),
+            _ => None,
+        })
         .collect();
 
-    let weights =
-        WeightedIndex::new(probabilites).expect("Cannot build weights from factory locations");
+    splices.shuffle(rng.borrow_mut().deref_mut());
+
+    // the combiners are not taken into account, since they are removed again if no path is found
+    let distances_to_deposits = distances.get(map, resource).unwrap_or_default();
+
+    for (trunk, conveyor, combiner) in splices
+        .into_iter()
+        .take(config.num_combiner_merge_attempts as usize)
+    {
+        if map.remove_object(&conveyor).is_err() {
+            continue;
+        }
+
+        if map.insert_object(combiner.clone()).is_ok() {
+            let trunk_ingress = conveyor.ingress();
+            let side_ingresses: Vec<Point> = combiner
+                .ingresses()
+                .into_iter()
+                .filter(|ingress| Some(*ingress) != trunk_ingress)
+                .collect();
+
+            for path in PathSearch::new(
+                &side_ingresses,
+                Arc::clone(&distances_to_deposits),
+                map,
+                turns,
+                Rc::clone(&rng),
+                config.bidirectional_path_search,
+            )
+            .take(config.num_paths_per_factory_and_resource as usize)
+            {
+                if delivers_in_time(map, &path, turns)
+                    && map
+                        .try_insert_objects(path.objects().cloned().collect())
+                        .is_ok()
+                {
+                    distances.insert_objects(std::iter::once(&combiner).chain(path.objects()));
+                    trunks[trunk].replace(&conveyor, combiner);
+                    return Some(path);
+                }
+            }
+
+            map.remove_object(&combiner)
+                .expect("Cannot remove combiner that was just inserted");
+        }
+
+        map.insert_object_unchecked(conveyor);
+    }
+
+    None
+}
 
-    let positions: Vec<Point> = positions_with_distances
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    /// A map with a path from a deposit of resource 0 to a factory and a (separate) deposit of
+    /// resource 1
+    fn map_with_trunk() -> (Map, Vec<Object>, Path, DistancesByType, Rc<RefCell<StdRng>>) {
+        let deposit = |x, y, subtype| Object::Deposit {
+            x,
+            y,
+            width: 3,
+            height: 3,
+            subtype,
+        };
+        let deposits = vec![deposit(0, 0, 0), deposit(8, 7, 1)];
+        let factory = Object::Factory {
+            x: 15,
+            y: 0,
+            subtype: 0,
+        };
+        let mut objects = deposits.clone();
+        objects.push(factory.clone());
+        let mut map = Map::new(20, 10, objects);
+        let rng = Rc::new(RefCell::new(StdRng::seed_from_u64(1)));
+
+        let trunk = PathSearch::new(
+            &factory.ingresses(),
+            get_distances(&map, &deposits[..1]),
+            &map,
+            100,
+            Rc::clone(&rng),
+            false,
+        )
+        .next()
+        .unwrap();
+        map.try_insert_objects(trunk.objects().cloned().collect())
+            .unwrap();
+
+        let deposits_by_type = [
+            (0, vec![deposits[0].clone()]),
+            (1, vec![deposits[1].clone()]),
+        ]
         .into_iter()
-        .map(|(_, position)| position)
-        .cloned()
         .collect();
+        let distances = DistancesByType::new(&map, &deposits_by_type);
+        (map, deposits, trunk, distances, rng)
+    }
+
+    #[test]
+    fn merged_path_replaces_conveyor_of_trunk() {
+        let (mut map, deposits, mut trunk, mut distances, rng) = map_with_trunk();
+        let config = SolverConfig {
+            num_combiner_merge_attempts: 20,
+            ..SolverConfig::default()
+        };
+        let combiners = |path: &Path| -> Vec<Object> {
+            path.objects()
+                .filter(|object| matches!(object, Object::Combiner { .. }))
+                .cloned()
+                .collect()
+        };
+        let num_objects = trunk.len();
+        let former_combiners = combiners(&trunk);
+
+        let path = merge_into_paths(
+            &mut map,
+            &mut [&mut trunk],
+            &mut distances,
+            1,
+            100,
+            rng,
+            &config,
+        )
+        .expect("No path merged into trunk");
+
+        assert_eq!(Some(&deposits[1]), path.deposit(&deposits));
+        assert_eq!(num_objects, trunk.len());
+        let combiner = combiners(&trunk)
+            .into_iter()
+            .find(|combiner| !former_combiners.contains(combiner))
+            .expect("No combiner in trunk");
+        assert!(path
+            .starting_points()
+            .iter()
+            .all(|point| combiner.ingresses().contains(point)));
+        for object in trunk.objects().chain(path.objects()) {
+            assert!(map.contains_object(&object.id()), "{:?} not in map", object);
+        }
+    }
+
+    #[test]
+    fn conveyor_is_kept_if_combiner_collides() {
+        let (mut map, _, mut trunk, mut distances, rng) = map_with_trunk();
+        // no combiner fits, if all free cells are blocked
+        for x in 0..map.width() as i8 {
+            for y in 0..map.height() as i8 {
+                if map.is_empty_at(x, y) {
+                    let _ = map.insert_object(Object::Obstacle {
+                        x,
+                        y,
+                        width: 1,
+                        height: 1,
+                    });
+                }
+            }
+        }
+        let config = SolverConfig {
+            num_combiner_merge_attempts: u32::MAX,
+            ..SolverConfig::default()
+        };
+        let objects = |map: &Map| -> Vec<ObjectID> {
+            let mut ids: Vec<ObjectID> = map.get_objects().map(Object::id).collect();
+            ids.sort();
+            ids
+        };
+        let former_objects = objects(&map);
+        let former_trunk: Vec<Object> = trunk.objects().cloned().collect();
+        assert!(former_trunk.iter().any(
+            |object| matches!(object, Object::Conveyor { subtype, .. } if !subtype.is_long())
+        ));
+
+        let path = merge_into_paths(
+            &mut map,
+            &mut [&mut trunk],
+            &mut distances,
+            1,
+            100,
+            rng,
+            &config,
+        );
 
-    (weights, positions)
+        assert!(path.is_none());
+        assert_eq!(former_objects, objects(&map));
+        assert_eq!(former_trunk, trunk.objects().cloned().collect::<Vec<_>>());
+    }
 }
@@ -0,0 +1,152 @@
+//! Search strategies a solver run can use
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use clap::ValueEnum;
+use model::{map::Map, task::Task};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use simulator::SimulatorResult;
+
+use crate::{
+    bandit::SharedStatistics, config::SolverConfig, greedy::GreedySolver, mcts::MctsSolver,
+    solve::Solver,
+};
+
+/// Strategy by which solutions are searched
+///
+/// The docs of the variants are the help texts of `--strategy`, so they do not link to items.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum Strategy {
+    /// Place factories and build paths at random in every iteration
+    #[default]
+    Iterative,
+    /// Monte-Carlo tree search over factory placements
+    Mcts,
+    /// Construct a single solution greedily
+    Greedy,
+    /// Spend the first part of the runtime (see --portfolio-greedy-percent) on the greedy strategy,
+    /// then search with iterative and mcts threads that warm-start from the best solution found so
+    /// far
+    Portfolio,
+}
+
+impl Strategy {
+    /// The strategy the worker with index `thread` uses
+    ///
+    /// Only [Strategy::Portfolio] mixes strategies: worker 0 runs the greedy phase, the other
+    /// workers alternate between [Strategy::Iterative] and [Strategy::Mcts].
+    pub fn for_worker(self, thread: usize) -> Strategy {
+        match self {
+            Strategy::Portfolio if thread == 0 => Strategy::Greedy,
+            Strategy::Portfolio if thread % 2 == 1 => Strategy::Iterative,
+            Strategy::Portfolio => Strategy::Mcts,
+            strategy => strategy,
+        }
+    }
+}
+
+/// A source of solutions, that can be steered by the best solution found by other workers
+pub trait SearchStrategy: Iterator<Item = (SimulatorResult, Map)> {
+    /// Checks if the solver will not produce any further solutions
+    fn is_exhausted(&self) -> bool {
+        false
+    }
+
+    /// Lets following solutions build upon `incumbent`, the best solution of the run so far
+    ///
+    /// Strategies that cannot make use of it ignore it.
+    fn warm_start(&mut self, _incumbent: &Map) {}
+
+    /// Lets the solver start from the factory position `statistics` of the run and record its
+    /// solutions to them (see [PositionBandit::share](crate::bandit::PositionBandit::share))
+    ///
+    /// Strategies that do not learn factory positions ignore them.
+    fn share_statistics(&mut self, _statistics: &SharedStatistics) {}
+}
+
+/// A solver that uses one of the [Strategy]s
+pub enum StrategySolver<'a, T> {
+    Iterative(Solver<'a, T>),
+    Mcts(MctsSolver<'a, T>),
+    Greedy(GreedySolver<'a, T>),
+}
+
+impl<'a, T> StrategySolver<'a, T> {
+    #[cfg(feature = "stats")]
+    /// Returns the total number of solutions produced so far
+    pub fn get_num_solutions(&self) -> usize {
+        match self {
+            StrategySolver::Iterative(solver) => solver.get_num_solutions(),
+            StrategySolver::Mcts(solver) => solver.get_num_solutions(),
+            StrategySolver::Greedy(solver) => solver.get_num_solutions(),
+        }
+    }
+}
+
+impl<'a, T: Rng> StrategySolver<'a, T> {
+    /// Creates a new solver for the given task / map, using the config's strategy
+    ///
+    /// [Strategy::Portfolio] is resolved to the strategy of its first search worker.
+    pub fn new(
+        task: &'a Task,
+        map: &'a Map,
+        config: SolverConfig,
+        rng: Rc<RefCell<T>>,
+        max_iteration_time: Duration,
+    ) -> Self {
+        match config.strategy {
+            Strategy::Iterative | Strategy::Portfolio => {
+                StrategySolver::Iterative(Solver::new(task, map, config, rng, max_iteration_time))
+            }
+            Strategy::Mcts => {
+                StrategySolver::Mcts(MctsSolver::new(task, map, config, rng, max_iteration_time))
+            }
+            Strategy::Greedy => StrategySolver::Greedy(GreedySolver::new(
+                task,
+                map,
+                config,
+                rng,
+                max_iteration_time,
+            )),
+        }
+    }
+}
+
+impl<'a, T: Rng> Iterator for StrategySolver<'a, T> {
+    type Item = (SimulatorResult, Map);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            StrategySolver::Iterative(solver) => solver.next(),
+            StrategySolver::Mcts(solver) => solver.next(),
+            StrategySolver::Greedy(solver) => solver.next(),
+        }
+    }
+}
+
+impl<'a, T: Rng> SearchStrategy for StrategySolver<'a, T> {
+    fn is_exhausted(&self) -> bool {
+        match self {
+            StrategySolver::Iterative(solver) => solver.is_exhausted(),
+            StrategySolver::Mcts(solver) => solver.is_exhausted(),
+            StrategySolver::Greedy(solver) => solver.is_exhausted(),
+        }
+    }
+
+    fn warm_start(&mut self, incumbent: &Map) {
+        match self {
+            StrategySolver::Iterative(solver) => solver.warm_start(incumbent),
+            StrategySolver::Mcts(solver) => solver.warm_start(incumbent),
+            StrategySolver::Greedy(solver) => solver.warm_start(incumbent),
+        }
+    }
+
+    fn share_statistics(&mut self, statistics: &SharedStatistics) {
+        match self {
+            StrategySolver::Iterative(solver) => solver.share_statistics(statistics),
+            StrategySolver::Mcts(solver) => solver.share_statistics(statistics),
+            StrategySolver::Greedy(solver) => solver.share_statistics(statistics),
+        }
+    }
+}
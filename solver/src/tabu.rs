@@ -0,0 +1,67 @@
+//! Factory placements that are skipped for a while, because they did not lead to any points
+
+use fxhash::FxHashMap as HashMap;
+use model::object::ObjectID;
+
+/// Recently tried factory placements that produced solutions without any points
+///
+/// Placements are identified by the set of their factories, so the order in which the factories
+/// were placed does not matter.
+#[derive(Debug, Clone, Default)]
+pub struct TabuList {
+    /// Number of iterations a placement stays on the list
+    tenure: u32,
+    /// Number of the current iteration
+    iteration: u64,
+    /// Iteration, in which expired placements are forgotten next
+    next_sweep: u64,
+    /// Iteration until which each placement is skipped, by the placement's key
+    entries: HashMap<u64, u64>,
+}
+
+impl TabuList {
+    /// Creates a list, whose placements are skipped for `tenure` iterations
+    ///
+    /// A tenure of 0 disables the list.
+    pub fn new(tenure: u32) -> TabuList {
+        TabuList {
+            tenure,
+            ..Default::default()
+        }
+    }
+
+    /// Starts the next iteration and forgets expired placements
+    pub fn tick(&mut self) {
+        self.iteration += 1;
+        if self.iteration >= self.next_sweep {
+            let iteration = self.iteration;
+            self.entries.retain(|_, &mut until| until > iteration);
+            self.next_sweep = iteration + self.tenure as u64;
+        }
+    }
+
+    /// Skips the placement of `factory_ids` for the next iterations
+    pub fn insert(&mut self, factory_ids: &[ObjectID]) {
+        if self.tenure > 0 {
+            self.entries.insert(
+                placement_key(factory_ids),
+                self.iteration + self.tenure as u64,
+            );
+        }
+    }
+
+    /// Checks if the placement of `factory_ids` is to be skipped in the current iteration
+    pub fn contains(&self, factory_ids: &[ObjectID]) -> bool {
+        !self.entries.is_empty()
+            && self
+                .entries
+                .get(&placement_key(factory_ids))
+                .is_some_and(|&until| until > self.iteration)
+    }
+}
+
+fn placement_key(factory_ids: &[ObjectID]) -> u64 {
+    let mut factory_ids = factory_ids.to_vec();
+    factory_ids.sort_unstable();
+    fxhash::hash64(&factory_ids)
+}
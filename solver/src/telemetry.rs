@@ -0,0 +1,222 @@
+//! Per-iteration metrics of solver runs, for analysing where the time of a run goes
+//!
+//! Workers measure each of their iterations (the calculation of a single solution). Metrics that
+//! arise deep inside the strategies (failed path searches, time spent simulating) are collected in
+//! thread local counters, which the worker takes after each iteration. A [Telemetry] sums up the
+//! metrics of all runs (see [Telemetry::to_prometheus]) and optionally writes every iteration as a
+//! CSV line.
+
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use model::{map::Map, task::Task};
+use simulator::{simulate, SimulatorResult};
+
+const CSV_HEADER: &str =
+    "run,thread,iteration,elapsed_ms,iteration_us,path_search_failures,simulation_us,score,best_score";
+
+thread_local! {
+    static ITERATION_METRICS: Cell<IterationMetrics> = Cell::new(IterationMetrics::default());
+}
+
+/// Metrics collected by a strategy during a single iteration
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct IterationMetrics {
+    pub(crate) path_search_failures: u32,
+    pub(crate) simulation_time: Duration,
+}
+
+/// Counts a path search of the current iteration that did not find any path that could be built
+pub(crate) fn record_path_search_failure() {
+    ITERATION_METRICS.with(|metrics| {
+        let mut current = metrics.get();
+        current.path_search_failures += 1;
+        metrics.set(current);
+    });
+}
+
+/// Simulates `map` (see [simulate]) and adds the time it took to the current iteration
+pub(crate) fn simulate_timed(task: &Task, map: &Map) -> SimulatorResult {
+    let time_start = Instant::now();
+    let result = simulate(task, map, true);
+    ITERATION_METRICS.with(|metrics| {
+        let mut current = metrics.get();
+        current.simulation_time += time_start.elapsed();
+        metrics.set(current);
+    });
+    result
+}
+
+/// Returns the metrics collected on this thread since the last call
+pub(crate) fn take_iteration_metrics() -> IterationMetrics {
+    ITERATION_METRICS.with(Cell::take)
+}
+
+/// Sink for the metrics of solver runs
+pub struct Telemetry {
+    time_start: Instant,
+    csv: Option<Mutex<BufWriter<File>>>,
+    /// Number of runs started so far
+    num_runs: AtomicU32,
+    num_iterations: AtomicU64,
+    iteration_time_us: AtomicU64,
+    path_search_failures: AtomicU64,
+    simulation_time_us: AtomicU64,
+    /// Best score of the current run + 1, so 0 can be used as 'no solution yet'
+    best_score: AtomicU32,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Telemetry::new(None)
+    }
+}
+
+impl Telemetry {
+    fn new(csv: Option<BufWriter<File>>) -> Self {
+        Telemetry {
+            time_start: Instant::now(),
+            csv: csv.map(Mutex::new),
+            num_runs: AtomicU32::new(0),
+            num_iterations: AtomicU64::new(0),
+            iteration_time_us: AtomicU64::new(0),
+            path_search_failures: AtomicU64::new(0),
+            simulation_time_us: AtomicU64::new(0),
+            best_score: AtomicU32::new(0),
+        }
+    }
+
+    /// Creates a sink that additionally writes each iteration to a CSV file at `path`
+    pub fn create_csv(path: &str) -> Result<Telemetry, String> {
+        let mut writer = File::create(path)
+            .map(BufWriter::new)
+            .map_err(|e| format!("Cannot write {}: {}", path, e))?;
+        writeln!(writer, "{}", CSV_HEADER).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+
+        Ok(Telemetry::new(Some(writer)))
+    }
+
+    /// Starts a new run, whose iterations are recorded from now on
+    pub(crate) fn start(&self) {
+        self.num_runs.fetch_add(1, Ordering::AcqRel);
+        self.best_score.store(0, Ordering::Release);
+    }
+
+    /// Records an iteration of worker `thread`, that found a solution of `score` (if any)
+    pub(crate) fn record(
+        &self,
+        thread: usize,
+        iteration: usize,
+        iteration_time: Duration,
+        metrics: IterationMetrics,
+        score: Option<u32>,
+    ) {
+        self.num_iterations.fetch_add(1, Ordering::Relaxed);
+        self.iteration_time_us
+            .fetch_add(iteration_time.as_micros() as u64, Ordering::Relaxed);
+        self.path_search_failures
+            .fetch_add(metrics.path_search_failures as u64, Ordering::Relaxed);
+        self.simulation_time_us.fetch_add(
+            metrics.simulation_time.as_micros() as u64,
+            Ordering::Relaxed,
+        );
+        if let Some(score) = score {
+            self.best_score
+                .fetch_max(score.saturating_add(1), Ordering::Relaxed);
+        }
+
+        if let Some(csv) = &self.csv {
+            let mut writer = csv.lock().unwrap();
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{}",
+                self.num_runs.load(Ordering::Acquire),
+                thread,
+                iteration,
+                self.time_start.elapsed().as_millis(),
+                iteration_time.as_micros(),
+                metrics.path_search_failures,
+                metrics.simulation_time.as_micros(),
+                score.map(|score| score.to_string()).unwrap_or_default(),
+                self.best_score()
+                    .map(|score| score.to_string())
+                    .unwrap_or_default(),
+            )
+            .expect("Cannot write telemetry");
+        }
+    }
+
+    /// Writes buffered CSV lines to the file
+    pub(crate) fn flush(&self) {
+        if let Some(csv) = &self.csv {
+            csv.lock().unwrap().flush().expect("Cannot write telemetry");
+        }
+    }
+
+    /// Best score of the current run so far
+    pub fn best_score(&self) -> Option<u32> {
+        match self.best_score.load(Ordering::Relaxed) {
+            0 => None,
+            score => Some(score - 1),
+        }
+    }
+
+    /// The metrics of all runs in the Prometheus text format
+    pub fn to_prometheus(&self) -> String {
+        let mut metrics = String::new();
+        let mut add = |name: &str, kind: &str, help: &str, value: String| {
+            metrics.push_str(&format!(
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+            ));
+        };
+        let seconds = |us: &AtomicU64| (us.load(Ordering::Relaxed) as f64 / 1e6).to_string();
+
+        add(
+            "solver_runs_total",
+            "counter",
+            "Number of solver runs",
+            self.num_runs.load(Ordering::Relaxed).to_string(),
+        );
+        add(
+            "solver_iterations_total",
+            "counter",
+            "Number of iterations of all workers",
+            self.num_iterations.load(Ordering::Relaxed).to_string(),
+        );
+        add(
+            "solver_iteration_seconds_total",
+            "counter",
+            "Time spent in iterations",
+            seconds(&self.iteration_time_us),
+        );
+        add(
+            "solver_path_search_failures_total",
+            "counter",
+            "Number of path searches that did not find a path that could be built",
+            self.path_search_failures
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
+        add(
+            "solver_simulation_seconds_total",
+            "counter",
+            "Time spent simulating solutions",
+            seconds(&self.simulation_time_us),
+        );
+        add(
+            "solver_best_score",
+            "gauge",
+            "Best score of the latest run",
+            self.best_score().unwrap_or_default().to_string(),
+        );
+        metrics
+    }
+}
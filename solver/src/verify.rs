@@ -0,0 +1,66 @@
+//! Self-check of the solver's output
+
+use model::{map::Map, object::Object, solution::Solution, task::Task};
+use simulator::{simulate, SimulatorResult};
+
+use crate::{cli::OutputFormat, envelope::Envelope};
+
+/// Rebuilds the map from the serialized `output` and checks that simulating it results in
+/// `expected`
+///
+/// Returns Err(diagnostic), if the output cannot be read, any object breaks a placement rule (see
+/// [Map::audit]) or the result differs.
+pub(crate) fn verify(
+    task: &Task,
+    output: &str,
+    format: &OutputFormat,
+    expected: &SimulatorResult,
+) -> Result<(), String> {
+    let objects: Vec<Object> = match format {
+        OutputFormat::Cli => {
+            Task::from_json_string(output)
+                .map_err(|e| format!("Cannot read serialized task: {}", e))?
+                .objects
+        }
+        // a single solution of the Pareto front
+        OutputFormat::Solution | OutputFormat::Placements | OutputFormat::Pareto => {
+            let solution = Solution::from_json_string(output)
+                .map_err(|e| format!("Cannot read serialized solution: {}", e))?;
+            task.objects.iter().cloned().chain(solution.0).collect()
+        }
+        OutputFormat::Envelope => {
+            let envelope: Envelope = serde_json::from_str(output)
+                .map_err(|e| format!("Cannot read serialized envelope: {}", e))?;
+            task.objects
+                .iter()
+                .cloned()
+                .chain(envelope.solution.0)
+                .collect()
+        }
+    };
+
+    let mut map = Map::new(task.width, task.height, vec![]);
+    for object in objects {
+        map.insert_object_unchecked(object);
+    }
+
+    let violations = map.audit();
+    if !violations.is_empty() {
+        let violations: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        return Err(format!(
+            "Cannot place {} object(s):\n{}",
+            violations.len(),
+            violations.join("\n")
+        ));
+    }
+
+    let result = simulate(task, &map, true);
+    if result != *expected {
+        return Err(format!(
+            "Simulating the serialized solution results in {:?}, but {:?} was expected",
+            result, expected
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+//! Covers the public API of the solver library on a small task
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use model::{
+    input::read_input_from_file,
+    object::Object,
+    task::{Product, Task},
+};
+use simulator::{generate_map, simulate};
+use solver::{
+    mcts::MctsSolver,
+    rng::{RngType, SolverRng},
+    run_solver,
+    strategy::Strategy,
+    RunOptions, Solver, SolverConfig,
+};
+
+const TASK: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/001.task.json");
+
+#[test]
+fn run_solver_returns_simulated_solution() {
+    let (task, _) = read_input_from_file(TASK).unwrap();
+    let map = generate_map(&task, &Default::default()).unwrap();
+    let config = SolverConfig {
+        strategy: Strategy::Greedy,
+        ..SolverConfig::default()
+    };
+
+    let result = run_solver(
+        &task,
+        &map,
+        &config,
+        1,
+        Some(Duration::from_secs(2)),
+        Some(1),
+        RunOptions::default(),
+    )
+    .unwrap();
+
+    assert!(result.result.score > 0);
+    assert_eq!(
+        simulate(&task, &result.map, true).score,
+        result.result.score
+    );
+}
+
+#[test]
+fn solver_yields_scoring_solutions() {
+    let (task, _) = read_input_from_file(TASK).unwrap();
+    let map = generate_map(&task, &Default::default()).unwrap();
+    let rng = Rc::new(RefCell::new(SolverRng::new(RngType::Std, Some(1))));
+    let solver = Solver::new(
+        &task,
+        &map,
+        SolverConfig::default(),
+        rng,
+        Duration::from_millis(500),
+    );
+
+    let solutions: Vec<_> = solver.take(3).collect();
+
+    assert!(!solutions.is_empty());
+    for (result, map) in solutions {
+        assert!(result.score > 0);
+        assert_eq!(simulate(&task, &map, true).score, result.score);
+    }
+}
+
+#[test]
+fn mcts_solver_yields_scoring_solutions() {
+    let (task, _) = read_input_from_file(TASK).unwrap();
+    let map = generate_map(&task, &Default::default()).unwrap();
+    let rng = Rc::new(RefCell::new(SolverRng::new(RngType::Std, Some(1))));
+    let solver = MctsSolver::new(
+        &task,
+        &map,
+        SolverConfig::default(),
+        rng,
+        Duration::from_millis(500),
+    );
+
+    let solutions: Vec<_> = solver.take(3).collect();
+
+    assert!(!solutions.is_empty());
+    for (result, map) in solutions {
+        assert!(result.score > 0);
+        assert_eq!(simulate(&task, &map, true).score, result.score);
+    }
+}
+
+#[test]
+fn stop_flag_is_only_read() {
+    let (task, _) = read_input_from_file(TASK).unwrap();
+    let map = generate_map(&task, &Default::default()).unwrap();
+    let stop = AtomicBool::new(false);
+
+    let options = RunOptions {
+        cancel: Some(&stop),
+        ..RunOptions::default()
+    };
+    let result = run_solver(
+        &task,
+        &map,
+        &SolverConfig::default(),
+        2,
+        Some(Duration::from_secs(2)),
+        Some(1),
+        options,
+    );
+
+    assert!(result.is_some());
+    assert!(!stop.load(Ordering::Acquire));
+}
+
+#[test]
+fn products_whose_factory_fits_nowhere_are_skipped() {
+    // a factory (5x5) does not fit on a 4x4 map
+    let task = Task {
+        width: 4,
+        height: 4,
+        objects: vec![Object::Deposit {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+            subtype: 0,
+        }],
+        products: vec![Product {
+            kind: String::from("product"),
+            subtype: 0,
+            resources: vec![1, 0, 0, 0, 0, 0, 0, 0],
+            points: 10,
+        }],
+        turns: 10,
+        time: None,
+    };
+    let map = generate_map(&task, &Default::default()).unwrap();
+
+    let result = run_solver(
+        &task,
+        &map,
+        &SolverConfig::default(),
+        2,
+        Some(Duration::from_millis(500)),
+        Some(1),
+        RunOptions::default(),
+    );
+
+    assert_eq!(result.map_or(0, |result| result.result.score), 0);
+}
@@ -0,0 +1,54 @@
+//! Runs the solver binary with invalid arguments
+
+use std::process::{Command, Output, Stdio};
+
+const TASK: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/001.task.json");
+
+/// Runs the solver on [TASK] with `args`
+fn solve(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_solver"))
+        .args(args)
+        .stdin(Stdio::from(std::fs::File::open(TASK).unwrap()))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn rejects_invalid_deadline() {
+    let output = solve(&["--time", "1", "--deadline", "tomorrow"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--deadline"), "{}", stderr);
+}
+
+#[test]
+fn reports_unwritable_telemetry_file() {
+    let output = solve(&["--time", "1", "--telemetry", "/nonexistent/telemetry.csv"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Cannot write /nonexistent/telemetry.csv"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn reports_missing_decision_log() {
+    let output = solve(&["--time", "1", "--replay", "/nonexistent/decisions.log"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("/nonexistent/decisions.log"), "{}", stderr);
+}
+
+#[test]
+fn reproduce_requires_seed() {
+    let output = solve(&["--time", "1", "--reproduce", "0:1"]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--seed"), "{}", stderr);
+}
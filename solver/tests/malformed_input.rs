@@ -0,0 +1,35 @@
+//! Runs the solver binary on malformed tasks
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// A task whose product needs more than 8 resource types
+const TASK: &str = r#"{"width":10,"height":10,"objects":[],"products":[{"type":"product","subtype":0,"resources":[1,0,0,0,0,0,0,0,0,1],"points":10}],"turns":10,"time":1}"#;
+
+#[test]
+fn reports_invalid_task() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_solver"))
+        .args(["--strict=false", "--time", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(TASK.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Invalid task"), "{}", stderr);
+    // the solution is only printed by release builds
+    if !cfg!(debug_assertions) {
+        assert_eq!("[]", String::from_utf8(output.stdout).unwrap().trim());
+    }
+}
@@ -0,0 +1,51 @@
+//! Runs the solver binary on multiple tasks from stdin
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use model::{object::Object, solution::Solution, task::Task};
+
+const TASKS: [&str; 2] = [
+    concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/001.task.json"),
+    concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/002.task.json"),
+];
+
+#[test]
+fn solves_every_task_on_multiple_cores() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_solver"))
+        .args(["--time", "6", "--cores", "2", "--seed", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for path in TASKS {
+        stdin.write_all(&std::fs::read(path).unwrap()).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let solutions: Vec<&str> = stdout.lines().collect();
+    assert_eq!(TASKS.len(), solutions.len(), "{}", stdout);
+    for solution in solutions {
+        // debug builds print the task with all placed objects
+        let num_placed = if cfg!(debug_assertions) {
+            Task::from_json_string(solution)
+                .unwrap()
+                .objects
+                .iter()
+                .filter(|object| {
+                    !matches!(object, Object::Deposit { .. } | Object::Obstacle { .. })
+                })
+                .count()
+        } else {
+            Solution::from_json_string(solution).unwrap().0.len()
+        };
+        assert!(num_placed > 0, "{}", solution);
+    }
+}
@@ -0,0 +1,65 @@
+//! Runs the solver binary as HTTP service (requires the `server` feature)
+#![cfg(feature = "server")]
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use model::solution::Solution;
+
+const TASK: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../inputs/001.task.json");
+
+/// POSTs `task` to `/solve` of the server on `addr` and returns the response
+fn post(addr: &str, task: &str) -> String {
+    let mut stream = (0..50)
+        .find_map(|_| {
+            TcpStream::connect(addr)
+                .map_err(|_| thread::sleep(Duration::from_millis(100)))
+                .ok()
+        })
+        .expect("Cannot connect to server");
+    write!(
+        stream,
+        "POST /solve?time_ms=2000 HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+        addr,
+        task.len(),
+        task
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn serves_multiple_requests() {
+    // a free port, that is released again for the server
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addr = format!("127.0.0.1:{}", port);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_solver"))
+        .args(["--serve", &addr, "--cores", "2", "--seed", "1"])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let task = std::fs::read_to_string(TASK).unwrap();
+    let responses: Vec<String> = (0..2).map(|_| post(&addr, &task)).collect();
+    let exited = child.try_wait().unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(None, exited, "Server exited after handling requests");
+    for response in responses {
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(!Solution::from_json_string(body).unwrap().0.is_empty());
+    }
+}